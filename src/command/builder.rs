@@ -1,81 +1,515 @@
 use std::{
-    fs::{read_to_string, File}, io::{Cursor, Write}, process::ExitCode, rc::Rc
+    collections::BTreeMap,
+    fs::{read_to_string, File},
+    io::{Cursor, Write},
+    process::ExitCode,
+    rc::Rc,
 };
 
-use anyhow::Context;
+use anyhow::{bail, Context, Result};
 use camino::Utf8PathBuf;
-use msi::{Package, PackageType};
+use clap::ValueEnum;
+use msi::{CodePage, Language, Package, PackageType};
+use uuid::Uuid;
 
-use crate::modules::config::msi_config::MsiConfig;
+use crate::modules::config::{
+    localization::LocalizationConfig, msi_config::MsiConfig, ui::UiSet,
+};
 use crate::modules::{
+    authoring,
+    component::file::File as SourceFile,
     helpers::{
         error::MsiError,
         log_return::{error, info},
-        scn
+        scan,
     },
-    tables,
+    localization, merge, preprocess, signing, tables, transform, ui,
+    validation, wxs,
 };
 
 // Make a shorthand way to refer to the package cursor for brevity.
 pub(crate) type Msi = Package<Cursor<Vec<u8>>>;
 
+/// The kind of package to build.
+///
+/// A merge module uses the same file format as an installer but holds its
+/// files in a `MergeModule.CABinet` stream and is signed with a
+/// `ModuleSignature` row instead of having product properties. It should be
+/// written to a file ending with `.msm`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum PackageKind {
+    #[default]
+    Installer,
+    Module,
+}
+
+/// The file the package is described by.
+pub(crate) enum ConfigSource {
+    /// A whimsi TOML config.
+    Toml(Utf8PathBuf),
+    /// A WiX `.wxs` source, which lists the files to install itself instead
+    /// of having the input directory scanned.
+    Wxs(Utf8PathBuf),
+}
+
+impl ConfigSource {
+    fn path(&self) -> &Utf8PathBuf {
+        match self {
+            ConfigSource::Toml(path) | ConfigSource::Wxs(path) => path,
+        }
+    }
+}
+
 pub(crate) fn build(
-    config_path: &Utf8PathBuf,
+    source: &ConfigSource,
+    input_directory: &Utf8PathBuf,
     output_path: &Utf8PathBuf,
+    kind: PackageKind,
+    cultures: &[String],
+    defines: &BTreeMap<String, String>,
 ) -> ExitCode {
+    match build_msi(
+        source,
+        input_directory,
+        output_path,
+        kind,
+        cultures,
+        defines,
+    ) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("Failed to build MSI.\n{e:?}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+pub(crate) fn build_msi(
+    source: &ConfigSource,
+    input_directory: &Utf8PathBuf,
+    output_path: &Utf8PathBuf,
+    kind: PackageKind,
+    cultures: &[String],
+    defines: &BTreeMap<String, String>,
+) -> Result<()> {
     info!("Building MSI at output path {}", output_path);
+    let config_path = source.path();
     // Validate paths before continuing
-    let Ok(_) = validate_paths(config_path, output_path)? else {
-        return ExitCode::FAILURE;
-    };
+    validate_paths(config_path, output_path)?;
+    if let ConfigSource::Wxs(_) = source {
+        if kind == PackageKind::Module {
+            bail!("WiX sources can only be built into installers");
+        }
+        if !cultures.is_empty() {
+            bail!("WiX sources can't be built for a culture");
+        }
+        if !defines.is_empty() {
+            bail!("WiX sources can't use --define");
+        }
+    }
 
     // The toml library seems to only accept strings as input so we read the whole file in here.
-    let raw_config = match read_to_string(config_path).with_context(|| format!("Failed to parse config file [{config_path}]")) {
-        Ok(c) => c,
-        Err(e) => {
-            error!(e);
-            return ExitCode::FAILURE;
+    let raw_config = read_to_string(config_path).with_context(|| {
+        format!("Failed to parse config file [{config_path}]")
+    })?;
+
+    let (config, mut bytes) = match cultures {
+        [] | [_] => {
+            let culture = cultures.first().map(String::as_str);
+            let (config, package) = build_package(
+                source,
+                &raw_config,
+                input_directory,
+                kind,
+                culture,
+                None,
+                defines,
+            )?;
+            let bytes = package
+                .into_inner()
+                .with_context(|| "Failed to finish writing the MSI")?
+                .into_inner();
+            (config, bytes)
         }
+        _ => build_multilingual(
+            source,
+            &raw_config,
+            input_directory,
+            kind,
+            cultures,
+            defines,
+        )?,
     };
+    if let Some(signing_config) = &config.signing {
+        let config_dir = config_path.parent().unwrap_or(config_path);
+        bytes = signing::sign(bytes, &signing_config.options(config_dir))
+            .with_context(|| "Failed to sign the MSI")?;
+    }
 
-    // Convert the string output into a usable TOML object.
-    let config: Rc<MsiConfig> = match toml::from_str(&raw_config).with_context(|| format!("Failed to parse TOML data from config file {config_path}")) {
-        Ok(c) => Rc::new(c),
-        Err(e) => {
-            error!(e);
-            return ExitCode::FAILURE;
+    write_msi(&bytes, output_path)?;
+    Ok(())
+}
+
+/// Build the first culture as the base package and embed a transform to
+/// each of the others in it, in a storage named after the language
+/// identifier. The template of the base lists every language, which lets
+/// Windows Installer pick the transform for the language of the user.
+fn build_multilingual(
+    source: &ConfigSource,
+    raw_config: &str,
+    input_directory: &Utf8PathBuf,
+    kind: PackageKind,
+    cultures: &[String],
+    defines: &BTreeMap<String, String>,
+) -> Result<(Rc<MsiConfig>, Vec<u8>)> {
+    if kind == PackageKind::Module {
+        bail!("Merge modules can only be built for one culture");
+    }
+    // Every language has to be the same product for the transforms to apply.
+    let product_code = format!("{{{}}}", Uuid::new_v4()).to_uppercase();
+    let mut builds = cultures
+        .iter()
+        .map(|culture| {
+            build_package(
+                source,
+                raw_config,
+                input_directory,
+                kind,
+                Some(culture),
+                Some(&product_code),
+                defines,
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut languages = Vec::new();
+    for (config, _) in &builds {
+        let language = config.product_info.product_language;
+        if languages.contains(&language) {
+            bail!("Two of the cultures have the language {}", language);
+        }
+        languages.push(language);
+    }
+    let (config, mut base) = builds.remove(0);
+    base.summary_info_mut().set_languages(
+        &languages
+            .iter()
+            .copied()
+            .map(Language::from_code)
+            .collect::<Vec<_>>(),
+    );
+
+    let mut transforms = Vec::new();
+    for (culture, (config, mut package)) in cultures[1..].iter().zip(builds) {
+        let transform = transform::create(&mut base, &mut package)
+            .with_context(|| {
+                format!("Failed to create the transform for {culture}")
+            })?;
+        let language = config.product_info.product_language.to_string();
+        transforms.push((language, transform));
+    }
+    let bytes = base
+        .into_inner()
+        .with_context(|| "Failed to finish writing the MSI")?
+        .into_inner();
+    let bytes = localization::embed_transforms(bytes, &transforms)?;
+    Ok((config, bytes))
+}
+
+/// Build a package in memory. `product_code` replaces a `product_code` of
+/// `*` in the config.
+fn build_package(
+    source: &ConfigSource,
+    raw_config: &str,
+    input_directory: &Utf8PathBuf,
+    kind: PackageKind,
+    culture: Option<&str>,
+    product_code: Option<&str>,
+    defines: &BTreeMap<String, String>,
+) -> Result<(Rc<MsiConfig>, Msi)> {
+    let config_path = source.path();
+    let (mut config, layout) = match source {
+        // Convert the string output into a usable TOML object.
+        ConfigSource::Toml(_) => (
+            read_config(config_path, raw_config, culture, defines)?,
+            None,
+        ),
+        ConfigSource::Wxs(_) => {
+            let (config, layout) =
+                wxs::import(config_path, raw_config, input_directory)?;
+            (config, Some(layout))
         }
     };
+    if let Some(product_code) = product_code {
+        if config.product_info.product_code.as_str() == "*" {
+            config.product_info.product_code = product_code.into();
+        }
+    }
+    let config = Rc::new(config);
 
     // Create an empty MSI that we can populate.
     let cursor = Cursor::new(Vec::new());
-    let mut package = Package::create(PackageType::Installer, cursor).unwrap();
+    let mut package = Package::create(PackageType::Installer, cursor)
+        .with_context(|| "Failed to create an empty MSI")?;
 
-    // Set the author
-    set_author(&mut package, config.clone());
+    // Set the author, template and codepage
+    let codepage = set_summary_info(&mut package, config.clone())?;
 
-    // Add the files from the input directory
-    let (directories, files) =
-        scan::scan_paths(config.clone(), input_directory).with_context(|| "Failed while scanning file system") else {
-        error!(e);
-        return ExitCode::FAILURE;
+    // Add the files from the input directory, or the files a WiX source
+    // lists along with the rows of the other tables it has.
+    let (mut directories, files, imported) = match layout {
+        Some(layout) => (layout.directories, layout.files, Some(layout.tables)),
+        None => {
+            let (directories, files) =
+                scan::scan_paths(config.clone(), input_directory, kind)
+                    .with_context(|| "Failed while scanning file system")?;
+            (directories, files, None)
+        }
+    };
+    // The features, registry values, shortcuts and custom actions of the
+    // config, which can add folders for shortcuts to the directories.
+    let from_wxs = imported.is_some();
+    let authored = match imported {
+        Some(tables) => tables,
+        None => authoring::author_tables(
+            &config,
+            config_path,
+            input_directory,
+            &mut directories,
+            &files,
+        )?,
     };
 
+    if kind == PackageKind::Installer {
+        tables::property::populate_property_table(
+            &mut package,
+            &config.product_info,
+        )?;
+        tables::property::insert_properties(
+            &mut package,
+            config.property.clone(),
+        )?;
+    }
     tables::directory::populate_directory_table(&mut package, &directories)?;
-    tables::component::populate_component_table(&mut package, &files)?;
+    if !from_wxs {
+        tables::component::populate_component_table(
+            &mut package,
+            &files,
+            &tables::component::guid_seed(&config),
+        )?;
+    }
     tables::file::populate_file_table(&mut package, &files)?;
+    tables::file_hash::populate_file_hash_table(&mut package, &files)?;
+
+    match kind {
+        PackageKind::Installer => {
+            add_ui(&mut package, config.clone(), config_path)?;
+            tables::authored::populate_authored_tables(&mut package, authored)?;
+            merge_modules(&mut package, config.clone(), config_path)?
+        }
+        PackageKind::Module => {
+            write_module(&mut package, config.clone(), &files)?
+        }
+    }
 
-    write_msi(package, output_path)
+    // Catch anything that would make the package fail to install before it
+    // gets written out.
+    check_package(&mut package)?;
+    if let Some(codepage) = codepage {
+        localization::check_codepage(&mut package, codepage)?;
+    }
+    Ok((config, package))
 }
 
-fn set_author(package: &mut Msi, config: Rc<MsiConfig>) {
-    package
-        .summary_info_mut()
-        .set_author(config.summary_info.author.clone().unwrap_or_default());
+/// Add the dialogs of the configured UI set and the dialogs of the config,
+/// along with the license agreement if there is one.
+fn add_ui(
+    package: &mut Msi,
+    config: Rc<MsiConfig>,
+    config_path: &Utf8PathBuf,
+) -> Result<()> {
+    let config_dir = config_path.parent().unwrap_or(config_path);
+    let license = config
+        .license
+        .as_ref()
+        .map(|license| {
+            let path = config_dir.join(license);
+            read_to_string(&path)
+                .with_context(|| format!("Failed to read license {path}"))
+        })
+        .transpose()?;
+    ui::populate_ui_tables(
+        package,
+        config.ui,
+        license.as_deref(),
+        &config.dialog,
+    )
+    .with_context(|| "Failed to add the UI")
+}
+
+/// Merge every `[[merge_module]]` from the config into the package.
+fn merge_modules(
+    package: &mut Msi,
+    config: Rc<MsiConfig>,
+    config_path: &Utf8PathBuf,
+) -> Result<()> {
+    if config.merge_module.is_empty() {
+        return Ok(());
+    }
+    let config_dir = config_path.parent().unwrap_or(config_path);
+    let first_feature = config.feature.first().map(|f| f.id.as_str());
+    for module_config in &config.merge_module {
+        let path = config_dir.join(&module_config.path);
+        let mut module = msi::open(&path)
+            .with_context(|| format!("Failed to open merge module {path}"))?;
+        merge::merge_module(package, &mut module, module_config, first_feature)
+            .with_context(|| format!("Failed to merge module {path}"))?;
+    }
+    merge::check_dependencies(package)
+}
+
+/// Sign the package as a merge module and store its files in the module
+/// cabinet.
+fn write_module(
+    package: &mut Msi,
+    config: Rc<MsiConfig>,
+    files: &[SourceFile],
+) -> Result<()> {
+    if !config.merge_module.is_empty() {
+        bail!("Merge modules can't be merged into a merge module");
+    }
+    if config.ui != UiSet::None || !config.dialog.is_empty() {
+        bail!("Merge modules can't have a UI");
+    }
+    if !(config.feature.is_empty()
+        && config.registry.is_empty()
+        && config.shortcut.is_empty()
+        && config.custom_action.is_empty())
+    {
+        bail!(
+            "Merge modules can't have features, registry values, shortcuts \
+            or custom actions"
+        );
+    }
+    let product_info = &config.product_info;
+    let module_id = tables::module_signature::module_id(product_info);
+    tables::module_signature::populate_module_signature_table(
+        package,
+        &module_id,
+        product_info,
+    )?;
+    tables::module_signature::populate_module_components_table(
+        package,
+        files,
+        &module_id,
+        product_info.product_language,
+    )?;
+    merge::write_module_cabinet(package, files)
+}
+
+/// Run the Internal Consistency Evaluators against the populated package and
+/// fail if any of them found an error.
+fn check_package(package: &mut Msi) -> Result<()> {
+    let findings = validation::validate(package)
+        .with_context(|| "Failed to validate the populated MSI")?;
+    for finding in &findings {
+        match finding.severity {
+            validation::Severity::Error => log::error!("{}", finding),
+            validation::Severity::Warning => log::warn!("{}", finding),
+        }
+    }
+    if validation::has_errors(&findings) {
+        bail!("Validation of the MSI failed, see the errors above");
+    }
+    Ok(())
+}
+
+/// Parse the config, localized for `culture` or else the default culture of
+/// the config if it has one. Its includes, variables and conditional
+/// sections are resolved first.
+fn read_config(
+    config_path: &Utf8PathBuf,
+    raw_config: &str,
+    culture: Option<&str>,
+    defines: &BTreeMap<String, String>,
+) -> Result<MsiConfig> {
+    let preprocess::Preprocessed { mut value, origins } =
+        preprocess::preprocess(config_path, raw_config, defines)?;
+    let localization_config = value
+        .get("localization")
+        .cloned()
+        .map(toml::Value::try_into::<LocalizationConfig>)
+        .transpose()
+        .with_context(|| "Failed to parse the [localization] section")?;
+    let culture = culture.or(localization_config
+        .as_ref()
+        .and_then(|l| l.default_culture.as_deref()));
+
+    let Some(culture) = culture else {
+        if localization::has_placeholders(&value) {
+            bail!("The config has !(loc.Key) placeholders but no culture");
+        }
+        return origins.deserialize(value).with_context(|| {
+            format!("Failed to parse TOML data from config file {config_path}")
+        });
+    };
+    let config_dir = config_path.parent().unwrap_or(config_path);
+    let localization =
+        localization::load(localization_config.as_ref(), config_dir, culture)?;
+    info!("Building for culture {}", localization.culture);
+    localization::resolve(&mut value, &localization.strings).with_context(
+        || format!("Failed to localize the config for culture {culture}"),
+    )?;
+    let mut config: MsiConfig =
+        origins.deserialize(value).with_context(|| {
+            format!("Failed to parse TOML data from config file {config_path}")
+        })?;
+    config.localize(&localization);
+    Ok(config)
+}
+
+/// Write the author, template and codepage of the summary information. The
+/// codepage is returned so the strings of the tables can be checked against
+/// it, as it is used for them too.
+fn set_summary_info(
+    package: &mut Msi,
+    config: Rc<MsiConfig>,
+) -> Result<Option<CodePage>> {
+    let summary = &config.summary_info;
+    let template: &str = &summary.template;
+    let (arch, languages) = template.split_once(';').unwrap_or((template, ""));
+    let languages = languages
+        .split(',')
+        .filter(|language| !language.is_empty())
+        .map(|language| {
+            language
+                .trim()
+                .parse()
+                .map(Language::from_code)
+                .with_context(|| {
+                    format!("Invalid language {language} in the template")
+                })
+        })
+        .collect::<Result<Vec<Language>>>()?;
+    let summary_info = package.summary_info_mut();
+    summary_info.set_author(summary.author.clone().unwrap_or_default());
+    summary_info.set_arch(arch);
+    summary_info.set_languages(&languages);
+
+    let Some(code_page) = &summary.code_page else {
+        return Ok(None);
+    };
+    let codepage = code_page
+        .parse()
+        .ok()
+        .and_then(CodePage::from_id)
+        .with_context(|| format!("Unsupported code page {code_page}"))?;
+    package.summary_info_mut().set_codepage(codepage);
+    package.set_database_codepage(codepage);
+    Ok(Some(codepage))
 }
 
-fn write_msi(package: Msi, output_path: &Utf8PathBuf) -> Result<(), MsiError> {
-    let cursor = package.into_inner().unwrap();
+fn write_msi(bytes: &[u8], output_path: &Utf8PathBuf) -> Result<(), MsiError> {
     let mut file = match File::create(output_path) {
         Ok(file) => file,
         Err(e) => {
@@ -86,7 +520,7 @@ fn write_msi(package: Msi, output_path: &Utf8PathBuf) -> Result<(), MsiError> {
             return Err(MsiError::nested(msg, e));
         }
     };
-    match file.write_all(cursor.get_ref()) {
+    match file.write_all(bytes) {
         Ok(_) => {
             info!("Wrote MSI to {}", output_path);
             Ok(())
@@ -1,7 +1,11 @@
 use camino::Utf8PathBuf;
-use clap::{arg, Parser, Subcommand};
+use clap::{Parser, Subcommand};
 use flexstr::SharedStr;
 
+use super::builder::PackageKind;
+use super::format::{DiffFormat, OutputFormat};
+use super::initializer::Platform;
+
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 #[command(propagate_version = true)]
@@ -16,23 +20,204 @@ pub(crate) struct App {
 pub(crate) enum Commands {
     Build {
         /// Path to config to build from
-        #[arg(short, long)]
-        config: Utf8PathBuf,
+        #[arg(short, long, required_unless_present = "wxs")]
+        config: Option<Utf8PathBuf>,
+        /// Path to a WiX v3 or v4 `.wxs` source to build from instead of a
+        /// config. The sources of its files are relative to the input
+        /// directory.
+        #[arg(long, conflicts_with = "config")]
+        wxs: Option<Utf8PathBuf>,
         /// Directory storing files used to be added to MSI
         #[arg(short, long)]
         input_directory: Utf8PathBuf,
-        /// File path to output. This should end with `.msi`.
+        /// File path to output. This should end with `.msi`, or `.msm` when
+        /// building a merge module.
         #[arg(short, long)]
         output_path: Utf8PathBuf,
+        /// Kind of package to build
+        #[arg(long, value_enum, default_value_t)]
+        package_type: PackageKind,
+        /// Culture to build, such as `de-DE`. Picks the string table for
+        /// `!(loc.Key)` placeholders and sets the language and codepage.
+        #[arg(long, conflicts_with = "cultures")]
+        culture: Option<String>,
+        /// Cultures to build into one package, separated by commas. The
+        /// first is the base and the others are embedded as transforms that
+        /// Windows Installer picks from by the language of the user.
+        #[arg(long, value_delimiter = ',')]
+        cultures: Vec<String>,
+        /// Variable for `${NAME}` in the config, which wins over the
+        /// `[variables]` table. Can be given more than once.
+        #[arg(
+            short,
+            long,
+            value_name = "NAME=VALUE",
+            value_parser = parse_define
+        )]
+        define: Vec<(String, String)>,
     },
     Inspect {
         /// Path to MSI to read from
         #[arg(short, long)]
         input_file: Utf8PathBuf,
+        /// Transform to apply before inspecting, to view the MSI as it looks
+        /// after the transform
+        #[arg(long, global = true)]
+        transform: Option<Utf8PathBuf>,
+        /// Format to write the output in
+        #[arg(long, value_enum, global = true, default_value_t)]
+        format: OutputFormat,
 
         #[command(subcommand)]
         list_args: AllowedToList,
     },
+    Query {
+        /// Path to the MSI to query
+        input_file: Utf8PathBuf,
+        /// Query to run, such as
+        /// `SELECT File, FileName FROM File WHERE Component_ = 'foo'`
+        sql: String,
+        /// Format to write the output in
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    Validate {
+        /// Path to the MSI to validate
+        input_file: Utf8PathBuf,
+        /// Format to write the findings in
+        #[arg(long, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    Diff {
+        /// Path to the original MSI
+        old_file: Utf8PathBuf,
+        /// Path to the MSI to compare against the original
+        new_file: Utf8PathBuf,
+        /// Format to write the differences in
+        #[arg(long, value_enum, default_value_t)]
+        format: DiffFormat,
+    },
+    Transform {
+        #[command(subcommand)]
+        action: TransformAction,
+    },
+    Patch {
+        /// Path to the MSI the patch applies to
+        baseline: Utf8PathBuf,
+        /// Path to the MSI the patch upgrades the baseline to
+        upgraded: Utf8PathBuf,
+        /// Directory the upgraded MSI was built from
+        #[arg(short, long)]
+        input_directory: Utf8PathBuf,
+        /// Config the upgraded MSI was built from. Its `[[files]]` entries
+        /// say where the files are in the input directory.
+        #[arg(short, long)]
+        config: Option<Utf8PathBuf>,
+        /// File path to output. This should end with `.msp`.
+        #[arg(short, long)]
+        output: Utf8PathBuf,
+        /// Patch family that sequences this patch against others. Defaults to
+        /// the product name.
+        #[arg(long)]
+        family: Option<String>,
+        /// Name shown for the patch in Programs and Features
+        #[arg(long)]
+        display_name: Option<String>,
+        /// Description shown for the patch
+        #[arg(long)]
+        description: Option<String>,
+        /// Kind of patch, such as `Hotfix`, `Security Rollup` or `Update`
+        #[arg(long, default_value = "Update")]
+        classification: String,
+    },
+    Sign {
+        /// Path to the MSI to sign
+        input_file: Utf8PathBuf,
+        /// PEM file with the signing certificate, followed by any
+        /// intermediate certificates to include
+        #[arg(long)]
+        cert: Utf8PathBuf,
+        /// PEM file with the RSA private key of the certificate
+        #[arg(long)]
+        key: Utf8PathBuf,
+        /// URL of an RFC 3161 timestamp server
+        #[arg(long)]
+        timestamp_url: Option<String>,
+        /// Also sign the stream metadata with `MsiDigitalSignatureEx`
+        #[arg(long)]
+        extended: bool,
+        /// File path to write the signed MSI to. The input is signed in
+        /// place when this isn't given.
+        #[arg(short, long)]
+        output: Option<Utf8PathBuf>,
+    },
+    Verify {
+        /// Path to the MSI to check the signature of
+        input_file: Utf8PathBuf,
+    },
+    Decompile {
+        /// Path to the MSI to decompile
+        input_file: Utf8PathBuf,
+        /// Directory to write the config and the extracted files to
+        #[arg(short, long)]
+        output: Utf8PathBuf,
+    },
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    Init {
+        /// Directory to write the config to
+        #[arg(default_value = ".")]
+        directory: Utf8PathBuf,
+        /// Name of the product. Defaults to the name of the directory.
+        #[arg(long)]
+        name: Option<String>,
+        /// Manufacturer of the product. Defaults to its name.
+        #[arg(long)]
+        manufacturer: Option<String>,
+        /// Platform to build packages for
+        #[arg(long, value_enum, default_value_t)]
+        template: Platform,
+        /// Existing directory to use as the input directory, with a feature
+        /// for every folder at its top
+        #[arg(long)]
+        scan: Option<Utf8PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+pub(crate) enum TransformAction {
+    // Write a transform that turns one MSI into another
+    Create {
+        /// Path to the MSI the transform applies to
+        base: Utf8PathBuf,
+        /// Path to the MSI the base should look like after the transform
+        modified: Utf8PathBuf,
+        /// File path to output. This should end with `.mst`.
+        #[arg(short, long)]
+        output: Utf8PathBuf,
+    },
+    // Apply a transform to an MSI and write the result to a new MSI
+    Apply {
+        /// Path to the MSI to apply the transform to
+        base: Utf8PathBuf,
+        /// Path to the transform
+        transform: Utf8PathBuf,
+        /// File path to output. This should end with `.msi`.
+        #[arg(short, long)]
+        output: Utf8PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+pub(crate) enum ConfigAction {
+    // Write the JSON Schema of the config, for editors to check configs with
+    Schema {
+        /// File path to output. The schema is printed when this isn't given.
+        #[arg(short, long)]
+        output: Option<Utf8PathBuf>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -40,10 +225,33 @@ pub(crate) enum Commands {
 pub(crate) enum AllowedToList {
     // List the author of the MSI
     Author,
+    // List every summary information property of the MSI
+    Summary,
     // List tables present in the MSI
     Tables,
     // List the columns that a given table has.
     TableColumns { table: SharedStr },
     // List the contents of a given table
     TableContents { table: SharedStr },
+    // List every stream and storage in the MSI container
+    Streams,
+    // Write the raw bytes of a single stream to a file
+    Stream {
+        /// Decoded name of the stream, such as `Binary.CustomAction`
+        #[arg(long)]
+        name: String,
+        /// File path to write the stream to
+        #[arg(long)]
+        out: Utf8PathBuf,
+    },
+}
+
+/// Split a `--define` into its name and value.
+fn parse_define(define: &str) -> Result<(String, String), String> {
+    match define.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_owned(), value.to_owned()))
+        }
+        _ => Err(format!("{define} isn't written as NAME=VALUE")),
+    }
 }
@@ -0,0 +1,38 @@
+use std::{fs, process::ExitCode};
+
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use log::{error, info};
+
+use super::command_line::ConfigAction;
+use crate::modules::config::schema::config_schema;
+
+pub(crate) fn config(action: ConfigAction) -> ExitCode {
+    let ret = match action {
+        ConfigAction::Schema { output } => schema(output.as_ref()),
+    };
+    match ret {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            error!("Error while trying to write config schema.\n{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Print the schema of the config, or write it to `output`. Editors that
+/// read JSON Schemas, such as Taplo through a `#:schema` comment at the top
+/// of the config, then complete and check configs as they are written.
+fn schema(output: Option<&Utf8PathBuf>) -> Result<()> {
+    let schema = serde_json::to_string_pretty(&config_schema())
+        .context("Failed to serialize the config schema")?;
+    match output {
+        Some(output) => {
+            fs::write(output, schema + "\n")
+                .with_context(|| format!("Failed to write {output}"))?;
+            info!("Wrote config schema to {output}");
+        }
+        None => println!("{schema}"),
+    }
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use std::process::ExitCode;
+
+use camino::Utf8PathBuf;
+use log::{error, info};
+
+use crate::modules::decompile::{self, FILES_DIR};
+
+pub(crate) fn decompile(
+    input_file: &Utf8PathBuf,
+    output: &Utf8PathBuf,
+) -> ExitCode {
+    info!("Decompiling MSI {} into {}", input_file, output);
+    match decompile::decompile(input_file, output) {
+        Ok(config_path) => {
+            info!(
+                "Wrote config to {}, build it again with `whimsi build -c {} \
+                -i {} -o <output>`",
+                config_path,
+                config_path,
+                output.join(FILES_DIR)
+            );
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            error!("Error while trying to decompile MSI.\n{err:?}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,637 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write as _,
+    io::{self, Read, Seek},
+    process::ExitCode,
+};
+
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use log::{debug, error, info};
+use msi::{Package, SummaryInfo};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use super::format::{CellValue, DiffFormat, RowListing};
+use super::lister::{read_summary_properties, read_table, ColumnListing};
+use super::transformer::{open_in_memory, MemoryPackage};
+use crate::modules::helpers::{
+    property_set::{PropertySet, PID_KEYWORDS, PID_PAGECOUNT, PID_SECURITY},
+    summary::format_timestamp,
+};
+
+/// The primary key of a row, used to match rows between two packages.
+type RowKey = Vec<Option<CellValue>>;
+
+pub(crate) fn diff(
+    old_file: &Utf8PathBuf,
+    new_file: &Utf8PathBuf,
+    format: DiffFormat,
+) -> ExitCode {
+    info!("Comparing MSI {} against {}", old_file, new_file);
+
+    let ret = diff_files(old_file, new_file).and_then(|d| match format {
+        DiffFormat::Text => Ok(d.to_text()),
+        DiffFormat::Json => serde_json::to_string_pretty(&d)
+            .with_context(|| "Failed to serialize diff to JSON"),
+    });
+    match ret {
+        Ok(output) => {
+            println!("{output}");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            error!("Error while trying to diff MSIs.\n{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn diff_files(
+    old_file: &Utf8PathBuf,
+    new_file: &Utf8PathBuf,
+) -> Result<PackageDiff> {
+    diff_packages(open_in_memory(old_file)?, open_in_memory(new_file)?)
+}
+
+/// Whether an item is only in the old package, only in the new package or in
+/// both with different contents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Status {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl std::fmt::Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            Status::Added => "+",
+            Status::Removed => "-",
+            Status::Changed => "~",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+/// A single value that differs between the two packages.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct FieldChange {
+    pub(crate) name: String,
+    pub(crate) old: Option<String>,
+    pub(crate) new: Option<String>,
+}
+
+/// A row that exists in both packages but has different values.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct RowChange {
+    pub(crate) key: RowListing,
+    pub(crate) changes: Vec<FieldChange>,
+}
+
+/// A column that was added, removed or redefined.
+#[derive(Clone, Serialize)]
+pub(crate) struct ColumnChange {
+    pub(crate) status: Status,
+    pub(crate) name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) old: Option<ColumnListing>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) new: Option<ColumnListing>,
+}
+
+/// Every difference found in a single table.
+#[derive(Clone, Serialize)]
+pub(crate) struct TableDiff {
+    pub(crate) table: String,
+    pub(crate) status: Status,
+    pub(crate) columns: Vec<ColumnChange>,
+    pub(crate) added_rows: Vec<RowListing>,
+    pub(crate) removed_rows: Vec<RowListing>,
+    pub(crate) changed_rows: Vec<RowChange>,
+}
+
+/// A stream whose contents differ, compared by SHA-256 hash.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct StreamChange {
+    pub(crate) name: String,
+    pub(crate) status: Status,
+    pub(crate) old_hash: Option<String>,
+    pub(crate) new_hash: Option<String>,
+}
+
+/// Every difference found between two packages.
+#[derive(Clone, Serialize)]
+pub(crate) struct PackageDiff {
+    pub(crate) summary: Vec<FieldChange>,
+    pub(crate) tables: Vec<TableDiff>,
+    pub(crate) streams: Vec<StreamChange>,
+}
+
+impl PackageDiff {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.summary.is_empty()
+            && self.tables.is_empty()
+            && self.streams.is_empty()
+    }
+
+    fn to_text(&self) -> String {
+        if self.is_empty() {
+            return "Packages are identical".to_owned();
+        }
+
+        let mut out = String::new();
+        if !self.summary.is_empty() {
+            let _ = writeln!(out, "Summary information:");
+            for change in &self.summary {
+                let _ = writeln!(out, "  ~ {}", format_field(change));
+            }
+        }
+        for table in &self.tables {
+            let _ = writeln!(out, "{} Table {}:", table.status, table.table);
+            for column in &table.columns {
+                let _ = writeln!(
+                    out,
+                    "  {} column {}",
+                    column.status, column.name
+                );
+            }
+            for row in &table.removed_rows {
+                let _ = writeln!(out, "  - {}", format_row(row));
+            }
+            for row in &table.added_rows {
+                let _ = writeln!(out, "  + {}", format_row(row));
+            }
+            for row in &table.changed_rows {
+                let _ = writeln!(out, "  ~ {}", format_row(&row.key));
+                for change in &row.changes {
+                    let _ = writeln!(out, "      {}", format_field(change));
+                }
+            }
+        }
+        if !self.streams.is_empty() {
+            let _ = writeln!(out, "Streams:");
+            for stream in &self.streams {
+                let _ = writeln!(out, "  {} {}", stream.status, stream.name);
+            }
+        }
+        out.trim_end().to_owned()
+    }
+}
+
+fn format_field(change: &FieldChange) -> String {
+    format!(
+        "{}: {} -> {}",
+        change.name,
+        change.old.as_deref().unwrap_or("<null>"),
+        change.new.as_deref().unwrap_or("<null>")
+    )
+}
+
+fn format_row(row: &RowListing) -> String {
+    row.0
+        .iter()
+        .map(|(column, value)| match value {
+            Some(v) => format!("{column}={v}"),
+            None => format!("{column}=<null>"),
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Compare two opened packages.
+pub(crate) fn diff_packages(
+    mut old: MemoryPackage,
+    mut new: MemoryPackage,
+) -> Result<PackageDiff> {
+    let mut old_summary = summary_properties(old.summary_info());
+    let mut new_summary = summary_properties(new.summary_info());
+
+    let old_tables: BTreeSet<String> =
+        old.tables().map(|t| t.name().to_owned()).collect();
+    let new_tables: BTreeSet<String> =
+        new.tables().map(|t| t.name().to_owned()).collect();
+
+    let mut tables = Vec::new();
+    for name in old_tables.union(&new_tables) {
+        debug!("Comparing table {}", name);
+        let diff = match (old_tables.contains(name), new_tables.contains(name))
+        {
+            (true, true) => diff_table(&mut old, &mut new, name)?,
+            (true, false) => whole_table(&mut old, name, Status::Removed)?,
+            _ => whole_table(&mut new, name, Status::Added)?,
+        };
+        if let Some(diff) = diff {
+            tables.push(diff);
+        }
+    }
+
+    let streams = diff_streams(&mut old, &mut new)?;
+    // Reading the summary information stream itself takes the package, so
+    // it is done last.
+    let old_properties = read_summary_properties(old)?;
+    let new_properties = read_summary_properties(new)?;
+    old_summary.extend(stream_summary_properties(old_properties));
+    new_summary.extend(stream_summary_properties(new_properties));
+    let summary = diff_fields(old_summary, new_summary);
+
+    Ok(PackageDiff {
+        summary,
+        tables,
+        streams,
+    })
+}
+
+/// Every summary information property the `msi` crate exposes, as display
+/// strings.
+pub(crate) fn summary_properties(
+    info: &SummaryInfo,
+) -> Vec<(&'static str, Option<String>)> {
+    let languages = info
+        .languages()
+        .iter()
+        .map(|l| l.tag().to_owned())
+        .collect::<Vec<String>>();
+    vec![
+        ("title", info.title().map(str::to_owned)),
+        ("subject", info.subject().map(str::to_owned)),
+        ("author", info.author().map(str::to_owned)),
+        ("comments", info.comments().map(str::to_owned)),
+        ("arch", info.arch().map(str::to_owned)),
+        ("languages", Some(languages.join(","))),
+        ("package_code", info.uuid().map(|u| u.to_string())),
+        (
+            "creating_application",
+            info.creating_application().map(str::to_owned),
+        ),
+        ("creation_time", info.creation_time().map(format_timestamp)),
+        ("word_count", info.word_count().map(|w| w.to_string())),
+        ("codepage", Some(info.codepage().name().to_owned())),
+    ]
+}
+
+/// The summary information properties that the `msi` crate doesn't expose,
+/// read from the summary information stream.
+fn stream_summary_properties(
+    properties: Option<PropertySet>,
+) -> Vec<(&'static str, Option<String>)> {
+    let properties = properties.unwrap_or_default();
+    vec![
+        ("keywords", properties.get_str(PID_KEYWORDS)),
+        (
+            "page_count",
+            properties.get_int(PID_PAGECOUNT).map(|p| p.to_string()),
+        ),
+        (
+            "security",
+            properties.get_int(PID_SECURITY).map(|s| s.to_string()),
+        ),
+    ]
+}
+
+fn diff_fields(
+    old: Vec<(&'static str, Option<String>)>,
+    new: Vec<(&'static str, Option<String>)>,
+) -> Vec<FieldChange> {
+    old.into_iter()
+        .zip(new)
+        .filter(|((_, o), (_, n))| o != n)
+        .map(|((name, old), (_, new))| FieldChange {
+            name: name.to_owned(),
+            old,
+            new,
+        })
+        .collect()
+}
+
+/// Report a table that only exists in one of the packages.
+fn whole_table<F: Read + Seek>(
+    msi: &mut Package<F>,
+    name: &str,
+    status: Status,
+) -> Result<Option<TableDiff>> {
+    let columns = table_columns(msi, name)
+        .into_iter()
+        .map(|c| ColumnChange {
+            status,
+            name: c.name.clone(),
+            old: None,
+            new: None,
+        })
+        .collect();
+    let rows = read_table(msi, name)?.rows;
+    let (added_rows, removed_rows) = match status {
+        Status::Removed => (Vec::new(), rows),
+        _ => (rows, Vec::new()),
+    };
+    Ok(Some(TableDiff {
+        table: name.to_owned(),
+        status,
+        columns,
+        added_rows,
+        removed_rows,
+        changed_rows: Vec::new(),
+    }))
+}
+
+/// Compare a table that exists in both packages.
+fn diff_table<F, G>(
+    old: &mut Package<F>,
+    new: &mut Package<G>,
+    name: &str,
+) -> Result<Option<TableDiff>>
+where
+    F: Read + Seek,
+    G: Read + Seek,
+{
+    let old_columns = table_columns(old, name);
+    let new_columns = table_columns(new, name);
+    let columns = diff_columns(&old_columns, &new_columns);
+
+    let old_rows = keyed_rows(old, name, &old_columns)?;
+    let new_rows = keyed_rows(new, name, &new_columns)?;
+
+    let mut added_rows = Vec::new();
+    let mut removed_rows = Vec::new();
+    let mut changed_rows = Vec::new();
+    for (key, row) in &old_rows {
+        match new_rows.get(key) {
+            None => removed_rows.push(row.clone()),
+            Some(new_row) => {
+                let changes = diff_row(row, new_row);
+                if !changes.is_empty() {
+                    changed_rows.push(RowChange {
+                        key: key_listing(row, &old_columns),
+                        changes,
+                    });
+                }
+            }
+        }
+    }
+    for (key, row) in &new_rows {
+        if !old_rows.contains_key(key) {
+            added_rows.push(row.clone());
+        }
+    }
+
+    if columns.is_empty()
+        && added_rows.is_empty()
+        && removed_rows.is_empty()
+        && changed_rows.is_empty()
+    {
+        return Ok(None);
+    }
+    Ok(Some(TableDiff {
+        table: name.to_owned(),
+        status: Status::Changed,
+        columns,
+        added_rows,
+        removed_rows,
+        changed_rows,
+    }))
+}
+
+fn table_columns<F: Read + Seek>(
+    msi: &Package<F>,
+    name: &str,
+) -> Vec<ColumnListing> {
+    msi.get_table(name)
+        .map(|t| t.columns().iter().map(ColumnListing::from).collect())
+        .unwrap_or_default()
+}
+
+fn diff_columns(
+    old: &[ColumnListing],
+    new: &[ColumnListing],
+) -> Vec<ColumnChange> {
+    let mut changes = Vec::new();
+    for column in old {
+        match new.iter().find(|c| c.name == column.name) {
+            None => changes.push(ColumnChange {
+                status: Status::Removed,
+                name: column.name.clone(),
+                old: Some(column.clone()),
+                new: None,
+            }),
+            Some(new_column) if new_column != column => {
+                changes.push(ColumnChange {
+                    status: Status::Changed,
+                    name: column.name.clone(),
+                    old: Some(column.clone()),
+                    new: Some(new_column.clone()),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    for column in new {
+        if !old.iter().any(|c| c.name == column.name) {
+            changes.push(ColumnChange {
+                status: Status::Added,
+                name: column.name.clone(),
+                old: None,
+                new: Some(column.clone()),
+            });
+        }
+    }
+    changes
+}
+
+/// Read every row of a table and index it by its primary key.
+///
+/// Tables without a primary key are keyed on every column, so any change to
+/// such a row shows up as one removed and one added row.
+fn keyed_rows<F: Read + Seek>(
+    msi: &mut Package<F>,
+    name: &str,
+    columns: &[ColumnListing],
+) -> Result<BTreeMap<RowKey, RowListing>> {
+    let rows = read_table(msi, name)?.rows;
+    Ok(rows
+        .into_iter()
+        .map(|row| (row_key(&row, columns), row))
+        .collect())
+}
+
+fn is_key_column(name: &str, columns: &[ColumnListing]) -> bool {
+    let has_key = columns.iter().any(|c| c.primary_key);
+    columns
+        .iter()
+        .any(|c| c.name == name && (c.primary_key || !has_key))
+}
+
+fn row_key(row: &RowListing, columns: &[ColumnListing]) -> RowKey {
+    row.0
+        .iter()
+        .filter(|(name, _)| is_key_column(name, columns))
+        .map(|(_, value)| value.clone())
+        .collect()
+}
+
+fn key_listing(row: &RowListing, columns: &[ColumnListing]) -> RowListing {
+    RowListing(
+        row.0
+            .iter()
+            .filter(|(name, _)| is_key_column(name, columns))
+            .cloned()
+            .collect(),
+    )
+}
+
+/// Compare the values of the columns present in both rows. Columns that only
+/// exist on one side are already reported as schema changes.
+fn diff_row(old: &RowListing, new: &RowListing) -> Vec<FieldChange> {
+    old.0
+        .iter()
+        .filter_map(|(name, old_value)| {
+            let (_, new_value) = new.0.iter().find(|(n, _)| n == name)?;
+            if old_value == new_value {
+                return None;
+            }
+            Some(FieldChange {
+                name: name.clone(),
+                old: old_value.as_ref().map(|v| v.to_string()),
+                new: new_value.as_ref().map(|v| v.to_string()),
+            })
+        })
+        .collect()
+}
+
+fn diff_streams<F, G>(
+    old: &mut Package<F>,
+    new: &mut Package<G>,
+) -> Result<Vec<StreamChange>>
+where
+    F: Read + Seek,
+    G: Read + Seek,
+{
+    let old_hashes = stream_hashes(old)?;
+    let new_hashes = stream_hashes(new)?;
+    let names: BTreeSet<&String> =
+        old_hashes.keys().chain(new_hashes.keys()).collect();
+
+    let mut changes = Vec::new();
+    for name in names {
+        let old_hash = old_hashes.get(name).cloned();
+        let new_hash = new_hashes.get(name).cloned();
+        let status = match (&old_hash, &new_hash) {
+            (Some(o), Some(n)) if o == n => continue,
+            (Some(_), Some(_)) => Status::Changed,
+            (Some(_), None) => Status::Removed,
+            _ => Status::Added,
+        };
+        changes.push(StreamChange {
+            name: name.clone(),
+            status,
+            old_hash,
+            new_hash,
+        });
+    }
+    Ok(changes)
+}
+
+/// Hash every non-table stream in the package.
+fn stream_hashes<F: Read + Seek>(
+    msi: &mut Package<F>,
+) -> Result<BTreeMap<String, String>> {
+    let names = msi.streams().collect::<Vec<String>>();
+    let mut hashes = BTreeMap::new();
+    for name in names {
+        let mut reader = msi
+            .read_stream(&name)
+            .with_context(|| format!("Failed to open stream {name}"))?;
+        let hash = sha256_hex(&mut reader)
+            .with_context(|| format!("Failed to read stream {name}"))?;
+        hashes.insert(name, hash);
+    }
+    Ok(hashes)
+}
+
+/// Hash the remaining contents of a reader with SHA-256.
+pub(crate) fn sha256_hex<R: Read>(reader: &mut R) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(reader, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use msi::{Column, Insert, Package, PackageType, Value};
+
+    use super::{diff_packages, Status};
+    use crate::command::{format::CellValue, transformer::MemoryPackage};
+
+    fn property(name: &str) -> (String, Option<CellValue>) {
+        ("Property".to_owned(), Some(CellValue::Str(name.to_owned())))
+    }
+
+    /// A package with a `Property` table holding `properties` and a stream
+    /// holding `logo`.
+    fn package(properties: &[(&str, &str)], logo: &[u8]) -> MemoryPackage {
+        let mut package =
+            Package::create(PackageType::Installer, Cursor::new(Vec::new()))
+                .unwrap();
+        package
+            .create_table(
+                "Property",
+                vec![
+                    Column::build("Property").primary_key().id_string(72),
+                    Column::build("Value").text_string(0),
+                ],
+            )
+            .unwrap();
+        let rows = properties
+            .iter()
+            .map(|(p, v)| vec![Value::from(*p), Value::from(*v)])
+            .collect();
+        package
+            .insert_rows(Insert::into("Property").rows(rows))
+            .unwrap();
+        package
+            .write_stream("Binary.Logo")
+            .unwrap()
+            .write_all(logo)
+            .unwrap();
+        package
+    }
+
+    #[test]
+    fn finds_changed_rows_and_streams() {
+        let old =
+            package(&[("Kept", "1"), ("Changed", "1"), ("Gone", "1")], b"old");
+        let new =
+            package(&[("Kept", "1"), ("Changed", "2"), ("New", "1")], b"new");
+        let diff = diff_packages(old, new).unwrap();
+
+        assert!(diff.summary.is_empty());
+        assert_eq!(diff.tables.len(), 1);
+        let table = &diff.tables[0];
+        assert_eq!(table.table, "Property");
+        assert_eq!(table.status, Status::Changed);
+        assert!(table.columns.is_empty());
+        assert_eq!(table.added_rows.len(), 1);
+        assert_eq!(table.added_rows[0].0[0], property("New"));
+        assert_eq!(table.removed_rows.len(), 1);
+        assert_eq!(table.removed_rows[0].0[0], property("Gone"));
+        assert_eq!(table.changed_rows.len(), 1);
+        let changed = &table.changed_rows[0];
+        assert_eq!(changed.key.0, [property("Changed")]);
+        assert_eq!(changed.changes[0].name, "Value");
+        assert_eq!(changed.changes[0].old.as_deref(), Some("1"));
+        assert_eq!(changed.changes[0].new.as_deref(), Some("2"));
+
+        assert_eq!(diff.streams.len(), 1);
+        assert_eq!(diff.streams[0].name, "Binary.Logo");
+        assert_eq!(diff.streams[0].status, Status::Changed);
+    }
+
+    #[test]
+    fn identical_packages_have_no_differences() {
+        let old = package(&[("Kept", "1")], b"logo");
+        let new = package(&[("Kept", "1")], b"logo");
+        assert!(diff_packages(old, new).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,209 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use cli_table::{Cell, CellStruct, Style, Table};
+use msi::Value;
+use serde::{ser::SerializeMap, Serialize, Serializer};
+
+/// The formats that `inspect` output can be written in.
+///
+/// `Table` is meant for humans reading the output in a terminal. The other
+/// formats are meant for scripts and follow the schema of the structure that
+/// is being rendered so they stay stable between releases.
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub(crate) enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+    Toml,
+}
+
+/// The formats that `diff` output can be written in.
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub(crate) enum DiffFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Anything that can be rendered as rows of text cells.
+///
+/// This is used for the `table` and `csv` output formats. The `json` and
+/// `toml` formats are rendered from the `Serialize` implementation instead.
+pub(crate) trait Tabular {
+    /// Names of the columns in the order they appear in each record.
+    fn headers(&self) -> Vec<String>;
+    /// Each row of the output as a list of cells.
+    fn records(&self) -> Vec<Vec<String>>;
+}
+
+/// Render the given listing in the requested format.
+pub(crate) fn render<T>(listing: &T, format: OutputFormat) -> Result<String>
+where
+    T: Tabular + Serialize,
+{
+    match format {
+        OutputFormat::Table => render_table(listing),
+        OutputFormat::Csv => render_csv(listing),
+        OutputFormat::Json => serde_json::to_string_pretty(listing)
+            .with_context(|| "Failed to serialize output to JSON"),
+        OutputFormat::Toml => toml::to_string_pretty(listing)
+            .with_context(|| "Failed to serialize output to TOML"),
+    }
+}
+
+fn render_table<T: Tabular>(listing: &T) -> Result<String> {
+    let contents: Vec<Vec<CellStruct>> = listing
+        .records()
+        .into_iter()
+        .map(|r| r.into_iter().map(|c| c.cell()).collect())
+        .collect();
+
+    let table = contents
+        .table()
+        .title(listing.headers().into_iter().map(|h| h.cell().bold(true)))
+        .bold(true);
+
+    Ok(table
+        .display()
+        .with_context(|| "Failed to display table")?
+        .to_string())
+}
+
+fn render_csv<T: Tabular>(listing: &T) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record(listing.headers())
+        .with_context(|| "Failed to write CSV header")?;
+    for record in listing.records() {
+        writer
+            .write_record(record)
+            .with_context(|| "Failed to write CSV record")?;
+    }
+    let bytes = writer
+        .into_inner()
+        .with_context(|| "Failed to flush CSV output")?;
+    String::from_utf8(bytes).with_context(|| "CSV output was not valid UTF-8")
+}
+
+/// A single value from an MSI table in a form that can be serialized.
+///
+/// `Null` values are represented by `None` wherever a `CellValue` is used so
+/// that formats without a null type, such as TOML, can skip them.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(untagged)]
+pub(crate) enum CellValue {
+    Int(i32),
+    Str(String),
+}
+
+impl CellValue {
+    pub(crate) fn from_value(value: &Value) -> Option<CellValue> {
+        match value {
+            Value::Null => None,
+            Value::Int(i) => Some(CellValue::Int(*i)),
+            Value::Str(s) => Some(CellValue::Str(s.clone())),
+        }
+    }
+}
+
+impl std::fmt::Display for CellValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CellValue::Int(i) => write!(f, "{}", i),
+            CellValue::Str(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+/// A row of an MSI table, serialized as a map from column name to value so
+/// the column order of the table is preserved.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RowListing(pub(crate) Vec<(String, Option<CellValue>)>);
+
+impl RowListing {
+    /// The cells of this row as display strings. Null values are empty.
+    pub(crate) fn cells(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .map(|(_, v)| v.as_ref().map(|v| v.to_string()).unwrap_or_default())
+            .collect()
+    }
+}
+
+impl Serialize for RowListing {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (column, value) in &self.0 {
+            map.serialize_entry(column, value)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, CellValue, OutputFormat, RowListing};
+    use crate::command::lister::ContentsListing;
+
+    fn row(name: &str, value: Option<CellValue>) -> RowListing {
+        RowListing(vec![
+            ("Name".to_owned(), Some(CellValue::Str(name.to_owned()))),
+            ("Value".to_owned(), value),
+        ])
+    }
+
+    fn listing(rows: Vec<RowListing>) -> ContentsListing {
+        ContentsListing {
+            table: "Things".to_owned(),
+            columns: vec!["Name".to_owned(), "Value".to_owned()],
+            rows,
+        }
+    }
+
+    #[test]
+    fn quotes_csv_cells() {
+        let listing = listing(vec![
+            row("a,b", Some(CellValue::Int(1))),
+            row("say \"hi\"", None),
+        ]);
+        assert_eq!(
+            render(&listing, OutputFormat::Csv).unwrap(),
+            "Name,Value\n\"a,b\",1\n\"say \"\"hi\"\"\",\n"
+        );
+    }
+
+    #[test]
+    fn writes_null_and_integer_cells_to_json() {
+        let listing = listing(vec![
+            row("Count", Some(CellValue::Int(3))),
+            row("Empty", None),
+        ]);
+        let json: serde_json::Value = serde_json::from_str(
+            &render(&listing, OutputFormat::Json).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            json["rows"],
+            serde_json::json!([
+                { "Name": "Count", "Value": 3 },
+                { "Name": "Empty", "Value": null },
+            ])
+        );
+    }
+
+    #[test]
+    fn writes_rows_to_toml() {
+        let listing = listing(vec![
+            row("Count", Some(CellValue::Int(3))),
+            row("Empty", None),
+        ]);
+        assert_eq!(
+            render(&listing, OutputFormat::Toml).unwrap(),
+            "table = \"Things\"\n\
+             columns = [\n    \"Name\",\n    \"Value\",\n]\n\n\
+             [[rows]]\nName = \"Count\"\nValue = 3\n\n\
+             [[rows]]\nName = \"Empty\"\n"
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use std::process::ExitCode;
+
+use clap::ValueEnum;
+use log::{error, info};
+
+use crate::modules::scaffold::{self, ScaffoldOptions};
+
+/// The platform a new project builds packages for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum Platform {
+    #[default]
+    X64,
+    Intel,
+    Arm64,
+}
+
+impl Platform {
+    /// The platform as written in the summary information template.
+    pub(crate) fn template(self) -> &'static str {
+        match self {
+            Platform::X64 => "x64",
+            Platform::Intel => "Intel",
+            Platform::Arm64 => "Arm64",
+        }
+    }
+
+    /// The Windows Installer version the platform needs, times 100.
+    pub(crate) fn page_count(self) -> u16 {
+        match self {
+            Platform::X64 | Platform::Intel => 200,
+            Platform::Arm64 => 500,
+        }
+    }
+}
+
+pub(crate) fn init(options: ScaffoldOptions) -> ExitCode {
+    info!("Creating a new project in {}", options.directory);
+    match scaffold::scaffold(options) {
+        Ok(scaffold) => {
+            info!(
+                "Wrote config to {}, build it with `whimsi build -c {} -i {} \
+                -o {}.msi`",
+                scaffold.config_path,
+                scaffold.config_path,
+                scaffold.input_directory,
+                scaffold.product_name
+            );
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            error!("Error while trying to create a project.\n{err:?}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,22 +1,46 @@
 use anyhow::bail;
 use anyhow::{Context, Result};
 use camino::Utf8PathBuf;
-use cli_table::{Cell, CellStruct, Style, Table};
 use flexstr::SharedStr;
 use log::debug;
 use log::error;
 use log::info;
-use msi::{Package, Select};
-use std::{fs::File, process::ExitCode};
+use msi::{Column, Package, Select};
+use serde::Serialize;
+use std::{
+    fs::File,
+    io::{self, Cursor, Read, Seek},
+    path::Path,
+    process::ExitCode,
+};
 
 use super::command_line::AllowedToList as ATL;
+use super::differ::sha256_hex;
+use super::format::{render, CellValue, OutputFormat, RowListing, Tabular};
+use super::transformer::{apply_file, open_in_memory, MemoryPackage};
+use crate::modules::{
+    helpers::{
+        property_set::{
+            PropertySet, PID_KEYWORDS, PID_PAGECOUNT, PID_SECURITY,
+        },
+        stream_name,
+        summary::{
+            describe_arch, describe_security, describe_word_count,
+            format_timestamp,
+        },
+    },
+    transform::SUMMARY_INFORMATION,
+};
 
-pub(crate) fn list(input_file: &Utf8PathBuf, list_item: ATL) -> ExitCode {
+pub(crate) fn list(
+    input_file: &Utf8PathBuf,
+    transform: Option<&Utf8PathBuf>,
+    list_item: ATL,
+    format: OutputFormat,
+) -> ExitCode {
     info!("Reading MSI {}", input_file);
 
-    let mut msi = match msi::open_rw(input_file)
-        .with_context(|| "Failed to open {input_file}")
-    {
+    let mut msi = match open_package(input_file, transform) {
         Ok(msi) => msi,
         Err(err) => {
             error!("Failed to read MSI {input_file}.\n{err}");
@@ -25,49 +49,468 @@ pub(crate) fn list(input_file: &Utf8PathBuf, list_item: ATL) -> ExitCode {
     };
 
     let ret = match list_item {
-        ATL::Author => list_author(msi),
-        ATL::Tables => list_tables(msi),
-        ATL::TableColumns { table } => list_table_columns(msi, table),
-        ATL::TableContents { table } => list_table_contents(&mut msi, table),
+        ATL::Author => list_author(msi).and_then(|l| render(&l, format)),
+        ATL::Summary => list_summary(msi).and_then(|l| render(&l, format)),
+        ATL::Tables => list_tables(msi).and_then(|l| render(&l, format)),
+        ATL::TableColumns { table } => {
+            list_table_columns(msi, table).and_then(|l| render(&l, format))
+        }
+        ATL::TableContents { table } => list_table_contents(&mut msi, table)
+            .and_then(|l| render(&l, format)),
+        ATL::Streams => into_compound_file(msi)
+            .and_then(|mut comp| list_streams(&mut comp))
+            .and_then(|l| render(&l, format)),
+        ATL::Stream { name, out } => into_compound_file(msi)
+            .and_then(|mut comp| dump_stream(&mut comp, &name, &out))
+            .and_then(|l| render(&l, format)),
     };
-    if let Err(err) = ret {
-        error!("Error while trying to inspect MSI.\n{err}");
-        return ExitCode::FAILURE;
-    } else {
-        return ExitCode::SUCCESS;
+    match ret {
+        Ok(output) => {
+            println!("{output}");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            error!("Error while trying to inspect MSI.\n{err}");
+            ExitCode::FAILURE
+        }
     }
 }
 
-pub(crate) fn validate_paths(input_file: &Utf8PathBuf) -> Result<()> {
-    let err_msg = if !input_file.exists() {
-        Some(format!("Input file {} does not exist", input_file))
-    } else if !input_file.is_file() {
-        Some(format!("Input file {} is not a file", input_file))
-    } else {
-        None
-    };
+/// Open the MSI in memory and apply the transform, if one was given, so the
+/// MSI on disk is never changed.
+fn open_package(
+    input_file: &Utf8PathBuf,
+    transform: Option<&Utf8PathBuf>,
+) -> Result<MemoryPackage> {
+    let mut msi = open_in_memory(input_file)?;
+    if let Some(transform) = transform {
+        info!("Applying transform {}", transform);
+        apply_file(&mut msi, transform)?;
+    }
+    Ok(msi)
+}
+
+/// The compound file backing the package, including any changes made to it.
+fn into_compound_file(
+    msi: MemoryPackage,
+) -> Result<cfb::CompoundFile<Cursor<Vec<u8>>>> {
+    let cursor = msi
+        .into_inner()
+        .with_context(|| "Failed to finish writing changes to the MSI")?;
+    cfb::CompoundFile::open(cursor)
+        .with_context(|| "Failed to open the MSI as a compound file")
+}
+
+/// The author listed in the summary information of the MSI.
+#[derive(Serialize)]
+pub(crate) struct AuthorListing {
+    author: String,
+}
+
+impl Tabular for AuthorListing {
+    fn headers(&self) -> Vec<String> {
+        vec!["Author".to_owned()]
+    }
+
+    fn records(&self) -> Vec<Vec<String>> {
+        vec![vec![self.author.clone()]]
+    }
+}
+
+/// A language listed in the template of the MSI.
+#[derive(Serialize)]
+pub(crate) struct LanguageListing {
+    code: u16,
+    tag: String,
+}
+
+/// The platform and languages the MSI supports.
+#[derive(Serialize)]
+pub(crate) struct TemplateListing {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    arch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    platform: Option<String>,
+    languages: Vec<LanguageListing>,
+}
+
+/// The raw value of the word count along with what each bit means.
+#[derive(Serialize)]
+pub(crate) struct WordCountListing {
+    value: i32,
+    meanings: Vec<String>,
+}
+
+/// The raw value of the security property along with what it means.
+#[derive(Serialize)]
+pub(crate) struct SecurityListing {
+    value: i32,
+    meaning: String,
+}
+
+/// The codepage used for strings in the summary information.
+#[derive(Serialize)]
+pub(crate) struct CodePageListing {
+    id: i32,
+    name: String,
+}
+
+/// Every summary information property of the MSI.
+///
+/// The `msi` crate doesn't expose the keywords, page count and security
+/// properties, so they are read from the summary information stream itself.
+#[derive(Serialize)]
+pub(crate) struct SummaryListing {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    subject: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keywords: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comments: Option<String>,
+    template: TemplateListing,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    creating_application: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    creation_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page_count: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    word_count: Option<WordCountListing>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    security: Option<SecurityListing>,
+    codepage: CodePageListing,
+}
+
+impl Tabular for SummaryListing {
+    fn headers(&self) -> Vec<String> {
+        vec!["Property".to_owned(), "Value".to_owned()]
+    }
+
+    fn records(&self) -> Vec<Vec<String>> {
+        let template = &self.template;
+        let arch = match (&template.arch, &template.platform) {
+            (Some(arch), Some(platform)) => format!("{arch} ({platform})"),
+            _ => String::new(),
+        };
+        let languages = template
+            .languages
+            .iter()
+            .map(|l| format!("{} ({})", l.code, l.tag))
+            .collect::<Vec<String>>()
+            .join(", ");
+        let word_count = match &self.word_count {
+            Some(w) => format!("{} ({})", w.value, w.meanings.join(", ")),
+            None => String::new(),
+        };
+        let security = match &self.security {
+            Some(s) => format!("{} ({})", s.value, s.meaning),
+            None => String::new(),
+        };
+
+        [
+            ("Title", self.title.clone().unwrap_or_default()),
+            ("Subject", self.subject.clone().unwrap_or_default()),
+            ("Author", self.author.clone().unwrap_or_default()),
+            ("Keywords", self.keywords.clone().unwrap_or_default()),
+            ("Comments", self.comments.clone().unwrap_or_default()),
+            ("Template Platform", arch),
+            ("Template Languages", languages),
+            ("Package Code", self.package_code.clone().unwrap_or_default()),
+            (
+                "Creating Application",
+                self.creating_application.clone().unwrap_or_default(),
+            ),
+            (
+                "Creation Time",
+                self.creation_time.clone().unwrap_or_default(),
+            ),
+            (
+                "Page Count",
+                self.page_count.map(|p| p.to_string()).unwrap_or_default(),
+            ),
+            ("Word Count", word_count),
+            ("Security", security),
+            (
+                "Codepage",
+                format!("{} ({})", self.codepage.id, self.codepage.name),
+            ),
+        ]
+        .into_iter()
+        .map(|(property, value)| vec![property.to_owned(), value])
+        .collect()
+    }
+}
+
+/// The names of every table present in the MSI.
+#[derive(Serialize)]
+pub(crate) struct TablesListing {
+    tables: Vec<String>,
+}
+
+impl Tabular for TablesListing {
+    fn headers(&self) -> Vec<String> {
+        vec!["Table".to_owned()]
+    }
+
+    fn records(&self) -> Vec<Vec<String>> {
+        self.tables.iter().map(|t| vec![t.clone()]).collect()
+    }
+}
+
+/// The definition of a single column in an MSI table.
+#[derive(Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct ColumnListing {
+    pub(crate) name: String,
+    #[serde(rename = "type")]
+    pub(crate) coltype: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) category: Option<String>,
+    pub(crate) nullable: bool,
+    pub(crate) primary_key: bool,
+}
+
+impl From<&Column> for ColumnListing {
+    fn from(column: &Column) -> Self {
+        ColumnListing {
+            name: column.name().to_owned(),
+            coltype: column.coltype().to_string(),
+            category: column.category().map(|c| format!("{:?}", c)),
+            nullable: column.is_nullable(),
+            primary_key: column.is_primary_key(),
+        }
+    }
+}
+
+/// The columns that make up an MSI table.
+#[derive(Serialize)]
+pub(crate) struct ColumnsListing {
+    table: String,
+    columns: Vec<ColumnListing>,
+}
+
+impl Tabular for ColumnsListing {
+    fn headers(&self) -> Vec<String> {
+        ["Column", "Type", "Category", "Nullable", "Primary Key"]
+            .iter()
+            .map(|h| h.to_string())
+            .collect()
+    }
+
+    fn records(&self) -> Vec<Vec<String>> {
+        self.columns
+            .iter()
+            .map(|c| {
+                vec![
+                    c.name.clone(),
+                    c.coltype.clone(),
+                    c.category.clone().unwrap_or_default(),
+                    c.nullable.to_string(),
+                    c.primary_key.to_string(),
+                ]
+            })
+            .collect()
+    }
+}
+
+/// The rows stored in an MSI table.
+#[derive(Serialize)]
+pub(crate) struct ContentsListing {
+    pub(crate) table: String,
+    pub(crate) columns: Vec<String>,
+    pub(crate) rows: Vec<RowListing>,
+}
+
+impl Tabular for ContentsListing {
+    fn headers(&self) -> Vec<String> {
+        self.columns.clone()
+    }
+
+    fn records(&self) -> Vec<Vec<String>> {
+        self.rows.iter().map(|r| r.cells()).collect()
+    }
+}
 
-    err_msg.with_context(|| format!("Failed to validate paths."))?;
-    Ok(())
+/// What kind of entry in the compound file a stream listing describes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum StreamKind {
+    /// A stream holding the rows of a database table.
+    Table,
+    /// A stream such as `Binary.*`, `Icon.*` or an embedded cabinet.
+    Stream,
+    /// A stream that is not named by Windows Installer, such as the summary
+    /// information or a digital signature.
+    Special,
+    /// A sub-storage, such as an embedded transform.
+    Storage,
 }
 
-fn list_author(msi: Package<File>) -> Result<String> {
+impl std::fmt::Display for StreamKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            StreamKind::Table => "table",
+            StreamKind::Stream => "stream",
+            StreamKind::Special => "special",
+            StreamKind::Storage => "storage",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+/// A single entry in the compound file that backs the MSI.
+#[derive(Serialize)]
+pub(crate) struct StreamListing {
+    /// The decoded path of the entry, with storages separated by `/`.
+    name: String,
+    /// The path of the entry as it is stored in the compound file.
+    raw_name: String,
+    kind: StreamKind,
+    size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+}
+
+/// Every stream and storage inside the MSI.
+#[derive(Serialize)]
+pub(crate) struct StreamsListing {
+    streams: Vec<StreamListing>,
+}
+
+impl Tabular for StreamsListing {
+    fn headers(&self) -> Vec<String> {
+        ["Name", "Kind", "Size", "SHA-256"]
+            .iter()
+            .map(|h| h.to_string())
+            .collect()
+    }
+
+    fn records(&self) -> Vec<Vec<String>> {
+        self.streams
+            .iter()
+            .map(|s| {
+                vec![
+                    s.name.clone(),
+                    s.kind.to_string(),
+                    s.size.to_string(),
+                    s.sha256.clone().unwrap_or_default(),
+                ]
+            })
+            .collect()
+    }
+}
+
+/// The result of writing a stream out to a file.
+#[derive(Serialize)]
+pub(crate) struct StreamDumpListing {
+    name: String,
+    out: String,
+    size: u64,
+}
+
+impl Tabular for StreamDumpListing {
+    fn headers(&self) -> Vec<String> {
+        ["Name", "Output", "Size"]
+            .iter()
+            .map(|h| h.to_string())
+            .collect()
+    }
+
+    fn records(&self) -> Vec<Vec<String>> {
+        vec![vec![self.name.clone(), self.out.clone(), self.size.to_string()]]
+    }
+}
+
+fn list_author(msi: MemoryPackage) -> Result<AuthorListing> {
     debug!("Listing author of MSI");
     let author = msi
         .summary_info()
         .author()
-        .with_context(|| format!("Couldn't find author in MSI"))?;
-    Ok(author.to_owned())
+        .context("Couldn't find author in MSI")?;
+    Ok(AuthorListing {
+        author: author.to_owned(),
+    })
 }
 
-fn list_tables(msi: Package<File>) -> Result<String> {
+fn list_summary(msi: MemoryPackage) -> Result<SummaryListing> {
+    debug!("Listing summary information of MSI");
+    let info = msi.summary_info();
+    let arch = info.arch().map(str::to_owned);
+    let template = TemplateListing {
+        platform: arch.as_deref().map(|a| describe_arch(a).to_owned()),
+        arch,
+        languages: info
+            .languages()
+            .iter()
+            .map(|l| LanguageListing {
+                code: l.code(),
+                tag: l.tag().to_owned(),
+            })
+            .collect(),
+    };
+    let mut listing = SummaryListing {
+        title: info.title().map(str::to_owned),
+        subject: info.subject().map(str::to_owned),
+        author: info.author().map(str::to_owned),
+        keywords: None,
+        comments: info.comments().map(str::to_owned),
+        template,
+        package_code: info.uuid().map(|u| u.hyphenated().to_string()),
+        creating_application: info.creating_application().map(str::to_owned),
+        creation_time: info.creation_time().map(format_timestamp),
+        page_count: None,
+        word_count: info.word_count().map(|w| WordCountListing {
+            value: w,
+            meanings: describe_word_count(w),
+        }),
+        security: None,
+        codepage: CodePageListing {
+            id: info.codepage().id(),
+            name: info.codepage().name().to_owned(),
+        },
+    };
+
+    if let Some(properties) = read_summary_properties(msi)? {
+        listing.keywords = properties.get_str(PID_KEYWORDS);
+        listing.page_count = properties.get_int(PID_PAGECOUNT);
+        listing.security =
+            properties.get_int(PID_SECURITY).map(|s| SecurityListing {
+                value: s,
+                meaning: describe_security(s).to_owned(),
+            });
+    }
+    Ok(listing)
+}
+
+/// Read the summary information stream itself, for the properties that the
+/// `msi` crate doesn't expose. `None` if the stream can't be parsed.
+pub(crate) fn read_summary_properties(
+    msi: MemoryPackage,
+) -> Result<Option<PropertySet>> {
+    let mut bytes = Vec::new();
+    into_compound_file(msi)?
+        .open_stream(format!("/{SUMMARY_INFORMATION}"))
+        .and_then(|mut stream| stream.read_to_end(&mut bytes))
+        .context("Failed to read the summary information stream")?;
+    Ok(PropertySet::read(&bytes))
+}
+
+fn list_tables(msi: MemoryPackage) -> Result<TablesListing> {
     debug!("Listing tables in MSI");
-    let tables = msi.tables().map(|t| t.name()).collect::<Vec<&str>>();
-    Ok(tables.join("\n"))
+    let tables = msi.tables().map(|t| t.name().to_owned()).collect();
+    Ok(TablesListing { tables })
 }
 
 /// List the columns present in the given table
-fn list_table_columns(msi: Package<File>, table: SharedStr) -> Result<String> {
+fn list_table_columns(
+    msi: MemoryPackage,
+    table: SharedStr,
+) -> Result<ColumnsListing> {
     debug!("Listing the columns of table {} in MSI", table);
     let table = match msi.get_table(&table) {
         Some(table) => table,
@@ -76,44 +519,41 @@ fn list_table_columns(msi: Package<File>, table: SharedStr) -> Result<String> {
         }
     };
 
-    let columns = table.columns();
+    let columns = table.columns().iter().map(ColumnListing::from).collect();
 
-    let contents: Vec<Vec<CellStruct>> = columns
-        .iter()
-        .map(|c| {
-            vec![
-                c.name().cell(),
-                c.coltype().to_string().cell(),
-                format!("{:?}", c.category()).cell(),
-            ]
-        })
-        .collect();
-
-    let table_columns = ["Column", "Type", "Category"];
-
-    let print_table = contents
-        .table()
-        .title(table_columns.iter().map(|c| c.cell().bold(true)))
-        .bold(true);
-
-    Ok(print_table
-        .display()
-        .expect("Failed to display table")
-        .to_string())
+    Ok(ColumnsListing {
+        table: table.name().to_owned(),
+        columns,
+    })
 }
 
 /// List the contents of the given table
 fn list_table_contents(
-    msi: &mut Package<File>,
+    msi: &mut MemoryPackage,
     table_name: SharedStr,
-) -> Result<String> {
+) -> Result<ContentsListing> {
     debug!("Listing the contents of table {} in MSI", table_name);
+    read_table(msi, &table_name)
+}
 
+/// Read every row of the given table.
+pub(crate) fn read_table<F: Read + Seek>(
+    msi: &mut Package<F>,
+    table_name: &str,
+) -> Result<ContentsListing> {
+    read_select(msi, Select::table(table_name.to_string()), table_name)
+}
+
+/// Read every row matched by the given selection. `label` names the
+/// selection in the output, which is the table name for whole tables.
+pub(crate) fn read_select<F: Read + Seek>(
+    msi: &mut Package<F>,
+    select: Select,
+    label: &str,
+) -> Result<ContentsListing> {
     let rows = msi
-        .select_rows(Select::table(table_name.to_string()))
-        .with_context(|| {
-            format!("Failed to get rows from table {table_name}")
-        })?;
+        .select_rows(select)
+        .with_context(|| format!("Failed to get rows from {label}"))?;
 
     let columns = rows
         .columns()
@@ -121,22 +561,186 @@ fn list_table_contents(
         .map(|c| c.name().to_string())
         .collect::<Vec<String>>();
 
-    let contents: Vec<Vec<CellStruct>> = rows
+    let rows = rows
         .map(|r| {
-            columns
-                .iter()
-                .map(|c| r[c.as_str()].to_string().cell())
-                .collect()
+            RowListing(
+                columns
+                    .iter()
+                    .map(|c| {
+                        (c.clone(), CellValue::from_value(&r[c.as_str()]))
+                    })
+                    .collect(),
+            )
         })
         .collect();
 
-    let table = contents
-        .table()
-        .title(columns.iter().map(|c| c.cell().bold(true)))
-        .bold(true);
+    Ok(ContentsListing {
+        table: label.to_string(),
+        columns,
+        rows,
+    })
+}
+
+/// Printable form of a raw compound file name. Names such as
+/// `\u{5}SummaryInformation` start with a control character.
+fn printable_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            c if c.is_control() => format!("\\{:03o}", c as u32),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Decode every component of a compound file path into the names Windows
+/// Installer uses for them.
+fn decode_path(path: &Path) -> (String, String, bool) {
+    let mut decoded = Vec::new();
+    let mut raw = Vec::new();
+    let mut is_table = false;
+    for component in path.iter().filter(|c| *c != "/") {
+        let component = component.to_string_lossy();
+        let (name, table) = stream_name::decode(&component);
+        decoded.push(printable_name(&name));
+        raw.push(printable_name(&component));
+        is_table = table;
+    }
+    (decoded.join("/"), raw.join("/"), is_table)
+}
+
+/// List every stream and storage in the compound file of the MSI.
+fn list_streams<F: Read + Seek>(
+    comp: &mut cfb::CompoundFile<F>,
+) -> Result<StreamsListing> {
+    debug!("Listing the streams in MSI");
+    let entries = comp
+        .walk()
+        .filter(|e| !e.is_root())
+        .map(|e| (e.path().to_path_buf(), e.is_storage(), e.len()))
+        .collect::<Vec<_>>();
+
+    let mut streams = Vec::new();
+    for (path, is_storage, size) in entries {
+        let (name, raw_name, is_table) = decode_path(&path);
+        let kind = if is_storage {
+            StreamKind::Storage
+        } else if is_table {
+            StreamKind::Table
+        } else if name.rsplit('/').next().is_some_and(|n| n.starts_with('\\')) {
+            // Streams such as the summary information of an embedded
+            // transform are special wherever they are.
+            StreamKind::Special
+        } else {
+            StreamKind::Stream
+        };
+        let sha256 = if is_storage {
+            None
+        } else {
+            let mut stream = comp
+                .open_stream(&path)
+                .with_context(|| format!("Failed to open stream {name}"))?;
+            Some(
+                sha256_hex(&mut stream)
+                    .with_context(|| format!("Failed to read stream {name}"))?,
+            )
+        };
+        streams.push(StreamListing {
+            name,
+            raw_name,
+            kind,
+            size,
+            sha256,
+        });
+    }
+
+    Ok(StreamsListing { streams })
+}
+
+/// Write the raw contents of the stream with the given name to `out`.
+///
+/// The name is matched against both the decoded and the raw path of every
+/// stream in the compound file.
+fn dump_stream<F: Read + Seek>(
+    comp: &mut cfb::CompoundFile<F>,
+    name: &str,
+    out: &Utf8PathBuf,
+) -> Result<StreamDumpListing> {
+    debug!("Writing stream {} to {}", name, out);
+    let matches = comp
+        .walk()
+        .filter(|e| e.is_stream())
+        .filter(|e| {
+            let (decoded, raw, _) = decode_path(e.path());
+            decoded == name || raw == name
+        })
+        .map(|e| e.path().to_path_buf())
+        .collect::<Vec<_>>();
+    let path = match matches.as_slice() {
+        [path] => path.clone(),
+        [] => bail!("Stream {} could not be found in MSI", name),
+        _ => bail!("Stream name {} matches more than one stream", name),
+    };
 
-    Ok(table
-        .display()
-        .with_context(|| format!("Failed to display table {table_name}"))?
-        .to_string())
+    let mut stream = comp
+        .open_stream(&path)
+        .with_context(|| format!("Failed to open stream {name}"))?;
+    let mut file = File::create(out)
+        .with_context(|| format!("Failed to create output file {out}"))?;
+    let size = io::copy(&mut stream, &mut file)
+        .with_context(|| format!("Failed to write stream {name} to {out}"))?;
+    info!("Wrote {} bytes from stream {} to {}", size, name, out);
+
+    Ok(StreamDumpListing {
+        name: name.to_owned(),
+        out: out.to_string(),
+        size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use super::{list_streams, StreamKind};
+    use crate::modules::helpers::stream_name;
+
+    #[test]
+    fn classifies_streams_in_embedded_storages() {
+        let mut comp =
+            cfb::CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+        let table = format!("/{}", stream_name::encode("File", true));
+        let binary = format!("/{}", stream_name::encode("Binary.Logo", false));
+        for path in [
+            table.as_str(),
+            binary.as_str(),
+            "/\u{5}SummaryInformation",
+            "/1031/\u{5}SummaryInformation",
+        ] {
+            if path.starts_with("/1031") {
+                comp.create_storage_all("/1031").unwrap();
+            }
+            comp.create_stream(path)
+                .unwrap()
+                .write_all(b"data")
+                .unwrap();
+        }
+
+        let listing = list_streams(&mut comp).unwrap();
+        let mut kinds = listing
+            .streams
+            .iter()
+            .map(|s| (s.name.as_str(), s.kind))
+            .collect::<Vec<_>>();
+        kinds.sort_by_key(|(name, _)| *name);
+        assert_eq!(
+            kinds,
+            [
+                ("1031", StreamKind::Storage),
+                ("1031/\\005SummaryInformation", StreamKind::Special),
+                ("Binary.Logo", StreamKind::Stream),
+                ("File", StreamKind::Table),
+                ("\\005SummaryInformation", StreamKind::Special),
+            ]
+        );
+    }
 }
@@ -1,4 +1,14 @@
 pub(crate) mod builder;
 pub(crate) mod command_line;
+pub(crate) mod configurer;
+pub(crate) mod decompiler;
+pub(crate) mod differ;
+pub(crate) mod format;
+pub(crate) mod initializer;
 pub(crate) mod lister;
+pub(crate) mod patcher;
+pub(crate) mod querier;
+pub(crate) mod signer;
+pub(crate) mod transformer;
+pub(crate) mod validator;
 
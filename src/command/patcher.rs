@@ -0,0 +1,40 @@
+use std::{fs, process::ExitCode};
+
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use log::{error, info};
+
+use crate::modules::patch::{self, PatchOptions};
+
+pub(crate) fn patch(
+    baseline: &Utf8PathBuf,
+    upgraded: &Utf8PathBuf,
+    output: &Utf8PathBuf,
+    options: PatchOptions,
+) -> ExitCode {
+    match create_patch(baseline, upgraded, output, &options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            error!("Error while trying to create patch.\n{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn create_patch(
+    baseline: &Utf8PathBuf,
+    upgraded: &Utf8PathBuf,
+    output: &Utf8PathBuf,
+    options: &PatchOptions,
+) -> Result<()> {
+    info!("Creating patch from {} to {}", baseline, upgraded);
+    let baseline_bytes = fs::read(baseline)
+        .with_context(|| format!("Failed to read {baseline}"))?;
+    let upgraded_bytes = fs::read(upgraded)
+        .with_context(|| format!("Failed to read {upgraded}"))?;
+    let bytes = patch::create(&baseline_bytes, &upgraded_bytes, options)?;
+    fs::write(output, bytes)
+        .with_context(|| format!("Failed to write patch to {output}"))?;
+    info!("Wrote patch to {}", output);
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use std::{cmp::Ordering, process::ExitCode};
+
+use anyhow::{bail, Context, Result};
+use camino::Utf8PathBuf;
+use log::{debug, error, info};
+
+use super::format::{render, OutputFormat};
+use super::lister::{read_select, ContentsListing};
+use crate::modules::query::{self, parser::OrderBy};
+
+pub(crate) fn query(
+    input_file: &Utf8PathBuf,
+    sql: &str,
+    format: OutputFormat,
+) -> ExitCode {
+    info!("Querying MSI {}", input_file);
+
+    let ret = run_query(input_file, sql).and_then(|l| render(&l, format));
+    match ret {
+        Ok(output) => {
+            println!("{output}");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            error!("Error while trying to query MSI.\n{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_query(input_file: &Utf8PathBuf, sql: &str) -> Result<ContentsListing> {
+    let (select, parsed) = query::compile(sql)?;
+    debug!("Running query {:?}", parsed);
+
+    let mut msi = msi::open(input_file)
+        .with_context(|| format!("Failed to open {input_file}"))?;
+    let mut listing = read_select(&mut msi, select, sql)?;
+
+    // The `msi` crate has no notion of ordering so sort the rows here.
+    let mut sort_keys = Vec::new();
+    for order in &parsed.order_by {
+        let Some(index) = column_index(&listing.columns, order, &parsed.table)
+        else {
+            bail!("ORDER BY column {} is not in the result", order.column);
+        };
+        sort_keys.push((index, order.descending));
+    }
+    listing.rows.sort_by(|a, b| {
+        sort_keys
+            .iter()
+            .map(|(index, descending)| {
+                let ordering = a.0[*index].1.cmp(&b.0[*index].1);
+                if *descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            })
+            .find(|o| *o != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    });
+
+    Ok(listing)
+}
+
+/// Find the result column an `ORDER BY` refers to. Joined selections name
+/// their columns `Table.Column`, so both qualified and unqualified names are
+/// accepted as long as they are unambiguous.
+fn column_index(
+    columns: &[String],
+    order: &OrderBy,
+    table: &str,
+) -> Option<usize> {
+    if let Some(index) = columns.iter().position(|c| *c == order.column) {
+        return Some(index);
+    }
+    let matches = columns
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| {
+            c.rsplit_once('.').is_some_and(|(_, n)| n == order.column)
+                || format!("{table}.{c}") == order.column
+        })
+        .map(|(i, _)| i)
+        .collect::<Vec<usize>>();
+    match matches.as_slice() {
+        [index] => Some(*index),
+        _ => None,
+    }
+}
@@ -0,0 +1,62 @@
+use std::{fs, process::ExitCode};
+
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use log::{error, info};
+
+use crate::modules::signing::{self, SigningOptions};
+
+pub(crate) fn sign(
+    input_file: &Utf8PathBuf,
+    output: Option<&Utf8PathBuf>,
+    options: SigningOptions,
+) -> ExitCode {
+    match sign_file(input_file, output.unwrap_or(input_file), &options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            error!("Error while trying to sign MSI.\n{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn sign_file(
+    input_file: &Utf8PathBuf,
+    output: &Utf8PathBuf,
+    options: &SigningOptions,
+) -> Result<()> {
+    info!("Signing {}", input_file);
+    let bytes = fs::read(input_file)
+        .with_context(|| format!("Failed to read {input_file}"))?;
+    let signed = signing::sign(bytes, options)?;
+    fs::write(output, signed)
+        .with_context(|| format!("Failed to write signed MSI to {output}"))?;
+    info!("Wrote signed MSI to {}", output);
+    Ok(())
+}
+
+pub(crate) fn verify(input_file: &Utf8PathBuf) -> ExitCode {
+    info!("Verifying signature of {}", input_file);
+    let verified = fs::read(input_file)
+        .with_context(|| format!("Failed to read {input_file}"))
+        .and_then(signing::verify);
+    match verified {
+        Ok(verified) => {
+            info!(
+                "Signature is valid. Signed by {}, issued by {}",
+                verified.signer, verified.issuer
+            );
+            if verified.extended {
+                info!("Stream metadata is covered by the signature");
+            }
+            if verified.timestamped {
+                info!("Signature is timestamped");
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            error!("Signature of {} is not valid.\n{err}", input_file);
+            ExitCode::FAILURE
+        }
+    }
+}
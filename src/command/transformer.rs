@@ -0,0 +1,90 @@
+use std::{fs, io::Cursor, process::ExitCode};
+
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use log::{error, info};
+use msi::Package;
+
+use super::command_line::TransformAction;
+use crate::modules::transform;
+
+/// A package read fully into memory so it can be changed without touching
+/// the file it came from.
+pub(crate) type MemoryPackage = Package<Cursor<Vec<u8>>>;
+
+pub(crate) fn transform(action: TransformAction) -> ExitCode {
+    let ret = match action {
+        TransformAction::Create {
+            base,
+            modified,
+            output,
+        } => create(&base, &modified, &output),
+        TransformAction::Apply {
+            base,
+            transform,
+            output,
+        } => apply(&base, &transform, &output),
+    };
+    match ret {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            error!("Error while trying to transform MSI.\n{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn create(
+    base: &Utf8PathBuf,
+    modified: &Utf8PathBuf,
+    output: &Utf8PathBuf,
+) -> Result<()> {
+    info!("Creating transform from {} to {}", base, modified);
+    let mut base_msi =
+        msi::open(base).with_context(|| format!("Failed to open {base}"))?;
+    let mut modified_msi = msi::open(modified)
+        .with_context(|| format!("Failed to open {modified}"))?;
+    let bytes = transform::create(&mut base_msi, &mut modified_msi)?;
+    fs::write(output, bytes)
+        .with_context(|| format!("Failed to write transform to {output}"))?;
+    info!("Wrote transform to {}", output);
+    Ok(())
+}
+
+fn apply(
+    base: &Utf8PathBuf,
+    transform_file: &Utf8PathBuf,
+    output: &Utf8PathBuf,
+) -> Result<()> {
+    info!("Applying transform {} to {}", transform_file, base);
+    let mut package = open_in_memory(base)?;
+    apply_file(&mut package, transform_file)?;
+    let bytes = package
+        .into_inner()
+        .with_context(|| "Failed to finish writing the transformed MSI")?
+        .into_inner();
+    fs::write(output, bytes)
+        .with_context(|| format!("Failed to write MSI to {output}"))?;
+    info!("Wrote transformed MSI to {}", output);
+    Ok(())
+}
+
+/// Open a package from a copy of its bytes.
+pub(crate) fn open_in_memory(path: &Utf8PathBuf) -> Result<MemoryPackage> {
+    let bytes =
+        fs::read(path).with_context(|| format!("Failed to read {path}"))?;
+    Package::open(Cursor::new(bytes))
+        .with_context(|| format!("Failed to open {path}"))
+}
+
+/// Apply the transform stored at `transform_file` to `package`.
+pub(crate) fn apply_file(
+    package: &mut MemoryPackage,
+    transform_file: &Utf8PathBuf,
+) -> Result<()> {
+    let mut comp = cfb::open(transform_file).with_context(|| {
+        format!("Failed to open transform {transform_file}")
+    })?;
+    transform::apply(package, &mut comp)
+        .with_context(|| format!("Failed to apply transform {transform_file}"))
+}
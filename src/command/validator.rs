@@ -0,0 +1,75 @@
+use std::process::ExitCode;
+
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use log::{error, info};
+use serde::Serialize;
+
+use super::format::{render, OutputFormat, Tabular};
+use crate::modules::validation::{self, Finding};
+
+pub(crate) fn validate(
+    input_file: &Utf8PathBuf,
+    format: OutputFormat,
+) -> ExitCode {
+    info!("Validating MSI {}", input_file);
+
+    let findings = match validate_file(input_file) {
+        Ok(findings) => findings,
+        Err(err) => {
+            error!("Error while trying to validate MSI.\n{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let failed = validation::has_errors(&findings);
+    let listing = FindingsListing { findings };
+    match render(&listing, format) {
+        Ok(output) => println!("{output}"),
+        Err(err) => {
+            error!("Error while trying to display findings.\n{err}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if failed {
+        error!("MSI {} failed validation", input_file);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn validate_file(input_file: &Utf8PathBuf) -> Result<Vec<Finding>> {
+    let mut msi = msi::open(input_file)
+        .with_context(|| format!("Failed to open {input_file}"))?;
+    validation::validate(&mut msi)
+}
+
+/// Every finding produced while validating an MSI.
+#[derive(Serialize)]
+pub(crate) struct FindingsListing {
+    findings: Vec<Finding>,
+}
+
+impl Tabular for FindingsListing {
+    fn headers(&self) -> Vec<String> {
+        ["ICE", "Severity", "Table", "Key", "Message"]
+            .iter()
+            .map(|h| h.to_string())
+            .collect()
+    }
+
+    fn records(&self) -> Vec<Vec<String>> {
+        self.findings
+            .iter()
+            .map(|f| {
+                vec![
+                    f.ice_name(),
+                    f.severity.to_string(),
+                    f.table.clone().unwrap_or_default(),
+                    f.key.clone().unwrap_or_default(),
+                    f.message.clone(),
+                ]
+            })
+            .collect()
+    }
+}
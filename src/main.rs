@@ -4,9 +4,15 @@ pub(crate) mod modules;
 use clap::Parser;
 use log::{error, info};
 use std::process::ExitCode;
-use command::{builder, lister};
+use command::{
+    builder, configurer, decompiler, differ, initializer, lister, patcher,
+    querier, signer, transformer, validator,
+};
 
 use crate::command::command_line::{App, Commands};
+use crate::modules::patch::PatchOptions;
+use crate::modules::scaffold::ScaffoldOptions;
+use crate::modules::signing::SigningOptions;
 
 fn main() -> ExitCode {
     // Read the passed in arguments
@@ -29,12 +35,105 @@ fn main() -> ExitCode {
     match args.command {
         Commands::Build {
             config,
+            wxs,
             input_directory,
             output_path,
-        } => builder::build(&config, &output_path),
+            package_type,
+            culture,
+            cultures,
+            define,
+        } => {
+            let source = match (config, wxs) {
+                (_, Some(wxs)) => builder::ConfigSource::Wxs(wxs),
+                (Some(config), None) => builder::ConfigSource::Toml(config),
+                (None, None) => unreachable!("clap requires a config or wxs"),
+            };
+            builder::build(
+                &source,
+                &input_directory,
+                &output_path,
+                package_type,
+                &culture.into_iter().chain(cultures).collect::<Vec<_>>(),
+                &define.into_iter().collect(),
+            )
+        }
         Commands::Inspect {
             input_file,
+            transform,
+            format,
             list_args,
-        } => lister::list(&input_file, list_args)
+        } => lister::list(&input_file, transform.as_ref(), list_args, format),
+        Commands::Query {
+            input_file,
+            sql,
+            format,
+        } => querier::query(&input_file, &sql, format),
+        Commands::Validate { input_file, format } => {
+            validator::validate(&input_file, format)
+        }
+        Commands::Diff {
+            old_file,
+            new_file,
+            format,
+        } => differ::diff(&old_file, &new_file, format),
+        Commands::Transform { action } => transformer::transform(action),
+        Commands::Patch {
+            baseline,
+            upgraded,
+            input_directory,
+            config,
+            output,
+            family,
+            display_name,
+            description,
+            classification,
+        } => patcher::patch(
+            &baseline,
+            &upgraded,
+            &output,
+            PatchOptions {
+                input_directory,
+                config,
+                family,
+                display_name,
+                description,
+                classification,
+            },
+        ),
+        Commands::Sign {
+            input_file,
+            cert,
+            key,
+            timestamp_url,
+            extended,
+            output,
+        } => signer::sign(
+            &input_file,
+            output.as_ref(),
+            SigningOptions {
+                certificate: cert,
+                key,
+                timestamp_url,
+                extended,
+            },
+        ),
+        Commands::Verify { input_file } => signer::verify(&input_file),
+        Commands::Decompile { input_file, output } => {
+            decompiler::decompile(&input_file, &output)
+        }
+        Commands::Config { action } => configurer::config(action),
+        Commands::Init {
+            directory,
+            name,
+            manufacturer,
+            template,
+            scan,
+        } => initializer::init(ScaffoldOptions {
+            directory,
+            name,
+            manufacturer,
+            platform: template,
+            scan,
+        }),
     }
 }
@@ -0,0 +1,521 @@
+//! Turning the `[[feature]]`, `[[registry]]`, `[[shortcut]]` and
+//! `[[custom_action]]` sections of a config into rows.
+//!
+//! Every file has a component of its own, so features list files, and
+//! registry values and shortcuts name the file they belong with. Registry
+//! values without a file and shortcuts get components of their own, which
+//! join the features of the file they belong with or the first feature.
+
+use std::collections::BTreeSet;
+
+use anyhow::{bail, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use flexstr::LocalStr;
+use msi::Value;
+use uuid::Uuid;
+
+use crate::modules::{
+    component::{directory::Directory, file::File},
+    config::{feature::FeatureDisplay, msi_config::MsiConfig},
+    helpers::filename::ShortNames,
+    tables::{
+        authored::AuthoredTables,
+        component::{component_guid, guid_seed},
+        sequence::{
+            EXECUTE_ACTIONS, EXECUTE_SEQUENCE, UI_ACTIONS, UI_SEQUENCE,
+        },
+        value_check::SourcedRow,
+    },
+    traits::identifier::Identifier,
+};
+
+const TARGETDIR: &str = "TARGETDIR";
+const INSTALLDIR: &str = "INSTALLDIR";
+
+/// `Component.Attributes` bit marking the key path as a `Registry` key.
+const REGISTRY_KEY_PATH: i32 = 0x4;
+/// `Registry.Root` value for `HKEY_CURRENT_USER`.
+const HKCU: i32 = 1;
+/// `RemoveFile.InstallMode` value that removes the folder on uninstall.
+const REMOVE_ON_UNINSTALL: i32 = 2;
+
+/// Write the rows for the sections of `config`. Folders that shortcuts are
+/// made in and that aren't in `directories` yet are added to it.
+pub(crate) fn author_tables(
+    config: &MsiConfig,
+    config_path: &Utf8Path,
+    input_directory: &Utf8Path,
+    directories: &mut Vec<Directory>,
+    files: &[File],
+) -> Result<AuthoredTables> {
+    let mut author = Author {
+        config,
+        source: Some(config_path.to_owned()),
+        input_directory,
+        directories,
+        files,
+        tables: AuthoredTables::default(),
+        members: Vec::new(),
+        problems: Vec::new(),
+    };
+    author.features();
+    author.registry();
+    author.shortcuts();
+    author.custom_actions(config_path.parent().unwrap_or(config_path));
+    author.feature_components();
+
+    if !author.problems.is_empty() {
+        bail!(
+            "Found {} problem(s) in config {}:\n{}",
+            author.problems.len(),
+            config_path,
+            author.problems.join("\n")
+        );
+    }
+    Ok(author.tables)
+}
+
+struct Author<'a> {
+    config: &'a MsiConfig,
+    /// The config file, as the source of every row and folder.
+    source: Option<Utf8PathBuf>,
+    input_directory: &'a Utf8Path,
+    directories: &'a mut Vec<Directory>,
+    files: &'a [File],
+    tables: AuthoredTables,
+    /// The components of each feature, in the order of the features.
+    members: Vec<BTreeSet<String>>,
+    problems: Vec<String>,
+}
+
+impl<'a> Author<'a> {
+    fn add(&mut self, table: &'static str, values: Vec<Value>) {
+        let row = SourcedRow::new(values, self.source.clone());
+        self.tables.add(table, row);
+    }
+
+    /// The file installed from `path` in the input directory.
+    fn file(&self, path: &Utf8Path) -> Option<&'a File> {
+        self.files.iter().find(|file| {
+            file.source()
+                .strip_prefix(self.input_directory)
+                .is_ok_and(|relative| relative == path)
+        })
+    }
+
+    fn features(&mut self) {
+        let config = self.config;
+        let ids = config
+            .feature
+            .iter()
+            .map(|feature| feature.id.as_str())
+            .collect::<BTreeSet<_>>();
+        if ids.len() != config.feature.len() {
+            self.problems
+                .push("Two features have the same id".to_owned());
+        }
+        if ids.is_empty()
+            && !(config.registry.is_empty() && config.shortcut.is_empty())
+        {
+            self.problems.push(
+                "Registry values and shortcuts are only installed with a \
+                feature, add a [[feature]]"
+                    .to_owned(),
+            );
+        }
+
+        let mut listed = BTreeSet::new();
+        for (index, feature) in config.feature.iter().enumerate() {
+            if let Some(parent) = &feature.parent {
+                if !ids.contains(parent.as_str()) {
+                    self.problems.push(format!(
+                        "Feature {} is in feature {}, which doesn't exist",
+                        feature.id, parent
+                    ));
+                }
+            }
+            // Features are shown in the order they are listed, collapsed with
+            // odd values and expanded with even ones.
+            let display = match feature.display {
+                FeatureDisplay::Collapse => index as i32 * 2 + 1,
+                FeatureDisplay::Expand => index as i32 * 2 + 2,
+                FeatureDisplay::Hidden => 0,
+            };
+            self.add(
+                "Feature",
+                vec![
+                    Value::from(feature.id.as_str()),
+                    optional(feature.parent.as_deref()),
+                    optional(feature.title.as_deref()),
+                    optional(feature.description.as_deref()),
+                    Value::from(display),
+                    Value::from(feature.level),
+                    optional(feature.configurable.then_some(INSTALLDIR)),
+                    Value::from(0),
+                ],
+            );
+
+            let mut members = BTreeSet::new();
+            for path in &feature.files {
+                match self.file(path) {
+                    Some(file) => {
+                        members.insert(file.component_id().to_string());
+                        listed.insert(file.component_id().to_string());
+                    }
+                    None => self.problems.push(format!(
+                        "Feature {} lists {}, which isn't in the input \
+                        directory",
+                        feature.id, path
+                    )),
+                }
+            }
+            self.members.push(members);
+        }
+
+        // Files that no feature lists are installed with the first one.
+        if let Some(first) = self.members.first_mut() {
+            first.extend(
+                self.files
+                    .iter()
+                    .map(|file| file.component_id().to_string())
+                    .filter(|component| !listed.contains(component)),
+            );
+        }
+    }
+
+    /// Add a component to the feature with the id `feature`, or to the first
+    /// feature.
+    fn join_feature(&mut self, feature: Option<&str>, component: &str) {
+        let index = match feature {
+            Some(feature) => self
+                .config
+                .feature
+                .iter()
+                .position(|candidate| candidate.id == feature),
+            None => (!self.members.is_empty()).then_some(0),
+        };
+        match index {
+            Some(index) => {
+                self.members[index].insert(component.to_owned());
+            }
+            None => {
+                if let Some(feature) = feature {
+                    self.problems.push(format!(
+                        "Component {component} is in feature {feature}, \
+                        which doesn't exist"
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Add a component of its own for a registry value or shortcut, with a
+    /// registry value as its key path.
+    fn component(&mut self, id: &str, directory: &str, key_path: &str) {
+        let seed = guid_seed(self.config);
+        self.add(
+            "Component",
+            vec![
+                Value::from(id),
+                Value::from(component_guid(&seed, directory, key_path)),
+                Value::from(directory),
+                Value::from(REGISTRY_KEY_PATH),
+                Value::Null,
+                Value::from(key_path),
+            ],
+        );
+    }
+
+    fn registry(&mut self) {
+        let config = self.config;
+        let mut ids = BTreeSet::new();
+        for registry in &config.registry {
+            let name = registry.name.as_deref();
+            let id = Uuid::from_seed(&format!(
+                "registry:{}/{}/{}",
+                registry.root.code(),
+                registry.key,
+                name.unwrap_or_default()
+            ))
+            .to_string();
+            if !ids.insert(id.clone()) {
+                self.problems.push(format!(
+                    "Registry value {}\\{} is written more than once",
+                    registry.key,
+                    name.unwrap_or_default()
+                ));
+                continue;
+            }
+            let component = match &registry.file {
+                Some(path) => match self.file(path) {
+                    Some(file) => file.component_id().to_string(),
+                    None => {
+                        self.problems.push(format!(
+                            "Registry value {}\\{} is installed with {}, \
+                            which isn't in the input directory",
+                            registry.key,
+                            name.unwrap_or_default(),
+                            path
+                        ));
+                        continue;
+                    }
+                },
+                None => {
+                    let component =
+                        Uuid::from_seed(&format!("component:{id}")).to_string();
+                    self.component(&component, INSTALLDIR, &id);
+                    self.join_feature(registry.feature.as_deref(), &component);
+                    component
+                }
+            };
+            self.add(
+                "Registry",
+                vec![
+                    Value::from(id.as_str()),
+                    Value::from(registry.root.code()),
+                    Value::from(registry.key.as_str()),
+                    optional(name),
+                    optional(registry.value.as_deref()),
+                    Value::from(component),
+                ],
+            );
+        }
+    }
+
+    fn shortcuts(&mut self) {
+        let config = self.config;
+        // The key path of every shortcut component, as shortcuts are
+        // usually made in the profile of the user.
+        let key = format!(
+            "Software\\{}\\{}",
+            config.product_info.manufacturer, config.product_info.product_name
+        );
+        let mut names: Vec<(String, ShortNames)> = Vec::new();
+        for shortcut in &config.shortcut {
+            let Some(file) = self.file(&shortcut.target) else {
+                self.problems.push(format!(
+                    "Shortcut {} opens {}, which isn't in the input directory",
+                    shortcut.name, shortcut.target
+                ));
+                continue;
+            };
+            let target = format!("[#{}]", file.file_id());
+            let file_component = file.component_id().to_string();
+            let (directory, created) = self.directory(&shortcut.directory);
+            let working_directory = shortcut
+                .working_directory
+                .as_deref()
+                .map(|path| self.directory(path).0);
+
+            let id = Uuid::from_seed(&format!(
+                "shortcut:{directory}/{}",
+                shortcut.name
+            ))
+            .to_string();
+            let component =
+                Uuid::from_seed(&format!("component:{id}")).to_string();
+            let registry =
+                Uuid::from_seed(&format!("registry:{id}")).to_string();
+            self.component(&component, &directory, &registry);
+            self.add(
+                "Registry",
+                vec![
+                    Value::from(registry.as_str()),
+                    Value::from(HKCU),
+                    Value::from(key.as_str()),
+                    Value::from(shortcut.name.as_str()),
+                    Value::from("#1"),
+                    Value::from(component.as_str()),
+                ],
+            );
+            // Folders made for the shortcut are removed with it.
+            for folder in created {
+                let remove = Uuid::from_seed(&format!(
+                    "removefolder:{component}/{folder}"
+                ))
+                .to_string();
+                self.add(
+                    "RemoveFile",
+                    vec![
+                        Value::from(remove),
+                        Value::from(component.as_str()),
+                        Value::Null,
+                        Value::from(folder),
+                        Value::from(REMOVE_ON_UNINSTALL),
+                    ],
+                );
+            }
+
+            let name = match names.iter_mut().find(|(d, _)| *d == directory) {
+                Some((_, short_names)) => short_names.msi_name(&shortcut.name),
+                None => {
+                    let mut short_names = ShortNames::default();
+                    let name = short_names.msi_name(&shortcut.name);
+                    names.push((directory.clone(), short_names));
+                    name
+                }
+            };
+            self.add(
+                "Shortcut",
+                vec![
+                    Value::from(id),
+                    Value::from(directory),
+                    Value::from(name),
+                    Value::from(component.as_str()),
+                    Value::from(target),
+                    optional(shortcut.arguments.as_deref()),
+                    optional(shortcut.description.as_deref()),
+                    Value::Null,
+                    Value::Null,
+                    Value::Null,
+                    shortcut
+                        .show
+                        .map_or(Value::Null, |show| Value::from(show.code())),
+                    optional(working_directory.as_deref()),
+                ],
+            );
+
+            // The shortcut is installed with every feature of its target.
+            for members in &mut self.members {
+                if members.contains(&file_component) {
+                    members.insert(component.clone());
+                }
+            }
+        }
+    }
+
+    /// The directory at `path`, a directory id followed by the names of the
+    /// folders under it, along with the ids of the folders that had to be
+    /// made for it.
+    fn directory(&mut self, path: &str) -> (String, Vec<String>) {
+        let mut names = path.split('/').filter(|name| !name.is_empty());
+        let root = names.next().unwrap_or(INSTALLDIR);
+        if !self.directories.iter().any(|d| d.id().as_str() == root) {
+            // A system folder, which Windows Installer resolves itself.
+            self.directories.push(Directory::new(
+                root,
+                Some(LocalStr::from(TARGETDIR)),
+                ".",
+                None,
+            ));
+        }
+
+        let mut current = root.to_owned();
+        let mut created = Vec::new();
+        for name in names {
+            let existing = self.directories.iter().find(|d| {
+                d.name().as_str() == name
+                    && d.parent_id()
+                        .as_ref()
+                        .is_some_and(|parent| parent.as_str() == current)
+            });
+            current = match existing {
+                Some(directory) => directory.id().to_string(),
+                None => {
+                    let id =
+                        Uuid::from_seed(&format!("directory:{current}/{name}"))
+                            .to_string();
+                    self.directories.push(Directory::new(
+                        id.as_str(),
+                        Some(LocalStr::from(current.as_str())),
+                        name,
+                        self.source.clone(),
+                    ));
+                    created.push(id.clone());
+                    id
+                }
+            };
+        }
+        (current, created)
+    }
+
+    fn custom_actions(&mut self, config_dir: &Utf8Path) {
+        let config = self.config;
+        let mut ids = BTreeSet::new();
+        for action in &config.custom_action {
+            if !ids.insert(action.id.as_str()) {
+                self.problems.push(format!(
+                    "Custom action {} is defined more than once",
+                    action.id
+                ));
+                continue;
+            }
+            self.add(
+                "CustomAction",
+                vec![
+                    Value::from(action.id.as_str()),
+                    Value::from(action.kind),
+                    optional(action.source.as_deref()),
+                    optional(action.target.as_deref()),
+                ],
+            );
+            for (table, schedule) in [
+                (EXECUTE_SEQUENCE, &action.execute),
+                (UI_SEQUENCE, &action.ui),
+            ] {
+                if let Some(schedule) = schedule {
+                    self.add(
+                        table,
+                        vec![
+                            Value::from(action.id.as_str()),
+                            optional(schedule.condition.as_deref()),
+                            Value::from(schedule.sequence),
+                        ],
+                    );
+                }
+            }
+        }
+        for (name, path) in &config.binary {
+            self.tables
+                .binaries
+                .push((name.clone(), config_dir.join(path)));
+        }
+
+        // Nothing is installed or run without the standard actions.
+        if !(config.feature.is_empty() && config.custom_action.is_empty()) {
+            for (table, actions) in [
+                (EXECUTE_SEQUENCE, EXECUTE_ACTIONS.as_slice()),
+                (UI_SEQUENCE, UI_ACTIONS.as_slice()),
+            ] {
+                for (action, sequence) in actions {
+                    self.add(
+                        table,
+                        vec![
+                            Value::from(*action),
+                            Value::Null,
+                            Value::from(*sequence),
+                        ],
+                    );
+                }
+            }
+        }
+    }
+
+    fn feature_components(&mut self) {
+        let config = self.config;
+        let rows = config
+            .feature
+            .iter()
+            .zip(&self.members)
+            .flat_map(|(feature, members)| {
+                members.iter().map(|component| {
+                    vec![
+                        Value::from(feature.id.as_str()),
+                        Value::from(component.as_str()),
+                    ]
+                })
+            })
+            .collect::<Vec<_>>();
+        for row in rows {
+            self.add("FeatureComponents", row);
+        }
+    }
+}
+
+/// A value for a column that can be null.
+fn optional(value: Option<&str>) -> Value {
+    match value {
+        Some(value) => Value::from(value),
+        None => Value::Null,
+    }
+}
@@ -36,14 +36,16 @@ pub(crate) struct Directory {
 }
 
 impl Directory {
+    /// The ID is derived from the parent ID and the name so the same
+    /// directory gets the same ID every time the package is built.
     pub fn from_path(source: &Utf8PathBuf, parent_id: &str) -> Self {
+        let name = source
+            .file_name()
+            .expect("Filename somehow ends with '..'. Ending in pure confusion.");
         Directory {
-            id: Uuid::as_identifier(),
+            id: Uuid::from_seed(&format!("directory:{parent_id}/{name}")),
             parent_id: Some(parent_id.into()),
-            name: source
-                .file_name()
-                .expect("Filename somehow ends with '..'. Ending in pure confusion.")
-                .into(),
+            name: name.into(),
             source: Some(source.clone()),
         }
     }
@@ -15,6 +15,8 @@ use crate::modules::traits::identifier::Identifier;
 ///
 /// - `component_id` Internal identifier of the component that controls this
 ///   file. Must correspond to a tracked component_id.
+/// - `directory_id` Identifier of the directory the file is installed into.
+///   Must correspond to a tracked directory id.
 /// - `file_id` Internal identifier of the file for the MSI. This must be
 ///   unique. Must correspond to a tracked file_id.
 /// - `name` Filename of the file when placed on the system.
@@ -26,6 +28,8 @@ use crate::modules::traits::identifier::Identifier;
 /// - `version` This field is the version string for a versioned file. This
 ///   field is blank for non-versioned files.
 /// - `language` A list of decimal language IDs separated by commas.
+/// - `guid` GUID of the component of the file when the config gives one,
+///   instead of having it derived.
 /// - `sequence` Sequence position of this file on the media images. This order
 ///   must correspond to the order of the files in the cabinet if the files are
 ///   compressed. The integers in this field must be equal or greater than 1.
@@ -33,6 +37,7 @@ use crate::modules::traits::identifier::Identifier;
 #[getset(get = "pub")]
 pub(crate) struct File {
     component_id: LocalStr,
+    directory_id: LocalStr,
     file_id: LocalStr,
     source: Utf8PathBuf,
     name: LocalStr,
@@ -40,21 +45,72 @@ pub(crate) struct File {
     vital: bool,
     version: Option<String>,
     language: Option<String>,
+    guid: Option<String>,
     sequence: u64,
 }
 
 impl File {
-    pub fn new(source: &Utf8PathBuf, sequence_number: u64, size: u64) -> File {
+    /// The file and component IDs are derived from the directory and name of
+    /// the file so they stay the same between builds.
+    pub fn new(
+        source: &Utf8PathBuf,
+        directory_id: &str,
+        sequence_number: u64,
+        size: u64,
+    ) -> File {
+        let name = source
+            .file_name()
+            .expect("Filename somehow ends with '..'. Ending in pure confusion.");
         File {
-            component_id: Uuid::as_identifier(),
-            file_id: Uuid::as_identifier(),
+            component_id: Uuid::from_seed(&format!(
+                "component:{directory_id}/{name}"
+            )),
+            directory_id: directory_id.into(),
+            file_id: Uuid::from_seed(&format!("file:{directory_id}/{name}")),
             source: source.into(),
-            name: source.to_string().into(),
+            name: name.into(),
             size,
             vital: false,
             version: None,
             language: None,
+            guid: None,
             sequence: sequence_number,
         }
     }
+
+    /// Use the file and component IDs an authored source gives, such as a
+    /// WiX `File` in a `Component`, instead of deriving them.
+    pub fn with_ids(mut self, file_id: &str, component_id: &str) -> File {
+        self.file_id = file_id.into();
+        self.component_id = component_id.into();
+        self
+    }
+
+    /// Install the file under a different name than its source has.
+    pub fn with_name(mut self, name: &str) -> File {
+        self.name = name.into();
+        self
+    }
+
+    pub fn with_vital(mut self, vital: bool) -> File {
+        self.vital = vital;
+        self
+    }
+
+    /// Set the version and languages of the file, or the key of the file
+    /// it is a companion of as its version.
+    pub fn with_version(
+        mut self,
+        version: Option<String>,
+        language: Option<String>,
+    ) -> File {
+        self.version = version;
+        self.language = language;
+        self
+    }
+
+    pub fn with_guid(mut self, guid: String) -> File {
+        self.guid = Some(guid);
+        self
+    }
 }
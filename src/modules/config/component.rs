@@ -0,0 +1,25 @@
+use camino::Utf8PathBuf;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// # [Component GUIDs](https://learn.microsoft.com/en-us/windows/win32/msi/component-table)
+///
+/// Every file is installed by a component of its own, whose GUID is derived
+/// from the product and where the file is installed so it stays the same
+/// between builds. A `[[component]]` entry gives the component of a file a
+/// GUID instead, such as the one it had in a package made by another tool,
+/// so the component rules still hold when that package is upgraded.
+///
+/// ## Properties
+///
+/// - `file` Path of the file, relative to the input directory.
+///
+/// - `guid` The GUID of the component, such as
+///   `{AC8ACB03-F948-40CE-9C35-297FF94C7C35}`.
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ComponentConfig {
+    #[schemars(with = "String")]
+    pub(crate) file: Utf8PathBuf,
+    pub(crate) guid: String,
+}
@@ -0,0 +1,47 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// # [Custom Action](https://learn.microsoft.com/en-us/windows/win32/msi/customaction-table)
+///
+/// A `[[custom_action]]` entry is a row of the `CustomAction` table, along
+/// with where it is scheduled.
+///
+/// ## Properties
+///
+/// - `id` Identifier of the action.
+///
+/// - [`type`](https://learn.microsoft.com/en-us/windows/win32/msi/summary-list-of-all-custom-action-types)
+///   The type of the action and its option bits, such as `1` for a function
+///   in a DLL stored in the `Binary` table.
+///
+/// - `source` and `target` The `Source` and `Target` columns, whose meaning
+///   depends on the type. For actions that run a binary, `source` is the
+///   name of an entry in the `[binary]` table.
+///
+/// - `execute` and `ui` Where the action is scheduled in the
+///   `InstallExecuteSequence` and `InstallUISequence`, with a `sequence`
+///   number and an optional `condition`. An action that isn't scheduled can
+///   still be run from a dialog.
+///
+/// ## Binaries
+///
+/// The top level `[binary]` table names the files stored in the `Binary`
+/// table, as paths relative to the config file.
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct CustomActionConfig {
+    pub(crate) id: String,
+    #[serde(rename = "type")]
+    pub(crate) kind: i32,
+    pub(crate) source: Option<String>,
+    pub(crate) target: Option<String>,
+    pub(crate) execute: Option<ScheduleConfig>,
+    pub(crate) ui: Option<ScheduleConfig>,
+}
+
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ScheduleConfig {
+    pub(crate) sequence: i32,
+    pub(crate) condition: Option<String>,
+}
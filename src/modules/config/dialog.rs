@@ -0,0 +1,182 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// # [Dialog](https://learn.microsoft.com/en-us/windows/win32/msi/dialog-table)
+///
+/// A `[[dialog]]` entry adds a dialog of its own to the UI set picked with
+/// `ui`, which can't be `none`.
+///
+/// ## Properties
+///
+/// - `id` Identifier of the dialog. It can't be the same as a dialog of the
+///   UI set.
+///
+/// - `title` For a page, the title in the banner. For any other dialog, the
+///   title of the window. Defaults to `[ProductName] Setup`.
+///
+/// - `description` Text under the title in the banner of a page.
+///
+/// - `after` or `before` Makes the dialog a page of the wizard, inserted
+///   after or before the page with this identifier. Pages are inserted in
+///   the order they are listed, so a page can be placed next to an earlier
+///   one. Pages get the banner and the Back, Next and Cancel buttons of the
+///   wizard, and their controls go in the space between them.
+///
+/// - `width` and `height` Size of a dialog that isn't a page, in dialog
+///   units. Defaults to the size of the wizard.
+///
+/// - `default_control` and `cancel_control` Controls of a dialog that isn't
+///   a page that are activated by the Enter and Escape keys.
+///
+/// - [`control`](https://learn.microsoft.com/en-us/windows/win32/msi/control-table)
+///   The controls of the dialog, listed in tab order.
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct DialogConfig {
+    pub(crate) id: String,
+    pub(crate) title: Option<String>,
+    pub(crate) description: Option<String>,
+    pub(crate) after: Option<String>,
+    pub(crate) before: Option<String>,
+    pub(crate) width: Option<i32>,
+    pub(crate) height: Option<i32>,
+    pub(crate) default_control: Option<String>,
+    pub(crate) cancel_control: Option<String>,
+    #[serde(default)]
+    pub(crate) control: Vec<ControlConfig>,
+}
+
+/// # [Control](https://learn.microsoft.com/en-us/windows/win32/msi/controls)
+///
+/// ## Properties
+///
+/// - `id` Identifier of the control, unique within its dialog.
+///
+/// - `type` One of `Text`, `Edit`, `PushButton`, `CheckBox`,
+///   `RadioButtonGroup`, `PathEdit` or `ComboBox`.
+///
+/// - `x`, `y`, `width` and `height` Position and size of the control in
+///   dialog units.
+///
+/// - `text` Text shown by the control. For an `Edit` control, `{N}` limits
+///   the input to N characters.
+///
+/// - `property` Property the control shows and sets. Required for every
+///   type other than `Text` and `PushButton`.
+///
+/// - `value` Initial value of `property`.
+///
+/// - `visible`, `enabled` Default to `true`. `sunken` Defaults to `false`.
+///
+/// - `option` The choices of a `RadioButtonGroup` or `ComboBox`, each with
+///   a `value` and `text`. Radio buttons can also have `x`, `y`, `width` and
+///   `height` relative to the group, and are otherwise stacked from the top.
+///
+/// - [`event`](https://learn.microsoft.com/en-us/windows/win32/msi/controlevent-table)
+///   Events published, in order, when a `PushButton` or `CheckBox` is used.
+///   Each has an `event` of:
+///     - `NewDialog` with the `dialog` to replace this one with.
+///     - `EndDialog` with an `action` of `Return`, `Exit`, `Retry` or
+///       `Ignore`.
+///     - `DoAction` with the custom `action` to run.
+///     - `SetProperty` with the `property` to set to `value`.
+///
+///   and an optional `condition` under which it is published.
+///
+/// - [`condition`](https://learn.microsoft.com/en-us/windows/win32/msi/controlcondition-table)
+///   An `action` of `Default`, `Disable`, `Enable`, `Hide` or `Show` taken
+///   on the control when `condition` is true.
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ControlConfig {
+    pub(crate) id: String,
+    #[serde(rename = "type")]
+    pub(crate) kind: ControlKind,
+    pub(crate) x: i32,
+    pub(crate) y: i32,
+    pub(crate) width: i32,
+    pub(crate) height: i32,
+    pub(crate) text: Option<String>,
+    pub(crate) property: Option<String>,
+    pub(crate) value: Option<String>,
+    #[serde(default = "default_true")]
+    pub(crate) visible: bool,
+    #[serde(default = "default_true")]
+    pub(crate) enabled: bool,
+    #[serde(default)]
+    pub(crate) sunken: bool,
+    #[serde(default)]
+    pub(crate) option: Vec<OptionConfig>,
+    #[serde(default)]
+    pub(crate) event: Vec<EventConfig>,
+    #[serde(default)]
+    pub(crate) condition: Vec<ConditionConfig>,
+}
+
+#[derive(
+    Clone, Copy, Debug, Deserialize, JsonSchema, Serialize, PartialEq, Eq,
+)]
+pub(crate) enum ControlKind {
+    Text,
+    Edit,
+    PushButton,
+    CheckBox,
+    RadioButtonGroup,
+    PathEdit,
+    ComboBox,
+}
+
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct OptionConfig {
+    pub(crate) value: String,
+    pub(crate) text: String,
+    pub(crate) x: Option<i32>,
+    pub(crate) y: Option<i32>,
+    pub(crate) width: Option<i32>,
+    pub(crate) height: Option<i32>,
+}
+
+#[derive(Deserialize, JsonSchema, Serialize)]
+pub(crate) struct EventConfig {
+    #[serde(flatten)]
+    pub(crate) kind: EventKind,
+    pub(crate) condition: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(tag = "event")]
+pub(crate) enum EventKind {
+    NewDialog { dialog: String },
+    EndDialog { action: EndDialogAction },
+    DoAction { action: String },
+    SetProperty { property: String, value: String },
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, JsonSchema, Serialize)]
+pub(crate) enum EndDialogAction {
+    Return,
+    Exit,
+    Retry,
+    Ignore,
+}
+
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ConditionConfig {
+    pub(crate) action: ConditionAction,
+    pub(crate) condition: String,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, JsonSchema, Serialize)]
+pub(crate) enum ConditionAction {
+    Default,
+    Disable,
+    Enable,
+    Hide,
+    Show,
+}
+
+fn default_true() -> bool {
+    true
+}
@@ -0,0 +1,63 @@
+use camino::Utf8PathBuf;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// # [Feature](https://learn.microsoft.com/en-us/windows/win32/msi/feature-table)
+///
+/// A `[[feature]]` entry is a part of the product that can be installed on
+/// its own. Without any features the files are added to the package but are
+/// never installed.
+///
+/// ## Properties
+///
+/// - `id` Identifier of the feature.
+///
+/// - `title` and `description` Shown for the feature in the feature tree.
+///
+/// - `parent` Identifier of the feature this one is nested in.
+///
+/// - `level` Install level of the feature. Features with a level of `0` are
+///   disabled and features with a level above the `INSTALLLEVEL` property
+///   are left out unless they are picked. Defaults to `1`.
+///
+/// - `display` One of `collapse`, `expand` or `hidden`, how the feature is
+///   shown in the feature tree. Defaults to `collapse`.
+///
+/// - `configurable` Whether the installation directory can be changed from
+///   the feature tree. Defaults to `false`.
+///
+/// - `files` Paths of the files installed with the feature, relative to the
+///   input directory. Files that no feature lists are installed with the
+///   first feature, as are merge modules that don't name a feature.
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FeatureConfig {
+    pub(crate) id: String,
+    pub(crate) title: Option<String>,
+    pub(crate) description: Option<String>,
+    pub(crate) parent: Option<String>,
+    #[serde(default = "default_level")]
+    pub(crate) level: i32,
+    #[serde(default)]
+    pub(crate) display: FeatureDisplay,
+    #[serde(default)]
+    pub(crate) configurable: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(with = "Vec<String>")]
+    pub(crate) files: Vec<Utf8PathBuf>,
+}
+
+#[derive(
+    Clone, Copy, Default, Deserialize, JsonSchema, Serialize, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum FeatureDisplay {
+    #[default]
+    Collapse,
+    Expand,
+    Hidden,
+}
+
+fn default_level() -> i32 {
+    1
+}
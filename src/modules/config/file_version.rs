@@ -0,0 +1,32 @@
+use camino::Utf8PathBuf;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// # [File Versions](https://learn.microsoft.com/en-us/windows/win32/msi/file-versioning-rules)
+///
+/// The version and languages of `.exe`, `.dll` and `.sys` files are read
+/// from their version resource, and every other file is unversioned. A
+/// `[[file_version]]` entry sets them for a single file instead, which
+/// decides whether the file replaces one that is already installed.
+///
+/// ## Properties
+///
+/// - `file` Path of the file, relative to the input directory.
+///
+/// - `version` and `language` The version of the file, such as `1.2.3.4`,
+///   and its language IDs separated by commas, such as `1033`. Leaving both
+///   out makes the file unversioned.
+///
+/// - [`companion`](https://learn.microsoft.com/en-us/windows/win32/msi/companion-files)
+///   Path of a file, relative to the input directory, whose version decides
+///   whether this one is installed. It can't be given along with `version`.
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FileVersionConfig {
+    #[schemars(with = "String")]
+    pub(crate) file: Utf8PathBuf,
+    pub(crate) version: Option<String>,
+    pub(crate) language: Option<String>,
+    #[schemars(with = "Option<String>")]
+    pub(crate) companion: Option<Utf8PathBuf>,
+}
@@ -0,0 +1,43 @@
+use camino::Utf8PathBuf;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// # Files
+///
+/// A `[[files]]` entry installs a folder of the input directory to a folder
+/// of the target system. Without any entries the whole input directory is
+/// installed to `INSTALLDIR`, or to `TARGETDIR` for a merge module.
+///
+/// ## Properties
+///
+/// - `source` Folder holding the files, relative to the input directory.
+///
+/// - `target` Folder to install the files to, as the id of a directory
+///   followed by the names of the folders below it, such as
+///   `INSTALLDIR/bin`. The id can be `INSTALLDIR`, a
+///   [system folder](https://learn.microsoft.com/en-us/windows/win32/msi/property-reference#system-folder-properties)
+///   such as `CommonAppDataFolder`, `SystemFolder` or `FontsFolder`, or a
+///   directory of your own whose path a `[property]` entry sets. Any other
+///   id fails the build. Entries with the same target are merged, and two
+///   files installed to the same path, compared without case, fail the
+///   build.
+///
+/// - `include` and `exclude` Patterns of the files to add and to leave out,
+///   relative to `source`, used along with those of the `[scan]` section.
+///
+/// - `vital` Files, relative to `source`, that fail the whole install when
+///   they can't be installed.
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FilesConfig {
+    #[schemars(with = "String")]
+    pub(crate) source: Utf8PathBuf,
+    pub(crate) target: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) include: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) exclude: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(with = "Vec<String>")]
+    pub(crate) vital: Vec<Utf8PathBuf>,
+}
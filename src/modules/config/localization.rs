@@ -0,0 +1,37 @@
+use camino::Utf8PathBuf;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// # [Localization](https://learn.microsoft.com/en-us/windows/win32/msi/localizing-the-database-columns-and-tables)
+///
+/// Config values can use `!(loc.Key)` placeholders, which are replaced with
+/// the strings of the culture given with `--culture`. Building for a culture
+/// also sets `product_language`, the languages of `template` and `code_page`
+/// to those of the culture.
+///
+/// With `--cultures`, the first culture is built as the package and the
+/// others are embedded in it as transforms, with every language listed in
+/// the `template`. A `product_code` of `*` gets the same GUID for every
+/// culture.
+///
+/// ## Properties
+///
+/// - `directory` Directory holding a string table for each culture, named
+///   after the culture such as `de-DE.toml` or `de-DE.wxl`. Relative paths
+///   are relative to the config file.
+///
+/// - `default_culture` Culture to build when `--culture` isn't given.
+///
+/// ## String Tables
+///
+/// A TOML string table has its strings in a `[strings]` table, and can set
+/// the `language` and `codepage` to use instead of those of the culture. A
+/// `.wxl` file is read like WiX does, with the `Language` and `Codepage`
+/// attributes of `WixLocalization` overriding those of the culture.
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct LocalizationConfig {
+    #[schemars(with = "String")]
+    pub(crate) directory: Utf8PathBuf,
+    pub(crate) default_culture: Option<String>,
+}
@@ -0,0 +1,40 @@
+use std::collections::BTreeMap;
+
+use camino::Utf8PathBuf;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// # [Merge Module](https://learn.microsoft.com/en-us/windows/win32/msi/merge-modules)
+///
+/// A `[[merge_module]]` entry merges the tables and files of a `.msm` into
+/// the package being built.
+///
+/// ## Properties
+///
+/// - `path` Path to the `.msm`. Relative paths are relative to the config
+///   file.
+///
+/// - `directory` Identifier of the directory that the root directory of the
+///   module, `TARGETDIR`, is redirected to. Defaults to `INSTALLDIR`.
+///
+/// - `language` Language the module must be built for. Language neutral
+///   modules are accepted for any language.
+///
+/// - `feature` Identifier of the feature the components of the module are
+///   installed with. Defaults to the first `[[feature]]`.
+///
+/// - [`configuration`](https://learn.microsoft.com/en-us/windows/win32/msi/moduleconfiguration-table)
+///   Values for the configurable items of the module, keyed by the name in
+///   its `ModuleConfiguration` table. Items without a value use their
+///   default.
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct MergeModuleConfig {
+    #[schemars(with = "String")]
+    pub(crate) path: Utf8PathBuf,
+    pub(crate) directory: Option<String>,
+    pub(crate) language: Option<u16>,
+    pub(crate) feature: Option<String>,
+    #[serde(default)]
+    pub(crate) configuration: BTreeMap<String, String>,
+}
@@ -1,6 +1,20 @@
 // TODO: Remove this when the library is done
 #![allow(dead_code)]
 
+pub(crate) mod component;
+pub(crate) mod custom_action;
+pub(crate) mod dialog;
+pub(crate) mod feature;
+pub(crate) mod file_version;
+pub(crate) mod files;
+pub(crate) mod localization;
+pub(crate) mod merge_module;
 pub mod msi_config;
 pub(crate) mod product_information;
+pub(crate) mod registry;
+pub(crate) mod scan;
+pub(crate) mod schema;
+pub(crate) mod shortcut;
+pub(crate) mod signing;
 pub(crate) mod summary_information;
+pub(crate) mod ui;
@@ -1,13 +1,72 @@
-use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use camino::Utf8PathBuf;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::modules::localization::Localization;
 
 use super::{
+    component::ComponentConfig, custom_action::CustomActionConfig,
+    dialog::DialogConfig, feature::FeatureConfig,
+    file_version::FileVersionConfig, files::FilesConfig,
+    localization::LocalizationConfig, merge_module::MergeModuleConfig,
     product_information::ProductInformationProperties,
-    summary_information::SummaryInformationProperties,
+    registry::RegistryConfig, scan::ScanConfig, shortcut::ShortcutConfig,
+    signing::SigningConfig, summary_information::SummaryInformationProperties,
+    ui::UiSet,
 };
 
-///
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
 pub(crate) struct MsiConfig {
     pub(crate) product_info: ProductInformationProperties,
     pub(crate) summary_info: SummaryInformationProperties,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) merge_module: Vec<MergeModuleConfig>,
+    pub(crate) signing: Option<SigningConfig>,
+    #[serde(default)]
+    pub(crate) ui: UiSet,
+    #[schemars(with = "Option<String>")]
+    pub(crate) license: Option<Utf8PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) dialog: Vec<DialogConfig>,
+    pub(crate) localization: Option<LocalizationConfig>,
+    /// [Properties](https://learn.microsoft.com/en-us/windows/win32/msi/property-table)
+    /// to add to the `Property` table, from the `[property]` table of names
+    /// and values.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) property: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) feature: Vec<FeatureConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) registry: Vec<RegistryConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) shortcut: Vec<ShortcutConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) custom_action: Vec<CustomActionConfig>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schemars(with = "BTreeMap<String, String>")]
+    pub(crate) binary: BTreeMap<String, Utf8PathBuf>,
+    #[serde(default, skip_serializing_if = "ScanConfig::is_empty")]
+    pub(crate) scan: ScanConfig,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) files: Vec<FilesConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) file_version: Vec<FileVersionConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) component: Vec<ComponentConfig>,
+}
+
+impl MsiConfig {
+    /// Use the language and codepage of the culture being built instead of
+    /// those in the config.
+    pub(crate) fn localize(&mut self, localization: &Localization) {
+        self.product_info.product_language = localization.language;
+        let template = &self.summary_info.template;
+        let arch = template.split(';').next().unwrap_or_default();
+        self.summary_info.template =
+            format!("{arch};{}", localization.language).into();
+        self.summary_info.code_page = Some(localization.codepage.to_string());
+    }
 }
@@ -1,5 +1,6 @@
 use flexstr::LocalStr;
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 /// # [Product Information Properties](https://learn.microsoft.com/en-us/windows/win32/msi/property-reference)
 ///
@@ -28,13 +29,19 @@ use serde::Deserialize;
 ///   A unique identifier for the particular product release, represented as a
 ///   string GUID. This ID must vary for different versions and languages. Set
 ///   this to `*` to have the program generate the GUID automatically.
+///   Every build then gets a new one, so products that are patched need a
+///   fixed GUID that only changes with a major upgrade.
 ///
-#[derive(Deserialize)]
-#[serde(rename = "product_info")]
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(rename = "product_info", deny_unknown_fields)]
 pub(crate) struct ProductInformationProperties {
+    #[schemars(with = "String")]
     pub(crate) product_name: LocalStr,
+    #[schemars(with = "String")]
     pub(crate) product_version: LocalStr,
+    #[schemars(with = "String")]
     pub(crate) manufacturer: LocalStr,
     pub(crate) product_language: u16,
+    #[schemars(with = "String")]
     pub(crate) product_code: LocalStr,
 }
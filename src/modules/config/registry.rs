@@ -0,0 +1,76 @@
+use camino::Utf8PathBuf;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// # [Registry](https://learn.microsoft.com/en-us/windows/win32/msi/registry-table)
+///
+/// A `[[registry]]` entry writes a value to the registry when its component
+/// is installed and removes it when the component is removed.
+///
+/// ## Properties
+///
+/// - `root` One of `HKCR`, `HKCU`, `HKLM`, `HKU` or `HKMU`, which is
+///   `HKLM` for per-machine installations and `HKCU` otherwise.
+///
+/// - `key` Path of the key under the root, separated by backslashes.
+///
+/// - `name` Name of the value. Without a name the default value of the key
+///   is written.
+///
+/// - [`value`](https://learn.microsoft.com/en-us/windows/win32/msi/registry-table)
+///   The value as the `Registry` table expects it, so `#1` is a `DWORD` and
+///   `#%text` an expandable string. Without a value only the key is created.
+///
+/// - `file` Path of a file relative to the input directory. The value is
+///   installed with that file. Without a file the value gets a component of
+///   its own, with the value as its key path.
+///
+/// - `feature` Feature a value without a file is installed with. Defaults to
+///   the first feature.
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RegistryConfig {
+    pub(crate) root: RegistryRoot,
+    pub(crate) key: String,
+    pub(crate) name: Option<String>,
+    pub(crate) value: Option<String>,
+    #[schemars(with = "Option<String>")]
+    pub(crate) file: Option<Utf8PathBuf>,
+    pub(crate) feature: Option<String>,
+}
+
+#[derive(
+    Clone, Copy, Debug, Deserialize, JsonSchema, Serialize, PartialEq, Eq,
+)]
+#[serde(rename_all = "UPPERCASE")]
+pub(crate) enum RegistryRoot {
+    Hkcr,
+    Hkcu,
+    Hklm,
+    Hku,
+    Hkmu,
+}
+
+impl RegistryRoot {
+    /// The value of the `Root` column.
+    pub(crate) fn code(self) -> i32 {
+        match self {
+            RegistryRoot::Hkmu => -1,
+            RegistryRoot::Hkcr => 0,
+            RegistryRoot::Hkcu => 1,
+            RegistryRoot::Hklm => 2,
+            RegistryRoot::Hku => 3,
+        }
+    }
+
+    pub(crate) fn from_code(code: i32) -> Option<RegistryRoot> {
+        match code {
+            -1 => Some(RegistryRoot::Hkmu),
+            0 => Some(RegistryRoot::Hkcr),
+            1 => Some(RegistryRoot::Hkcu),
+            2 => Some(RegistryRoot::Hklm),
+            3 => Some(RegistryRoot::Hku),
+            _ => None,
+        }
+    }
+}
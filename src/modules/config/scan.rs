@@ -0,0 +1,64 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// # Scan
+///
+/// The `[scan]` section picks the files of the input directory that are
+/// added to the package. Patterns use the syntax of `.gitignore` files and
+/// are matched against paths relative to the folder being scanned, which is
+/// the input directory or the `source` of a `[[files]]` entry. A
+/// `.whimsiignore` file at the top of that folder adds exclude patterns of
+/// its own, and is never added itself.
+///
+/// ## Properties
+///
+/// - `include` Patterns of the files to add. When it is empty every file is
+///   added.
+///
+/// - `exclude` Patterns of the files and folders to leave out, such as
+///   `*.pdb` or `tests/`. They are read after `.whimsiignore`, so a `!`
+///   pattern here adds back a file that it leaves out.
+///
+/// - `symlinks` One of `skip`, `follow` or `error`, what to do with
+///   symbolic links. Followed links are added as the files and folders
+///   they point to, and links to a folder that they are in fail the build.
+///   With `error` any link fails the build, and every link found is listed.
+///   Links that the patterns leave out are never looked at. Defaults to
+///   `skip`.
+#[derive(Default, Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ScanConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) include: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) exclude: Vec<String>,
+    #[serde(default)]
+    pub(crate) symlinks: Symlinks,
+}
+
+impl ScanConfig {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.include.is_empty()
+            && self.exclude.is_empty()
+            && self.symlinks == Symlinks::default()
+    }
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    JsonSchema,
+    Serialize,
+    PartialEq,
+    Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Symlinks {
+    #[default]
+    Skip,
+    Follow,
+    Error,
+}
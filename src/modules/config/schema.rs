@@ -0,0 +1,97 @@
+//! The JSON Schema of a config, so editors can complete and check configs
+//! as they are written.
+
+use schemars::{
+    schema::{
+        ArrayValidation, InstanceType, ObjectValidation, RootSchema, Schema,
+        SchemaObject, SingleOrVec,
+    },
+    schema_for,
+};
+
+use super::msi_config::MsiConfig;
+
+/// The schema of [`MsiConfig`], along with the `include`, `variables` and
+/// `[[if]]` keys that are resolved before the config is read.
+pub(crate) fn config_schema() -> RootSchema {
+    let mut root = schema_for!(MsiConfig);
+    let object = root.schema.object();
+
+    let mut conditional = object.clone();
+    conditional.required.clear();
+    conditional.required.insert("condition".to_owned());
+    conditional.properties.insert(
+        "condition".to_owned(),
+        described(
+            "Applies the entry when two values compared with `==` or `!=` \
+            match, or when a single value isn't empty, `0` or `false`.",
+            instance(InstanceType::String),
+        ),
+    );
+
+    object.properties.insert(
+        "include".to_owned(),
+        described(
+            "Configs merged below this one, as paths relative to it.",
+            array(instance(InstanceType::String)),
+        ),
+    );
+    object.properties.insert(
+        "variables".to_owned(),
+        described(
+            "Values of the `${name}` variables used in strings.",
+            SchemaObject {
+                instance_type: Some(InstanceType::Object.into()),
+                object: Some(Box::new(ObjectValidation {
+                    additional_properties: Some(Box::new(Schema::Object(
+                        SchemaObject {
+                            instance_type: Some(SingleOrVec::Vec(vec![
+                                InstanceType::String,
+                                InstanceType::Number,
+                                InstanceType::Boolean,
+                            ])),
+                            ..Default::default()
+                        },
+                    ))),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            },
+        ),
+    );
+    object.properties.insert(
+        "if".to_owned(),
+        described(
+            "Sections merged into the config when their `condition` holds.",
+            array(SchemaObject {
+                instance_type: Some(InstanceType::Object.into()),
+                object: Some(Box::new(conditional)),
+                ..Default::default()
+            }),
+        ),
+    );
+    root
+}
+
+fn instance(instance_type: InstanceType) -> SchemaObject {
+    SchemaObject {
+        instance_type: Some(instance_type.into()),
+        ..Default::default()
+    }
+}
+
+fn array(items: SchemaObject) -> SchemaObject {
+    SchemaObject {
+        instance_type: Some(InstanceType::Array.into()),
+        array: Some(Box::new(ArrayValidation {
+            items: Some(SingleOrVec::Single(Box::new(Schema::Object(items)))),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+}
+
+fn described(description: &str, mut schema: SchemaObject) -> Schema {
+    schema.metadata().description = Some(description.to_owned());
+    Schema::Object(schema)
+}
@@ -0,0 +1,75 @@
+use camino::Utf8PathBuf;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// # [Shortcut](https://learn.microsoft.com/en-us/windows/win32/msi/shortcut-table)
+///
+/// A `[[shortcut]]` entry creates a shortcut to one of the installed files.
+///
+/// Each shortcut gets a component of its own that uses a value under
+/// `HKCU\Software\<manufacturer>\<product name>` as its key path, since
+/// shortcuts are usually made in the profile of the user. Folders made for
+/// the shortcut are removed again when it is uninstalled.
+///
+/// ## Properties
+///
+/// - `name` Name of the shortcut.
+///
+/// - `directory` Folder the shortcut is made in, as a directory identifier
+///   followed by the names of the folders under it, such as
+///   `ProgramMenuFolder/My App`. The identifier can be `INSTALLDIR` or a
+///   [system folder](https://learn.microsoft.com/en-us/windows/win32/msi/property-reference#system-folder-properties).
+///   Folders that don't exist yet are made.
+///
+/// - `target` Path of the file the shortcut opens, relative to the input
+///   directory.
+///
+/// - `arguments` Command line arguments passed to the target.
+///
+/// - `description` Tooltip of the shortcut.
+///
+/// - `working_directory` Folder the target is started in, written the same
+///   way as `directory`.
+///
+/// - `show` One of `normal`, `maximized` or `minimized`, how the window of
+///   the target is shown.
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ShortcutConfig {
+    pub(crate) name: String,
+    pub(crate) directory: String,
+    #[schemars(with = "String")]
+    pub(crate) target: Utf8PathBuf,
+    pub(crate) arguments: Option<String>,
+    pub(crate) description: Option<String>,
+    pub(crate) working_directory: Option<String>,
+    pub(crate) show: Option<ShowCommand>,
+}
+
+#[derive(Clone, Copy, Deserialize, JsonSchema, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ShowCommand {
+    Normal,
+    Maximized,
+    Minimized,
+}
+
+impl ShowCommand {
+    /// The value of the `ShowCmd` column.
+    pub(crate) fn code(self) -> i32 {
+        match self {
+            ShowCommand::Normal => 1,
+            ShowCommand::Maximized => 3,
+            ShowCommand::Minimized => 7,
+        }
+    }
+
+    pub(crate) fn from_code(code: i32) -> Option<ShowCommand> {
+        match code {
+            1 => Some(ShowCommand::Normal),
+            3 => Some(ShowCommand::Maximized),
+            7 => Some(ShowCommand::Minimized),
+            _ => None,
+        }
+    }
+}
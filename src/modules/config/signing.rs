@@ -0,0 +1,48 @@
+use camino::{Utf8Path, Utf8PathBuf};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::modules::signing::SigningOptions;
+
+/// # [Signing](https://learn.microsoft.com/en-us/windows/win32/msi/digital-signatures-and-windows-installer)
+///
+/// When a `[signing]` section is present the package is signed with
+/// Authenticode after it is built.
+///
+/// ## Properties
+///
+/// - `certificate` PEM file holding the signing certificate, optionally
+///   followed by intermediate certificates to include in the signature.
+///   Relative paths are relative to the config file.
+///
+/// - `key` PEM file holding the RSA private key of the signing certificate.
+///   Relative paths are relative to the config file.
+///
+/// - `timestamp_url` URL of an RFC 3161 timestamp server. Timestamped
+///   signatures stay valid after the certificate expires.
+///
+/// - `extended` Whether to also sign the metadata of the streams in the
+///   package by writing `MsiDigitalSignatureEx`. Defaults to `false`.
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SigningConfig {
+    #[schemars(with = "String")]
+    pub(crate) certificate: Utf8PathBuf,
+    #[schemars(with = "String")]
+    pub(crate) key: Utf8PathBuf,
+    pub(crate) timestamp_url: Option<String>,
+    #[serde(default)]
+    pub(crate) extended: bool,
+}
+
+impl SigningConfig {
+    /// The options for signing, with paths resolved against `config_dir`.
+    pub(crate) fn options(&self, config_dir: &Utf8Path) -> SigningOptions {
+        SigningOptions {
+            certificate: config_dir.join(&self.certificate),
+            key: config_dir.join(&self.key),
+            timestamp_url: self.timestamp_url.clone(),
+            extended: self.extended,
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use flexstr::LocalStr;
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 /// # [Summary Information Properties](https://learn.microsoft.com/en-us/windows/win32/msi/summary-property-descriptions)
 ///
@@ -33,7 +34,8 @@ use serde::Deserialize;
 ///
 /// - [`code_page`](https://learn.microsoft.com/en-us/windows/win32/msi/codepage-summary)
 ///   The numeric value of the ANSI code page used for any strings that are
-///   stored in the summary information
+///   stored in the summary information and the tables, such as `"1252"`.
+///   The build fails if a string can't be represented in it.
 ///
 /// - [`comments`](https://learn.microsoft.com/en-us/windows/win32/msi/comments-summary)
 ///   Conveys the general purpose of the installation package, transform, or
@@ -43,12 +45,14 @@ use serde::Deserialize;
 ///   Contains the name of the software used to author this MSI. If this is not
 ///   set in the config, it is populated with "whimsi".
 ///
-#[derive(Deserialize)]
-#[serde(rename = "summary_info")]
+#[derive(Deserialize, JsonSchema, Serialize)]
+#[serde(rename = "summary_info", deny_unknown_fields)]
 pub(crate) struct SummaryInformationProperties {
     // Required
     pub(crate) page_count: u16,
+    #[schemars(with = "String")]
     pub(crate) revision_number: LocalStr,
+    #[schemars(with = "String")]
     pub(crate) template: LocalStr,
     // Optional in config, required by MSI.
     pub(crate) word_count: Option<u16>,
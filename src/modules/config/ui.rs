@@ -0,0 +1,43 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// # [User Interface](https://learn.microsoft.com/en-us/windows/win32/msi/user-interface)
+///
+/// The top level `ui` key picks one of the built-in wizards shown when the
+/// package is installed with a full UI.
+///
+/// ## Values
+///
+/// - `none` No dialogs, so only the basic progress bar is shown. This is
+///   the default.
+///
+/// - `minimal` A welcome page, the license agreement and a page to start
+///   the installation.
+///
+/// - `installdir` The `minimal` pages with a page to change the
+///   installation directory in between.
+///
+/// - `featuretree` The `minimal` pages with a feature selection tree in
+///   between. The tree shows the `[[feature]]` entries, or the features of
+///   a WiX source.
+///
+/// Every wizard also has progress, exit, cancel and error dialogs.
+///
+/// ## License
+///
+/// The top level `license` key is the path of the license agreement, shown
+/// on a page that must be accepted before continuing. Files starting with
+/// `{\rtf` are used as RTF and any other file is used as plain text.
+/// Relative paths are relative to the config file. Without a license the
+/// page is left out.
+#[derive(
+    Clone, Copy, Default, Deserialize, JsonSchema, Serialize, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum UiSet {
+    #[default]
+    None,
+    Minimal,
+    InstallDir,
+    FeatureTree,
+}
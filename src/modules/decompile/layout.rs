@@ -0,0 +1,202 @@
+//! Where the directories of a package are, both on the target system and in
+//! the source tree next to the package.
+
+use std::collections::HashMap;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use flexstr::LocalStr;
+
+use crate::modules::{
+    component::{directory::Directory, file::File},
+    validation::database::Database,
+};
+
+const TARGETDIR: &str = "TARGETDIR";
+const INSTALLDIR: &str = "INSTALLDIR";
+
+/// The parent and the target and source names of every directory, from the
+/// `DefaultDir` column written as `target[:source]`.
+pub(super) struct Directories<'a> {
+    entries: HashMap<&'a str, Entry<'a>>,
+}
+
+struct Entry<'a> {
+    parent: Option<&'a str>,
+    target: &'a str,
+    source: &'a str,
+}
+
+impl<'a> Directories<'a> {
+    pub(super) fn new(db: &'a Database) -> Self {
+        let mut entries = HashMap::new();
+        if let Some(table) = db.table("Directory") {
+            for row in &table.rows {
+                let Some(id) = table.str(row, "Directory") else {
+                    continue;
+                };
+                let default_dir = table.str(row, "DefaultDir").unwrap_or(".");
+                let (target, source) = default_dir
+                    .split_once(':')
+                    .unwrap_or((default_dir, default_dir));
+                let parent = table
+                    .str(row, "Directory_Parent")
+                    .filter(|parent| *parent != id);
+                entries.insert(
+                    id,
+                    Entry {
+                        parent,
+                        target,
+                        source,
+                    },
+                );
+            }
+        }
+        Directories { entries }
+    }
+
+    /// `id` and the directories above it, starting with `id`.
+    fn ancestors(&self, id: &'a str) -> Vec<&'a str> {
+        let mut ancestors = vec![id];
+        let mut current = id;
+        // Stop on a cycle, which ICE03 reports on its own.
+        while let Some(parent) =
+            self.entries.get(current).and_then(|e| e.parent)
+        {
+            if ancestors.contains(&parent) {
+                break;
+            }
+            ancestors.push(parent);
+            current = parent;
+        }
+        ancestors
+    }
+
+    /// The directory that the input directory is installed to, which is
+    /// `INSTALLDIR` when the package has it and otherwise the deepest
+    /// directory that holds every one of `directories`.
+    pub(super) fn install_root<'b>(
+        &self,
+        directories: impl IntoIterator<Item = &'b str>,
+    ) -> &'a str {
+        if let Some(root) = self.key(INSTALLDIR) {
+            return root;
+        }
+        let mut common: Option<Vec<&'a str>> = None;
+        for directory in directories {
+            let Some(id) = self.key(directory) else {
+                continue;
+            };
+            let mut chain = self.ancestors(id);
+            chain.reverse();
+            common = Some(match common {
+                None => chain,
+                Some(common) => common
+                    .into_iter()
+                    .zip(chain)
+                    .take_while(|(a, b)| a == b)
+                    .map(|(a, _)| a)
+                    .collect(),
+            });
+        }
+        common
+            .and_then(|chain| chain.last().copied())
+            .unwrap_or(TARGETDIR)
+    }
+
+    /// The path of `id` below `root` on the target system, or `None` if it
+    /// isn't below `root`.
+    pub(super) fn target_path(
+        &self,
+        id: &str,
+        root: &str,
+    ) -> Option<Utf8PathBuf> {
+        let mut names = Vec::new();
+        for directory in self.ancestors(self.key(id)?) {
+            if directory == root {
+                return Some(names.iter().rev().collect());
+            }
+            let name = long_name(self.entries.get(directory)?.target);
+            if name != "." {
+                names.push(name);
+            }
+        }
+        None
+    }
+
+    /// The path of `id` as a directory identifier followed by the names of
+    /// the folders under it, the way shortcuts name their folder. Folders
+    /// below `root` start from `INSTALLDIR`, and other folders start from the
+    /// standard folder they are in.
+    pub(super) fn anchored_path(&self, id: &str, root: &str) -> String {
+        if let Some(path) = self.target_path(id, root) {
+            return match path.as_str() {
+                "" => INSTALLDIR.to_owned(),
+                path => format!("{INSTALLDIR}/{path}"),
+            };
+        }
+        let Some(id) = self.key(id) else {
+            return id.to_owned();
+        };
+        let mut names = Vec::new();
+        for directory in self.ancestors(id) {
+            let entry = &self.entries[directory];
+            if matches!(entry.parent, None | Some(TARGETDIR)) {
+                names.push(directory);
+                break;
+            }
+            names.push(long_name(entry.target));
+        }
+        names.retain(|name| *name != ".");
+        names.reverse();
+        names.join("/")
+    }
+
+    /// The path of `id` in the source tree, relative to the folder of the
+    /// package. Packages with short file names use the short halves.
+    pub(super) fn source_path(&self, id: &str, short: bool) -> Utf8PathBuf {
+        let Some(id) = self.key(id) else {
+            return Utf8PathBuf::new();
+        };
+        let mut names = self
+            .ancestors(id)
+            .into_iter()
+            // The root is the folder of the package itself.
+            .filter(|directory| self.entries[directory].parent.is_some())
+            .map(|directory| file_name(self.entries[directory].source, short))
+            .filter(|name| *name != ".")
+            .collect::<Vec<_>>();
+        names.reverse();
+        names.iter().collect()
+    }
+
+    fn key(&self, id: &str) -> Option<&'a str> {
+        self.entries.get_key_value(id).map(|(key, _)| *key)
+    }
+}
+
+/// The key that the file at `path` below the input directory gets when the
+/// config is built again, which scanning derives from the folders it is in.
+pub(super) fn rebuilt_file_key(path: &Utf8Path) -> String {
+    let mut directory = LocalStr::from(INSTALLDIR);
+    for folder in path.parent().into_iter().flat_map(Utf8Path::iter) {
+        let folder = Utf8PathBuf::from(folder);
+        directory = Directory::from_path(&folder, &directory).id().clone();
+    }
+    File::new(&path.to_owned(), &directory, 0, 0)
+        .file_id()
+        .to_string()
+}
+
+/// The long half of a `short|long` name, or the name if it has no halves.
+pub(super) fn long_name(name: &str) -> &str {
+    file_name(name, false)
+}
+
+/// The short or long half of a `short|long` name.
+pub(super) fn file_name(name: &str, short: bool) -> &str {
+    match name.split_once('|') {
+        Some((short_name, _)) if short => short_name,
+        Some((_, long)) => long,
+        None => name,
+    }
+}
@@ -0,0 +1,794 @@
+//! Turning an installer back into a config and an input directory that
+//! build an equivalent package.
+//!
+//! The config covers the product and summary information, the properties,
+//! features, registry values, shortcuts and custom actions, and the GUIDs of
+//! the components of the files. The files installed to `INSTALLDIR` are
+//! extracted to the input directory, and the values that name them by their
+//! key are changed to the keys the rebuilt package gives them. Anything
+//! else, such as the dialogs or files installed elsewhere, is left out with
+//! a warning, along with the properties and custom actions that refer to
+//! it.
+
+mod layout;
+mod payload;
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    io::Read,
+};
+
+use anyhow::{bail, Context, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use log::warn;
+use msi::{Package, PackageType};
+
+use crate::modules::{
+    config::{
+        component::ComponentConfig,
+        custom_action::{CustomActionConfig, ScheduleConfig},
+        feature::{FeatureConfig, FeatureDisplay},
+        msi_config::MsiConfig,
+        product_information::ProductInformationProperties,
+        registry::{RegistryConfig, RegistryRoot},
+        scan::ScanConfig,
+        shortcut::{ShortcutConfig, ShowCommand},
+        summary_information::SummaryInformationProperties,
+        ui::UiSet,
+    },
+    tables::sequence::{EXECUTE_SEQUENCE, UI_SEQUENCE},
+    transform::{property, template},
+    validation::{database::Database, schema::SYSTEM_FOLDERS},
+};
+
+use self::{
+    layout::{long_name, rebuilt_file_key, Directories},
+    payload::Payload,
+};
+
+const CONFIG_FILE: &str = "whimsi.toml";
+/// Folder of the output that the files are extracted to, which is the input
+/// directory when the config is built again.
+pub(crate) const FILES_DIR: &str = "files";
+/// Folder of the output that custom action binaries are extracted to.
+const BINARY_DIR: &str = "binary";
+
+/// The properties that the `product_info` section holds.
+const PRODUCT_PROPERTIES: [&str; 5] = [
+    "ProductName",
+    "ProductVersion",
+    "Manufacturer",
+    "ProductLanguage",
+    "ProductCode",
+];
+
+/// Tables that the config describes. Rows of other tables are lost, so
+/// their tables are warned about.
+const DECOMPILED_TABLES: [&str; 15] = [
+    "_Validation",
+    "Property",
+    "Directory",
+    "Component",
+    "File",
+    "MsiFileHash",
+    "Media",
+    "Feature",
+    "FeatureComponents",
+    "Registry",
+    "Shortcut",
+    "CustomAction",
+    "Binary",
+    EXECUTE_SEQUENCE,
+    UI_SEQUENCE,
+];
+
+/// `CustomAction.Type` base types that run a file from the `Binary` table.
+const BINARY_ACTION_TYPES: [i32; 4] = [1, 2, 5, 6];
+/// `CustomAction.Type` base types that run an installed file, whose key is
+/// the `Source`.
+const FILE_ACTION_TYPES: [i32; 4] = [17, 18, 21, 22];
+
+/// Properties whose value is the key of a row of a table, with that table.
+const TABLE_PROPERTIES: [(&str, &str); 2] =
+    [("DefaultUIFont", "TextStyle"), ("ErrorDialog", "Dialog")];
+/// Directories that the rebuilt package has under the same id.
+const KEPT_DIRECTORIES: [&str; 2] = ["TARGETDIR", "INSTALLDIR"];
+
+/// Decompile the installer at `input_file` into the `output` folder and
+/// return the path of the config written to it.
+pub(crate) fn decompile(
+    input_file: &Utf8Path,
+    output: &Utf8Path,
+) -> Result<Utf8PathBuf> {
+    let mut package = msi::open(input_file)
+        .with_context(|| format!("Failed to open {input_file}"))?;
+    if package.package_type() != PackageType::Installer {
+        bail!("Only installers can be decompiled");
+    }
+    let db = Database::load(&mut package)?;
+    let directories = Directories::new(&db);
+    let decompiler = Decompiler::new(&db, &directories);
+    decompiler.warn_lost_tables();
+
+    let custom_action = decompiler.custom_actions();
+    let binaries = decompiler.binaries(&custom_action);
+    let mut config = MsiConfig {
+        product_info: decompiler.product_info()?,
+        summary_info: summary_info(&package),
+        merge_module: Vec::new(),
+        signing: None,
+        ui: UiSet::None,
+        license: None,
+        dialog: Vec::new(),
+        localization: None,
+        property: decompiler.properties(),
+        feature: decompiler.features(),
+        registry: decompiler.registry(),
+        shortcut: decompiler.shortcuts(),
+        custom_action,
+        binary: BTreeMap::new(),
+        scan: ScanConfig::default(),
+        files: Vec::new(),
+        file_version: Vec::new(),
+        component: decompiler.component_guids(),
+    };
+
+    let payload = decompiler.payload(&output.join(FILES_DIR));
+    payload::extract(&mut package, input_file, &db, &directories, &payload)?;
+    for name in binaries {
+        let path = Utf8PathBuf::from(BINARY_DIR).join(&name);
+        let stream = format!("Binary.{name}");
+        let mut bytes = Vec::new();
+        package
+            .read_stream(&stream)
+            .and_then(|mut reader| reader.read_to_end(&mut bytes))
+            .with_context(|| format!("Failed to read stream {stream}"))?;
+        payload::write_file(&output.join(&path), &bytes)?;
+        config.binary.insert(name, path);
+    }
+
+    let config_path = output.join(CONFIG_FILE);
+    let text = toml::to_string(&config)
+        .with_context(|| "Failed to serialize the config")?;
+    payload::write_file(&config_path, text.as_bytes())?;
+    Ok(config_path)
+}
+
+/// The summary information of the package. The package code is left for
+/// every build to generate, as the rebuilt package is a different one.
+fn summary_info<F>(package: &Package<F>) -> SummaryInformationProperties {
+    let info = package.summary_info();
+    SummaryInformationProperties {
+        page_count: 200,
+        revision_number: "*".into(),
+        template: template(package).into(),
+        word_count: None,
+        author: info.author().map(str::to_owned),
+        code_page: Some(info.codepage().id().to_string()),
+        comments: info.comments().map(str::to_owned),
+        generating_application: None,
+    }
+}
+
+/// A file that is extracted to the input directory.
+struct FileEntry<'a> {
+    component: &'a str,
+    /// Path of the file below the input directory.
+    path: Utf8PathBuf,
+    /// The key the file gets when the config is built again.
+    key: String,
+}
+
+struct Decompiler<'a> {
+    db: &'a Database,
+    directories: &'a Directories<'a>,
+    /// The directory that the input directory is installed to.
+    root: &'a str,
+    /// The directory and key path of every component.
+    components: HashMap<&'a str, (&'a str, Option<&'a str>)>,
+    /// The features of every component, in the order of the `Feature` table.
+    features: HashMap<&'a str, Vec<&'a str>>,
+    /// The files that are extracted, by their key in the `File` table.
+    files: BTreeMap<&'a str, FileEntry<'a>>,
+}
+
+impl<'a> Decompiler<'a> {
+    fn new(db: &'a Database, directories: &'a Directories<'a>) -> Self {
+        let mut components = HashMap::new();
+        if let Some(table) = db.table("Component") {
+            for row in &table.rows {
+                let (Some(id), Some(directory)) =
+                    (table.str(row, "Component"), table.str(row, "Directory_"))
+                else {
+                    continue;
+                };
+                components.insert(id, (directory, table.str(row, "KeyPath")));
+            }
+        }
+
+        let mut features: HashMap<&str, Vec<&str>> = HashMap::new();
+        if let Some(table) = db.table("FeatureComponents") {
+            for row in &table.rows {
+                if let (Some(feature), Some(component)) =
+                    (table.str(row, "Feature_"), table.str(row, "Component_"))
+                {
+                    features.entry(component).or_default().push(feature);
+                }
+            }
+        }
+        let order = db
+            .table("Feature")
+            .map(|table| {
+                table
+                    .rows
+                    .iter()
+                    .filter_map(|row| table.str(row, "Feature"))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        for members in features.values_mut() {
+            members.sort_by_key(|feature| {
+                order.iter().position(|candidate| candidate == feature)
+            });
+        }
+
+        let file_rows = db.rows("File");
+        let table = db.table("File");
+        let file_directory = |row: &'a [msi::Value]| {
+            let component = table?.str(row, "Component_")?;
+            Some((component, components.get(component)?.0))
+        };
+        let root = directories.install_root(
+            file_rows
+                .iter()
+                .filter_map(|row| file_directory(row))
+                .map(|(_, directory)| directory),
+        );
+
+        let mut files = BTreeMap::new();
+        let mut paths = BTreeSet::new();
+        for row in file_rows {
+            let (Some(table), Some((component, directory))) =
+                (table, file_directory(row))
+            else {
+                continue;
+            };
+            let Some(key) = table.str(row, "File") else {
+                continue;
+            };
+            let name = long_name(table.str(row, "FileName").unwrap_or(key));
+            let Some(path) = directories.target_path(directory, root) else {
+                warn!(
+                    "File {key} is installed outside {root}, so it is left out"
+                );
+                continue;
+            };
+            let path = path.join(name);
+            if !paths.insert(path.clone()) {
+                warn!(
+                    "File {key} is installed to {path} like another file, so \
+                    it is left out"
+                );
+                continue;
+            }
+            let entry = FileEntry {
+                component,
+                key: rebuilt_file_key(&path),
+                path,
+            };
+            files.insert(key, entry);
+        }
+
+        Decompiler {
+            db,
+            directories,
+            root,
+            components,
+            features,
+            files,
+        }
+    }
+
+    /// Warn about the tables with rows that the config can't describe.
+    fn warn_lost_tables(&self) {
+        let shortcut_components = self.shortcut_components();
+        for table in self.db.tables() {
+            let lost = match table.name.as_str() {
+                name if DECOMPILED_TABLES.contains(&name) => false,
+                // Shortcut folders are removed again by the rebuilt package.
+                "RemoveFile" => table.rows.iter().any(|row| {
+                    !matches!(
+                        table.str(row, "Component_"),
+                        Some(component) if shortcut_components.contains(component)
+                    )
+                }),
+                _ => !table.rows.is_empty(),
+            };
+            if lost {
+                warn!(
+                    "The {} table can't be decompiled and is left out",
+                    table.name
+                );
+            }
+        }
+    }
+
+    /// The components of shortcuts, whose key path registry value the
+    /// rebuilt package writes itself.
+    fn shortcut_components(&self) -> HashSet<&'a str> {
+        let Some(table) = self.db.table("Shortcut") else {
+            return HashSet::new();
+        };
+        table
+            .rows
+            .iter()
+            .filter_map(|row| table.str(row, "Component_"))
+            .collect()
+    }
+
+    fn product_info(&self) -> Result<ProductInformationProperties> {
+        let property = |name| {
+            property(self.db, name)
+                .with_context(|| format!("The package has no {name} property"))
+        };
+        let language = property("ProductLanguage")?;
+        Ok(ProductInformationProperties {
+            product_name: property("ProductName")?.into(),
+            product_version: property("ProductVersion")?.into(),
+            manufacturer: property("Manufacturer")?.into(),
+            product_language: language.parse().with_context(|| {
+                format!(
+                    "ProductLanguage {language} isn't a language identifier"
+                )
+            })?,
+            product_code: property("ProductCode")?.into(),
+        })
+    }
+
+    fn properties(&self) -> BTreeMap<String, String> {
+        let Some(table) = self.db.table("Property") else {
+            return BTreeMap::new();
+        };
+        table
+            .rows
+            .iter()
+            .filter_map(|row| {
+                let name = table.str(row, "Property")?;
+                let value = table.str(row, "Value")?;
+                if PRODUCT_PROPERTIES.contains(&name) {
+                    return None;
+                }
+                if let Some((_, table)) = TABLE_PROPERTIES
+                    .iter()
+                    .find(|(property, _)| *property == name)
+                    .filter(|(_, table)| !DECOMPILED_TABLES.contains(table))
+                {
+                    warn!(
+                        "Property {name} names a row of the {table} table, \
+                        so it is left out along with the table"
+                    );
+                    return None;
+                }
+                if self.is_lost_directory(value) {
+                    warn!(
+                        "Property {name} names directory {value}, which the \
+                        rebuilt package doesn't have, so it is left out"
+                    );
+                    return None;
+                }
+                Some((name.to_owned(), value.to_owned()))
+            })
+            .collect()
+    }
+
+    /// Whether `id` is a directory of the package that the rebuilt package
+    /// doesn't have, as the directories below the root get new ids and the
+    /// others are only made for shortcuts.
+    fn is_lost_directory(&self, id: &str) -> bool {
+        self.db.has_key("Directory", id)
+            && !KEPT_DIRECTORIES.contains(&id)
+            && !SYSTEM_FOLDERS.contains(&id)
+    }
+
+    /// `text` with the `[#key]` and `[!key]` references to files changed to
+    /// the keys of the rebuilt package, or `None` if it refers to a file
+    /// that is left out.
+    fn rebuilt_references(&self, text: &str) -> Option<String> {
+        let mut rebuilt = String::new();
+        let mut rest = text;
+        while let Some(start) = [rest.find("[#"), rest.find("[!")]
+            .into_iter()
+            .flatten()
+            .min()
+        {
+            let Some(end) = rest[start..].find(']').map(|end| start + end)
+            else {
+                break;
+            };
+            let file = self.files.get(&rest[start + 2..end])?;
+            rebuilt.push_str(&rest[..start + 2]);
+            rebuilt.push_str(&file.key);
+            rebuilt.push(']');
+            rest = &rest[end + 1..];
+        }
+        rebuilt.push_str(rest);
+        Some(rebuilt)
+    }
+
+    /// The GUIDs of the components whose key path is an extracted file, as
+    /// every file gets a component of its own when the config is built
+    /// again.
+    fn component_guids(&self) -> Vec<ComponentConfig> {
+        let Some(table) = self.db.table("Component") else {
+            return Vec::new();
+        };
+        self.files
+            .iter()
+            .filter_map(|(key, file)| {
+                let (_, key_path) = self.components.get(file.component)?;
+                if *key_path != Some(*key) {
+                    return None;
+                }
+                let row = self.db.row("Component", file.component)?;
+                Some(ComponentConfig {
+                    file: file.path.clone(),
+                    guid: table.str(row, "ComponentId")?.to_owned(),
+                })
+            })
+            .collect()
+    }
+
+    /// The first feature, which the rebuilt package installs every file
+    /// that no feature lists with.
+    fn first_feature(&self) -> Option<&'a str> {
+        let table = self.db.table("Feature")?;
+        table.str(table.rows.first()?, "Feature")
+    }
+
+    fn features(&self) -> Vec<FeatureConfig> {
+        let Some(table) = self.db.table("Feature") else {
+            return Vec::new();
+        };
+        let first = self.first_feature();
+        table
+            .rows
+            .iter()
+            .filter_map(|row| {
+                let id = table.str(row, "Feature")?;
+                // Files of only the first feature don't have to be listed.
+                let files = self
+                    .files
+                    .values()
+                    .filter(|file| {
+                        let features = self
+                            .features
+                            .get(file.component)
+                            .map(Vec::as_slice)
+                            .unwrap_or_default();
+                        features.contains(&id)
+                            && (Some(id) != first || features.len() > 1)
+                    })
+                    .map(|file| file.path.clone())
+                    .collect();
+                Some(FeatureConfig {
+                    id: id.to_owned(),
+                    title: table.str(row, "Title").map(str::to_owned),
+                    description: table
+                        .str(row, "Description")
+                        .map(str::to_owned),
+                    parent: table.str(row, "Feature_Parent").map(str::to_owned),
+                    level: table.int(row, "Level").unwrap_or(1),
+                    display: match table.int(row, "Display").unwrap_or(0) {
+                        0 => FeatureDisplay::Hidden,
+                        display if display % 2 == 0 => FeatureDisplay::Expand,
+                        _ => FeatureDisplay::Collapse,
+                    },
+                    configurable: table.str(row, "Directory_").is_some(),
+                    files,
+                })
+            })
+            .collect()
+    }
+
+    fn registry(&self) -> Vec<RegistryConfig> {
+        let Some(table) = self.db.table("Registry") else {
+            return Vec::new();
+        };
+        let shortcut_components = self.shortcut_components();
+        let first = self.first_feature();
+        table
+            .rows
+            .iter()
+            .filter_map(|row| {
+                let id = table.str(row, "Registry")?;
+                let component = table.str(row, "Component_")?;
+                let key_path = self.components.get(component)?.1;
+                if shortcut_components.contains(component)
+                    && key_path == Some(id)
+                {
+                    return None;
+                }
+                let root = RegistryRoot::from_code(table.int(row, "Root")?)?;
+                let value = match table.str(row, "Value") {
+                    Some(value) => self.rebuilt_references(value).map(Some),
+                    None => Some(None),
+                };
+                let Some(value) = value else {
+                    warn!(
+                        "Registry value {id} refers to a file that is left \
+                        out, so it is left out too"
+                    );
+                    return None;
+                };
+                // Values of a file component are installed with that file,
+                // and the others with the first feature of their component.
+                let file = key_path
+                    .and_then(|key| self.files.get(key))
+                    .filter(|file| file.component == component)
+                    .map(|file| file.path.clone());
+                let feature = match file {
+                    Some(_) => None,
+                    None => self
+                        .features
+                        .get(component)
+                        .and_then(|features| features.first().copied())
+                        .filter(|feature| Some(*feature) != first)
+                        .map(str::to_owned),
+                };
+                Some(RegistryConfig {
+                    root,
+                    key: table.str(row, "Key")?.to_owned(),
+                    name: table.str(row, "Name").map(str::to_owned),
+                    value,
+                    file,
+                    feature,
+                })
+            })
+            .collect()
+    }
+
+    fn shortcuts(&self) -> Vec<ShortcutConfig> {
+        let Some(table) = self.db.table("Shortcut") else {
+            return Vec::new();
+        };
+        table
+            .rows
+            .iter()
+            .filter_map(|row| {
+                let name = long_name(table.str(row, "Name")?);
+                let target = table.str(row, "Target")?;
+                let file_key = target
+                    .strip_prefix("[#")
+                    .or_else(|| target.strip_prefix("[!"))
+                    .and_then(|key| key.strip_suffix(']'));
+                let file = match file_key {
+                    Some(key) => self.files.get(key),
+                    None => {
+                        // An advertised shortcut, whose target is a feature.
+                        warn!(
+                            "Shortcut {name} is advertised and becomes a \
+                            shortcut to the key file of its component"
+                        );
+                        let component = table.str(row, "Component_")?;
+                        self.components
+                            .get(component)
+                            .and_then(|(_, key_path)| *key_path)
+                            .and_then(|key| self.files.get(key))
+                    }
+                };
+                let Some(file) = file else {
+                    warn!(
+                        "Shortcut {name} doesn't open an extracted file, so \
+                        it is left out"
+                    );
+                    return None;
+                };
+                let directory = table.str(row, "Directory_")?;
+                Some(ShortcutConfig {
+                    name: name.to_owned(),
+                    directory: self
+                        .directories
+                        .anchored_path(directory, self.root),
+                    target: file.path.clone(),
+                    arguments: table.str(row, "Arguments").map(str::to_owned),
+                    description: table
+                        .str(row, "Description")
+                        .map(str::to_owned),
+                    working_directory: table.str(row, "WkDir").map(
+                        |directory| {
+                            self.directories.anchored_path(directory, self.root)
+                        },
+                    ),
+                    show: table
+                        .int(row, "ShowCmd")
+                        .and_then(ShowCommand::from_code),
+                })
+            })
+            .collect()
+    }
+
+    fn custom_actions(&self) -> Vec<CustomActionConfig> {
+        let Some(table) = self.db.table("CustomAction") else {
+            return Vec::new();
+        };
+        table
+            .rows
+            .iter()
+            .filter_map(|row| {
+                let id = table.str(row, "Action")?;
+                let kind = table.int(row, "Type")?;
+                // Files are named by the keys the rebuilt package gives
+                // them.
+                let source = match table.str(row, "Source") {
+                    Some(key) if FILE_ACTION_TYPES.contains(&(kind & 0x3F)) => {
+                        self.files.get(key).map(|file| Some(file.key.clone()))
+                    }
+                    source => Some(source.map(str::to_owned)),
+                };
+                let target = match table.str(row, "Target") {
+                    Some(target) => self.rebuilt_references(target).map(Some),
+                    None => Some(None),
+                };
+                let (Some(source), Some(target)) = (source, target) else {
+                    warn!(
+                        "Custom action {id} runs or refers to a file that is \
+                        left out, so it is left out too"
+                    );
+                    return None;
+                };
+                Some(CustomActionConfig {
+                    id: id.to_owned(),
+                    kind,
+                    source,
+                    target,
+                    execute: self.schedule(EXECUTE_SEQUENCE, id),
+                    ui: self.schedule(UI_SEQUENCE, id),
+                })
+            })
+            .collect()
+    }
+
+    /// Where an action is scheduled in a sequence table.
+    fn schedule(&self, table: &str, action: &str) -> Option<ScheduleConfig> {
+        let table = self.db.table(table)?;
+        let row = table
+            .rows
+            .iter()
+            .find(|row| table.str(row, "Action") == Some(action))?;
+        Some(ScheduleConfig {
+            sequence: table.int(row, "Sequence")?,
+            condition: table.str(row, "Condition").map(str::to_owned),
+        })
+    }
+
+    /// The names of the `Binary` rows that custom actions run.
+    fn binaries(&self, actions: &[CustomActionConfig]) -> BTreeSet<String> {
+        let names = self
+            .db
+            .rows("Binary")
+            .iter()
+            .filter_map(|row| row.first().and_then(|name| name.as_str()))
+            .collect::<HashSet<_>>();
+        actions
+            .iter()
+            .filter(|action| {
+                BINARY_ACTION_TYPES.contains(&(action.kind & 0x3F))
+            })
+            .filter_map(|action| action.source.as_deref())
+            .filter(|source| names.contains(source))
+            .map(str::to_owned)
+            .collect()
+    }
+
+    /// The files to extract to `files_dir`.
+    fn payload(&self, files_dir: &Utf8Path) -> Vec<Payload<'a>> {
+        let Some(table) = self.db.table("File") else {
+            return Vec::new();
+        };
+        table
+            .rows
+            .iter()
+            .filter_map(|row| {
+                let key = table.str(row, "File")?;
+                let file = self.files.get(key)?;
+                Some(Payload {
+                    key,
+                    directory: self.components.get(file.component)?.0,
+                    name: table.str(row, "FileName")?,
+                    sequence: table.int(row, "Sequence").unwrap_or(0),
+                    attributes: table.int(row, "Attributes").unwrap_or(0),
+                    destination: files_dir.join(&file.path),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, process::ExitCode};
+
+    use camino::Utf8Path;
+    use msi::{Expr, Select};
+
+    use super::{decompile, FILES_DIR};
+    use crate::{
+        command::builder::{build, ConfigSource, PackageKind},
+        modules::{config::msi_config::MsiConfig, helpers::test_dir::TestDir},
+    };
+
+    /// The string values of the rows `query` selects from the package at
+    /// `path`.
+    fn select(path: &Utf8Path, query: Select) -> Vec<Vec<String>> {
+        let mut package = msi::open(path).unwrap();
+        package
+            .select_rows(query)
+            .unwrap()
+            .map(|row| {
+                (0..row.len())
+                    .map(|index| row[index].as_str().unwrap().to_owned())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rebuilds_an_equivalent_package() {
+        let dir = TestDir::new();
+        let config_path = decompile(
+            Utf8Path::new("examples/example_2.msi"),
+            &dir.path().join("out"),
+        )
+        .unwrap();
+        let text = std::fs::read_to_string(&config_path).unwrap();
+        let config: MsiConfig = toml::from_str(&text).unwrap();
+
+        // The properties of the dialogs and of the directory that only the
+        // dialogs use are left out with them.
+        for property in ["DefaultUIFont", "ErrorDialog", "WIXUI_INSTALLDIR"] {
+            assert!(!config.property.contains_key(property), "{property}");
+        }
+        assert!(config.property.contains_key("ARPNOMODIFY"));
+
+        let package_path = dir.path().join("rebuilt.msi");
+        let built = build(
+            &ConfigSource::Toml(config_path.clone()),
+            &config_path.parent().unwrap().join(FILES_DIR),
+            &package_path,
+            PackageKind::Installer,
+            &[],
+            &BTreeMap::new(),
+        );
+        assert!(built == ExitCode::SUCCESS);
+
+        // The action that starts the application runs the rebuilt file.
+        let actions = select(
+            &package_path,
+            Select::table("CustomAction")
+                .inner_join(
+                    Select::table("File"),
+                    Expr::col("CustomAction.Source").eq(Expr::col("File.File")),
+                )
+                .columns(&["CustomAction.Action", "File.FileName"]),
+        );
+        assert_eq!(actions, [["StartAppOnExit", "DMT.exe"]]);
+        // The components of the files keep their GUIDs.
+        let guids = |path: &Utf8Path| {
+            let mut guids = select(
+                path,
+                Select::table("Component")
+                    .inner_join(
+                        Select::table("File"),
+                        Expr::col("Component.KeyPath")
+                            .eq(Expr::col("File.File")),
+                    )
+                    .columns(&["Component.ComponentId"]),
+            );
+            guids.sort();
+            guids
+        };
+        let original = guids(Utf8Path::new("examples/example_2.msi"));
+        assert_eq!(original.len(), 7);
+        assert_eq!(guids(&package_path), original);
+    }
+}
@@ -0,0 +1,166 @@
+//! Extracting the files of a package, from the cabinets that the `Media`
+//! table lists or from the source tree next to the package.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Cursor, Read, Seek},
+};
+
+use anyhow::{Context, Result};
+use cab::Cabinet;
+use camino::{Utf8Path, Utf8PathBuf};
+use log::warn;
+use msi::Package;
+
+use crate::modules::validation::database::Database;
+
+use super::layout::{file_name, Directories};
+
+/// `Word Count` bit set when the source tree uses short file names.
+const SHORT_NAMES: i32 = 0x1;
+/// `Word Count` bit set when the files are compressed in cabinets.
+const COMPRESSED: i32 = 0x2;
+/// `File.Attributes` bits that override the compression of the package.
+const FILE_NONCOMPRESSED: i32 = 0x2000;
+const FILE_COMPRESSED: i32 = 0x4000;
+
+/// A file of the package and where it is extracted to.
+pub(super) struct Payload<'a> {
+    /// The key of the file in the `File` table, which is also its name in
+    /// the cabinet.
+    pub(super) key: &'a str,
+    pub(super) directory: &'a str,
+    /// The `short|long` name of the file.
+    pub(super) name: &'a str,
+    pub(super) sequence: i32,
+    pub(super) attributes: i32,
+    pub(super) destination: Utf8PathBuf,
+}
+
+type PackageCabinet = Cabinet<Cursor<Vec<u8>>>;
+
+/// Extract every file to its destination. Files that can't be found, such
+/// as those of an external cabinet that isn't next to the package, are
+/// skipped with a warning.
+pub(super) fn extract<F: Read + Seek>(
+    package: &mut Package<F>,
+    package_path: &Utf8Path,
+    db: &Database,
+    directories: &Directories,
+    files: &[Payload],
+) -> Result<()> {
+    let word_count = package.summary_info().word_count().unwrap_or(0);
+    let short_names = word_count & SHORT_NAMES != 0;
+    let package_dir = package_path.parent().unwrap_or(package_path);
+    let media = media(db);
+    let mut cabinets: HashMap<&str, Option<PackageCabinet>> = HashMap::new();
+
+    for file in files {
+        let compressed = match file.attributes {
+            attributes if attributes & FILE_NONCOMPRESSED != 0 => false,
+            attributes if attributes & FILE_COMPRESSED != 0 => true,
+            _ => word_count & COMPRESSED != 0,
+        };
+        let contents = if compressed {
+            let Some(name) = media
+                .iter()
+                .find(|(last_sequence, _)| *last_sequence >= file.sequence)
+                .and_then(|(_, cabinet)| *cabinet)
+            else {
+                warn!("Skipped {}, no cabinet holds it", file.destination);
+                continue;
+            };
+            if !cabinets.contains_key(name) {
+                let cabinet = open_cabinet(package, package_dir, name)
+                    .inspect_err(|err| warn!("{err:#}"))
+                    .ok();
+                cabinets.insert(name, cabinet);
+            }
+            let Some(cabinet) = cabinets.get_mut(name).and_then(Option::as_mut)
+            else {
+                warn!(
+                    "Skipped {}, its cabinet can't be read",
+                    file.destination
+                );
+                continue;
+            };
+            read_cabinet_file(cabinet, file.key)
+        } else {
+            let source = package_dir
+                .join(directories.source_path(file.directory, short_names))
+                .join(file_name(file.name, short_names));
+            fs::read(&source)
+                .with_context(|| format!("Failed to read {source}"))
+        };
+
+        match contents {
+            Ok(contents) => write_file(&file.destination, &contents)?,
+            Err(err) => warn!("Skipped {}: {err:#}", file.destination),
+        }
+    }
+    Ok(())
+}
+
+/// The last sequence number and cabinet of every disk, in order.
+fn media(db: &Database) -> Vec<(i32, Option<&str>)> {
+    let Some(table) = db.table("Media") else {
+        return Vec::new();
+    };
+    let mut media = table
+        .rows
+        .iter()
+        .filter_map(|row| {
+            let last_sequence = table.int(row, "LastSequence")?;
+            Some((last_sequence, table.str(row, "Cabinet")))
+        })
+        .collect::<Vec<_>>();
+    media.sort_by_key(|(last_sequence, _)| *last_sequence);
+    media
+}
+
+/// Open a cabinet, which is a stream of the package when its name starts
+/// with `#` and a file next to the package otherwise.
+fn open_cabinet<F: Read + Seek>(
+    package: &mut Package<F>,
+    package_dir: &Utf8Path,
+    name: &str,
+) -> Result<PackageCabinet> {
+    let bytes = match name.strip_prefix('#') {
+        Some(stream) => {
+            let mut bytes = Vec::new();
+            package
+                .read_stream(stream)
+                .and_then(|mut reader| reader.read_to_end(&mut bytes))
+                .with_context(|| format!("Failed to read stream {stream}"))?;
+            bytes
+        }
+        None => {
+            let path = package_dir.join(name);
+            fs::read(&path)
+                .with_context(|| format!("Failed to read cabinet {path}"))?
+        }
+    };
+    Cabinet::new(Cursor::new(bytes))
+        .with_context(|| format!("Failed to open cabinet {name}"))
+}
+
+fn read_cabinet_file(
+    cabinet: &mut PackageCabinet,
+    key: &str,
+) -> Result<Vec<u8>> {
+    let mut contents = Vec::new();
+    cabinet
+        .read_file(key)
+        .and_then(|mut reader| reader.read_to_end(&mut contents))
+        .with_context(|| format!("Failed to extract {key} from its cabinet"))?;
+    Ok(contents)
+}
+
+pub(super) fn write_file(path: &Utf8Path, contents: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {parent}"))?;
+    }
+    fs::write(path, contents).with_context(|| format!("Failed to write {path}"))
+}
@@ -0,0 +1,109 @@
+//! A writer for uncompressed cabinet files.
+//!
+//! Every file is stored in a single folder without compression. Windows
+//! Installer extracts files by the name they have in the cabinet, which is
+//! the key of their row in the `File` table.
+
+use anyhow::{bail, Result};
+
+/// Size of the header when no reserved areas are present.
+const HEADER_SIZE: usize = 36;
+const FOLDER_SIZE: usize = 8;
+/// Fixed size of a file entry before its name.
+const FILE_ENTRY_SIZE: usize = 16;
+const DATA_HEADER_SIZE: usize = 8;
+/// Largest amount of data a single data block may hold.
+const MAX_BLOCK: usize = 0x8000;
+/// Set on file entries whose names are UTF-8.
+const NAME_IS_UTF: u16 = 0x80;
+
+/// Files waiting to be written to a cabinet, in the order they will be
+/// extracted.
+#[derive(Default)]
+pub(crate) struct Cabinet {
+    files: Vec<(String, Vec<u8>)>,
+}
+
+impl Cabinet {
+    pub(crate) fn add_file(&mut self, name: &str, contents: Vec<u8>) {
+        self.files.push((name.to_owned(), contents));
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Serialize the cabinet.
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>> {
+        if self.files.len() > u16::MAX as usize {
+            bail!("A cabinet can hold at most {} files", u16::MAX);
+        }
+        let data = self
+            .files
+            .iter()
+            .flat_map(|(_, contents)| contents.iter().copied())
+            .collect::<Vec<u8>>();
+        let blocks = data.chunks(MAX_BLOCK).collect::<Vec<&[u8]>>();
+        if blocks.len() > u16::MAX as usize {
+            bail!("Files are too large to fit in a single cabinet");
+        }
+
+        let files_offset = HEADER_SIZE + FOLDER_SIZE;
+        let entries_size = self
+            .files
+            .iter()
+            .map(|(name, _)| FILE_ENTRY_SIZE + name.len() + 1)
+            .sum::<usize>();
+        let data_offset = files_offset + entries_size;
+        let total = data_offset + blocks.len() * DATA_HEADER_SIZE + data.len();
+        let Ok(total) = u32::try_from(total) else {
+            bail!("Files are too large to fit in a single cabinet");
+        };
+
+        let mut out = Vec::with_capacity(total as usize);
+        out.extend_from_slice(b"MSCF");
+        out.extend_from_slice(&0u32.to_le_bytes());
+        out.extend_from_slice(&total.to_le_bytes());
+        out.extend_from_slice(&0u32.to_le_bytes());
+        out.extend_from_slice(&(files_offset as u32).to_le_bytes());
+        out.extend_from_slice(&0u32.to_le_bytes());
+        // Format version 1.3.
+        out.extend_from_slice(&[3, 1]);
+        out.extend_from_slice(&1u16.to_le_bytes());
+        out.extend_from_slice(&(self.files.len() as u16).to_le_bytes());
+        // No flags, set ID and index within the set.
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+
+        // The single folder, which isn't compressed.
+        out.extend_from_slice(&(data_offset as u32).to_le_bytes());
+        out.extend_from_slice(&(blocks.len() as u16).to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+
+        let mut offset = 0u32;
+        for (name, contents) in &self.files {
+            out.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+            out.extend_from_slice(&offset.to_le_bytes());
+            // Folder index, then a DOS date of 1980-01-01 and a time of
+            // midnight so the cabinet is the same on every build.
+            out.extend_from_slice(&0u16.to_le_bytes());
+            out.extend_from_slice(&0x0021u16.to_le_bytes());
+            out.extend_from_slice(&0u16.to_le_bytes());
+            let attributes = if name.is_ascii() { 0 } else { NAME_IS_UTF };
+            out.extend_from_slice(&attributes.to_le_bytes());
+            out.extend_from_slice(name.as_bytes());
+            out.push(0);
+            offset += contents.len() as u32;
+        }
+
+        for block in blocks {
+            // A checksum of zero means it isn't checked.
+            out.extend_from_slice(&0u32.to_le_bytes());
+            out.extend_from_slice(&(block.len() as u16).to_le_bytes());
+            out.extend_from_slice(&(block.len() as u16).to_le_bytes());
+            out.extend_from_slice(block);
+        }
+        Ok(out)
+    }
+}
@@ -0,0 +1,349 @@
+//! Just enough DER encoding and decoding to build and read Authenticode
+//! signatures.
+//!
+//! Values are built from already encoded parts, so a structure is written by
+//! nesting calls the same way its ASN.1 definition nests.
+
+use anyhow::{bail, Context, Result};
+
+pub(crate) const INTEGER: u8 = 0x02;
+pub(crate) const BIT_STRING: u8 = 0x03;
+pub(crate) const OCTET_STRING: u8 = 0x04;
+pub(crate) const NULL: u8 = 0x05;
+pub(crate) const OID: u8 = 0x06;
+pub(crate) const SEQUENCE: u8 = 0x30;
+pub(crate) const SET: u8 = 0x31;
+const BOOLEAN: u8 = 0x01;
+const CONTEXT_CONSTRUCTED: u8 = 0xA0;
+const CONTEXT_PRIMITIVE: u8 = 0x80;
+
+/// Encode a value from its tag and contents.
+pub(crate) fn tlv(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = contents.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|b| **b == 0).count();
+        out.push(0x80 | (bytes.len() - skip) as u8);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+    out.extend_from_slice(contents);
+    out
+}
+
+pub(crate) fn sequence(parts: &[Vec<u8>]) -> Vec<u8> {
+    tlv(SEQUENCE, &parts.concat())
+}
+
+/// A `SET OF`, which DER requires to be sorted by the encoded elements.
+pub(crate) fn set(parts: &[Vec<u8>]) -> Vec<u8> {
+    let mut sorted = parts.to_vec();
+    sorted.sort();
+    tlv(SET, &sorted.concat())
+}
+
+/// A constructed `[n]` value. This is both an `[n] EXPLICIT` wrapper around
+/// an encoded value and an `[n] IMPLICIT` `SET OF` built from its elements.
+pub(crate) fn context(number: u8, contents: &[u8]) -> Vec<u8> {
+    tlv(CONTEXT_CONSTRUCTED | number, contents)
+}
+
+pub(crate) fn null() -> Vec<u8> {
+    tlv(NULL, &[])
+}
+
+pub(crate) fn boolean(value: bool) -> Vec<u8> {
+    tlv(BOOLEAN, &[if value { 0xFF } else { 0 }])
+}
+
+pub(crate) fn octet_string(contents: &[u8]) -> Vec<u8> {
+    tlv(OCTET_STRING, contents)
+}
+
+pub(crate) fn integer(value: u64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|b| **b == 0).count().min(7);
+    let mut contents = bytes[skip..].to_vec();
+    // Keep the value positive.
+    if contents[0] & 0x80 != 0 {
+        contents.insert(0, 0);
+    }
+    tlv(INTEGER, &contents)
+}
+
+/// Encode an object identifier written in dotted form.
+pub(crate) fn oid(dotted: &str) -> Vec<u8> {
+    let arcs = dotted
+        .split('.')
+        .map(|arc| arc.parse::<u64>().expect("OIDs are written correctly"))
+        .collect::<Vec<u64>>();
+    let mut contents = vec![(arcs[0] * 40 + arcs[1]) as u8];
+    for arc in &arcs[2..] {
+        let mut chunk = vec![(arc & 0x7F) as u8];
+        let mut rest = arc >> 7;
+        while rest > 0 {
+            chunk.insert(0, (rest & 0x7F) as u8 | 0x80);
+            rest >>= 7;
+        }
+        contents.extend(chunk);
+    }
+    tlv(OID, &contents)
+}
+
+/// An `AlgorithmIdentifier` with `NULL` parameters.
+pub(crate) fn algorithm(dotted: &str) -> Vec<u8> {
+    sequence(&[oid(dotted), null()])
+}
+
+/// A single decoded value.
+#[derive(Clone, Copy)]
+pub(crate) struct Tlv<'a> {
+    pub(crate) tag: u8,
+    pub(crate) contents: &'a [u8],
+    /// The whole encoding, including the tag and length.
+    pub(crate) raw: &'a [u8],
+}
+
+impl<'a> Tlv<'a> {
+    /// Decode a value that must take up all of `bytes`.
+    pub(crate) fn parse(bytes: &'a [u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let value = reader.read()?;
+        if !reader.is_empty() {
+            bail!("Unexpected data after DER value");
+        }
+        Ok(value)
+    }
+
+    /// Check the tag and read the contents as a series of values.
+    pub(crate) fn expect(self, tag: u8) -> Result<Reader<'a>> {
+        Ok(Reader::new(self.expect_contents(tag)?))
+    }
+
+    /// Check the tag and return the contents as they are.
+    pub(crate) fn expect_contents(self, tag: u8) -> Result<&'a [u8]> {
+        if self.tag != tag {
+            bail!("Expected DER tag {:#04x} but found {:#04x}", tag, self.tag);
+        }
+        Ok(self.contents)
+    }
+
+    /// Whether this is the `[n]` context specific value.
+    pub(crate) fn is_context(&self, number: u8) -> bool {
+        self.tag == CONTEXT_CONSTRUCTED | number
+            || self.tag == CONTEXT_PRIMITIVE | number
+    }
+
+    /// The dotted form of an object identifier.
+    pub(crate) fn oid(&self) -> Result<String> {
+        if self.tag != OID || self.contents.is_empty() {
+            bail!("Expected an object identifier");
+        }
+        let first = self.contents[0] as u64;
+        let mut arcs =
+            vec![(first / 40).min(2), first - (first / 40).min(2) * 40];
+        let mut arc = 0u64;
+        for byte in &self.contents[1..] {
+            arc = (arc << 7) | (byte & 0x7F) as u64;
+            if byte & 0x80 == 0 {
+                arcs.push(arc);
+                arc = 0;
+            }
+        }
+        Ok(arcs
+            .iter()
+            .map(u64::to_string)
+            .collect::<Vec<String>>()
+            .join("."))
+    }
+
+    /// The bytes of an `INTEGER` without leading zero padding.
+    pub(crate) fn unsigned(&self) -> Result<&'a [u8]> {
+        if self.tag != INTEGER {
+            bail!("Expected an integer");
+        }
+        let skip = self.contents.iter().take_while(|b| **b == 0).count();
+        Ok(&self.contents[skip.min(self.contents.len().saturating_sub(1))..])
+    }
+}
+
+/// Reads consecutive values out of the contents of a constructed value.
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub(crate) fn read(&mut self) -> Result<Tlv<'a>> {
+        let bytes = self.bytes;
+        let (&tag, rest) = bytes
+            .split_first()
+            .with_context(|| "DER value is truncated")?;
+        let (&first, rest) = rest
+            .split_first()
+            .with_context(|| "DER value is truncated")?;
+        let (len, rest) = if first < 0x80 {
+            (first as usize, rest)
+        } else {
+            let count = (first & 0x7F) as usize;
+            if count == 0 || count > 4 || rest.len() < count {
+                bail!("DER value has an unsupported length");
+            }
+            let len = rest[..count]
+                .iter()
+                .fold(0usize, |len, b| (len << 8) | *b as usize);
+            (len, &rest[count..])
+        };
+        if rest.len() < len {
+            bail!("DER value is truncated");
+        }
+        let header = bytes.len() - rest.len();
+        self.bytes = &rest[len..];
+        Ok(Tlv {
+            tag,
+            contents: &rest[..len],
+            raw: &bytes[..header + len],
+        })
+    }
+
+    /// Read the next value if it is the `[n]` context specific value.
+    pub(crate) fn optional(&mut self, number: u8) -> Option<Tlv<'a>> {
+        let mut peek = Reader::new(self.bytes);
+        match peek.read() {
+            Ok(value) if value.is_context(number) => {
+                self.bytes = peek.bytes;
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        context, integer, null, octet_string, oid, sequence, set, Tlv,
+        OCTET_STRING, SEQUENCE,
+    };
+
+    fn error(bytes: &[u8]) -> String {
+        Tlv::parse(bytes).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn encodes_lengths_in_the_fewest_bytes() {
+        for (len, header) in [
+            (0, vec![0x04, 0x00]),
+            (0x7F, vec![0x04, 0x7F]),
+            (0x80, vec![0x04, 0x81, 0x80]),
+            (0xFF, vec![0x04, 0x81, 0xFF]),
+            (0x100, vec![0x04, 0x82, 0x01, 0x00]),
+            (0x1_0000, vec![0x04, 0x83, 0x01, 0x00, 0x00]),
+        ] {
+            let contents = vec![0xAB; len];
+            let encoded = octet_string(&contents);
+            assert_eq!(encoded[..header.len()], header, "length {len:#x}");
+            assert_eq!(encoded.len(), header.len() + len);
+
+            let value = Tlv::parse(&encoded).unwrap();
+            assert_eq!(value.tag, OCTET_STRING);
+            assert_eq!(value.contents, contents.as_slice());
+            assert_eq!(value.raw, encoded.as_slice());
+        }
+    }
+
+    #[test]
+    fn encodes_integers_as_positive() {
+        assert_eq!(integer(0), [0x02, 0x01, 0x00]);
+        assert_eq!(integer(0x7F), [0x02, 0x01, 0x7F]);
+        assert_eq!(integer(0x80), [0x02, 0x02, 0x00, 0x80]);
+        assert_eq!(integer(0x0100), [0x02, 0x02, 0x01, 0x00]);
+        let max = integer(u64::MAX);
+        assert_eq!(max[..3], [0x02, 0x09, 0x00]);
+        assert_eq!(Tlv::parse(&max).unwrap().unsigned().unwrap(), [0xFF; 8]);
+        assert_eq!(
+            Tlv::parse(&integer(0)).unwrap().unsigned().unwrap(),
+            [0x00]
+        );
+    }
+
+    #[test]
+    fn round_trips_object_identifiers() {
+        let encoded = oid("1.2.840.113549.1.7.2");
+        assert_eq!(
+            encoded,
+            [
+                0x06, 0x09, 0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07,
+                0x02
+            ]
+        );
+        for dotted in ["1.2.840.113549.1.7.2", "2.16.840.1.101.3.4.2.1"] {
+            assert_eq!(
+                Tlv::parse(&oid(dotted)).unwrap().oid().unwrap(),
+                dotted
+            );
+        }
+    }
+
+    #[test]
+    fn sorts_sets_by_their_encoding() {
+        assert_eq!(
+            set(&[integer(2), null(), integer(1)]),
+            [0x31, 0x08, 0x02, 0x01, 0x01, 0x02, 0x01, 0x02, 0x05, 0x00]
+        );
+    }
+
+    #[test]
+    fn reads_nested_and_optional_values() {
+        let encoded =
+            sequence(&[context(0, &integer(3)), octet_string(b"abc")]);
+        let mut reader =
+            Tlv::parse(&encoded).unwrap().expect(SEQUENCE).unwrap();
+        assert!(reader.optional(1).is_none());
+        let explicit = reader.optional(0).unwrap();
+        assert_eq!(explicit.contents, integer(3).as_slice());
+        let contents = reader.read().unwrap().expect_contents(OCTET_STRING);
+        assert_eq!(contents.unwrap(), b"abc");
+        assert!(reader.is_empty());
+        assert!(reader.optional(0).is_none());
+    }
+
+    #[test]
+    fn rejects_malformed_values() {
+        assert_eq!(error(&[]), "DER value is truncated");
+        assert_eq!(error(&[0x04]), "DER value is truncated");
+        assert_eq!(error(&[0x04, 0x02, 0x00]), "DER value is truncated");
+        assert_eq!(
+            error(&[0x04, 0x82, 0x01]),
+            "DER value has an unsupported length"
+        );
+        // Indefinite lengths aren't DER.
+        assert_eq!(
+            error(&[0x30, 0x80, 0x00, 0x00]),
+            "DER value has an unsupported length"
+        );
+        assert_eq!(
+            error(&[0x04, 0x85, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00]),
+            "DER value has an unsupported length"
+        );
+        assert_eq!(
+            error(&[0x05, 0x00, 0x00]),
+            "Unexpected data after DER value"
+        );
+        let encoded = null();
+        let wrong_tag = Tlv::parse(&encoded).unwrap().expect(SEQUENCE);
+        assert_eq!(
+            wrong_tag.err().unwrap().to_string(),
+            "Expected DER tag 0x30 but found 0x05"
+        );
+    }
+}
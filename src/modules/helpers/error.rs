@@ -1,31 +1,27 @@
-use flexstr::LocalStr;
+use flexstr::SharedStr;
 
-type Inner = Box<dyn std::error::Error>;
+type Inner = Box<dyn std::error::Error + Send + Sync>;
 
 #[derive(Debug)]
 pub(crate) struct MsiError {
-    message: LocalStr,
+    message: SharedStr,
     inner: Option<Inner>,
 }
 
 impl MsiError {
-    pub fn short(message: impl Into<LocalStr>) -> MsiError {
+    pub fn short(message: impl Into<SharedStr>) -> MsiError {
         MsiError {
             message: message.into(),
             inner: None,
         }
     }
 
-    pub fn nested(message: impl Into<LocalStr>, inner: impl Into<Inner>) -> MsiError {
+    pub fn nested(message: impl Into<SharedStr>, inner: impl Into<Inner>) -> MsiError {
         MsiError {
             message: message.into(),
             inner: Some(inner.into()),
         }
     }
-
-    pub fn inner(&self) -> &Option<Inner> {
-        &self.inner
-    }
 }
 
 impl std::fmt::Display for MsiError {
@@ -38,3 +34,11 @@ impl std::fmt::Display for MsiError {
         write!(f, "{}", msg)
     }
 }
+
+impl std::error::Error for MsiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.inner
+            .as_ref()
+            .map(|e| e.as_ref() as &(dyn std::error::Error + 'static))
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::{Context, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use ignore::{
+    gitignore::{Gitignore, GitignoreBuilder},
+    Match,
+};
+use log::debug;
+
+/// File at the top of a source root listing patterns to leave out.
+pub(crate) const IGNORE_FILE: &str = ".whimsiignore";
+
+/// Decides which files and folders below a source root are scanned, from
+/// the `include` and `exclude` patterns of the config and the
+/// `.whimsiignore` file of the root.
+pub(crate) struct FileFilter {
+    root: Utf8PathBuf,
+    include: Option<Gitignore>,
+    exclude: Gitignore,
+}
+
+impl FileFilter {
+    pub(crate) fn new(
+        root: &Utf8Path,
+        include: &[String],
+        exclude: &[String],
+    ) -> Result<Self> {
+        let include = if include.is_empty() {
+            None
+        } else {
+            Some(matcher(root, None, include)?)
+        };
+        let ignore_file = root.join(IGNORE_FILE);
+        let exclude = matcher(
+            root,
+            ignore_file.is_file().then_some(ignore_file.as_path()),
+            exclude,
+        )?;
+        Ok(FileFilter {
+            root: root.to_owned(),
+            include,
+            exclude,
+        })
+    }
+
+    /// Whether the file or folder at `path` is left out, which is logged
+    /// along with the reason.
+    pub(crate) fn excludes(&self, path: &Utf8Path, is_dir: bool) -> bool {
+        if !is_dir && path == self.root.join(IGNORE_FILE) {
+            return true;
+        }
+        if let Match::Ignore(glob) = self
+            .exclude
+            .matched_path_or_any_parents(path.as_std_path(), is_dir)
+        {
+            match glob.from() {
+                Some(file) => debug!(
+                    "Excluded {}, it matches `{}` in {}",
+                    path,
+                    glob.original(),
+                    file.display()
+                ),
+                None => debug!(
+                    "Excluded {}, it matches exclude pattern `{}`",
+                    path,
+                    glob.original()
+                ),
+            }
+            return true;
+        }
+        let Some(include) = &self.include else {
+            return false;
+        };
+        // Folders are always scanned, as files below them can be included.
+        if is_dir
+            || include
+                .matched_path_or_any_parents(path.as_std_path(), false)
+                .is_ignore()
+        {
+            return false;
+        }
+        debug!("Excluded {}, no include pattern matches it", path);
+        true
+    }
+}
+
+/// A matcher of `patterns` and those of `file`, relative to `root`.
+fn matcher(
+    root: &Utf8Path,
+    file: Option<&Utf8Path>,
+    patterns: &[String],
+) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(root);
+    if let Some(file) = file {
+        if let Some(err) = builder.add(file) {
+            return Err(err).with_context(|| format!("Failed to read {file}"));
+        }
+    }
+    for pattern in patterns {
+        builder
+            .add_line(None, pattern)
+            .with_context(|| format!("Invalid pattern `{pattern}`"))?;
+    }
+    builder
+        .build()
+        .with_context(|| format!("Failed to read the patterns of {root}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileFilter, IGNORE_FILE};
+    use crate::modules::helpers::test_dir::TestDir;
+
+    fn filter(dir: &TestDir, include: &[&str], exclude: &[&str]) -> FileFilter {
+        let strings = |patterns: &[&str]| {
+            patterns
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<String>>()
+        };
+        FileFilter::new(dir.path(), &strings(include), &strings(exclude))
+            .unwrap()
+    }
+
+    fn excludes(
+        filter: &FileFilter,
+        dir: &TestDir,
+        path: &str,
+        is_dir: bool,
+    ) -> bool {
+        filter.excludes(&dir.path().join(path), is_dir)
+    }
+
+    #[test]
+    fn keeps_everything_but_the_ignore_file_by_default() {
+        let dir = TestDir::new();
+        dir.file(IGNORE_FILE, "");
+        let filter = filter(&dir, &[], &[]);
+        assert!(!excludes(&filter, &dir, "app.exe", false));
+        assert!(!excludes(&filter, &dir, "bin", true));
+        assert!(excludes(&filter, &dir, IGNORE_FILE, false));
+        // Only the ignore file at the top is special.
+        assert!(!excludes(&filter, &dir, "bin/.whimsiignore", false));
+    }
+
+    #[test]
+    fn excludes_matching_files_and_everything_below_matching_folders() {
+        let dir = TestDir::new();
+        let filter = filter(&dir, &[], &["*.pdb", "logs/"]);
+        assert!(excludes(&filter, &dir, "app.pdb", false));
+        assert!(excludes(&filter, &dir, "bin/app.pdb", false));
+        assert!(excludes(&filter, &dir, "logs", true));
+        assert!(excludes(&filter, &dir, "logs/today.txt", false));
+        assert!(!excludes(&filter, &dir, "logs", false));
+        assert!(!excludes(&filter, &dir, "app.exe", false));
+    }
+
+    #[test]
+    fn includes_only_matching_files_but_scans_every_folder() {
+        let dir = TestDir::new();
+        let filter = filter(&dir, &["*.exe", "data/"], &[]);
+        assert!(!excludes(&filter, &dir, "app.exe", false));
+        assert!(!excludes(&filter, &dir, "bin/tool.exe", false));
+        assert!(!excludes(&filter, &dir, "data/settings.ini", false));
+        assert!(excludes(&filter, &dir, "readme.txt", false));
+        assert!(!excludes(&filter, &dir, "docs", true));
+    }
+
+    #[test]
+    fn exclude_patterns_win_over_include_patterns() {
+        let dir = TestDir::new();
+        let filter = filter(&dir, &["*.exe"], &["debug.exe", "tests/"]);
+        assert!(!excludes(&filter, &dir, "app.exe", false));
+        assert!(excludes(&filter, &dir, "debug.exe", false));
+        assert!(excludes(&filter, &dir, "tests/runner.exe", false));
+    }
+
+    #[test]
+    fn config_patterns_come_after_the_ignore_file() {
+        let dir = TestDir::new();
+        dir.file(IGNORE_FILE, "*.log\n# A comment\ncache/\n");
+        let filter = filter(&dir, &[], &["!keep.log"]);
+        assert!(excludes(&filter, &dir, "today.log", false));
+        assert!(excludes(&filter, &dir, "cache", true));
+        assert!(!excludes(&filter, &dir, "keep.log", false));
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        let dir = TestDir::new();
+        let error = FileFilter::new(dir.path(), &[], &["{a".to_owned()])
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "Invalid pattern `{a`");
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+
+/// Characters that are never allowed in a file or directory name.
+const INVALID_LONG: &[char] = &['\\', '?', '|', '>', '<', ':', '/', '*', '"'];
+/// Characters that are additionally not allowed in an 8.3 short name.
+const INVALID_SHORT: &[char] = &['+', ',', ';', '=', '[', ']', ' '];
+
+/// Whether `name` can be used as a long file or directory name on the target
+/// system.
+pub(crate) fn is_valid_long_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().count() <= 255
+        && name != "."
+        && name != ".."
+        && !name.ends_with(['.', ' '])
+        && !name
+            .chars()
+            .any(|c| c.is_control() || INVALID_LONG.contains(&c))
+}
+
+/// Whether `name` is a valid 8.3 short name.
+pub(crate) fn is_valid_short_name(name: &str) -> bool {
+    let (base, extension) = name.rsplit_once('.').unwrap_or((name, ""));
+    (1..=8).contains(&base.chars().count())
+        && extension.chars().count() <= 3
+        && !base.contains('.')
+        && name.is_ascii()
+        && !name.chars().any(|c| {
+            c.is_control()
+                || INVALID_LONG.contains(&c)
+                || INVALID_SHORT.contains(&c)
+        })
+}
+
+/// Generates the `short|long` names that the `Filename` and `DefaultDir`
+/// columns expect, keeping the short names unique within one directory.
+#[derive(Default)]
+pub(crate) struct ShortNames {
+    used: HashSet<String>,
+}
+
+impl ShortNames {
+    /// The name to store in the MSI for `long`. Names that are already valid
+    /// short names are used as they are.
+    pub(crate) fn msi_name(&mut self, long: &str) -> String {
+        if is_valid_short_name(long) && self.used.insert(long.to_uppercase()) {
+            return long.to_owned();
+        }
+
+        let (base, extension) = match long.rsplit_once('.') {
+            Some((base, extension)) if !base.is_empty() => (base, extension),
+            _ => (long, ""),
+        };
+        let clean = |s: &str, len: usize| {
+            s.chars()
+                .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .take(len)
+                .collect::<String>()
+                .to_uppercase()
+        };
+        let base = clean(base, 6);
+        let extension = clean(extension, 3);
+
+        for n in 1.. {
+            // Keep the base short enough that the `~N` suffix still fits in
+            // eight characters.
+            let suffix = format!("~{n}");
+            let keep = 8usize.saturating_sub(suffix.len()).min(base.len());
+            let mut short = format!("{}{}", &base[..keep], suffix);
+            if !extension.is_empty() {
+                short = format!("{short}.{extension}");
+            }
+            if self.used.insert(short.clone()) {
+                return format!("{short}|{long}");
+            }
+        }
+        unreachable!("Ran out of short names")
+    }
+}
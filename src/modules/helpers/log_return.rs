@@ -0,0 +1,30 @@
+//! Logging macros that also hand back the message they logged.
+//!
+//! These make it possible to log an error and reuse the same text in the
+//! error that gets returned, without formatting the message twice.
+
+/// Log a message at the error level and return it as a `String`.
+macro_rules! error {
+    ($fmt:literal $(, $args:expr)* $(,)?) => {{
+        let msg = format!($fmt $(, $args)*);
+        log::error!("{}", msg);
+        msg
+    }};
+    ($err:expr) => {{
+        let msg = format!("{}", $err);
+        log::error!("{}", msg);
+        msg
+    }};
+}
+
+/// Log a message at the info level and return it as a `String`.
+macro_rules! info {
+    ($fmt:literal $(, $args:expr)* $(,)?) => {{
+        let msg = format!($fmt $(, $args)*);
+        log::info!("{}", msg);
+        msg
+    }};
+}
+
+pub(crate) use error;
+pub(crate) use info;
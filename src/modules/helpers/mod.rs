@@ -1,4 +1,15 @@
+pub(crate) mod cabinet;
+pub(crate) mod der;
 pub mod error;
+pub(crate) mod file_filter;
+pub(crate) mod filename;
 pub(crate) mod log_return;
+pub(crate) mod property_set;
 pub(crate) mod scan;
 pub(crate) mod sequencer;
+pub(crate) mod stream_name;
+pub(crate) mod suggest;
+pub(crate) mod summary;
+#[cfg(test)]
+pub(crate) mod test_dir;
+pub(crate) mod version_info;
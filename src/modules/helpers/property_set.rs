@@ -0,0 +1,274 @@
+//! A minimal writer for the OLE property sets that hold summary information.
+//!
+//! The `msi` crate only writes the summary information of a package it opened
+//! itself and doesn't expose every property. Transforms and patches need
+//! properties such as the character count, so their summary information is
+//! written with this instead.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `FMTID_SummaryInformation` in the byte order it is stored in.
+const FMTID_SUMMARY_INFORMATION: [u8; 16] = [
+    0xE0, 0x85, 0x9F, 0xF2, 0xF9, 0x4F, 0x68, 0x10, 0xAB, 0x91, 0x08, 0x00,
+    0x2B, 0x27, 0xB3, 0xD9,
+];
+
+pub(crate) const PID_CODEPAGE: u32 = 1;
+pub(crate) const PID_TITLE: u32 = 2;
+pub(crate) const PID_SUBJECT: u32 = 3;
+pub(crate) const PID_AUTHOR: u32 = 4;
+pub(crate) const PID_KEYWORDS: u32 = 5;
+pub(crate) const PID_COMMENTS: u32 = 6;
+pub(crate) const PID_TEMPLATE: u32 = 7;
+pub(crate) const PID_LASTAUTHOR: u32 = 8;
+pub(crate) const PID_REVNUMBER: u32 = 9;
+pub(crate) const PID_CREATE_DTM: u32 = 12;
+pub(crate) const PID_PAGECOUNT: u32 = 14;
+pub(crate) const PID_WORDCOUNT: u32 = 15;
+pub(crate) const PID_CHARCOUNT: u32 = 16;
+pub(crate) const PID_APPNAME: u32 = 18;
+pub(crate) const PID_SECURITY: u32 = 19;
+
+const VT_I2: u32 = 2;
+const VT_I4: u32 = 3;
+const VT_LPSTR: u32 = 30;
+const VT_FILETIME: u32 = 64;
+
+/// Seconds between 1601-01-01, the FILETIME epoch, and the UNIX epoch.
+const FILETIME_EPOCH_OFFSET: u64 = 11_644_473_600;
+
+/// A value that can be stored in the property set.
+#[derive(Clone, Debug)]
+pub(crate) enum Property {
+    I2(i16),
+    I4(i32),
+    /// A string that has already been encoded in the codepage of the set.
+    Str(Vec<u8>),
+    Time(SystemTime),
+}
+
+/// The properties of a single summary information stream.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PropertySet {
+    properties: Vec<(u32, Property)>,
+}
+
+impl PropertySet {
+    /// Set a property, replacing any earlier value.
+    pub(crate) fn set(&mut self, id: u32, value: Property) -> &mut Self {
+        self.properties.retain(|(i, _)| *i != id);
+        self.properties.push((id, value));
+        self
+    }
+
+    /// Set a string property. Strings in summary information use the codepage
+    /// stored in `PID_CODEPAGE`, which whimsi always sets to one that covers
+    /// ASCII, so non-ASCII characters are replaced.
+    pub(crate) fn set_str(&mut self, id: u32, value: &str) -> &mut Self {
+        let bytes = value
+            .chars()
+            .map(|c| if c.is_ascii() { c as u8 } else { b'?' })
+            .collect();
+        self.set(id, Property::Str(bytes))
+    }
+
+    /// Serialize the property set into the bytes of a
+    /// `\u{5}SummaryInformation` stream.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut properties = self.properties.clone();
+        properties.sort_by_key(|(id, _)| *id);
+
+        // Each property is written as its type followed by its value, padded
+        // to a multiple of four bytes.
+        let values = properties
+            .iter()
+            .map(|(_, value)| encode_value(value))
+            .collect::<Vec<Vec<u8>>>();
+
+        let header_len = 8 + 8 * properties.len();
+        let section_len =
+            header_len + values.iter().map(|v| v.len()).sum::<usize>();
+
+        let mut out = Vec::new();
+        // Byte order, format version, OS version and an empty CLSID.
+        out.extend_from_slice(&0xFFFEu16.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(&0x0002_0006u32.to_le_bytes());
+        out.extend_from_slice(&[0; 16]);
+        // A single section that starts right after the section list.
+        out.extend_from_slice(&1u32.to_le_bytes());
+        out.extend_from_slice(&FMTID_SUMMARY_INFORMATION);
+        out.extend_from_slice(&48u32.to_le_bytes());
+
+        out.extend_from_slice(&(section_len as u32).to_le_bytes());
+        out.extend_from_slice(&(properties.len() as u32).to_le_bytes());
+        let mut offset = header_len;
+        for ((id, _), value) in properties.iter().zip(&values) {
+            out.extend_from_slice(&id.to_le_bytes());
+            out.extend_from_slice(&(offset as u32).to_le_bytes());
+            offset += value.len();
+        }
+        for value in values {
+            out.extend_from_slice(&value);
+        }
+        out
+    }
+}
+
+fn encode_value(value: &Property) -> Vec<u8> {
+    let mut out = Vec::new();
+    match value {
+        Property::I2(i) => {
+            out.extend_from_slice(&VT_I2.to_le_bytes());
+            out.extend_from_slice(&i.to_le_bytes());
+        }
+        Property::I4(i) => {
+            out.extend_from_slice(&VT_I4.to_le_bytes());
+            out.extend_from_slice(&i.to_le_bytes());
+        }
+        Property::Str(bytes) => {
+            out.extend_from_slice(&VT_LPSTR.to_le_bytes());
+            // The length includes the terminating null.
+            out.extend_from_slice(&(bytes.len() as u32 + 1).to_le_bytes());
+            out.extend_from_slice(bytes);
+            out.push(0);
+        }
+        Property::Time(time) => {
+            let since_epoch = time
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos()
+                / 100;
+            let filetime =
+                since_epoch as u64 + FILETIME_EPOCH_OFFSET * 10_000_000;
+            out.extend_from_slice(&VT_FILETIME.to_le_bytes());
+            out.extend_from_slice(&filetime.to_le_bytes());
+        }
+    }
+    while out.len() % 4 != 0 {
+        out.push(0);
+    }
+    out
+}
+
+impl PropertySet {
+    /// Read the first section of a serialized property set. Properties of
+    /// types other than the ones whimsi writes are skipped.
+    pub(crate) fn read(bytes: &[u8]) -> Option<Self> {
+        let u32_at = |offset: usize| {
+            bytes
+                .get(offset..offset + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        };
+
+        let section = u32_at(44)? as usize;
+        let count = u32_at(section + 4)? as usize;
+        let mut set = PropertySet::default();
+        for i in 0..count {
+            let id = u32_at(section + 8 + 8 * i)?;
+            let start = section + u32_at(section + 12 + 8 * i)? as usize;
+            let value = match u32_at(start)? {
+                VT_I2 => {
+                    let b = bytes.get(start + 4..start + 6)?;
+                    Property::I2(i16::from_le_bytes([b[0], b[1]]))
+                }
+                VT_I4 => Property::I4(u32_at(start + 4)? as i32),
+                VT_LPSTR => {
+                    let length = u32_at(start + 4)? as usize;
+                    let b = bytes.get(start + 8..start + 8 + length)?;
+                    let end = b.iter().position(|c| *c == 0).unwrap_or(b.len());
+                    Property::Str(b[..end].to_vec())
+                }
+                _ => continue,
+            };
+            set.properties.push((id, value));
+        }
+        Some(set)
+    }
+
+    /// A string property, with bytes outside of ASCII replaced.
+    pub(crate) fn get_str(&self, id: u32) -> Option<String> {
+        self.properties.iter().find_map(|(i, value)| match value {
+            Property::Str(bytes) if *i == id => {
+                Some(String::from_utf8_lossy(bytes).into_owned())
+            }
+            _ => None,
+        })
+    }
+
+    /// An integer property of either width.
+    pub(crate) fn get_int(&self, id: u32) -> Option<i32> {
+        self.properties.iter().find_map(|(i, value)| match value {
+            Property::I2(v) if *i == id => Some(*v as i32),
+            Property::I4(v) if *i == id => Some(*v),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{
+        Property, PropertySet, PID_CODEPAGE, PID_CREATE_DTM, PID_PAGECOUNT,
+        PID_TITLE, PID_WORDCOUNT,
+    };
+
+    #[test]
+    fn round_trips_properties() {
+        let mut set = PropertySet::default();
+        set.set(PID_CODEPAGE, Property::I2(1252))
+            .set_str(PID_TITLE, "Transform")
+            .set(PID_PAGECOUNT, Property::I4(200))
+            .set(PID_WORDCOUNT, Property::I4(0))
+            .set(PID_WORDCOUNT, Property::I4(2));
+
+        let read = PropertySet::read(&set.to_bytes()).unwrap();
+        assert_eq!(read.get_int(PID_CODEPAGE), Some(1252));
+        assert_eq!(read.get_str(PID_TITLE).as_deref(), Some("Transform"));
+        assert_eq!(read.get_int(PID_PAGECOUNT), Some(200));
+        // Setting a property again replaces it.
+        assert_eq!(read.get_int(PID_WORDCOUNT), Some(2));
+        assert_eq!(read.get_str(PID_PAGECOUNT), None);
+    }
+
+    #[test]
+    fn pads_values_and_replaces_non_ascii() {
+        let mut set = PropertySet::default();
+        set.set_str(PID_TITLE, "Café");
+        let bytes = set.to_bytes();
+        // One section of one property, then the type, length, the string,
+        // its null and padding to four bytes.
+        assert_eq!(bytes.len(), 48 + 16 + 16);
+        assert_eq!(&bytes[72..78], b"Caf?\0\0");
+        let read = PropertySet::read(&bytes).unwrap();
+        assert_eq!(read.get_str(PID_TITLE).as_deref(), Some("Caf?"));
+    }
+
+    #[test]
+    fn writes_times_as_filetimes() {
+        let mut set = PropertySet::default();
+        set.set(
+            PID_CREATE_DTM,
+            Property::Time(UNIX_EPOCH + Duration::from_secs(1)),
+        );
+        let bytes = set.to_bytes();
+        let filetime = u64::from_le_bytes(bytes[68..76].try_into().unwrap());
+        assert_eq!(filetime, (11_644_473_600 + 1) * 10_000_000);
+        // Times are skipped when reading.
+        assert_eq!(
+            PropertySet::read(&bytes).unwrap().get_int(PID_CREATE_DTM),
+            None
+        );
+    }
+
+    #[test]
+    fn reading_malformed_sets_gives_none() {
+        assert!(PropertySet::read(&[]).is_none());
+        let mut bytes = PropertySet::default().to_bytes();
+        // A section offset past the end.
+        bytes[44] = 0xff;
+        assert!(PropertySet::read(&bytes).is_none());
+    }
+}
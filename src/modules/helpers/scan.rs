@@ -1,17 +1,29 @@
-#[cfg(target_os = "linux")]
-use std::os::unix::fs::MetadataExt;
-#[cfg(target_os = "windows")]
-use std::os::windows::fs::MetadataExt;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::FileType,
+    rc::Rc,
+};
 
-use anyhow::{Context, Result};
-use camino::Utf8PathBuf;
+use anyhow::{bail, Context, Result};
+use camino::{Utf8DirEntry, Utf8Path, Utf8PathBuf};
 use flexstr::{local_str, LocalStr};
-use itertools::Itertools;
-use log::{debug, error};
+use log::{debug, warn};
+use rayon::prelude::*;
+use uuid::Uuid;
 
+use crate::command::builder::PackageKind;
 use crate::modules::{
     component::{directory::Directory, file::File},
-    helpers::sequencer::Sequencer,
+    config::{
+        component::ComponentConfig, file_version::FileVersionConfig,
+        msi_config::MsiConfig, scan::Symlinks,
+    },
+    helpers::{
+        file_filter::FileFilter, sequencer::Sequencer, suggest::closest,
+        version_info,
+    },
+    traits::identifier::Identifier,
+    validation::schema::SYSTEM_FOLDERS,
 };
 
 const DOT: LocalStr = local_str!(".");
@@ -19,115 +31,1046 @@ const SOURCEDIR: LocalStr = local_str!("SourceDir");
 const TARGETDIR: LocalStr = local_str!("TARGETDIR");
 const PROGRAMFILESFOLDER: LocalStr = local_str!("ProgramFilesFolder");
 const PROGRAMFILES64FOLDER: LocalStr = local_str!("ProgramFiles64Folder");
+const INSTALLDIR: LocalStr = local_str!("INSTALLDIR");
 
-fn scan_path(
-    scan_target: &Utf8PathBuf,
-    sequencer: &mut Sequencer,
-    parent_directory_id: &str,
+/// Scan the input directory and build the directory tree that it will be
+/// installed into.
+///
+/// Without `[[files]]` entries in the config, the contents of
+/// `input_directory` are placed in `INSTALLDIR`, which is a folder named
+/// after the product inside the program files folder that matches the
+/// platform in the summary information template. Otherwise the `source` of
+/// every entry is placed in its `target`, and the directories that several
+/// entries share are only added once.
+///
+/// A merge module leaves the choice of folder to the package it is merged
+/// into, so its contents are placed directly in `TARGETDIR`.
+///
+/// Files and folders that the `[scan]` patterns of the config or the
+/// `.whimsiignore` file leave out are skipped. Symbolic links are skipped,
+/// followed or fail the scan as `symlinks` of the `[scan]` section says.
+///
+/// The folders are read in parallel, one level of the tree at a time, and
+/// every entry that can't be read is reported at once when the walk is
+/// done. The sizes of the files are then looked up in parallel. Once the
+/// files are known, the version and languages of executables are read from
+/// their version resource, unless a `[[file_version]]` entry gives them, and
+/// the components that `[[component]]` entries give a GUID get it.
+pub(crate) fn scan_paths(
+    config: Rc<MsiConfig>,
+    input_directory: &Utf8PathBuf,
+    kind: PackageKind,
 ) -> Result<(Vec<Directory>, Vec<File>)> {
-    debug!("Scanning directory path [{}]", scan_target);
-    // Get the entries present in the `scan_target` directory.
-    let directory_entries = scan_target.read_dir_utf8().with_context(|| format!("Failed to read directory {scan_target}"))?;
-
-    // Get all the entries that did not return an `Err` when scanned.
-    let (ok_entries, errs): (Vec<_>, Vec<_>) =
-        directory_entries.partition_result();
-    // If any of them returned an error, short circuit and return that error.
-    // May change this behavior based on config if desired in the future.
-    let _ = errs.first().with_context(|| format!("Failed to read file inside {scan_target}"))?;
-    
-    // Get all the entries that have a valid filetype. We need to check if
-    // these are directories so if we can't read that from somewhere we need to
-    // exit.
-    //
-    // Also I shamelessly stole the [implementation] for
-    // [`partition_result`](https://docs.rs/itertools/0.14.0/src/itertools/lib.rs.html#3669-3679)
-    // in itertools to make this because I needed the entries back out, not the
-    // filetypes that have to be checked.
-    let mut ok_type_entries = Vec::new();
-    for entry in ok_entries {
-        let filetype = entry.file_type()?;
-        ok_type_entries.push((entry, filetype));
-    }
-
-    // Convert all the entries that are directories into PathBufs for later use.
-    let (mut found_dir_paths, mut found_file_paths) = (Vec::new(), Vec::new());
-    for (entry, filetype) in ok_type_entries {
-        // Only keep the entries that are either directories or files. We don't
-        // care about symlinks or other filetypes.
-        if filetype.is_dir() {
-            found_dir_paths.push(entry.path().to_path_buf())
-        } else if filetype.is_file() {
-            found_file_paths.push(entry.path().to_path_buf())
-        }
-    }
-
-    // Convert all the found directories found in the scan_path directory to
-    // Directory objects. We need to generate a UUID for them and have those
-    // available to pass into the recursive `scan_path` call so they know what
-    // parent directory they are related to
-    //
-    // We only have to do this because we return Directory objects instead of
-    // just PathBuf objects.
-    //
-    // TODO: Look into only returning PathBuf objects and
-    // converting them outside of this function. I'm hesitant this will be much
-    // cleaner because I feel like I'll just have to remake the structure
-    // already present here but required more thought.
-    let found_directories = found_dir_paths
+    let mut directories =
+        vec![Directory::new(TARGETDIR, None::<LocalStr>, SOURCEDIR, None)];
+    let default_target = if kind == PackageKind::Module {
+        TARGETDIR
+    } else {
+        let program_files = if config.summary_info.template.starts_with("x64")
+            || config.summary_info.template.starts_with("Arm64")
+        {
+            PROGRAMFILES64FOLDER
+        } else {
+            PROGRAMFILESFOLDER
+        };
+        directories.extend([
+            Directory::new(program_files.clone(), Some(TARGETDIR), DOT, None),
+            Directory::new(
+                INSTALLDIR,
+                Some(program_files),
+                config.product_info.product_name.clone(),
+                Some(input_directory.clone()),
+            ),
+        ]);
+        INSTALLDIR
+    };
+
+    let scan = &config.scan;
+    let mappings = if config.files.is_empty() {
+        vec![Mapping {
+            source: input_directory.clone(),
+            target: default_target.to_string(),
+            include: scan.include.clone(),
+            exclude: scan.exclude.clone(),
+            vital: Vec::new(),
+        }]
+    } else {
+        config
+            .files
+            .iter()
+            .map(|files| Mapping {
+                source: input_directory.join(&files.source),
+                target: files.target.clone(),
+                // The patterns of an entry win over those of `[scan]`.
+                include: if files.include.is_empty() {
+                    scan.include.clone()
+                } else {
+                    files.include.clone()
+                },
+                exclude: [scan.exclude.as_slice(), &files.exclude].concat(),
+                vital: files.vital.clone(),
+            })
+            .collect()
+    };
+
+    let mut sequencer = Sequencer::new(1);
+    let mut known = HashSet::new();
+    let mut files = Vec::new();
+    for mapping in mappings {
+        if !mapping.source.is_dir() {
+            bail!("Source {} of [[files]] isn't a directory", mapping.source);
+        }
+        let filter = FileFilter::new(
+            &mapping.source,
+            &mapping.include,
+            &mapping.exclude,
+        )?;
+        let scanner = Scanner {
+            filter: &filter,
+            symlinks: scan.symlinks,
+        };
+        let target =
+            target_directory(&mapping, &config.property, &mut directories)?;
+        let (found_directories, mut found_files) =
+            scan_path(&mapping.source, &mut sequencer, &target, &scanner)?;
+        mark_vital(&mapping, &mut found_files)?;
+        // Entries with overlapping targets find the same directories.
+        known.extend(directories.iter().map(|d| d.id().clone()));
+        directories.extend(
+            found_directories
+                .into_iter()
+                .filter(|directory| known.insert(directory.id().clone())),
+        );
+        files.append(&mut found_files);
+    }
+    check_overlaps(&files, &directories)?;
+    read_versions(&config.file_version, input_directory, &mut files)?;
+    apply_file_versions(&config.file_version, input_directory, &mut files)?;
+    apply_component_guids(&config.component, input_directory, &mut files)?;
+    Ok((directories, files))
+}
+
+/// A folder of the input directory and where it is installed.
+struct Mapping {
+    source: Utf8PathBuf,
+    /// A directory id followed by the names of folders below it.
+    target: String,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    /// Files, relative to `source`, that fail the install when they can't
+    /// be installed.
+    vital: Vec<Utf8PathBuf>,
+}
+
+/// The id of the directory that the target of `mapping`, such as
+/// `INSTALLDIR/bin`, names. The directories it needs are added to
+/// `directories`. A root that isn't there yet has to be a system folder or
+/// a directory that a property of the config sets, which Windows Installer
+/// resolves itself.
+fn target_directory(
+    mapping: &Mapping,
+    properties: &BTreeMap<String, String>,
+    directories: &mut Vec<Directory>,
+) -> Result<LocalStr> {
+    let mut names = mapping.target.split('/').filter(|name| !name.is_empty());
+    let Some(root) = names.next() else {
+        bail!(
+            "The [[files]] entry of {} has an empty target",
+            mapping.source
+        );
+    };
+    if !directories.iter().any(|d| d.id().as_str() == root) {
+        if !SYSTEM_FOLDERS.contains(&root) && !properties.contains_key(root) {
+            let candidates = SYSTEM_FOLDERS
+                .iter()
+                .copied()
+                .chain(directories.iter().map(|d| d.id().as_str()))
+                .chain(properties.keys().map(String::as_str));
+            // Ids are compared with case, so a root that only differs in
+            // case is the likeliest meaning.
+            let hint = candidates
+                .clone()
+                .find(|c| c.eq_ignore_ascii_case(root))
+                .or_else(|| closest(root, candidates))
+                .map(|c| format!(", did you mean `{c}`?"))
+                .unwrap_or_default();
+            bail!(
+                "The [[files]] entry of {} installs to {}, but {} is neither \
+                a system folder nor a directory of the package{}",
+                mapping.source,
+                mapping.target,
+                root,
+                hint
+            );
+        }
+        directories.push(Directory::new(root, Some(TARGETDIR), DOT, None));
+    }
+    let mut current = LocalStr::from(root);
+    for name in names {
+        // Windows compares folder names without case, so `bin` and `Bin`
+        // are the same folder.
+        let existing = directories.iter().find(|d| {
+            d.name().eq_ignore_ascii_case(name)
+                && d.parent_id().as_ref() == Some(&current)
+        });
+        current = match existing {
+            Some(directory) => directory.id().clone(),
+            None => {
+                // The same ID that scanning a folder with this name would
+                // give.
+                let id =
+                    Uuid::from_seed(&format!("directory:{current}/{name}"));
+                directories.push(Directory::new(
+                    id.clone(),
+                    Some(current),
+                    name,
+                    Some(mapping.source.clone()),
+                ));
+                id
+            }
+        };
+    }
+    Ok(current)
+}
+
+/// Fail when two files would be installed to the same path. Windows
+/// compares paths without case, so folders are told apart by the path they
+/// are installed to rather than by their ids.
+fn check_overlaps(files: &[File], directories: &[Directory]) -> Result<()> {
+    let by_id = directories
         .iter()
-        .map(|source| Directory::from_path(source, parent_directory_id))
-        .collect_vec();
-
-    // Recursively scan all the directories that were found in the
-    // `scan_target` directory and return all the files and directories that
-    // were found.
-    //
-    // There has to be a better way than making `errs` mutable and popping it
-    // out but if I try to do `errs.first()`but that returns a reference and I
-    // couldn't figure out how to get around the `cannot move out of `*err`
-    // which is behind a shared reference`.
-    let (mut all_dirs, mut all_files): (Vec<Directory>, Vec<File>) =
-        (Default::default(), Default::default());
-    let path_scan_results = found_directories
+        .map(|d| (d.id().as_str(), d))
+        .collect::<HashMap<&str, &Directory>>();
+    let folder = |id: &str| {
+        let mut parts = Vec::new();
+        let mut current = by_id.get(id);
+        while let Some(directory) = current {
+            // A folder that Windows Installer resolves starts the path.
+            if directory.name().as_str() == "." {
+                parts.push(directory.id().to_lowercase());
+                break;
+            }
+            parts.push(directory.name().to_lowercase());
+            current = directory
+                .parent_id()
+                .as_ref()
+                .and_then(|parent| by_id.get(parent.as_str()));
+        }
+        parts.reverse();
+        parts.join("/")
+    };
+
+    let mut installed = HashMap::new();
+    let mut problems = Vec::new();
+    for file in files {
+        let path = (folder(file.directory_id()), file.name().to_lowercase());
+        if let Some(other) = installed.insert(path, file.source()) {
+            problems.push(format!(
+                "{} and {} are both installed as {}",
+                other,
+                file.source(),
+                file.name()
+            ));
+        }
+    }
+    if !problems.is_empty() {
+        bail!(
+            "Found {} file(s) installed to the same path:\n{}",
+            problems.len(),
+            problems.join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// Read the version resources of the files, in parallel, leaving out those
+/// that a `[[file_version]]` entry gives a version for.
+fn read_versions(
+    entries: &[FileVersionConfig],
+    input_directory: &Utf8Path,
+    files: &mut [File],
+) -> Result<()> {
+    let given = entries
         .iter()
-        .map(|dir| {
-            all_dirs.push(dir.clone());
-            scan_path(dir.source().as_ref().unwrap(), sequencer, dir.id())
+        .map(|entry| input_directory.join(&entry.file))
+        .collect::<HashSet<Utf8PathBuf>>();
+    let paths = files
+        .iter()
+        .map(|file| file.source().clone())
+        .collect::<Vec<Utf8PathBuf>>();
+    let versions = paths
+        .par_iter()
+        .map(|path| {
+            if given.contains(path) {
+                return Ok(None);
+            }
+            version_info::read(path)
+                .map_err(|err| format!("Failed to read file {path}: {err}"))
         })
-        .collect_vec();
-    for paths in path_scan_results {
-        match paths {
-            Ok(paths) => {
-                let (mut found_dirs, mut found_files) = paths;
-                all_dirs.append(&mut found_dirs);
-                all_files.append(&mut found_files);
+        .collect::<Vec<_>>();
+
+    let mut problems = Vec::new();
+    for (file, version) in files.iter_mut().zip(versions) {
+        match version {
+            Ok(Some(info)) => {
+                let language = info.language();
+                *file = file
+                    .clone()
+                    .with_version(Some(info.version), Some(language));
             }
-            // Short circuit if any errors were hit during the recursive scan.
-            Err(err) => return Err(err),
+            Ok(None) => {}
+            Err(problem) => problems.push(problem),
         }
     }
+    if !problems.is_empty() {
+        bail!(
+            "Found {} problem(s) while reading file versions:\n{}",
+            problems.len(),
+            problems.join("\n")
+        );
+    }
+    Ok(())
+}
 
-    for file_path in found_file_paths {
-        let size = match file_path.metadata() {
-            Ok(metadata) => {
-                #[cfg(target_os = "linux")]
-                {
-                    metadata.size()
+/// Set the versions that `[[file_version]]` entries give, over those read
+/// from the files.
+fn apply_file_versions(
+    entries: &[FileVersionConfig],
+    input_directory: &Utf8Path,
+    files: &mut [File],
+) -> Result<()> {
+    let find = |files: &[File], path| find_file(files, input_directory, path);
+    let mut problems = Vec::new();
+    for entry in entries {
+        let Some(index) = find(files, &entry.file) else {
+            problems.push(format!(
+                "[[file_version]] {} isn't in the input directory",
+                entry.file
+            ));
+            continue;
+        };
+        // A companion file has the key of the file it follows as its
+        // version.
+        let version = match (&entry.companion, &entry.version) {
+            (Some(_), Some(_)) => {
+                problems.push(format!(
+                    "[[file_version]] {} has both a version and a companion",
+                    entry.file
+                ));
+                continue;
+            }
+            (Some(companion), None) => match find(files, companion) {
+                Some(other) if other != index => {
+                    Some(files[other].file_id().to_string())
+                }
+                _ => {
+                    problems.push(format!(
+                        "Companion {} of {} isn't another file of the input \
+                        directory",
+                        companion, entry.file
+                    ));
+                    continue;
                 }
-                #[cfg(target_os = "windows")]
-                {
-                    metadata.file_size()
+            },
+            (None, version) => version.clone(),
+        };
+        files[index] = files[index]
+            .clone()
+            .with_version(version, entry.language.clone());
+    }
+    if !problems.is_empty() {
+        bail!(
+            "Found {} problem(s) with file versions:\n{}",
+            problems.len(),
+            problems.join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// Give the components of files the GUIDs that `[[component]]` entries
+/// give, written in uppercase between braces as the `Component` table needs
+/// them.
+fn apply_component_guids(
+    entries: &[ComponentConfig],
+    input_directory: &Utf8Path,
+    files: &mut [File],
+) -> Result<()> {
+    let mut problems = Vec::new();
+    for entry in entries {
+        let Some(index) = find_file(files, input_directory, &entry.file) else {
+            problems.push(format!(
+                "[[component]] {} isn't in the input directory",
+                entry.file
+            ));
+            continue;
+        };
+        let Ok(guid) = Uuid::parse_str(&entry.guid) else {
+            problems.push(format!(
+                "[[component]] {} has {}, which isn't a GUID",
+                entry.file, entry.guid
+            ));
+            continue;
+        };
+        if files[index].guid().is_some() {
+            problems.push(format!(
+                "[[component]] {} is given more than once",
+                entry.file
+            ));
+            continue;
+        }
+        let guid = format!("{{{guid}}}").to_uppercase();
+        files[index] = files[index].clone().with_guid(guid);
+    }
+    if !problems.is_empty() {
+        bail!(
+            "Found {} problem(s) with component GUIDs:\n{}",
+            problems.len(),
+            problems.join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// Mark the files that the `vital` list of a `[[files]]` entry names.
+fn mark_vital(mapping: &Mapping, files: &mut [File]) -> Result<()> {
+    for path in &mapping.vital {
+        let Some(index) = find_file(files, &mapping.source, path) else {
+            bail!(
+                "Vital file {} isn't among the files of the [[files]] entry \
+                of {}",
+                path,
+                mapping.source
+            );
+        };
+        files[index] = files[index].clone().with_vital(true);
+    }
+    Ok(())
+}
+
+/// The index of the file installed from `path` in the input directory.
+fn find_file(
+    files: &[File],
+    input_directory: &Utf8Path,
+    path: &Utf8Path,
+) -> Option<usize> {
+    files.iter().position(|file| {
+        file.source()
+            .strip_prefix(input_directory)
+            .is_ok_and(|relative| relative == path)
+    })
+}
+
+/// What the scan of every folder keeps.
+struct Scanner<'a> {
+    filter: &'a FileFilter,
+    symlinks: Symlinks,
+}
+
+/// Scan `scan_target` and the folders below it, placing them in the
+/// directory with the ID `parent_directory_id`.
+fn scan_path(
+    scan_target: &Utf8PathBuf,
+    sequencer: &mut Sequencer,
+    parent_directory_id: &str,
+    scanner: &Scanner,
+) -> Result<(Vec<Directory>, Vec<File>)> {
+    let folders = walk(scan_target, scanner)?;
+    Ok(collect(&folders, parent_directory_id, sequencer))
+}
+
+/// A folder found by the walk, along with what is in it.
+struct Folder {
+    path: Utf8PathBuf,
+    /// The path with every symlink resolved, to catch symlinks to a folder
+    /// that they are in.
+    resolved: Utf8PathBuf,
+    parent: Option<usize>,
+    /// Indexes of the folders in this one, sorted by path.
+    folders: Vec<usize>,
+    /// The files in this folder, sorted by path.
+    files: Vec<ScannedFile>,
+}
+
+/// What reading a single folder found.
+#[derive(Default)]
+struct Listing {
+    /// Paths of the folders in it, along with their resolved paths.
+    folders: Vec<(Utf8PathBuf, Utf8PathBuf)>,
+    files: Vec<ScannedFile>,
+    problems: Vec<String>,
+}
+
+/// A file found by the walk.
+struct ScannedFile {
+    path: Utf8PathBuf,
+    /// Looked up for every file at once when the walk is done.
+    size: u64,
+}
+
+/// Walk the tree below `root` one level at a time, reading the folders of a
+/// level in parallel. The first folder is `root` itself.
+fn walk(root: &Utf8PathBuf, scanner: &Scanner) -> Result<Vec<Folder>> {
+    let resolved = root
+        .canonicalize_utf8()
+        .with_context(|| format!("Failed to resolve directory {root}"))?;
+    let mut folders = vec![Folder {
+        path: root.clone(),
+        resolved,
+        parent: None,
+        folders: Vec::new(),
+        files: Vec::new(),
+    }];
+    let mut problems = Vec::new();
+    let mut level = vec![0];
+    while !level.is_empty() {
+        debug!("Scanning {} directories", level.len());
+        let listings = level
+            .par_iter()
+            .map(|&index| list_folder(&folders[index].path, scanner))
+            .collect::<Vec<_>>();
+        let mut next = Vec::new();
+        for (index, listing) in level.into_iter().zip(listings) {
+            problems.extend(listing.problems);
+            folders[index].files = listing.files;
+            for (path, resolved) in listing.folders {
+                if is_within(&folders, index, &resolved) {
+                    problems.push(format!(
+                        "Symlink {path} points to {resolved}, which it is in"
+                    ));
+                    continue;
                 }
+                let child = folders.len();
+                folders.push(Folder {
+                    path,
+                    resolved,
+                    parent: Some(index),
+                    folders: Vec::new(),
+                    files: Vec::new(),
+                });
+                folders[index].folders.push(child);
+                next.push(child);
+            }
+        }
+        level = next;
+    }
+    problems.extend(read_sizes(&mut folders));
+
+    if !problems.is_empty() {
+        bail!(
+            "Found {} problem(s) while scanning {}:\n{}",
+            problems.len(),
+            root,
+            problems.join("\n")
+        );
+    }
+    Ok(folders)
+}
+
+/// Look up the sizes of the files of every folder in parallel. Returns the
+/// files that can't be read as problems.
+fn read_sizes(folders: &mut [Folder]) -> Vec<String> {
+    folders
+        .par_iter_mut()
+        .flat_map(|folder| folder.files.par_iter_mut())
+        .filter_map(|file| match file.path.metadata() {
+            Ok(metadata) => {
+                file.size = metadata.len();
+                None
             }
             Err(err) => {
-                error!("Couldn't get metadata from file [{}]", file_path);
-                return Err(err.into());
+                Some(format!("Failed to read metadata of {}: {err}", file.path))
+            }
+        })
+        .collect()
+}
+
+/// Whether the folder at `index` or a folder above it resolves to
+/// `resolved`.
+fn is_within(folders: &[Folder], index: usize, resolved: &Utf8Path) -> bool {
+    let mut current = Some(index);
+    while let Some(index) = current {
+        if folders[index].resolved == resolved {
+            return true;
+        }
+        current = folders[index].parent;
+    }
+    false
+}
+
+/// Read the entries of a folder. Their types come from the listing of the
+/// folder, so only followed symlinks need their metadata looked up. Entries
+/// that can't be read are added as problems instead of stopping the walk.
+fn list_folder(path: &Utf8Path, scanner: &Scanner) -> Listing {
+    let mut listing = Listing::default();
+    let entries = match path.read_dir_utf8() {
+        Ok(entries) => entries,
+        Err(err) => {
+            listing
+                .problems
+                .push(format!("Failed to read directory {path}: {err}"));
+            return listing;
+        }
+    };
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                listing
+                    .problems
+                    .push(format!("Failed to read an entry of {path}: {err}"));
+                continue;
+            }
+        };
+        let file_type = match file_type(&entry, scanner) {
+            Ok(Some(file_type)) => file_type,
+            Ok(None) => continue,
+            Err(problem) => {
+                listing.problems.push(problem);
+                continue;
+            }
+        };
+        let entry_path = entry.into_path();
+        if scanner.filter.excludes(&entry_path, file_type.is_dir()) {
+            continue;
+        }
+        // Only keep the entries that are either directories or files.
+        if file_type.is_dir() {
+            match entry_path.canonicalize_utf8() {
+                Ok(resolved) => listing.folders.push((entry_path, resolved)),
+                Err(err) => listing.problems.push(format!(
+                    "Failed to resolve directory {entry_path}: {err}"
+                )),
             }
+        } else if file_type.is_file() {
+            listing.files.push(ScannedFile {
+                path: entry_path,
+                size: 0,
+            });
+        } else {
+            warn!(
+                "Skipped {}, it is neither a file nor a directory",
+                entry_path
+            );
+        }
+    }
+    // `read_dir` returns entries in whatever order the file system keeps
+    // them. Sort them so sequence numbers are the same on every build.
+    listing.folders.sort();
+    listing.files.sort_by(|a, b| a.path.cmp(&b.path));
+    listing
+}
+
+/// The type of an entry, which is that of what it points to for a followed
+/// symlink, or `None` when the entry is a skipped symlink.
+fn file_type(
+    entry: &Utf8DirEntry,
+    scanner: &Scanner,
+) -> Result<Option<FileType>, String> {
+    let path = entry.path();
+    let file_type = entry
+        .file_type()
+        .map_err(|err| format!("Failed to read the type of {path}: {err}"))?;
+    if !file_type.is_symlink() {
+        return Ok(Some(file_type));
+    }
+    match scanner.symlinks {
+        Symlinks::Skip => {
+            debug!("Skipped symlink {}", path);
+            Ok(None)
+        }
+        Symlinks::Error if scanner.filter.excludes(path, path.is_dir()) => {
+            Ok(None)
+        }
+        Symlinks::Error => Err(format!(
+            "Found symlink {path}, which `symlinks = \"error\"` doesn't allow"
+        )),
+        Symlinks::Follow => path
+            .metadata()
+            .map(|metadata| Some(metadata.file_type()))
+            .map_err(|err| format!("Failed to follow symlink {path}: {err}")),
+    }
+}
+
+/// Turn the folders of a walk into directories and files, with sequence
+/// numbers given to the files of the deepest folders first.
+fn collect(
+    folders: &[Folder],
+    parent_directory_id: &str,
+    sequencer: &mut Sequencer,
+) -> (Vec<Directory>, Vec<File>) {
+    /// A step of a depth-first traversal that doesn't use the call stack.
+    enum Step {
+        /// Add a folder as a directory in the given parent.
+        Enter(usize, LocalStr),
+        /// Add the files of a folder to the given directory, once the
+        /// folders below it have been added.
+        Files(usize, LocalStr),
+    }
+
+    let (mut directories, mut files) = (Vec::new(), Vec::new());
+    let parent: LocalStr = parent_directory_id.into();
+    let mut stack = vec![Step::Files(0, parent.clone())];
+    stack.extend(
+        folders[0]
+            .folders
+            .iter()
+            .rev()
+            .map(|&child| Step::Enter(child, parent.clone())),
+    );
+    while let Some(step) = stack.pop() {
+        match step {
+            Step::Enter(index, parent) => {
+                let directory =
+                    Directory::from_path(&folders[index].path, &parent);
+                let id = directory.id().clone();
+                directories.push(directory);
+                stack.push(Step::Files(index, id.clone()));
+                stack.extend(
+                    folders[index]
+                        .folders
+                        .iter()
+                        .rev()
+                        .map(|&child| Step::Enter(child, id.clone())),
+                );
+            }
+            Step::Files(index, directory_id) => {
+                files.extend(folders[index].files.iter().map(|scanned| {
+                    File::new(
+                        &scanned.path,
+                        &directory_id,
+                        sequencer.get(),
+                        scanned.size,
+                    )
+                }));
+            }
+        }
+    }
+    (directories, files)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use camino::{Utf8Path, Utf8PathBuf};
+    use flexstr::LocalStr;
+
+    use super::{
+        apply_component_guids, check_overlaps, mark_vital, target_directory,
+        Mapping, TARGETDIR,
+    };
+    use crate::modules::{
+        component::{directory::Directory, file::File},
+        config::component::ComponentConfig,
+    };
+
+    fn mapping(target: &str) -> Mapping {
+        Mapping {
+            source: Utf8PathBuf::from("in/app"),
+            target: target.to_owned(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            vital: Vec::new(),
+        }
+    }
+
+    fn directories() -> Vec<Directory> {
+        vec![
+            Directory::new(TARGETDIR, None::<LocalStr>, "SourceDir", None),
+            Directory::new("ProgramFilesFolder", Some(TARGETDIR), ".", None),
+            Directory::new(
+                "INSTALLDIR",
+                Some(LocalStr::from("ProgramFilesFolder")),
+                "Product",
+                None,
+            ),
+        ]
+    }
+
+    fn target(
+        target: &str,
+        directories: &mut Vec<Directory>,
+    ) -> Result<LocalStr, String> {
+        let properties =
+            BTreeMap::from([("DATADIR".to_owned(), "C:\\Data".to_owned())]);
+        target_directory(&mapping(target), &properties, directories)
+            .map_err(|err| err.to_string())
+    }
+
+    #[test]
+    fn adds_the_folders_of_a_target_once() {
+        let mut directories = directories();
+        let bin = target("INSTALLDIR/bin/", &mut directories).unwrap();
+        assert_eq!(directories.len(), 4);
+        assert_eq!(directories[3].parent_id().as_deref(), Some("INSTALLDIR"));
+        assert_eq!(directories[3].name(), "bin");
+        // Folder names are compared without case.
+        assert_eq!(target("INSTALLDIR/Bin", &mut directories).unwrap(), bin);
+        assert_eq!(directories.len(), 4);
+        assert_eq!(
+            target("INSTALLDIR", &mut directories).unwrap(),
+            "INSTALLDIR"
+        );
+    }
+
+    #[test]
+    fn adds_system_folders_and_property_directories_as_roots() {
+        let mut directories = directories();
+        target("CommonAppDataFolder/Product", &mut directories).unwrap();
+        assert_eq!(target("DATADIR", &mut directories).unwrap(), "DATADIR");
+        let roots = directories
+            .iter()
+            .filter(|d| {
+                d.name() == "." && d.parent_id().as_deref() == Some("TARGETDIR")
+            })
+            .map(|d| d.id().to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            roots,
+            ["ProgramFilesFolder", "CommonAppDataFolder", "DATADIR"]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_roots() {
+        let mut directories = directories();
+        assert_eq!(
+            target("CommonAppDatFolder/Product", &mut directories).unwrap_err(),
+            "The [[files]] entry of in/app installs to \
+            CommonAppDatFolder/Product, but CommonAppDatFolder is neither a \
+            system folder nor a directory of the package, did you mean \
+            `CommonAppDataFolder`?"
+        );
+        assert_eq!(
+            target("installdir", &mut directories).unwrap_err(),
+            "The [[files]] entry of in/app installs to installdir, but \
+            installdir is neither a system folder nor a directory of the \
+            package, did you mean `INSTALLDIR`?"
+        );
+        assert_eq!(
+            target("/", &mut directories).unwrap_err(),
+            "The [[files]] entry of in/app has an empty target"
+        );
+        assert_eq!(directories.len(), 3);
+    }
+
+    #[test]
+    fn marks_the_vital_files_of_an_entry() {
+        let mut files = [
+            File::new(&Utf8PathBuf::from("in/app/app.exe"), "INSTALLDIR", 1, 0),
+            File::new(&Utf8PathBuf::from("in/app/bin/a.dll"), "Bin", 2, 0),
+        ];
+        let mut entry = mapping("INSTALLDIR");
+        entry.vital = vec![Utf8PathBuf::from("bin/a.dll")];
+        mark_vital(&entry, &mut files).unwrap();
+        assert!(!files[0].vital());
+        assert!(files[1].vital());
+
+        entry.vital = vec![Utf8PathBuf::from("app/app.exe")];
+        assert_eq!(
+            mark_vital(&entry, &mut files).unwrap_err().to_string(),
+            "Vital file app/app.exe isn't among the files of the [[files]] \
+            entry of in/app"
+        );
+    }
+
+    #[test]
+    fn finds_files_installed_to_the_same_path() {
+        let mut directories = directories();
+        directories.extend([
+            Directory::new(
+                "Upper",
+                Some(LocalStr::from("INSTALLDIR")),
+                "Bin",
+                None,
+            ),
+            Directory::new(
+                "Lower",
+                Some(LocalStr::from("INSTALLDIR")),
+                "bin",
+                None,
+            ),
+        ]);
+        let file = |source: &str, directory: &str| {
+            File::new(&Utf8PathBuf::from(source), directory, 1, 0)
         };
-        let file = File::new(&file_path, sequencer.get(), size);
-        all_files.push(file);
+        assert!(check_overlaps(
+            &[file("a/App.exe", "Upper"), file("b/App.exe", "INSTALLDIR")],
+            &directories
+        )
+        .is_ok());
+        let error = check_overlaps(
+            &[file("a/App.exe", "Upper"), file("b/app.EXE", "Lower")],
+            &directories,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Found 1 file(s) installed to the same path:\na/App.exe and \
+            b/app.EXE are both installed as app.EXE"
+        );
     }
 
-    Ok((all_dirs, all_files))
+    #[test]
+    fn gives_components_the_guids_of_their_entries() {
+        let entry = |file: &str, guid: &str| ComponentConfig {
+            file: Utf8PathBuf::from(file),
+            guid: guid.to_owned(),
+        };
+        let files = || {
+            [
+                File::new(&Utf8PathBuf::from("in/app.exe"), "INSTALLDIR", 1, 0),
+                File::new(
+                    &Utf8PathBuf::from("in/bin/a.dll"),
+                    "INSTALLDIR",
+                    2,
+                    0,
+                ),
+            ]
+        };
+        let input_directory = Utf8Path::new("in");
+        let mut guided = files();
+        apply_component_guids(
+            &[entry("bin/a.dll", "ac8acb03-f948-40ce-9c35-297ff94c7c35")],
+            input_directory,
+            &mut guided,
+        )
+        .unwrap();
+        assert_eq!(guided[0].guid(), &None);
+        assert_eq!(
+            guided[1].guid().as_deref(),
+            Some("{AC8ACB03-F948-40CE-9C35-297FF94C7C35}")
+        );
+
+        let error = apply_component_guids(
+            &[
+                entry("missing.exe", "{AC8ACB03-F948-40CE-9C35-297FF94C7C35}"),
+                entry("app.exe", "AC8ACB03"),
+                entry("bin/a.dll", "{027947FE-8219-4233-9998-63AEDC347DA0}"),
+                entry("bin/a.dll", "{B7C9E53F-BA59-4B22-8FFF-4877E7031E9F}"),
+            ],
+            input_directory,
+            &mut files(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Found 3 problem(s) with component GUIDs:\n[[component]] \
+            missing.exe isn't in the input directory\n[[component]] app.exe \
+            has AC8ACB03, which isn't a GUID\n[[component]] bin/a.dll is given \
+            more than once"
+        );
+    }
+
+    #[cfg(unix)]
+    mod symlinks {
+        use std::os::unix::fs::symlink;
+
+        use super::super::{walk, Folder, Scanner};
+        use crate::modules::{
+            config::scan::Symlinks,
+            helpers::{file_filter::FileFilter, test_dir::TestDir},
+        };
+
+        /// A folder with a file, a folder and links to both of them.
+        fn tree() -> TestDir {
+            let dir = TestDir::new();
+            dir.file("app.exe", "app");
+            dir.file("data/settings.ini", "settings");
+            symlink(dir.path().join("app.exe"), dir.path().join("app.lnk"))
+                .unwrap();
+            symlink(dir.path().join("data"), dir.path().join("linked"))
+                .unwrap();
+            dir
+        }
+
+        /// The paths of the files the walk found, relative to `dir`.
+        fn scan(
+            dir: &TestDir,
+            symlinks: Symlinks,
+            exclude: &[&str],
+        ) -> Result<Vec<String>, String> {
+            let exclude =
+                exclude.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+            let filter = FileFilter::new(dir.path(), &[], &exclude).unwrap();
+            let scanner = Scanner {
+                filter: &filter,
+                symlinks,
+            };
+            let folders = walk(&dir.path().to_owned(), &scanner)
+                .map_err(|err| err.to_string())?;
+            Ok(folders
+                .iter()
+                .flat_map(|folder: &Folder| &folder.files)
+                .map(|file| {
+                    file.path.strip_prefix(dir.path()).unwrap().to_string()
+                })
+                .collect())
+        }
+
+        #[test]
+        fn skips_links() {
+            let dir = tree();
+            assert_eq!(
+                scan(&dir, Symlinks::Skip, &[]).unwrap(),
+                ["app.exe", "data/settings.ini"]
+            );
+        }
+
+        #[test]
+        fn follows_links_to_files_and_folders() {
+            let dir = tree();
+            assert_eq!(
+                scan(&dir, Symlinks::Follow, &[]).unwrap(),
+                [
+                    "app.exe",
+                    "app.lnk",
+                    "data/settings.ini",
+                    "linked/settings.ini"
+                ]
+            );
+        }
+
+        #[test]
+        fn fails_on_links_to_a_folder_they_are_in() {
+            let dir = tree();
+            symlink(dir.path(), dir.path().join("data/loop")).unwrap();
+            let error = scan(&dir, Symlinks::Follow, &[]).unwrap_err();
+            // The loop is found through the link to its folder as well.
+            assert!(error.starts_with("Found 2 problem(s) while scanning"));
+            assert!(error.contains("data/loop points to"), "{error}");
+            assert!(error.contains("linked/loop points to"), "{error}");
+        }
+
+        #[test]
+        fn lists_every_link_when_links_are_errors() {
+            let dir = tree();
+            let error = scan(&dir, Symlinks::Error, &[]).unwrap_err();
+            assert!(error.starts_with("Found 2 problem(s) while scanning"));
+            assert!(error.contains("app.lnk, which `symlinks = \"error\"`"));
+            assert!(error.contains("linked, which `symlinks = \"error\"`"));
+            assert_eq!(
+                scan(&dir, Symlinks::Error, &["*.lnk", "linked"]).unwrap(),
+                ["app.exe", "data/settings.ini"]
+            );
+        }
+
+        #[test]
+        fn reads_the_sizes_of_followed_files() {
+            let dir = tree();
+            let filter = FileFilter::new(dir.path(), &[], &[]).unwrap();
+            let scanner = Scanner {
+                filter: &filter,
+                symlinks: Symlinks::Follow,
+            };
+            let folders = walk(&dir.path().to_owned(), &scanner).unwrap();
+            let sizes = folders[0]
+                .files
+                .iter()
+                .map(|file| file.size)
+                .collect::<Vec<u64>>();
+            assert_eq!(sizes, [3, 3]);
+        }
+    }
 }
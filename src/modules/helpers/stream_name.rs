@@ -0,0 +1,119 @@
+//! Encoding and decoding of the stream names used inside an MSI.
+//!
+//! The compound file that backs an MSI limits stream names to 31 characters,
+//! so Windows Installer packs two name characters into a single character
+//! from a private range of the Basic Multilingual Plane. Table streams are
+//! additionally prefixed with `U+4840`.
+
+const TABLE_PREFIX: char = '\u{4840}';
+
+fn to_b64(ch: char) -> Option<u32> {
+    match ch {
+        '0'..='9' => Some(ch as u32 - '0' as u32),
+        'A'..='Z' => Some(ch as u32 - 'A' as u32 + 10),
+        'a'..='z' => Some(ch as u32 - 'a' as u32 + 36),
+        '.' => Some(62),
+        '_' => Some(63),
+        _ => None,
+    }
+}
+
+fn from_b64(value: u32) -> char {
+    match value {
+        0..=9 => char::from(b'0' + value as u8),
+        10..=35 => char::from(b'A' + (value - 10) as u8),
+        36..=61 => char::from(b'a' + (value - 36) as u8),
+        62 => '.',
+        _ => '_',
+    }
+}
+
+/// Decode a raw stream name into the name Windows Installer uses for it and
+/// whether the stream holds a table.
+pub(crate) fn decode(name: &str) -> (String, bool) {
+    let mut output = String::new();
+    let mut chars = name.chars().peekable();
+    let is_table = chars.next_if_eq(&TABLE_PREFIX).is_some();
+    for ch in chars {
+        let value = ch as u32;
+        if (0x3800..0x4800).contains(&value) {
+            let value = value - 0x3800;
+            output.push(from_b64(value & 0x3f));
+            output.push(from_b64(value >> 6));
+        } else if (0x4800..0x4840).contains(&value) {
+            output.push(from_b64(value - 0x4800));
+        } else {
+            output.push(ch);
+        }
+    }
+    (output, is_table)
+}
+
+/// Encode a name into the raw stream name that is written to the compound
+/// file.
+pub(crate) fn encode(name: &str, is_table: bool) -> String {
+    let mut output = String::new();
+    if is_table {
+        output.push(TABLE_PREFIX);
+    }
+    let mut chars = name.chars().peekable();
+    while let Some(ch) = chars.next() {
+        let Some(first) = to_b64(ch) else {
+            output.push(ch);
+            continue;
+        };
+        match chars.peek().copied().and_then(to_b64) {
+            Some(second) => {
+                chars.next();
+                // Both values are below 64 so this is always in the
+                // `0x3800..0x4800` range and a valid character.
+                output.push(
+                    char::from_u32(0x3800 + first + (second << 6))
+                        .expect("Encoded stream character is out of range"),
+                );
+            }
+            None => output.push(
+                char::from_u32(0x4800 + first)
+                    .expect("Encoded stream character is out of range"),
+            ),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode};
+
+    #[test]
+    fn round_trips_table_names() {
+        for name in ["File", "_Validation", "_StringPool", "Component", "A"] {
+            let encoded = encode(name, true);
+            assert!(encoded.starts_with('\u{4840}'));
+            assert_eq!(decode(&encoded), (name.to_owned(), true));
+        }
+    }
+
+    #[test]
+    fn round_trips_stream_names() {
+        for name in ["Binary.Icon", "disk1.cab", "a_b.c_d", "1"] {
+            assert_eq!(decode(&encode(name, false)), (name.to_owned(), false));
+        }
+    }
+
+    #[test]
+    fn packs_two_characters_into_one() {
+        // `Fi` and `le` each take a single character, then the prefix.
+        let encoded = encode("File", true);
+        assert_eq!(encoded.chars().count(), 3);
+        // An odd character out is stored on its own.
+        assert_eq!(encode("A", false), "\u{480a}");
+    }
+
+    #[test]
+    fn keeps_characters_outside_the_alphabet() {
+        let encoded = encode("a-b", false);
+        assert_eq!(encoded.chars().nth(1), Some('-'));
+        assert_eq!(decode(&encoded), ("a-b".to_owned(), false));
+    }
+}
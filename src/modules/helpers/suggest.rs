@@ -0,0 +1,66 @@
+/// The candidate closest to `name`, when it is close enough to be what a
+/// misspelled `name` meant.
+pub(crate) fn closest<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .map(|candidate| (distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The number of characters to insert, remove or replace, or pairs of
+/// neighbouring characters to swap, to turn `a` into `b`, as long as no
+/// part is edited twice.
+fn distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    // The distances from the first `i - 1` and `i` characters of `a` to
+    // every start of `b`.
+    let mut before = Vec::new();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for i in 1..=a.len() {
+        let mut current = vec![i];
+        for j in 1..=b.len() {
+            let replace = previous[j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut best = replace.min(previous[j] + 1).min(current[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(before[j - 2] + 1);
+            }
+            current.push(best);
+        }
+        before = std::mem::replace(&mut previous, current);
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{closest, distance};
+
+    #[test]
+    fn counts_swapped_neighbours_as_one_edit() {
+        assert_eq!(distance("level", "level"), 0);
+        assert_eq!(distance("levle", "level"), 1);
+        assert_eq!(distance("nmae", "name"), 1);
+        assert_eq!(distance("lvel", "level"), 1);
+        assert_eq!(distance("levels", "level"), 1);
+        assert_eq!(distance("", "id"), 2);
+        assert_eq!(distance("ca", "abc"), 3);
+    }
+
+    #[test]
+    fn suggests_the_closest_candidate_that_is_close_enough() {
+        let candidates = ["id", "level", "name", "display"];
+        assert_eq!(closest("levle", candidates), Some("level"));
+        assert_eq!(closest("nmae", candidates), Some("name"));
+        assert_eq!(closest("di", candidates), Some("id"));
+        assert_eq!(closest("dispaly", candidates), Some("display"));
+        assert_eq!(closest("version", candidates), None);
+        assert_eq!(closest("lvl", candidates), None);
+    }
+}
@@ -0,0 +1,150 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// # [Word Count](https://learn.microsoft.com/en-us/windows/win32/msi/word-count-summary)
+///
+/// Decode the `word_count` bitfield of an installation package into a
+/// description of every bit. Bits that are not defined for installation
+/// packages are reported as unknown.
+pub(crate) fn describe_word_count(word_count: i32) -> Vec<String> {
+    let mut meanings = vec![
+        if word_count & 0b0001 == 0 {
+            "Long file names"
+        } else {
+            "Short file names"
+        },
+        if word_count & 0b0010 == 0 {
+            "Uncompressed source files"
+        } else {
+            "Compressed source files"
+        },
+        if word_count & 0b0100 == 0 {
+            "Original source image"
+        } else {
+            "Administrative image"
+        },
+        if word_count & 0b1000 == 0 {
+            "Elevated privileges required"
+        } else {
+            "Elevated privileges not required"
+        },
+    ]
+    .into_iter()
+    .map(str::to_owned)
+    .collect::<Vec<String>>();
+
+    let unknown = word_count & !0b1111;
+    if unknown != 0 {
+        meanings.push(format!("Unknown bits {:#x}", unknown));
+    }
+    meanings
+}
+
+/// # [Security](https://learn.microsoft.com/en-us/windows/win32/msi/security-summary)
+///
+/// Describe the `security` property, which tells tools whether to open the
+/// package read-only.
+pub(crate) fn describe_security(security: i32) -> &'static str {
+    match security {
+        0 => "No restriction",
+        2 => "Read-only recommended",
+        4 => "Read-only enforced",
+        _ => "Unknown restriction",
+    }
+}
+
+/// # [Template](https://learn.microsoft.com/en-us/windows/win32/msi/template-summary)
+///
+/// Describe the platform half of the `template` property.
+pub(crate) fn describe_arch(arch: &str) -> &'static str {
+    match arch {
+        "Intel" => "32-bit x86",
+        "x64" | "AMD64" => "64-bit x64",
+        "Intel64" => "64-bit Itanium",
+        "Arm" => "32-bit ARM",
+        "Arm64" => "64-bit ARM",
+        _ => "Unknown platform",
+    }
+}
+
+/// Format a timestamp as an ISO 8601 string in UTC.
+///
+/// This avoids pulling in a date library for the handful of places that need
+/// to show the creation time of a package.
+pub(crate) fn format_timestamp(time: SystemTime) -> String {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs() as i64),
+    };
+    let days = seconds.div_euclid(86_400);
+    let secs_of_day = seconds.rem_euclid(86_400);
+
+    // Convert days since the epoch to a civil date. This is Howard Hinnant's
+    // `civil_from_days` algorithm.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{describe_security, describe_word_count};
+
+    #[test]
+    fn describes_every_word_count_bit() {
+        assert_eq!(
+            describe_word_count(0),
+            [
+                "Long file names",
+                "Uncompressed source files",
+                "Original source image",
+                "Elevated privileges required",
+            ]
+        );
+        assert_eq!(
+            describe_word_count(2),
+            [
+                "Long file names",
+                "Compressed source files",
+                "Original source image",
+                "Elevated privileges required",
+            ]
+        );
+        assert_eq!(
+            describe_word_count(8),
+            [
+                "Long file names",
+                "Uncompressed source files",
+                "Original source image",
+                "Elevated privileges not required",
+            ]
+        );
+        assert_eq!(
+            describe_word_count(0x12).last().map(String::as_str),
+            Some("Unknown bits 0x10")
+        );
+    }
+
+    #[test]
+    fn describes_security() {
+        assert_eq!(describe_security(0), "No restriction");
+        assert_eq!(describe_security(2), "Read-only recommended");
+        assert_eq!(describe_security(4), "Read-only enforced");
+        assert_eq!(describe_security(1), "Unknown restriction");
+    }
+}
@@ -0,0 +1,70 @@
+//! Folders for tests that read from the file system.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use camino::{Utf8Path, Utf8PathBuf};
+
+use crate::command::builder::{self, ConfigSource, PackageKind};
+
+static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// An empty folder in the temporary directory that is removed along with
+/// everything in it when dropped.
+pub(crate) struct TestDir {
+    path: Utf8PathBuf,
+}
+
+impl TestDir {
+    pub(crate) fn new() -> Self {
+        let temp = Utf8PathBuf::try_from(std::env::temp_dir())
+            .expect("The temporary directory is UTF-8");
+        let path = temp.join(format!(
+            "whimsi-test-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("Failed to create test folder");
+        TestDir { path }
+    }
+
+    pub(crate) fn path(&self) -> &Utf8Path {
+        &self.path
+    }
+
+    /// Write a file at `relative`, creating the folders it is in.
+    pub(crate) fn file(&self, relative: &str, contents: &str) -> Utf8PathBuf {
+        let path = self.path.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("Failed to create test folder");
+        }
+        fs::write(&path, contents).expect("Failed to write test file");
+        path
+    }
+
+    /// Build an installer from the config and input directory at `config`
+    /// and `input` and return the bytes of the package.
+    pub(crate) fn build(&self, config: &str, input: &str) -> Vec<u8> {
+        let output = self.path.join(format!("{config}.msi"));
+        builder::build_msi(
+            &ConfigSource::Toml(self.path.join(config)),
+            &self.path.join(input),
+            &output,
+            PackageKind::Installer,
+            &[],
+            &BTreeMap::new(),
+        )
+        .expect("Failed to build the test package");
+        fs::read(&output).expect("Failed to read the test package")
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,540 @@
+//! Reading the version and languages of a Windows executable from the
+//! [`VS_VERSIONINFO`](https://learn.microsoft.com/en-us/windows/win32/menurc/vs-versioninfo)
+//! resource of its PE image, so versioned files can be recognized on any
+//! platform.
+
+use std::{
+    fs,
+    io::{self, Read, Seek, SeekFrom},
+};
+
+use camino::Utf8Path;
+
+/// Extensions of the files whose version resource is read.
+const VERSIONED_EXTENSIONS: [&str; 3] = ["exe", "dll", "sys"];
+
+const DOS_MAGIC: &[u8] = b"MZ";
+const PE_SIGNATURE: &[u8] = b"PE\0\0";
+/// Offset of the PE header offset in the DOS header.
+const PE_OFFSET: usize = 0x3c;
+const PE32: u16 = 0x10b;
+const PE32_PLUS: u16 = 0x20b;
+/// Index of the resource table in the data directories.
+const RESOURCE_TABLE: usize = 2;
+const SECTION_HEADER_SIZE: usize = 40;
+/// Resource type of version resources.
+const RT_VERSION: u32 = 16;
+/// Bit of a resource directory entry that points to another directory.
+const SUBDIRECTORY: u32 = 0x8000_0000;
+const FIXED_FILE_INFO_SIGNATURE: u32 = 0xfeef_04bd;
+
+/// The version of a file and the languages it supports.
+pub(crate) struct VersionInfo {
+    /// The file version, as four numbers separated by periods.
+    pub(crate) version: String,
+    pub(crate) languages: Vec<u16>,
+}
+
+impl VersionInfo {
+    /// The languages as the `Language` column of the `File` table holds
+    /// them.
+    pub(crate) fn language(&self) -> String {
+        let mut languages = Vec::new();
+        for language in &self.languages {
+            if !languages.contains(language) {
+                languages.push(*language);
+            }
+        }
+        languages
+            .iter()
+            .map(u16::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// The version resource of the file at `path`, when it is an executable
+/// that has one.
+pub(crate) fn read(path: &Utf8Path) -> io::Result<Option<VersionInfo>> {
+    let versioned = path.extension().is_some_and(|extension| {
+        VERSIONED_EXTENSIONS
+            .iter()
+            .any(|versioned| extension.eq_ignore_ascii_case(versioned))
+    });
+    if !versioned {
+        return Ok(None);
+    }
+    parse(fs::File::open(path)?)
+}
+
+/// The version resource of a PE image, or `None` when the image doesn't
+/// have one or is malformed. Only the headers and the section that holds
+/// the resources are read.
+fn parse<R: Read + Seek>(reader: R) -> io::Result<Option<VersionInfo>> {
+    let mut image = Image::new(reader)?;
+    let Some(dos) = image.read(0, PE_OFFSET + 4)? else {
+        return Ok(None);
+    };
+    if dos.get(..2) != Some(DOS_MAGIC) {
+        return Ok(None);
+    }
+    let Some(pe) = u32_at(&dos, PE_OFFSET).map(|pe| pe as usize) else {
+        return Ok(None);
+    };
+    let Some(coff) = image.read(pe, 24)? else {
+        return Ok(None);
+    };
+    if coff.get(..4) != Some(PE_SIGNATURE) {
+        return Ok(None);
+    }
+    let (Some(section_count), Some(optional_size)) =
+        (u16_at(&coff, 6), u16_at(&coff, 20))
+    else {
+        return Ok(None);
+    };
+    let table = pe + 24 + optional_size as usize;
+    let headers_end = table + section_count as usize * SECTION_HEADER_SIZE;
+    let Some(headers) = image.read(0, headers_end)? else {
+        return Ok(None);
+    };
+    let Some(resource_rva) = resource_rva(&headers, pe + 24) else {
+        return Ok(None);
+    };
+    let sections = Sections {
+        headers: &headers,
+        table,
+        count: section_count as usize,
+    };
+    let Some(section) = sections.find(resource_rva) else {
+        return Ok(None);
+    };
+    let Some(data) = image.read(section.raw_offset, section.raw_size)? else {
+        return Ok(None);
+    };
+    Ok(resource(&data, &section, resource_rva))
+}
+
+/// The address of the resource table from the optional header at
+/// `optional`.
+fn resource_rva(headers: &[u8], optional: usize) -> Option<u32> {
+    let data_directories = match u16_at(headers, optional)? {
+        PE32 => optional + 96,
+        PE32_PLUS => optional + 112,
+        _ => return None,
+    };
+    let directory_count = u32_at(headers, data_directories - 4)? as usize;
+    if directory_count <= RESOURCE_TABLE {
+        return None;
+    }
+    let rva = u32_at(headers, data_directories + RESOURCE_TABLE * 8)?;
+    (rva != 0).then_some(rva)
+}
+
+/// The version resource in `data`, the contents of the section that holds
+/// the resource table at `resource_rva`.
+fn resource(
+    data: &[u8],
+    section: &Section,
+    resource_rva: u32,
+) -> Option<VersionInfo> {
+    // The resource tree has a level for the type, the name and the
+    // language, and the first version resource is used.
+    let root = section.offset(resource_rva)?;
+    let (names, _) = resource_entry(data, root, root, Some(RT_VERSION))?;
+    let (languages, _) = resource_entry(data, root, names, None)?;
+    let (data_entry, language) = resource_entry(data, root, languages, None)?;
+    let start = section.offset(u32_at(data, data_entry)?)?;
+    let size = u32_at(data, data_entry + 4)? as usize;
+    let resource = data.get(start..start.checked_add(size)?)?;
+    version_info(resource, language as u16)
+}
+
+/// An image being read, which knows its length so that sizes read from
+/// a malformed image are never allocated.
+struct Image<R> {
+    reader: R,
+    length: u64,
+}
+
+impl<R: Read + Seek> Image<R> {
+    fn new(mut reader: R) -> io::Result<Self> {
+        let length = reader.seek(SeekFrom::End(0))?;
+        Ok(Image { reader, length })
+    }
+
+    /// The `size` bytes at `offset`, or `None` when the image is shorter.
+    fn read(
+        &mut self,
+        offset: usize,
+        size: usize,
+    ) -> io::Result<Option<Vec<u8>>> {
+        let Some(end) = offset.checked_add(size) else {
+            return Ok(None);
+        };
+        if end as u64 > self.length {
+            return Ok(None);
+        }
+        self.reader.seek(SeekFrom::Start(offset as u64))?;
+        let mut bytes = vec![0; size];
+        self.reader.read_exact(&mut bytes)?;
+        Ok(Some(bytes))
+    }
+}
+
+/// The section headers of an image, to find the section that a relative
+/// virtual address is in.
+struct Sections<'a> {
+    headers: &'a [u8],
+    table: usize,
+    count: usize,
+}
+
+impl Sections<'_> {
+    fn find(&self, rva: u32) -> Option<Section> {
+        (0..self.count).find_map(|index| {
+            let header = self.table + index * SECTION_HEADER_SIZE;
+            let section = Section {
+                address: u32_at(self.headers, header + 12)?,
+                raw_size: u32_at(self.headers, header + 16)? as usize,
+                raw_offset: u32_at(self.headers, header + 20)? as usize,
+            };
+            section.offset(rva).map(|_| section)
+        })
+    }
+}
+
+/// Where a section is in memory and in the file.
+struct Section {
+    address: u32,
+    raw_offset: usize,
+    raw_size: usize,
+}
+
+impl Section {
+    /// Where `rva` is in the contents of the section in the file.
+    fn offset(&self, rva: u32) -> Option<usize> {
+        let offset = rva.checked_sub(self.address)? as usize;
+        (offset < self.raw_size).then_some(offset)
+    }
+}
+
+/// The first entry of the resource directory at `directory` with the ID
+/// `id`, or the first entry of all. Returns where the entry points to and
+/// its ID.
+fn resource_entry(
+    image: &[u8],
+    root: usize,
+    directory: usize,
+    id: Option<u32>,
+) -> Option<(usize, u32)> {
+    let named = u16_at(image, directory + 12)? as usize;
+    let ids = u16_at(image, directory + 14)? as usize;
+    (0..named + ids).find_map(|index| {
+        let entry = directory + 16 + index * 8;
+        let name = u32_at(image, entry)?;
+        if id.is_some_and(|id| id != name) {
+            return None;
+        }
+        let target = u32_at(image, entry + 4)? & !SUBDIRECTORY;
+        Some((root + target as usize, name))
+    })
+}
+
+/// Read a `VS_VERSIONINFO` structure. The languages come from the
+/// `Translation` value, then the string tables, then the language of the
+/// resource.
+fn version_info(resource: &[u8], language: u16) -> Option<VersionInfo> {
+    let info = Block::read(resource, 0)?;
+    let fixed = info.value;
+    if u32_at(resource, fixed)? != FIXED_FILE_INFO_SIGNATURE {
+        return None;
+    }
+    let most = u32_at(resource, fixed + 8)?;
+    let least = u32_at(resource, fixed + 12)?;
+    let version = format!(
+        "{}.{}.{}.{}",
+        most >> 16,
+        most & 0xffff,
+        least >> 16,
+        least & 0xffff
+    );
+
+    let mut translations = Vec::new();
+    let mut tables = Vec::new();
+    for child in info.children(resource) {
+        match child.key.as_str() {
+            "VarFileInfo" => {
+                for var in child.children(resource) {
+                    if var.key != "Translation" {
+                        continue;
+                    }
+                    let value = resource.get(var.value..var.value_end())?;
+                    translations.extend(
+                        value
+                            .chunks_exact(4)
+                            .map(|pair| u16::from_le_bytes([pair[0], pair[1]])),
+                    );
+                }
+            }
+            "StringFileInfo" => {
+                tables.extend(child.children(resource).filter_map(|table| {
+                    u16::from_str_radix(table.key.get(..4)?, 16).ok()
+                }));
+            }
+            _ => {}
+        }
+    }
+    let languages = [translations, tables]
+        .into_iter()
+        .find(|languages| !languages.is_empty())
+        .unwrap_or_else(|| vec![language]);
+    Some(VersionInfo { version, languages })
+}
+
+/// A block of a version resource, which is a key, a value and child blocks.
+struct Block {
+    key: String,
+    /// Offset of the value.
+    value: usize,
+    /// Length of the value in bytes.
+    value_length: usize,
+    /// Offset of the end of the block.
+    end: usize,
+}
+
+impl Block {
+    fn read(resource: &[u8], start: usize) -> Option<Block> {
+        let length = u16_at(resource, start)? as usize;
+        let value_length = u16_at(resource, start + 2)? as usize;
+        // Text values give their length in UTF-16 code units.
+        let value_length = match u16_at(resource, start + 4)? {
+            1 => value_length * 2,
+            _ => value_length,
+        };
+        if length == 0 {
+            return None;
+        }
+        let (key, key_end) = utf16_key(resource, start + 6)?;
+        Some(Block {
+            key,
+            value: align(key_end),
+            value_length,
+            end: (start + length).min(resource.len()),
+        })
+    }
+
+    fn value_end(&self) -> usize {
+        self.value + self.value_length
+    }
+
+    fn children<'a>(
+        &self,
+        resource: &'a [u8],
+    ) -> impl Iterator<Item = Block> + 'a {
+        let end = self.end;
+        let mut next = align(self.value_end());
+        std::iter::from_fn(move || {
+            if next >= end {
+                return None;
+            }
+            let child = Block::read(resource, next)?;
+            next = align(child.end);
+            Some(child)
+        })
+    }
+}
+
+/// A null terminated UTF-16 string and the offset after its terminator.
+fn utf16_key(resource: &[u8], start: usize) -> Option<(String, usize)> {
+    let mut units = Vec::new();
+    let mut offset = start;
+    loop {
+        let unit = u16_at(resource, offset)?;
+        offset += 2;
+        if unit == 0 {
+            return Some((String::from_utf16_lossy(&units), offset));
+        }
+        units.push(unit);
+    }
+}
+
+/// Blocks and values start on 32-bit boundaries.
+fn align(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use camino::Utf8Path;
+
+    use super::{parse, read, VersionInfo};
+
+    /// Offset of the resource section in the file.
+    const RAW_OFFSET: usize = 0x200;
+    /// Address of the resource section in memory.
+    const RVA: u32 = 0x1000;
+    /// Offset of the version resource in the resource section.
+    const DATA: usize = 0x58;
+
+    fn put(image: &mut [u8], offset: usize, bytes: &[u8]) {
+        image[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    fn pad(bytes: &mut Vec<u8>) {
+        bytes.resize((bytes.len() + 3) & !3, 0);
+    }
+
+    /// A block of a version resource.
+    fn block(key: &str, value: &[u8], children: &[Vec<u8>]) -> Vec<u8> {
+        let mut bytes = vec![0; 6];
+        put(&mut bytes, 2, &(value.len() as u16).to_le_bytes());
+        for unit in key.encode_utf16().chain([0]) {
+            bytes.extend(unit.to_le_bytes());
+        }
+        pad(&mut bytes);
+        bytes.extend(value);
+        for child in children {
+            pad(&mut bytes);
+            bytes.extend(child);
+        }
+        let length = bytes.len() as u16;
+        put(&mut bytes, 0, &length.to_le_bytes());
+        bytes
+    }
+
+    fn version_info(version: [u16; 4], children: &[Vec<u8>]) -> Vec<u8> {
+        let mut fixed = vec![0; 52];
+        put(&mut fixed, 0, &0xfeef_04bdu32.to_le_bytes());
+        put(&mut fixed, 8, &version[1].to_le_bytes());
+        put(&mut fixed, 10, &version[0].to_le_bytes());
+        put(&mut fixed, 12, &version[3].to_le_bytes());
+        put(&mut fixed, 14, &version[2].to_le_bytes());
+        block("VS_VERSION_INFO", &fixed, children)
+    }
+
+    fn translation(languages: &[u16]) -> Vec<u8> {
+        let value = languages
+            .iter()
+            .flat_map(|language| [*language, 1200])
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<u8>>();
+        block("VarFileInfo", &[], &[block("Translation", &value, &[])])
+    }
+
+    /// A PE32 image with one section that holds the resource tree, with a
+    /// version resource in the language 0x0409.
+    fn image(resource: &[u8]) -> Vec<u8> {
+        let mut image = vec![0; RAW_OFFSET + DATA + resource.len()];
+        put(&mut image, 0, b"MZ");
+        let pe = 0x40;
+        put(&mut image, 0x3c, &(pe as u32).to_le_bytes());
+        put(&mut image, pe, b"PE\0\0");
+        put(&mut image, pe + 6, &1u16.to_le_bytes());
+        let optional_size = 96 + 16 * 8;
+        put(&mut image, pe + 20, &(optional_size as u16).to_le_bytes());
+        let optional = pe + 24;
+        put(&mut image, optional, &0x10bu16.to_le_bytes());
+        put(&mut image, optional + 92, &16u32.to_le_bytes());
+        put(&mut image, optional + 96 + 16, &RVA.to_le_bytes());
+
+        let section = optional + optional_size;
+        put(&mut image, section, b".rsrc\0\0\0");
+        let size = (DATA + resource.len()) as u32;
+        put(&mut image, section + 8, &size.to_le_bytes());
+        put(&mut image, section + 12, &RVA.to_le_bytes());
+        put(&mut image, section + 16, &size.to_le_bytes());
+        put(&mut image, section + 20, &(RAW_OFFSET as u32).to_le_bytes());
+
+        // Directories for the type, the name and the language, each with a
+        // single entry, followed by the data entry.
+        let root = RAW_OFFSET;
+        let entries: [(u32, u32); 3] =
+            [(16, 0x8000_0018), (1, 0x8000_0030), (0x0409, 0x48)];
+        for (index, (id, target)) in entries.into_iter().enumerate() {
+            let directory = root + index * 0x18;
+            put(&mut image, directory + 14, &1u16.to_le_bytes());
+            put(&mut image, directory + 16, &id.to_le_bytes());
+            put(&mut image, directory + 20, &target.to_le_bytes());
+        }
+        let data = RVA + DATA as u32;
+        put(&mut image, root + 0x48, &data.to_le_bytes());
+        put(
+            &mut image,
+            root + 0x4c,
+            &(resource.len() as u32).to_le_bytes(),
+        );
+        put(&mut image, root + DATA, resource);
+        image
+    }
+
+    fn info(image: Vec<u8>) -> Option<VersionInfo> {
+        parse(Cursor::new(image)).unwrap()
+    }
+
+    #[test]
+    fn reads_the_version_and_translation_languages() {
+        let resource = version_info(
+            [1, 2, 3, 4],
+            &[translation(&[0x0407, 0x0409, 0x0407])],
+        );
+        let info = info(image(&resource)).unwrap();
+        assert_eq!(info.version, "1.2.3.4");
+        assert_eq!(info.languages, [0x0407, 0x0409, 0x0407]);
+        assert_eq!(info.language(), "1031,1033");
+    }
+
+    #[test]
+    fn falls_back_to_string_tables_and_then_the_resource_language() {
+        let tables =
+            block("StringFileInfo", &[], &[block("040c04b0", &[], &[])]);
+        let tables = info(image(&version_info([10, 0, 0, 1], &[tables])));
+        assert_eq!(tables.unwrap().languages, [0x040c]);
+        let resource = info(image(&version_info([10, 0, 0, 1], &[])));
+        assert_eq!(resource.unwrap().language(), "1033");
+    }
+
+    #[test]
+    fn malformed_images_have_no_version() {
+        let valid = image(&version_info([1, 0, 0, 0], &[]));
+        assert!(info(valid.clone()).is_some());
+
+        let mut not_dos = valid.clone();
+        not_dos[0] = b'X';
+        assert!(info(not_dos).is_none());
+
+        let mut not_pe = valid.clone();
+        not_pe[0x40] = b'X';
+        assert!(info(not_pe).is_none());
+
+        let mut no_resources = valid.clone();
+        no_resources[0x40 + 24 + 96 + 16..][..4].fill(0);
+        assert!(info(no_resources).is_none());
+
+        let mut bad_signature = valid.clone();
+        bad_signature[RAW_OFFSET + DATA + 40] ^= 0xff;
+        assert!(info(bad_signature).is_none());
+
+        assert!(info(valid[..RAW_OFFSET + 0x20].to_vec()).is_none());
+        assert!(info(valid[..0x30].to_vec()).is_none());
+        assert!(info(Vec::new()).is_none());
+    }
+
+    #[test]
+    fn only_reads_executables() {
+        // Files of other types aren't even opened.
+        assert!(read(Utf8Path::new("missing.txt")).unwrap().is_none());
+        assert!(read(Utf8Path::new("missing.dll")).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+//! The language identifier and ANSI codepage of each supported culture.
+
+/// Pairs of a culture name and its language identifier and codepage.
+const CULTURES: &[(&str, u16, i32)] = &[
+    ("ar-SA", 1025, 1256),
+    ("bg-BG", 1026, 1251),
+    ("ca-ES", 1027, 1252),
+    ("cs-CZ", 1029, 1250),
+    ("da-DK", 1030, 1252),
+    ("de-AT", 3079, 1252),
+    ("de-CH", 2055, 1252),
+    ("de-DE", 1031, 1252),
+    ("el-GR", 1032, 1253),
+    ("en-AU", 3081, 1252),
+    ("en-CA", 4105, 1252),
+    ("en-GB", 2057, 1252),
+    ("en-US", 1033, 1252),
+    ("es-ES", 3082, 1252),
+    ("es-MX", 2058, 1252),
+    ("et-EE", 1061, 1257),
+    ("fi-FI", 1035, 1252),
+    ("fr-CA", 3084, 1252),
+    ("fr-FR", 1036, 1252),
+    ("he-IL", 1037, 1255),
+    ("hr-HR", 1050, 1250),
+    ("hu-HU", 1038, 1250),
+    ("it-IT", 1040, 1252),
+    ("ja-JP", 1041, 932),
+    ("ko-KR", 1042, 949),
+    ("lt-LT", 1063, 1257),
+    ("lv-LV", 1062, 1257),
+    ("nb-NO", 1044, 1252),
+    ("nl-NL", 1043, 1252),
+    ("pl-PL", 1045, 1250),
+    ("pt-BR", 1046, 1252),
+    ("pt-PT", 2070, 1252),
+    ("ro-RO", 1048, 1250),
+    ("ru-RU", 1049, 1251),
+    ("sk-SK", 1051, 1250),
+    ("sl-SI", 1060, 1250),
+    ("sr-Latn-RS", 9242, 1250),
+    ("sv-SE", 1053, 1252),
+    ("th-TH", 1054, 874),
+    ("tr-TR", 1055, 1254),
+    ("uk-UA", 1058, 1251),
+    ("vi-VN", 1066, 1258),
+    ("zh-CN", 2052, 936),
+    ("zh-TW", 1028, 950),
+];
+
+/// The language identifier and codepage of a culture such as `de-DE`,
+/// ignoring case.
+pub(crate) fn lookup(culture: &str) -> Option<(u16, i32)> {
+    CULTURES
+        .iter()
+        .find(|(name, _, _)| name.eq_ignore_ascii_case(culture))
+        .map(|(_, language, codepage)| (*language, *codepage))
+}
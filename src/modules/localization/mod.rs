@@ -0,0 +1,359 @@
+//! Localizing a package for a culture.
+//!
+//! Config values can hold `!(loc.Key)` placeholders that are replaced with
+//! the strings of the culture being built before the config is read. The
+//! culture also decides the language and codepage of the package, and every
+//! string in the tables has to be representable in that codepage.
+//!
+//! A package for several cultures is the package of the first culture with a
+//! transform to each of the others embedded in it.
+
+mod cultures;
+mod strings;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Cursor,
+};
+
+use anyhow::{bail, Context, Result};
+use camino::Utf8Path;
+use msi::CodePage;
+
+use crate::{
+    command::builder::Msi,
+    modules::{
+        config::localization::LocalizationConfig, patch::copy_storage,
+        validation::database::Database,
+    },
+};
+
+use strings::StringTable;
+
+const PLACEHOLDER_START: &str = "!(loc.";
+
+/// The culture a package is built for.
+pub(crate) struct Localization {
+    pub(crate) culture: String,
+    pub(crate) language: u16,
+    pub(crate) codepage: i32,
+    pub(crate) strings: BTreeMap<String, String>,
+}
+
+/// Load the strings of `culture` from the directory in `config`, if there is
+/// one. The string table can override the language and codepage of the
+/// culture, and has to for cultures whimsi doesn't know.
+pub(crate) fn load(
+    config: Option<&LocalizationConfig>,
+    config_dir: &Utf8Path,
+    culture: &str,
+) -> Result<Localization> {
+    let table = match config {
+        Some(config) => {
+            let directory = config_dir.join(&config.directory);
+            let candidates = ["toml", "wxl"].map(|extension| {
+                directory.join(format!("{culture}.{extension}"))
+            });
+            match candidates.iter().find(|path| path.is_file()) {
+                Some(path) => StringTable::load(path)?,
+                None => bail!(
+                    "No string table for culture {} in {}",
+                    culture,
+                    directory
+                ),
+            }
+        }
+        None => StringTable::default(),
+    };
+    if let Some(declared) = &table.culture {
+        if !declared.eq_ignore_ascii_case(culture) {
+            bail!(
+                "The string table for culture {} is for culture {}",
+                culture,
+                declared
+            );
+        }
+    }
+
+    let known = cultures::lookup(culture);
+    let (Some(language), Some(codepage)) = (
+        table.language.or(known.map(|(language, _)| language)),
+        table.codepage.or(known.map(|(_, codepage)| codepage)),
+    ) else {
+        bail!(
+            "Unknown culture {}, give its language and codepage in its string \
+            table",
+            culture
+        );
+    };
+    Ok(Localization {
+        culture: culture.to_owned(),
+        language,
+        codepage,
+        strings: table.strings,
+    })
+}
+
+/// Placeholders that couldn't be replaced.
+#[derive(Default)]
+struct Unresolved {
+    /// Placeholders without a string.
+    missing: BTreeSet<String>,
+    /// Strings with a placeholder that isn't closed.
+    unterminated: BTreeSet<String>,
+}
+
+/// Replace every `!(loc.Key)` placeholder in the strings of `value`.
+pub(crate) fn resolve(
+    value: &mut toml::Value,
+    strings: &BTreeMap<String, String>,
+) -> Result<()> {
+    let mut unresolved = Unresolved::default();
+    resolve_value(value, strings, &mut unresolved);
+    if let Some(text) = unresolved.unterminated.first() {
+        bail!("Placeholder without a closing `)` in [{}]", text);
+    }
+    if !unresolved.missing.is_empty() {
+        bail!(
+            "No string for {}",
+            unresolved
+                .missing
+                .into_iter()
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Whether any string in `value` holds a placeholder.
+pub(crate) fn has_placeholders(value: &toml::Value) -> bool {
+    match value {
+        toml::Value::String(s) => s.contains(PLACEHOLDER_START),
+        toml::Value::Array(values) => values.iter().any(has_placeholders),
+        toml::Value::Table(table) => table.values().any(has_placeholders),
+        _ => false,
+    }
+}
+
+fn resolve_value(
+    value: &mut toml::Value,
+    strings: &BTreeMap<String, String>,
+    unresolved: &mut Unresolved,
+) {
+    match value {
+        toml::Value::String(s) => *s = replace(s, strings, unresolved),
+        toml::Value::Array(values) => values
+            .iter_mut()
+            .for_each(|v| resolve_value(v, strings, unresolved)),
+        toml::Value::Table(table) => table
+            .iter_mut()
+            .for_each(|(_, v)| resolve_value(v, strings, unresolved)),
+        _ => {}
+    }
+}
+
+fn replace(
+    text: &str,
+    strings: &BTreeMap<String, String>,
+    unresolved: &mut Unresolved,
+) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(PLACEHOLDER_START) {
+        let after = &rest[start + PLACEHOLDER_START.len()..];
+        let Some(end) = after.find(')') else {
+            unresolved.unterminated.insert(text.to_owned());
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let key = &after[..end];
+        match strings.get(key) {
+            Some(string) => out.push_str(string),
+            None => {
+                unresolved.missing.insert(format!("!(loc.{key})"));
+            }
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Fail if any string in the tables of `package` can't be encoded in
+/// `codepage`, since it would otherwise be written with replacement
+/// characters.
+pub(crate) fn check_codepage(
+    package: &mut Msi,
+    codepage: CodePage,
+) -> Result<()> {
+    let db = Database::load(package)
+        .with_context(|| "Failed to read the tables to check their strings")?;
+    let mut problems = Vec::new();
+    for table in db.tables() {
+        for row in &table.rows {
+            for (column, value) in table.columns.iter().zip(row) {
+                let Some(string) = value.as_str() else {
+                    continue;
+                };
+                if codepage.decode(&codepage.encode(string)) != string {
+                    problems.push(format!(
+                        "{}.{} [{}]: {}",
+                        table.name,
+                        column.name(),
+                        table.key(row),
+                        string
+                    ));
+                }
+            }
+        }
+    }
+    if !problems.is_empty() {
+        bail!(
+            "Found {} string(s) that can't be represented in codepage {}:\n{}",
+            problems.len(),
+            codepage.id(),
+            problems.join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// Embed language transforms in a package as storages named after their
+/// language identifiers. `transforms` are pairs of a name and the bytes of
+/// the transform.
+pub(crate) fn embed_transforms(
+    package: Vec<u8>,
+    transforms: &[(String, Vec<u8>)],
+) -> Result<Vec<u8>> {
+    let mut comp = cfb::CompoundFile::open(Cursor::new(package))
+        .with_context(|| "Failed to reopen the package")?;
+    for (name, transform) in transforms {
+        copy_storage(&mut comp, name, transform)?;
+    }
+    comp.flush()
+        .with_context(|| "Failed to finish embedding the transforms")?;
+    Ok(comp.into_inner().into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, io::Cursor};
+
+    use msi::{Column, Insert, Package, PackageType, Value};
+
+    use super::{embed_transforms, has_placeholders, resolve};
+    use crate::{command::builder::Msi, modules::transform};
+
+    fn strings() -> BTreeMap<String, String> {
+        [("Name", "Beispiel"), ("Company", "Beispiel GmbH")]
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value.to_owned()))
+            .collect()
+    }
+
+    fn resolved(config: &str) -> Result<toml::Value, String> {
+        let mut value = toml::from_str(config).unwrap();
+        resolve(&mut value, &strings()).map_err(|err| err.to_string())?;
+        Ok(value)
+    }
+
+    #[test]
+    fn replaces_every_placeholder_in_a_string() {
+        let value = resolved(
+            r#"
+            title = "!(loc.Name) by !(loc.Company), !(loc.Name)"
+            [[shortcut]]
+            name = ["!(loc.Name)"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            value["title"].as_str(),
+            Some("Beispiel by Beispiel GmbH, Beispiel")
+        );
+        assert_eq!(value["shortcut"][0]["name"][0].as_str(), Some("Beispiel"));
+        assert!(!has_placeholders(&value));
+    }
+
+    #[test]
+    fn lists_every_missing_string() {
+        let err = resolved(
+            r#"
+            title = "!(loc.Title) !(loc.Name)"
+            description = "!(loc.Description) !(loc.Title)"
+            "#,
+        )
+        .unwrap_err();
+        assert_eq!(err, "No string for !(loc.Description), !(loc.Title)");
+    }
+
+    #[test]
+    fn rejects_unterminated_placeholders() {
+        let err =
+            resolved(r#"title = "!(loc.Name) !(loc.Company""#).unwrap_err();
+        assert_eq!(
+            err,
+            "Placeholder without a closing `)` in [!(loc.Name) !(loc.Company]"
+        );
+    }
+
+    fn package(title: &str) -> Msi {
+        let mut package =
+            Package::create(PackageType::Installer, Cursor::new(Vec::new()))
+                .unwrap();
+        package
+            .create_table(
+                "Property",
+                vec![
+                    Column::build("Property").primary_key().id_string(72),
+                    Column::build("Value").text_string(0),
+                ],
+            )
+            .unwrap();
+        package
+            .insert_rows(
+                Insert::into("Property")
+                    .row(vec![Value::from("ProductName"), Value::from(title)]),
+            )
+            .unwrap();
+        package
+    }
+
+    /// The names of the streams below `path` and the CLSID of `path`.
+    fn storage(
+        comp: &cfb::CompoundFile<Cursor<Vec<u8>>>,
+        path: &str,
+    ) -> (Vec<String>, uuid::Uuid) {
+        let streams = comp
+            .walk_storage(path)
+            .unwrap()
+            .filter(|entry| entry.is_stream())
+            .map(|entry| entry.name().to_owned())
+            .collect();
+        (streams, *comp.entry(path).unwrap().clsid())
+    }
+
+    #[test]
+    fn embeds_transforms_as_storages_named_after_the_language() {
+        let mut base = package("Example");
+        let mut german = package("Beispiel");
+        let transform = transform::create(&mut base, &mut german).unwrap();
+        let base = base.into_inner().unwrap().into_inner();
+
+        let embedded =
+            embed_transforms(base, &[("1031".to_owned(), transform.clone())])
+                .unwrap();
+        let comp = cfb::CompoundFile::open(Cursor::new(embedded)).unwrap();
+        let original = cfb::CompoundFile::open(Cursor::new(transform)).unwrap();
+        assert!(comp.is_storage("/1031"));
+        let (streams, _) = storage(&original, "/");
+        assert!(!streams.is_empty());
+        assert_eq!(storage(&comp, "/1031"), storage(&original, "/"));
+
+        // The base package is still a package of its own.
+        let package =
+            Package::open(Cursor::new(comp.into_inner().into_inner())).unwrap();
+        assert!(package.has_table("Property"));
+    }
+}
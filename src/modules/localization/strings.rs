@@ -0,0 +1,147 @@
+//! Reading the string tables of a culture, either as TOML or as WiX `.wxl`
+//! XML.
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context, Result};
+use camino::Utf8Path;
+use serde::Deserialize;
+
+/// The strings of one culture, along with the language and codepage the
+/// file asks for, if any.
+#[derive(Default, Deserialize)]
+pub(crate) struct StringTable {
+    pub(crate) culture: Option<String>,
+    pub(crate) language: Option<u16>,
+    pub(crate) codepage: Option<i32>,
+    #[serde(default)]
+    pub(crate) strings: BTreeMap<String, String>,
+}
+
+impl StringTable {
+    /// Read a string table, picking the format from the file extension.
+    pub(crate) fn load(path: &Utf8Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read string table {path}"))?;
+        let table = match path.extension() {
+            Some("toml") => toml::from_str(&text).map_err(anyhow::Error::from),
+            Some("wxl") => parse_wxl(&text),
+            _ => bail!("String table {} is not a .toml or .wxl file", path),
+        };
+        table.with_context(|| format!("Failed to parse string table {path}"))
+    }
+}
+
+/// Parse a `WixLocalization` document. Both the WiX v3 form, with the value
+/// as the text of the `String` element, and the v4 form, with a `Value`
+/// attribute, are accepted.
+fn parse_wxl(text: &str) -> Result<StringTable> {
+    let document = roxmltree::Document::parse(text)?;
+    let root = document.root_element();
+    if root.tag_name().name() != "WixLocalization" {
+        bail!("The root element is not WixLocalization");
+    }
+    let codepage = match root.attribute("Codepage") {
+        Some(codepage) if codepage.eq_ignore_ascii_case("utf-8") => Some(65001),
+        Some(codepage) => Some(
+            codepage
+                .parse()
+                .with_context(|| format!("Invalid codepage {codepage}"))?,
+        ),
+        None => None,
+    };
+    let language = match root.attribute("Language") {
+        Some(language) => Some(
+            language
+                .parse()
+                .with_context(|| format!("Invalid language {language}"))?,
+        ),
+        None => None,
+    };
+
+    let mut strings = BTreeMap::new();
+    for element in root
+        .children()
+        .filter(|n| n.is_element() && n.tag_name().name() == "String")
+    {
+        let Some(id) = element.attribute("Id") else {
+            bail!(
+                "String on line {} has no Id",
+                document.text_pos_at(element.range().start).row
+            );
+        };
+        let value = element
+            .attribute("Value")
+            .or_else(|| element.text())
+            .unwrap_or_default();
+        if strings.insert(id.to_owned(), value.to_owned()).is_some() {
+            bail!("String {} is defined more than once", id);
+        }
+    }
+    Ok(StringTable {
+        culture: root.attribute("Culture").map(str::to_owned),
+        language,
+        codepage,
+        strings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_wxl;
+
+    #[test]
+    fn reads_values_in_the_v3_and_v4_forms() {
+        let table = parse_wxl(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+            <WixLocalization Culture="de-DE" Codepage="1252" Language="1031">
+                <String Id="V3">Willkommen</String>
+                <String Id="V4" Value="Fertig" />
+                <String Id="Empty" />
+            </WixLocalization>"#,
+        )
+        .unwrap();
+        assert_eq!(table.culture.as_deref(), Some("de-DE"));
+        assert_eq!(table.codepage, Some(1252));
+        assert_eq!(table.language, Some(1031));
+        assert_eq!(table.strings["V3"], "Willkommen");
+        assert_eq!(table.strings["V4"], "Fertig");
+        assert_eq!(table.strings["Empty"], "");
+    }
+
+    #[test]
+    fn reads_utf8_as_codepage_65001() {
+        let table = parse_wxl(
+            r#"<WixLocalization Codepage="utf-8"
+                xmlns="http://wixtoolset.org/schemas/v4/wxl">
+                <String Id="Title" Value="ようこそ" />
+            </WixLocalization>"#,
+        )
+        .unwrap();
+        assert_eq!(table.codepage, Some(65001));
+        assert_eq!(table.language, None);
+        assert_eq!(table.strings["Title"], "ようこそ");
+    }
+
+    #[test]
+    fn rejects_bad_strings() {
+        let message = |text: &str| parse_wxl(text).err().unwrap().to_string();
+        assert_eq!(
+            message(
+                "<WixLocalization>\n<String Id=\"A\">a</String>\n\
+                <String Id=\"A\">b</String></WixLocalization>"
+            ),
+            "String A is defined more than once"
+        );
+        assert_eq!(
+            message(
+                "<WixLocalization>\n\n<String>a</String></WixLocalization>"
+            ),
+            "String on line 3 has no Id"
+        );
+        assert_eq!(
+            message("<Wix />"),
+            "The root element is not WixLocalization"
+        );
+    }
+}
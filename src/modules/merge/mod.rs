@@ -0,0 +1,816 @@
+//! Merging merge modules (`.msm`) into the package being built.
+//!
+//! The tables of the module are copied into the package with a few changes:
+//!
+//! - The root directory of the module, `TARGETDIR`, is redirected to the
+//!   directory chosen in the config.
+//! - Configurable items from `ModuleConfiguration` are substituted into the
+//!   rows listed in `ModuleSubstitution`.
+//! - Rows of the `Module*Sequence` tables are scheduled into the matching
+//!   sequence tables of the package. Standard actions without a sequence
+//!   number or base action get their standard sequence number.
+//! - Files are moved to sequence numbers after the files already in the
+//!   package, and `MergeModule.CABinet` is embedded with a `Media` row of its
+//!   own so it doesn't have to be unpacked.
+//! - Every component of the module is installed with the feature chosen in
+//!   the config, or else the first `[[feature]]`.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{Read, Seek, Write},
+};
+
+use anyhow::{bail, Context, Result};
+use log::{debug, info, warn};
+use msi::{Column, ColumnType, Insert, Package, Value};
+
+use crate::{
+    command::builder::Msi,
+    modules::{
+        component::file::File,
+        config::merge_module::MergeModuleConfig,
+        helpers::cabinet::Cabinet,
+        tables::{authored, media, sequence},
+        validation::database::{Database, TableData},
+    },
+};
+
+/// Stream that holds the files of a merge module.
+pub(crate) const MODULE_CABINET: &str = "MergeModule.CABinet";
+
+/// Module sequence tables and the sequence tables they are merged into.
+const SEQUENCE_TABLES: [(&str, &str); 6] = [
+    ("ModuleInstallExecuteSequence", "InstallExecuteSequence"),
+    ("ModuleInstallUISequence", "InstallUISequence"),
+    ("ModuleAdminExecuteSequence", "AdminExecuteSequence"),
+    ("ModuleAdminUISequence", "AdminUISequence"),
+    ("ModuleAdvtExecuteSequence", "AdvtExecuteSequence"),
+    ("ModuleAdvtUISequence", "AdvtUISequence"),
+];
+
+/// Tables that only describe how to merge the module and are never copied.
+const MERGE_ONLY_TABLES: [&str; 3] = [
+    "ModuleConfiguration",
+    "ModuleSubstitution",
+    "ModuleIgnoreTable",
+];
+
+/// Tables that the `msi` crate maintains itself. It writes the `_Validation`
+/// rows of every table it creates, so the ones of the module aren't needed.
+const SYSTEM_TABLES: [&str; 5] = [
+    "_Tables",
+    "_Columns",
+    "_Streams",
+    "_Storages",
+    "_Validation",
+];
+
+/// `msidbMsmConfigurableOptionNonNullable`
+const NON_NULLABLE: i32 = 0x2;
+
+/// Formats of a configurable item.
+const FORMAT_INTEGER: i32 = 2;
+const FORMAT_BITFIELD: i32 = 3;
+
+/// Merge the module into `package`. Its components are installed with
+/// `default_feature` unless the config names another feature.
+pub(crate) fn merge_module<F: Read + Seek>(
+    package: &mut Msi,
+    module: &mut Package<F>,
+    config: &MergeModuleConfig,
+    default_feature: Option<&str>,
+) -> Result<()> {
+    let mut module_db = Database::load(module)?;
+    let (module_id, language) = signature(&module_db)?;
+    info!("Merging module {} from {}", module_id, config.path);
+    if let Some(expected) = config.language {
+        if language != 0 && language != expected as i32 {
+            bail!(
+                "Module {} is for language {} but language {} was requested",
+                module_id,
+                language,
+                expected
+            );
+        }
+    }
+
+    substitute_configuration(&mut module_db, &config.configuration)?;
+    let directory = config.directory.as_deref().unwrap_or("INSTALLDIR");
+    redirect_directories(&mut module_db, directory)?;
+
+    let target_db = Database::load(package)?;
+    if target_db
+        .rows("Directory")
+        .iter()
+        .all(|row| row.first() != Some(&Value::from(directory)))
+    {
+        bail!("Directory {} is not in the package", directory);
+    }
+    let feature = config.feature.as_deref().or(default_feature);
+    match feature {
+        Some(feature) if !target_db.has_key("Feature", feature) => {
+            bail!("Feature {} is not in the package", feature);
+        }
+        Some(_) => {}
+        None => warn!(
+            "The package has no features, so the components of module {} \
+            are never installed",
+            module_id
+        ),
+    }
+    let (disk_id, first_sequence) = media::next_media(&target_db);
+    let last_sequence = resequence_files(&mut module_db, first_sequence)?;
+
+    let ignored = module_db
+        .rows("ModuleIgnoreTable")
+        .iter()
+        .filter_map(|row| match row.first() {
+            Some(Value::Str(table)) => Some(table.clone()),
+            _ => None,
+        })
+        .collect::<BTreeSet<String>>();
+    for table in module_db.tables() {
+        let name = table.name.as_str();
+        if MERGE_ONLY_TABLES.contains(&name)
+            || SYSTEM_TABLES.contains(&name)
+            || SEQUENCE_TABLES.iter().any(|(module, _)| *module == name)
+            || ignored.contains(name)
+        {
+            continue;
+        }
+        merge_table(package, &target_db, table)?;
+    }
+    for (module_table, target_table) in SEQUENCE_TABLES {
+        if let Some(table) = module_db.table(module_table) {
+            merge_sequence(package, &target_db, table, target_table)?;
+        }
+    }
+    if let Some(feature) = feature {
+        add_feature_components(package, &target_db, &module_db, feature)?;
+    }
+
+    if let Some(last_sequence) = last_sequence {
+        embed_cabinet(package, module, &target_db, disk_id, last_sequence)?;
+    }
+    Ok(())
+}
+
+/// The ID and language of the module from `ModuleSignature`.
+fn signature(db: &Database) -> Result<(String, i32)> {
+    let table = db
+        .table("ModuleSignature")
+        .with_context(|| "Module has no ModuleSignature table")?;
+    let row = table
+        .rows
+        .first()
+        .with_context(|| "Module has an empty ModuleSignature table")?;
+    let id = table
+        .str(row, "ModuleID")
+        .with_context(|| "Module has no ModuleID")?;
+    Ok((id.to_owned(), table.int(row, "Language").unwrap_or(0)))
+}
+
+/// Replace every `[=Name]` in the `ModuleSubstitution` table with the value of
+/// the configurable item and write the result into the row it names.
+fn substitute_configuration(
+    db: &mut Database,
+    values: &BTreeMap<String, String>,
+) -> Result<()> {
+    let items = match db.table("ModuleConfiguration") {
+        Some(table) => table
+            .rows
+            .iter()
+            .filter_map(|row| {
+                let name = table.str(row, "Name")?.to_owned();
+                let item = ConfigItem {
+                    format: table.int(row, "Format").unwrap_or(0),
+                    context: table.str(row, "ContextData").map(str::to_owned),
+                    default: table.str(row, "DefaultValue").map(str::to_owned),
+                    non_nullable: table.int(row, "Attributes").unwrap_or(0)
+                        & NON_NULLABLE
+                        != 0,
+                };
+                Some((name, item))
+            })
+            .collect::<BTreeMap<String, ConfigItem>>(),
+        None => BTreeMap::new(),
+    };
+    if let Some(unknown) = values.keys().find(|k| !items.contains_key(*k)) {
+        bail!("Module has no configurable item named {}", unknown);
+    }
+
+    let substitutions = match db.table("ModuleSubstitution") {
+        Some(table) => table
+            .rows
+            .iter()
+            .map(|row| {
+                (
+                    table.str(row, "Table").unwrap_or_default().to_owned(),
+                    table.str(row, "Row").unwrap_or_default().to_owned(),
+                    table.str(row, "Column").unwrap_or_default().to_owned(),
+                    table.str(row, "Value").map(str::to_owned),
+                )
+            })
+            .collect::<Vec<_>>(),
+        None => Vec::new(),
+    };
+
+    for (table_name, row_key, column, template) in substitutions {
+        let (value, bitfield) =
+            substitute(template.as_deref(), &items, values)?;
+        let Some(table) = db.table_mut(&table_name) else {
+            bail!("ModuleSubstitution refers to missing table {}", table_name);
+        };
+        let Some(index) = table.column_index(&column) else {
+            bail!(
+                "ModuleSubstitution refers to missing column {}.{}",
+                table_name,
+                column
+            );
+        };
+        let key = row_key.split(';').collect::<Vec<&str>>();
+        let column_type = table.columns[index].coltype();
+        let Some(row) = table
+            .rows
+            .iter_mut()
+            .find(|row| key_matches(&table.columns, row, &key))
+        else {
+            bail!(
+                "ModuleSubstitution refers to missing row {} of {}",
+                row_key,
+                table_name
+            );
+        };
+
+        row[index] = match (value, column_type) {
+            (None, _) => Value::Null,
+            (Some(value), ColumnType::Str(_)) => Value::Str(value),
+            (Some(value), _) => {
+                let Ok(number) = value.parse::<i32>() else {
+                    bail!(
+                        "Configured value [{}] for {}.{} is not an integer",
+                        value,
+                        table_name,
+                        column
+                    );
+                };
+                match (bitfield, &row[index]) {
+                    // Only the bits in the mask come from the configured
+                    // value, the rest keep the value authored in the module.
+                    (Some(mask), Value::Int(current)) => {
+                        Value::Int((current & !mask) | (number & mask))
+                    }
+                    (Some(mask), _) => Value::Int(number & mask),
+                    (None, _) => Value::Int(number),
+                }
+            }
+        };
+    }
+    Ok(())
+}
+
+/// An item from the `ModuleConfiguration` table.
+struct ConfigItem {
+    format: i32,
+    context: Option<String>,
+    default: Option<String>,
+    non_nullable: bool,
+}
+
+/// Fill in the `[=Name]` references of a substitution. Returns the value and
+/// the bit mask when the value is a single bitfield item.
+fn substitute(
+    template: Option<&str>,
+    items: &BTreeMap<String, ConfigItem>,
+    values: &BTreeMap<String, String>,
+) -> Result<(Option<String>, Option<i32>)> {
+    let Some(template) = template else {
+        return Ok((None, None));
+    };
+    let mut out = String::new();
+    let mut bitfield = None;
+    let mut rest = template;
+    while let Some(start) = rest.find("[=") {
+        let Some(end) = rest[start..].find(']') else {
+            break;
+        };
+        let name = &rest[start + 2..start + end];
+        let Some(item) = items.get(name) else {
+            bail!("ModuleSubstitution refers to unknown item {}", name);
+        };
+        let value = values
+            .get(name)
+            .cloned()
+            .or_else(|| item.default.clone())
+            .unwrap_or_default();
+        if value.is_empty() && item.non_nullable {
+            bail!("Configurable item {} of the module needs a value", name);
+        }
+        if (item.format == FORMAT_INTEGER || item.format == FORMAT_BITFIELD)
+            && value.parse::<i32>().is_err()
+        {
+            bail!("Configurable item {} must be an integer", name);
+        }
+        if item.format == FORMAT_BITFIELD && template == format!("[={name}]") {
+            bitfield =
+                item.context.as_deref().and_then(|c| c.parse::<i32>().ok());
+        }
+        out.push_str(&rest[..start]);
+        out.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    match out.is_empty() {
+        true => Ok((None, None)),
+        false => Ok((Some(out), bitfield)),
+    }
+}
+
+/// Whether the primary key of `row` is `key`, as written in the `Row` column
+/// of `ModuleSubstitution`.
+fn key_matches(columns: &[Column], row: &[Value], key: &[&str]) -> bool {
+    let keys = columns.iter().filter(|c| c.is_primary_key()).count();
+    keys == key.len()
+        && row.iter().zip(key).all(|(value, key)| match value {
+            Value::Str(s) => s == key,
+            Value::Int(i) => i.to_string() == *key,
+            Value::Null => key.is_empty(),
+        })
+}
+
+/// Move the contents of the module's `TARGETDIR` into `directory`.
+fn redirect_directories(db: &mut Database, directory: &str) -> Result<()> {
+    let Some(table) = db.table_mut("Directory") else {
+        return Ok(());
+    };
+    let (Some(id), Some(parent)) = (
+        table.column_index("Directory"),
+        table.column_index("Directory_Parent"),
+    ) else {
+        bail!("Module has a malformed Directory table");
+    };
+    table.rows.retain(|row| row[id] != Value::from("TARGETDIR"));
+    for row in table.rows.iter_mut() {
+        if row[parent] == Value::from("TARGETDIR") {
+            row[parent] = Value::from(directory);
+        }
+    }
+    Ok(())
+}
+
+/// Shift the sequence numbers of the module's files so they start at `first`.
+/// Returns the last sequence number, or nothing if the module has no files.
+fn resequence_files(db: &mut Database, first: i32) -> Result<Option<i32>> {
+    let Some(table) = db.table_mut("File") else {
+        return Ok(None);
+    };
+    let Some(index) = table.column_index("Sequence") else {
+        bail!("Module has a File table without a Sequence column");
+    };
+    let sequences = table.rows.iter().filter_map(|row| match row[index] {
+        Value::Int(sequence) => Some(sequence),
+        _ => None,
+    });
+    let Some(lowest) = sequences.clone().min() else {
+        return Ok(None);
+    };
+    let offset = first - lowest;
+    let last = sequences.max().unwrap_or(lowest) + offset;
+    if last > i16::MAX as i32 {
+        bail!("Module needs sequence number {} which is too large", last);
+    }
+    for row in table.rows.iter_mut() {
+        if let Value::Int(sequence) = row[index] {
+            row[index] = Value::Int(sequence + offset);
+        }
+    }
+    Ok(Some(last))
+}
+
+/// Copy the rows of a module table into the package, creating the table if
+/// the package doesn't have it yet.
+fn merge_table(
+    package: &mut Msi,
+    target_db: &Database,
+    table: &TableData,
+) -> Result<()> {
+    let existing = match target_db.table(&table.name) {
+        Some(target) => {
+            if !same_columns(&table.columns, &target.columns) {
+                bail!(
+                    "Table {} of the module has different columns than the \
+                    package",
+                    table.name
+                );
+            }
+            target
+                .rows
+                .iter()
+                .map(|row| (target.key(row), row.clone()))
+                .collect::<BTreeMap<String, Vec<Value>>>()
+        }
+        None => {
+            debug!("Creating table {} from module", table.name);
+            package
+                .create_table(table.name.clone(), table.columns.clone())
+                .with_context(|| format!("Failed to create {}", table.name))?;
+            BTreeMap::new()
+        }
+    };
+
+    let mut rows = Vec::new();
+    for row in &table.rows {
+        let key = table.key(row);
+        match existing.get(&key) {
+            None => rows.push(row.clone()),
+            Some(current) if current == row => {}
+            Some(_) => bail!(
+                "Row {} of table {} in the module conflicts with a row \
+                already in the package",
+                key,
+                table.name
+            ),
+        }
+    }
+    if rows.is_empty() {
+        return Ok(());
+    }
+    debug!("Merging {} row(s) into {}", rows.len(), table.name);
+    package
+        .insert_rows(Insert::into(table.name.clone()).rows(rows))
+        .with_context(|| format!("Failed to merge rows into {}", table.name))
+}
+
+/// Whether two tables have columns with the same names and types.
+fn same_columns(theirs: &[Column], ours: &[Column]) -> bool {
+    let describe = |c: &Column| {
+        (
+            c.name().to_owned(),
+            c.coltype().to_string(),
+            c.is_primary_key(),
+            c.is_nullable(),
+        )
+    };
+    theirs.len() == ours.len()
+        && theirs
+            .iter()
+            .zip(ours)
+            .all(|(a, b)| describe(a) == describe(b))
+}
+
+/// Schedule the actions of a module sequence table into `target_table`.
+/// Actions without a sequence number are placed right before or after their
+/// base action, and standard actions without either get their standard
+/// sequence number.
+fn merge_sequence(
+    package: &mut Msi,
+    target_db: &Database,
+    table: &TableData,
+    target_table: &str,
+) -> Result<()> {
+    let mut scheduled = match target_db.table(target_table) {
+        Some(target) => target
+            .rows
+            .iter()
+            .filter_map(|row| {
+                Some((
+                    target.str(row, "Action")?.to_owned(),
+                    target.int(row, "Sequence"),
+                ))
+            })
+            .collect::<BTreeMap<String, Option<i32>>>(),
+        None => {
+            package
+                .create_table(target_table, sequence::columns())
+                .with_context(|| format!("Failed to create {target_table}"))?;
+            BTreeMap::new()
+        }
+    };
+
+    let mut pending = table
+        .rows
+        .iter()
+        .filter_map(|row| {
+            let action = table.str(row, "Action")?;
+            // Standard actions that the package already schedules stay
+            // where they are.
+            if scheduled.contains_key(action) {
+                return None;
+            }
+            Some((
+                action.to_owned(),
+                table.int(row, "Sequence"),
+                table.str(row, "BaseAction").map(str::to_owned),
+                table.int(row, "After").unwrap_or(0) != 0,
+                table.str(row, "Condition").map(str::to_owned),
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    let mut rows = Vec::new();
+    while !pending.is_empty() {
+        let before = pending.len();
+        pending.retain(|(action, sequence, base, after, condition)| {
+            let sequence = match (sequence, base) {
+                (Some(sequence), _) => *sequence,
+                (None, Some(base)) => match scheduled.get(base) {
+                    Some(Some(base)) if *after => base + 1,
+                    Some(Some(base)) => base - 1,
+                    _ => return true,
+                },
+                (None, None) => match sequence::standard_sequence(action) {
+                    Some(sequence) => sequence,
+                    None => return true,
+                },
+            };
+            scheduled.insert(action.clone(), Some(sequence));
+            rows.push(vec![
+                Value::from(action.as_str()),
+                match condition {
+                    Some(c) => Value::from(c.as_str()),
+                    None => Value::Null,
+                },
+                Value::Int(sequence),
+            ]);
+            false
+        });
+        if pending.len() == before {
+            let (action, _, base, _, _) = &pending[0];
+            match base {
+                Some(base) => bail!(
+                    "Can't schedule action {} from {} because its base action \
+                    {} isn't scheduled",
+                    action,
+                    table.name,
+                    base
+                ),
+                None => bail!(
+                    "Can't schedule action {} from {} because it has no \
+                    sequence number or base action and isn't a standard action",
+                    action,
+                    table.name
+                ),
+            }
+        }
+    }
+    if rows.is_empty() {
+        return Ok(());
+    }
+    package
+        .insert_rows(Insert::into(target_table).rows(rows))
+        .with_context(|| format!("Failed to merge actions into {target_table}"))
+}
+
+/// Install every component of the module with `feature`.
+fn add_feature_components(
+    package: &mut Msi,
+    target_db: &Database,
+    module_db: &Database,
+    feature: &str,
+) -> Result<()> {
+    let Some(components) = module_db.table("Component") else {
+        return Ok(());
+    };
+    let rows = components
+        .rows
+        .iter()
+        .filter_map(|row| components.str(row, "Component"))
+        .map(|component| vec![Value::from(feature), Value::from(component)])
+        .collect::<Vec<_>>();
+    if rows.is_empty() {
+        return Ok(());
+    }
+    if target_db.table("FeatureComponents").is_none() {
+        package
+            .create_table("FeatureComponents", authored::feature_components())
+            .with_context(|| "Failed to create FeatureComponents table")?;
+    }
+    debug!("Adding {} module component(s) to {}", rows.len(), feature);
+    package
+        .insert_rows(Insert::into("FeatureComponents").rows(rows))
+        .with_context(|| format!("Failed to add components to {feature}"))
+}
+
+/// Copy the module's cabinet into the package and add a `Media` row for it.
+fn embed_cabinet<F: Read + Seek>(
+    package: &mut Msi,
+    module: &mut Package<F>,
+    target_db: &Database,
+    disk_id: i32,
+    last_sequence: i32,
+) -> Result<()> {
+    if !module.has_stream(MODULE_CABINET) {
+        bail!("Module has files but no {} stream", MODULE_CABINET);
+    }
+    let mut cabinet = Vec::new();
+    module
+        .read_stream(MODULE_CABINET)
+        .and_then(|mut stream| stream.read_to_end(&mut cabinet))
+        .with_context(|| format!("Failed to read {MODULE_CABINET}"))?;
+
+    let stream = format!("MergeModule{disk_id}.cab");
+    package
+        .write_stream(&stream)
+        .and_then(|mut out| out.write_all(&cabinet))
+        .with_context(|| format!("Failed to write stream {stream}"))?;
+
+    if target_db.table(media::TABLE_NAME).is_none() {
+        package
+            .create_table(media::TABLE_NAME, media::columns())
+            .with_context(|| "Failed to create Media table")?;
+    }
+    package
+        .insert_rows(Insert::into(media::TABLE_NAME).row(vec![
+            Value::Int(disk_id),
+            Value::Int(last_sequence),
+            Value::Null,
+            Value::from(format!("#{stream}")),
+            Value::Null,
+            Value::Null,
+        ]))
+        .with_context(|| "Failed to add a Media row for the module")
+}
+
+/// Check the `ModuleDependency` and `ModuleExclusion` tables of every merged
+/// module against the modules that ended up in the package.
+pub(crate) fn check_dependencies(package: &mut Msi) -> Result<()> {
+    let db = Database::load(package)?;
+    let Some(signatures) = db.table("ModuleSignature") else {
+        return Ok(());
+    };
+    let merged = signatures
+        .rows
+        .iter()
+        .filter_map(|row| {
+            Some((
+                signatures.str(row, "ModuleID")?,
+                signatures.int(row, "Language").unwrap_or(0),
+            ))
+        })
+        .collect::<Vec<(&str, i32)>>();
+    let present = |id: &str, language: Option<i32>| {
+        merged.iter().any(|(m, l)| {
+            *m == id && language.is_none_or(|lang| lang == 0 || lang == *l)
+        })
+    };
+
+    if let Some(dependencies) = db.table("ModuleDependency") {
+        for row in &dependencies.rows {
+            let module = dependencies.str(row, "ModuleID").unwrap_or_default();
+            let required =
+                dependencies.str(row, "RequiredID").unwrap_or_default();
+            let language = dependencies.int(row, "RequiredLanguage");
+            if !present(required, language) {
+                bail!(
+                    "Module {} needs module {} to be merged",
+                    module,
+                    required
+                );
+            }
+        }
+    }
+    if let Some(exclusions) = db.table("ModuleExclusion") {
+        for row in &exclusions.rows {
+            let module = exclusions.str(row, "ModuleID").unwrap_or_default();
+            let excluded =
+                exclusions.str(row, "ExcludedID").unwrap_or_default();
+            let language = exclusions.int(row, "ExcludedLanguage");
+            if present(excluded, language) {
+                bail!(
+                    "Module {} can't be merged with module {}",
+                    module,
+                    excluded
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Store the files of a module being built in `MergeModule.CABinet`, ordered
+/// by their sequence number.
+pub(crate) fn write_module_cabinet(
+    package: &mut Msi,
+    files: &[File],
+) -> Result<()> {
+    let mut sorted = files.iter().collect::<Vec<&File>>();
+    sorted.sort_by_key(|file| *file.sequence());
+    let mut cabinet = Cabinet::default();
+    for file in sorted {
+        let contents = std::fs::read(file.source()).with_context(|| {
+            format!("Failed to read file {}", file.source())
+        })?;
+        cabinet.add_file(file.file_id(), contents);
+    }
+    if cabinet.is_empty() {
+        return Ok(());
+    }
+    let bytes = cabinet.to_bytes()?;
+    package
+        .write_stream(MODULE_CABINET)
+        .and_then(|mut out| out.write_all(&bytes))
+        .with_context(|| format!("Failed to write stream {MODULE_CABINET}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use msi::{Package, PackageType, Select};
+
+    use super::{add_feature_components, merge_sequence};
+    use crate::{
+        command::builder::Msi,
+        modules::{
+            tables::sequence::EXECUTE_SEQUENCE, validation::database::Database,
+        },
+    };
+
+    const MODULE_SEQUENCE: &[&str] =
+        &["Action", "Sequence", "BaseAction", "After", "Condition"];
+
+    fn package() -> Msi {
+        Package::create(PackageType::Installer, Cursor::new(Vec::new()))
+            .unwrap()
+    }
+
+    /// The actions and sequence numbers the module sequence rows are
+    /// scheduled at, in sequence order.
+    fn schedule(rows: &[&[&str]]) -> Result<Vec<(String, i32)>, String> {
+        let mut package = package();
+        let target_db = Database::load(&mut package).unwrap();
+        let module_db = Database::of_strings(&[(MODULE_SEQUENCE, rows)]);
+        let table = module_db.table("Action").unwrap();
+        merge_sequence(&mut package, &target_db, table, EXECUTE_SEQUENCE)
+            .map_err(|err| err.to_string())?;
+        let mut scheduled = package
+            .select_rows(Select::table(EXECUTE_SEQUENCE))
+            .unwrap()
+            .map(|row| {
+                (
+                    row["Action"].as_str().unwrap().to_owned(),
+                    row["Sequence"].as_int().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        scheduled.sort_by_key(|(_, sequence)| *sequence);
+        Ok(scheduled)
+    }
+
+    #[test]
+    fn schedules_standard_actions_at_their_standard_sequence() {
+        let scheduled = schedule(&[
+            &["RegisterTypeLibraries", "", "", "", ""],
+            &["Cleanup", "", "InstallFiles", "", ""],
+            &["InstallFiles", "", "", "", ""],
+        ]);
+        assert_eq!(
+            scheduled.unwrap(),
+            [
+                ("Cleanup".to_owned(), 3999),
+                ("InstallFiles".to_owned(), 4000),
+                ("RegisterTypeLibraries".to_owned(), 5500),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_custom_actions_without_a_place() {
+        let err = schedule(&[&["Cleanup", "", "", "", ""]]).unwrap_err();
+        assert_eq!(
+            err,
+            "Can't schedule action Cleanup from Action because it has no \
+            sequence number or base action and isn't a standard action"
+        );
+    }
+
+    #[test]
+    fn installs_module_components_with_the_feature() {
+        let mut package = package();
+        let target_db = Database::load(&mut package).unwrap();
+        let module_db = Database::of_strings(&[(
+            &["Component", "Directory_"],
+            &[&["Library.ABC", "Lib.ABC"], &["Help.ABC", "Doc.ABC"]],
+        )]);
+        add_feature_components(&mut package, &target_db, &module_db, "Main")
+            .unwrap();
+        let mut rows = package
+            .select_rows(Select::table("FeatureComponents"))
+            .unwrap()
+            .map(|row| {
+                (
+                    row["Feature_"].as_str().unwrap().to_owned(),
+                    row["Component_"].as_str().unwrap().to_owned(),
+                )
+            })
+            .collect::<Vec<_>>();
+        rows.sort();
+        assert_eq!(
+            rows,
+            [
+                ("Main".to_owned(), "Help.ABC".to_owned()),
+                ("Main".to_owned(), "Library.ABC".to_owned()),
+            ]
+        );
+    }
+}
@@ -1,5 +1,18 @@
+pub(crate) mod authoring;
 pub(crate) mod component;
 pub(crate) mod config;
+pub(crate) mod decompile;
+pub(crate) mod merge;
+pub(crate) mod patch;
+pub(crate) mod preprocess;
 pub mod helpers;
+pub(crate) mod localization;
+pub(crate) mod query;
+pub(crate) mod scaffold;
+pub(crate) mod signing;
 pub(crate) mod tables;
 pub(crate) mod traits;
+pub(crate) mod transform;
+pub(crate) mod ui;
+pub(crate) mod validation;
+pub(crate) mod wxs;
@@ -0,0 +1,915 @@
+//! Building patch packages (`.msp`).
+//!
+//! A patch holds a pair of transforms for every product it targets. The first
+//! turns the baseline into the upgraded package. The second, whose name starts
+//! with `#`, adds a `Media` row for the cabinet inside the patch, a
+//! `PatchPackage` row for the patch itself and moves the patched files to
+//! sequence numbers inside that cabinet. Files are always replaced whole.
+//!
+//! Both packages need the same ProductCode, so products that are patched
+//! can't use a `product_code` of `*`, which gives every build a new one.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::{Cursor, Read, Seek, Write},
+    time::SystemTime,
+};
+
+use anyhow::{bail, Context, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use log::{debug, info, warn};
+use msi::{
+    Category, Column, Delete, Expr, Insert, Package, PackageType, Update, Value,
+};
+use uuid::Uuid;
+
+use crate::{
+    command::transformer::MemoryPackage,
+    modules::{
+        config::files::FilesConfig,
+        helpers::{
+            cabinet::Cabinet,
+            property_set::{
+                Property, PropertySet, PID_APPNAME, PID_AUTHOR, PID_CODEPAGE,
+                PID_COMMENTS, PID_CREATE_DTM, PID_LASTAUTHOR, PID_REVNUMBER,
+                PID_SUBJECT, PID_TEMPLATE, PID_TITLE, PID_WORDCOUNT,
+            },
+            stream_name,
+        },
+        preprocess,
+        tables::media,
+        transform::{self, property},
+        validation::database::{Database, TableData},
+    },
+};
+
+/// Name of the transform that turns the baseline into the upgraded package.
+/// The patch transform has the same name with a `#` in front.
+const TRANSFORM_NAME: &str = "Upgrade";
+/// Name of the stream in the patch that holds the cabinet.
+const CABINET_STREAM: &str = "PatchFiles";
+/// Patches that use `MsiPatchSequence` need Windows Installer 3.0.
+const PATCH_WORD_COUNT: i32 = 3;
+
+/// Settings for a patch that can't be read from the packages.
+pub(crate) struct PatchOptions {
+    /// Directory the upgraded package was built from.
+    pub(crate) input_directory: Utf8PathBuf,
+    /// Config the upgraded package was built from, whose `[[files]]`
+    /// entries say which folder of the input directory every file is in.
+    pub(crate) config: Option<Utf8PathBuf>,
+    /// Patches in the same family supersede each other in sequence order.
+    /// Defaults to the product name.
+    pub(crate) family: Option<String>,
+    pub(crate) display_name: Option<String>,
+    pub(crate) description: Option<String>,
+    pub(crate) classification: String,
+}
+
+/// A folder of the input directory and the folder it is installed to.
+struct SourceFolder {
+    source: Utf8PathBuf,
+    /// The id of the directory the target starts at.
+    root: String,
+    /// The names of the folders of the target below `root`.
+    names: Vec<String>,
+}
+
+/// A file that is in the patch cabinet.
+struct PatchedFile {
+    id: String,
+    source: Utf8PathBuf,
+}
+
+/// Build a patch that updates the package in `baseline` to the one in
+/// `upgraded`, returning the bytes of the `.msp`.
+pub(crate) fn create(
+    baseline: &[u8],
+    upgraded: &[u8],
+    options: &PatchOptions,
+) -> Result<Vec<u8>> {
+    let config = match &options.config {
+        Some(path) => {
+            let config = read_config(path)?;
+            check_product_code(&config, path)?;
+            Some(config)
+        }
+        None => None,
+    };
+
+    let mut base = open(baseline)?;
+    let mut new = open(upgraded)?;
+    let base_db = Database::load(&mut base)?;
+    let new_db = Database::load(&mut new)?;
+
+    let product_code = property(&base_db, "ProductCode")
+        .with_context(|| "Baseline has no ProductCode property")?
+        .to_owned();
+    if property(&new_db, "ProductCode") != Some(product_code.as_str()) {
+        bail!(
+            "The upgraded package has a different ProductCode. Patches for \
+            major upgrades are not supported, and packages built with a \
+            product_code of `*` get a new ProductCode every time"
+        );
+    }
+    if property(&base_db, "ProductVersion")
+        == property(&new_db, "ProductVersion")
+    {
+        warn!("Baseline and upgraded package have the same ProductVersion");
+    }
+
+    let sequences =
+        stable_sequences(&file_sequences(&base_db), &file_sequences(&new_db));
+    keep_sequences(&mut new, &new_db, &sequences)?;
+
+    info!("Creating transform from baseline to upgraded package");
+    let upgrade = transform::create(&mut base, &mut new)?;
+
+    // Apply the upgrade to a copy of the baseline to get the package the
+    // patch transform is applied to.
+    let mut staged = open(baseline)?;
+    let mut comp = cfb::CompoundFile::open(Cursor::new(upgrade.clone()))
+        .with_context(|| "Failed to read the upgrade transform")?;
+    transform::apply(&mut staged, &mut comp)?;
+    let staged = into_bytes(staged)?;
+
+    let folders = source_folders(options, config.as_ref())?;
+    let files = patched_files(&base_db, &new_db, &folders)?;
+    if files.is_empty() {
+        warn!("No files changed between the baseline and upgraded package");
+    }
+
+    let patch_code = format!("{{{}}}", Uuid::new_v4()).to_uppercase();
+    let mut before = open(&staged)?;
+    let mut after = open(&staged)?;
+    add_patch_media(&mut after, &patch_code, &files)?;
+    info!("Creating patch transform");
+    let patch_transform = transform::create(&mut before, &mut after)?;
+
+    let mut cabinet = Cabinet::default();
+    for file in &files {
+        debug!("Adding {} to the patch cabinet", file.source);
+        let contents = std::fs::read(&file.source)
+            .with_context(|| format!("Failed to read {}", file.source))?;
+        cabinet.add_file(&file.id, contents);
+    }
+
+    write_patch(
+        &new_db,
+        options,
+        &patch_code,
+        &product_code,
+        &[
+            (TRANSFORM_NAME.to_owned(), upgrade),
+            (format!("#{TRANSFORM_NAME}"), patch_transform),
+        ],
+        &cabinet,
+    )
+}
+
+fn open(bytes: &[u8]) -> Result<MemoryPackage> {
+    Package::open(Cursor::new(bytes.to_vec()))
+        .with_context(|| "Failed to open package")
+}
+
+fn into_bytes(package: MemoryPackage) -> Result<Vec<u8>> {
+    Ok(package
+        .into_inner()
+        .with_context(|| "Failed to finish writing package")?
+        .into_inner())
+}
+
+/// Files that are new or whose size, version or hash changed.
+fn patched_files(
+    base_db: &Database,
+    new_db: &Database,
+    folders: &[SourceFolder],
+) -> Result<Vec<PatchedFile>> {
+    let Some(new_files) = new_db.table("File") else {
+        return Ok(Vec::new());
+    };
+    let base_files = file_summaries(base_db);
+
+    let mut files = Vec::new();
+    for (id, summary) in file_summaries(new_db) {
+        if base_files.get(&id) == Some(&summary) {
+            continue;
+        }
+        let row = new_db
+            .row("File", &id)
+            .with_context(|| format!("File {id} is missing"))?;
+        let source = file_source(new_db, new_files, row, folders)?;
+        let size = std::fs::metadata(&source)
+            .with_context(|| format!("Failed to read {source}"))?
+            .len();
+        if Some(size as i64) != summary.0.map(i64::from) {
+            bail!(
+                "{} is not the file the upgraded package was built with",
+                source
+            );
+        }
+        debug!("File {} changed", source);
+        files.push(PatchedFile { id, source });
+    }
+    Ok(files)
+}
+
+/// The sequence number and key of every file, in sequence order.
+fn file_sequences(db: &Database) -> Vec<(i32, String)> {
+    let Some(files) = db.table("File") else {
+        return Vec::new();
+    };
+    let mut sequences = files
+        .rows
+        .iter()
+        .filter_map(|row| {
+            Some((files.int(row, "Sequence")?, files.str(row, "File")?.into()))
+        })
+        .collect::<Vec<_>>();
+    sequences.sort();
+    sequences
+}
+
+/// Sequence numbers for the files of the upgraded package. Files that are in
+/// the baseline keep their number, so the upgrade doesn't move the files the
+/// patch leaves alone out of the cabinets of the baseline. New files follow
+/// the last file of the baseline in the order they have in the upgraded
+/// package.
+fn stable_sequences(
+    base: &[(i32, String)],
+    new: &[(i32, String)],
+) -> BTreeMap<String, i32> {
+    let kept = base
+        .iter()
+        .map(|(sequence, id)| (id.as_str(), *sequence))
+        .collect::<HashMap<&str, i32>>();
+    let mut next = base.iter().map(|(sequence, _)| *sequence).max();
+    new.iter()
+        .map(|(_, id)| {
+            let sequence = match kept.get(id.as_str()) {
+                Some(sequence) => *sequence,
+                None => {
+                    let sequence = next.unwrap_or(0) + 1;
+                    next = Some(sequence);
+                    sequence
+                }
+            };
+            (id.clone(), sequence)
+        })
+        .collect()
+}
+
+/// Write the sequence numbers into the File table of the upgraded package.
+/// The table is written again as a whole, since updating the rows one at a
+/// time reads the whole table for every row.
+fn keep_sequences(
+    package: &mut MemoryPackage,
+    db: &Database,
+    sequences: &BTreeMap<String, i32>,
+) -> Result<()> {
+    let Some(files) = db.table("File") else {
+        return Ok(());
+    };
+    let Some(column) = files.column_index("Sequence") else {
+        bail!("The File table of the upgraded package has no Sequence");
+    };
+    let mut changed = false;
+    let rows = files
+        .rows
+        .iter()
+        .map(|row| {
+            let mut row = row.clone();
+            let id = files.str(&row, "File").unwrap_or_default();
+            if let Some(sequence) = sequences.get(id) {
+                changed |= row[column] != Value::Int(*sequence);
+                row[column] = Value::Int(*sequence);
+            }
+            row
+        })
+        .collect::<Vec<_>>();
+    if !changed {
+        return Ok(());
+    }
+    debug!("Moving the files of the upgraded package to stable sequences");
+    package
+        .delete_rows(Delete::from("File"))
+        .and_then(|_| package.insert_rows(Insert::into("File").rows(rows)))
+        .with_context(|| "Failed to resequence the upgraded package")
+}
+
+/// The size, version and hash of a file.
+type FileSummary = (Option<i32>, Option<String>, Vec<Value>);
+
+/// Summaries of every file, keyed by file ID.
+fn file_summaries(db: &Database) -> BTreeMap<String, FileSummary> {
+    let Some(files) = db.table("File") else {
+        return BTreeMap::new();
+    };
+    files
+        .rows
+        .iter()
+        .filter_map(|row| {
+            let id = files.str(row, "File")?.to_owned();
+            let hash = db
+                .row("MsiFileHash", &id)
+                .map(|r| r[1..].to_vec())
+                .unwrap_or_default();
+            let summary = (
+                files.int(row, "FileSize"),
+                files.str(row, "Version").map(str::to_owned),
+                hash,
+            );
+            Some((id, summary))
+        })
+        .collect()
+}
+
+/// The config at `config_path`, after preprocessing.
+fn read_config(config_path: &Utf8Path) -> Result<toml::Value> {
+    let raw_config = std::fs::read_to_string(config_path)
+        .with_context(|| format!("Failed to read {config_path}"))?;
+    let preprocess::Preprocessed { value, .. } =
+        preprocess::preprocess(config_path, &raw_config, &BTreeMap::new())?;
+    Ok(value)
+}
+
+/// Fail when the config of the upgraded package lets every build pick its
+/// own ProductCode, since the baseline was then built with another one.
+fn check_product_code(
+    config: &toml::Value,
+    config_path: &Utf8Path,
+) -> Result<()> {
+    let product_code = config
+        .get("product_info")
+        .and_then(|info| info.get("product_code"))
+        .and_then(toml::Value::as_str);
+    if product_code == Some("*") {
+        bail!(
+            "{} has a product_code of `*`, which gives every build a new \
+            ProductCode. Set it to a fixed GUID to build patches for the \
+            product",
+            config_path
+        );
+    }
+    Ok(())
+}
+
+/// The folders of the input directory the upgraded package was built from.
+/// They are the `[[files]]` entries of its config if it has any, or else the
+/// whole input directory installed to `INSTALLDIR`.
+fn source_folders(
+    options: &PatchOptions,
+    config: Option<&toml::Value>,
+) -> Result<Vec<SourceFolder>> {
+    let entries = match config {
+        Some(config) => files_config(config)?,
+        None => Vec::new(),
+    };
+    if entries.is_empty() {
+        return Ok(vec![SourceFolder {
+            source: options.input_directory.clone(),
+            root: "INSTALLDIR".to_owned(),
+            names: Vec::new(),
+        }]);
+    }
+    Ok(entries
+        .into_iter()
+        .map(|entry| {
+            let mut names = entry
+                .target
+                .split('/')
+                .filter(|name| !name.is_empty())
+                .map(str::to_owned);
+            SourceFolder {
+                source: options.input_directory.join(&entry.source),
+                root: names.next().unwrap_or_default(),
+                names: names.collect(),
+            }
+        })
+        .collect())
+}
+
+/// The `[[files]]` entries of a config.
+fn files_config(config: &toml::Value) -> Result<Vec<FilesConfig>> {
+    let entries = config
+        .get("files")
+        .cloned()
+        .map(toml::Value::try_into::<Vec<FilesConfig>>)
+        .transpose()
+        .with_context(|| "Failed to parse the [[files]] entries")?;
+    Ok(entries.unwrap_or_default())
+}
+
+/// Where a file of the upgraded package is in its input directory, found by
+/// following its directory up to the target of the folder it came from.
+/// That is the folder with the longest target that holds the file.
+fn file_source(
+    db: &Database,
+    files: &TableData,
+    row: &[Value],
+    folders: &[SourceFolder],
+) -> Result<Utf8PathBuf> {
+    let directories = db
+        .table("Directory")
+        .with_context(|| "Upgraded package has no Directory table")?;
+    let name = files.str(row, "FileName").unwrap_or_default();
+    // The names of the folders the file is in, from the innermost out.
+    let mut names: Vec<String> = Vec::new();
+
+    let mut directory = files
+        .str(row, "Component_")
+        .and_then(|component| db.component_directory(component));
+    while let Some(id) = directory {
+        let folder = folders
+            .iter()
+            .filter(|folder| {
+                folder.root == id
+                    && folder.names.len() <= names.len()
+                    && folder
+                        .names
+                        .iter()
+                        .zip(names.iter().rev())
+                        .all(|(a, b)| a.eq_ignore_ascii_case(b))
+            })
+            .max_by_key(|folder| folder.names.len());
+        if let Some(folder) = folder {
+            let path = names
+                .iter()
+                .rev()
+                .skip(folder.names.len())
+                .fold(folder.source.clone(), |path, part| path.join(part));
+            return Ok(path.join(long_name(name)));
+        }
+
+        let Some(dir) = db.row("Directory", id) else {
+            bail!("Directory {} is missing", id);
+        };
+        let default_dir = directories.str(dir, "DefaultDir").unwrap_or(".");
+        let target = default_dir.split(':').next().unwrap_or(default_dir);
+        if target != "." {
+            names.push(long_name(target).to_owned());
+        }
+        directory = directories.str(dir, "Directory_Parent");
+    }
+    bail!(
+        "File {} is not installed to the target of a source folder",
+        name
+    )
+}
+
+/// The long half of a `short|long` name.
+fn long_name(name: &str) -> &str {
+    name.rsplit('|').next().unwrap_or(name)
+}
+
+fn patch_package_columns() -> Vec<Column> {
+    vec![
+        Column::build("PatchId")
+            .primary_key()
+            .category(Category::Guid)
+            .string(38),
+        Column::build("Media_").int16(),
+    ]
+}
+
+/// Add the rows the patch transform carries: a `Media` row for the cabinet in
+/// the patch, a `PatchPackage` row and new sequence numbers for the patched
+/// files that place them in that cabinet.
+fn add_patch_media(
+    package: &mut MemoryPackage,
+    patch_code: &str,
+    files: &[PatchedFile],
+) -> Result<()> {
+    let db = Database::load(package)?;
+    for (name, columns) in [
+        (media::TABLE_NAME, media::columns()),
+        ("PatchPackage", patch_package_columns()),
+    ] {
+        if db.table(name).is_none() {
+            package
+                .create_table(name, columns)
+                .with_context(|| format!("Failed to create table {name}"))?;
+        }
+    }
+
+    let (disk_id, first) = media::next_media(&db);
+    let last = first + files.len() as i32 - 1;
+    if last > i16::MAX as i32 {
+        bail!("Patch needs sequence number {} which is too large", last);
+    }
+
+    for (sequence, file) in (first..).zip(files) {
+        package
+            .update_rows(
+                Update::table("File")
+                    .set("Sequence", Value::Int(sequence))
+                    .with(Expr::col("File").eq(Expr::string(&file.id))),
+            )
+            .with_context(|| format!("Failed to resequence {}", file.id))?;
+    }
+    package
+        .insert_rows(Insert::into(media::TABLE_NAME).row(vec![
+            Value::Int(disk_id),
+            Value::Int(last),
+            Value::Null,
+            Value::from(format!("#{CABINET_STREAM}")),
+            Value::Null,
+            Value::Null,
+        ]))
+        .with_context(|| "Failed to add the patch Media row")?;
+    package
+        .insert_rows(
+            Insert::into("PatchPackage")
+                .row(vec![Value::from(patch_code), Value::Int(disk_id)]),
+        )
+        .with_context(|| "Failed to add the PatchPackage row")?;
+    Ok(())
+}
+
+/// Write the patch database, then add the transforms, the cabinet and the
+/// summary information to its compound file.
+fn write_patch(
+    new_db: &Database,
+    options: &PatchOptions,
+    patch_code: &str,
+    product_code: &str,
+    transforms: &[(String, Vec<u8>)],
+    cabinet: &Cabinet,
+) -> Result<Vec<u8>> {
+    let product_name = property(new_db, "ProductName").unwrap_or_default();
+    let manufacturer = property(new_db, "Manufacturer").unwrap_or_default();
+    let version = property(new_db, "ProductVersion").unwrap_or_default();
+    let display_name = options
+        .display_name
+        .clone()
+        .unwrap_or_else(|| format!("{product_name} {version}"));
+    let description = options.description.clone().unwrap_or_default();
+    let family = match &options.family {
+        Some(family) => family.clone(),
+        None => default_family(product_name),
+    };
+
+    let mut package =
+        Package::create(PackageType::Patch, Cursor::new(Vec::new()))
+            .with_context(|| "Failed to create an empty patch")?;
+    package
+        .create_table("MsiPatchMetadata", patch_metadata_columns())
+        .with_context(|| "Failed to create table MsiPatchMetadata")?;
+    let metadata = [
+        ("AllowRemoval", "1"),
+        ("Classification", options.classification.as_str()),
+        ("Description", description.as_str()),
+        ("DisplayName", display_name.as_str()),
+        ("ManufacturerName", manufacturer),
+        ("TargetProductName", product_name),
+    ];
+    package
+        .insert_rows(
+            Insert::into("MsiPatchMetadata").rows(
+                metadata
+                    .iter()
+                    // Value isn't nullable, so unset properties are left out.
+                    .filter(|(_, value)| !value.is_empty())
+                    .map(|(property, value)| {
+                        vec![
+                            Value::Null,
+                            Value::from(*property),
+                            Value::from(*value),
+                        ]
+                    })
+                    .collect(),
+            ),
+        )
+        .with_context(|| "Failed to add patch metadata")?;
+    package
+        .create_table("MsiPatchSequence", patch_sequence_columns())
+        .with_context(|| "Failed to create table MsiPatchSequence")?;
+    package
+        .insert_rows(Insert::into("MsiPatchSequence").row(vec![
+            Value::from(family),
+            Value::from(product_code),
+            Value::from(version),
+            Value::Null,
+        ]))
+        .with_context(|| "Failed to add patch sequence")?;
+
+    let bytes = into_bytes(package)?;
+    let mut comp = cfb::CompoundFile::open(Cursor::new(bytes))
+        .with_context(|| "Failed to reopen the patch")?;
+
+    for (name, transform) in transforms {
+        copy_storage(&mut comp, name, transform)?;
+    }
+    if !cabinet.is_empty() {
+        write_stream(
+            &mut comp,
+            &stream_name::encode(CABINET_STREAM, false),
+            &cabinet.to_bytes()?,
+        )?;
+    }
+
+    let transform_list = transforms
+        .iter()
+        .map(|(name, _)| format!(":{name}"))
+        .collect::<Vec<String>>()
+        .join(";");
+    let mut summary = PropertySet::default();
+    summary
+        .set(PID_CODEPAGE, Property::I2(1252))
+        .set_str(PID_TITLE, "Patch")
+        .set_str(PID_SUBJECT, &display_name)
+        .set_str(PID_AUTHOR, manufacturer)
+        .set_str(PID_COMMENTS, &description)
+        .set_str(PID_TEMPLATE, product_code)
+        .set_str(PID_LASTAUTHOR, &transform_list)
+        .set_str(PID_REVNUMBER, patch_code)
+        .set(PID_CREATE_DTM, Property::Time(SystemTime::now()))
+        .set(PID_WORDCOUNT, Property::I4(PATCH_WORD_COUNT))
+        .set_str(PID_APPNAME, "whimsi");
+    write_stream(&mut comp, "\u{5}SummaryInformation", &summary.to_bytes())?;
+
+    comp.flush().with_context(|| "Failed to finish the patch")?;
+    Ok(comp.into_inner().into_inner())
+}
+
+/// A patch family is an identifier, so strip anything from the product name
+/// that isn't allowed in one.
+fn default_family(product_name: &str) -> String {
+    let family = product_name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect::<String>();
+    match family.chars().next() {
+        None => "Patch".to_owned(),
+        Some(c) if c.is_ascii_digit() => format!("_{family}"),
+        Some(_) => family,
+    }
+}
+
+fn patch_metadata_columns() -> Vec<Column> {
+    vec![
+        Column::build("Company")
+            .primary_key()
+            .nullable()
+            .category(Category::Identifier)
+            .string(72),
+        Column::build("Property")
+            .primary_key()
+            .category(Category::Identifier)
+            .string(72),
+        Column::build("Value")
+            .localizable()
+            .category(Category::Text)
+            .string(0),
+    ]
+}
+
+fn patch_sequence_columns() -> Vec<Column> {
+    vec![
+        Column::build("PatchFamily")
+            .primary_key()
+            .category(Category::Identifier)
+            .string(72),
+        Column::build("ProductCode")
+            .primary_key()
+            .nullable()
+            .category(Category::Guid)
+            .string(38),
+        Column::build("Sequence")
+            .category(Category::Version)
+            .string(72),
+        Column::build("Attributes").nullable().int32(),
+    ]
+}
+
+/// Copy every stream of a transform into a storage of `comp`.
+pub(crate) fn copy_storage<F: Read + Write + Seek>(
+    comp: &mut cfb::CompoundFile<F>,
+    name: &str,
+    transform: &[u8],
+) -> Result<()> {
+    let mut source = cfb::CompoundFile::open(Cursor::new(transform))
+        .with_context(|| format!("Failed to read transform {name}"))?;
+    let storage = format!("/{name}");
+    comp.create_storage(&storage)
+        .with_context(|| format!("Failed to create storage {name}"))?;
+    comp.set_storage_clsid(&storage, *source.root_entry().clsid())
+        .with_context(|| format!("Failed to mark storage {name}"))?;
+
+    let streams = source
+        .walk()
+        .filter(|e| e.is_stream())
+        .map(|e| e.name().to_owned())
+        .collect::<Vec<String>>();
+    for stream in streams {
+        let mut bytes = Vec::new();
+        source
+            .open_stream(format!("/{stream}"))
+            .and_then(|mut s| s.read_to_end(&mut bytes))
+            .with_context(|| format!("Failed to read transform {name}"))?;
+        write_stream(comp, &format!("{name}/{stream}"), &bytes)?;
+    }
+    Ok(())
+}
+
+fn write_stream<F: Read + Write + Seek>(
+    comp: &mut cfb::CompoundFile<F>,
+    path: &str,
+    bytes: &[u8],
+) -> Result<()> {
+    comp.create_stream(format!("/{path}"))
+        .and_then(|mut stream| stream.write_all(bytes))
+        .with_context(|| {
+            format!("Failed to write stream {}", stream_name::decode(path).0)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use camino::{Utf8Path, Utf8PathBuf};
+
+    use super::{
+        check_product_code, create, file_source, stable_sequences,
+        PatchOptions, SourceFolder,
+    };
+    use crate::modules::{
+        helpers::test_dir::TestDir, validation::database::Database,
+    };
+
+    fn folder(source: &str, root: &str, names: &[&str]) -> SourceFolder {
+        SourceFolder {
+            source: Utf8PathBuf::from(source),
+            root: root.to_owned(),
+            names: names.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    fn source(file: &str, folders: &[SourceFolder]) -> String {
+        let db = Database::of_strings(&[
+            (
+                &["Directory", "Directory_Parent", "DefaultDir"],
+                &[
+                    &["TARGETDIR", "", "SourceDir"],
+                    &["ProgramFilesFolder", "TARGETDIR", "."],
+                    &["INSTALLDIR", "ProgramFilesFolder", "Product"],
+                    &["Bin", "INSTALLDIR", "bin"],
+                    &["Tools", "Bin", "TOOLS~1|Tools"],
+                    &["CommonAppDataFolder", "TARGETDIR", "."],
+                    &["Data", "CommonAppDataFolder", "Product"],
+                ],
+            ),
+            (
+                &["Component", "Directory_"],
+                &[
+                    &["App", "INSTALLDIR"],
+                    &["Tool", "Tools"],
+                    &["Settings", "Data"],
+                ],
+            ),
+            (
+                &["File", "Component_", "FileName"],
+                &[
+                    &["App", "App", "app.exe"],
+                    &["Tool", "Tool", "TOOL~1.EXE|tool.exe"],
+                    &["Settings", "Settings", "settings.ini"],
+                ],
+            ),
+        ]);
+        let files = db.table("File").unwrap();
+        let row = files
+            .rows
+            .iter()
+            .find(|row| files.str(row, "File") == Some(file))
+            .unwrap();
+        match file_source(&db, files, row, folders) {
+            Ok(path) => path.to_string(),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn finds_files_under_installdir() {
+        let folders = [folder("in", "INSTALLDIR", &[])];
+        assert_eq!(source("App", &folders), "in/app.exe");
+        assert_eq!(source("Tool", &folders), "in/bin/Tools/tool.exe");
+        assert_eq!(
+            source("Settings", &folders),
+            "File settings.ini is not installed to the target of a source \
+            folder"
+        );
+    }
+
+    #[test]
+    fn finds_files_through_the_longest_matching_target() {
+        let folders = [
+            folder("in/app", "INSTALLDIR", &[]),
+            folder("in/tools", "INSTALLDIR", &["BIN", "tools"]),
+            folder("in/data", "CommonAppDataFolder", &["Product"]),
+        ];
+        assert_eq!(source("App", &folders), "in/app/app.exe");
+        assert_eq!(source("Tool", &folders), "in/tools/tool.exe");
+        assert_eq!(source("Settings", &folders), "in/data/settings.ini");
+    }
+
+    fn sequences(files: &[(i32, &str)]) -> Vec<(i32, String)> {
+        files.iter().map(|(s, id)| (*s, id.to_string())).collect()
+    }
+
+    #[test]
+    fn keeps_the_sequences_of_the_baseline() {
+        let base = sequences(&[(1, "A"), (2, "B"), (3, "C")]);
+        // A file added before the others moves them in a new build.
+        let new = sequences(&[(1, "New"), (2, "A"), (3, "B"), (4, "Later")]);
+        let stable = stable_sequences(&base, &new);
+        assert_eq!(
+            stable.into_iter().collect::<Vec<_>>(),
+            [
+                ("A".to_owned(), 1),
+                ("B".to_owned(), 2),
+                ("Later".to_owned(), 5),
+                ("New".to_owned(), 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_generated_product_codes() {
+        let config = |code: &str| {
+            toml::from_str::<toml::Value>(&format!(
+                "[product_info]\nproduct_code = \"{code}\""
+            ))
+            .unwrap()
+        };
+        let path = Utf8Path::new("app.toml");
+        let err = check_product_code(&config("*"), path).unwrap_err();
+        assert!(err.to_string().starts_with("app.toml has a product_code"));
+        let fixed = "{1D4B9F5E-7A43-4E37-8F0A-2C7B1E6F9D10}";
+        assert!(check_product_code(&config(fixed), path).is_ok());
+    }
+
+    /// A config of a product with a fixed ProductCode at `version`.
+    fn config(version: &str) -> String {
+        format!(
+            r#"
+            [product_info]
+            product_name = "Patched"
+            product_version = "{version}"
+            manufacturer = "Whimsi"
+            product_language = 1033
+            product_code = "{{6A1F0B52-3C0E-4F7B-9D2A-58E4C1B7A903}}"
+
+            [summary_info]
+            page_count = 200
+            revision_number = "*"
+            template = "x64;1033"
+            author = "Whimsi"
+
+            [[files]]
+            source = "{version}"
+            target = "INSTALLDIR"
+            "#
+        )
+    }
+
+    #[test]
+    fn builds_a_patch_that_opens() {
+        let dir = TestDir::new();
+        dir.file("v1.toml", &config("1.0.0"));
+        dir.file("v2.toml", &config("1.0.1"));
+        dir.file("1.0.0/app.txt", "one");
+        dir.file("1.0.0/readme.txt", "same");
+        dir.file("1.0.1/app.txt", "two, which is longer");
+        dir.file("1.0.1/readme.txt", "same");
+        let baseline = dir.build("v1.toml", "");
+        let upgraded = dir.build("v2.toml", "");
+
+        let options = PatchOptions {
+            input_directory: dir.path().to_owned(),
+            config: Some(dir.path().join("v2.toml")),
+            family: None,
+            display_name: None,
+            description: None,
+            classification: "Update".to_owned(),
+        };
+        let patch = create(&baseline, &upgraded, &options).unwrap();
+        let path = dir.file("patch.msp", "");
+        std::fs::write(&path, patch).unwrap();
+
+        let mut package = msi::open(&path).unwrap();
+        let metadata = package
+            .select_rows(msi::Select::table("MsiPatchMetadata"))
+            .unwrap()
+            .map(|row| {
+                let property = row["Property"].as_str().unwrap().to_owned();
+                (property, row["Value"].as_str().unwrap().to_owned())
+            })
+            .collect::<Vec<_>>();
+        assert!(metadata
+            .contains(&("DisplayName".to_owned(), "Patched 1.0.1".to_owned())));
+        // Without a description the row is left out.
+        assert!(!metadata
+            .iter()
+            .any(|(property, _)| property == "Description"));
+        assert_eq!(package.summary_info().subject(), Some("Patched 1.0.1"));
+    }
+}
@@ -0,0 +1,457 @@
+//! Preprocessing a config before it is read.
+//!
+//! A config can `include` other configs, given as paths relative to it,
+//! which are merged below it. Tables are merged key by key, arrays of tables
+//! such as `[[feature]]` are joined with the entries of the included configs
+//! first, and other values of the including config replace the included
+//! ones. Paths in the merged config stay relative to the config being built.
+//!
+//! Strings can then use `${name}` variables, taken from `--define`, the
+//! `[variables]` table and the built-in `version`, `product_name` and
+//! `manufacturer` of `product_info`, in that order. `${env.NAME}` reads an
+//! environment variable and `$${` is written for a literal `${`.
+//!
+//! Last, every `[[if]]` entry whose `condition` holds is merged into the
+//! config. A condition compares two values with `==` or `!=`, such as
+//! `"${edition} == pro"`, or is a single value that holds unless it is
+//! empty, `0` or `false`.
+//!
+//! Where every value was written is kept, so problems found when the config
+//! is read point to the file and line of the value.
+
+mod origins;
+mod variables;
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+
+use origins::{copy_values, remove_values, Key, Origins, Values};
+use variables::Variables;
+
+const INCLUDE: &str = "include";
+const VARIABLES: &str = "variables";
+const IF: &str = "if";
+const CONDITION: &str = "condition";
+
+/// A preprocessed config, along with where its values were written.
+pub(crate) struct Preprocessed {
+    pub(crate) value: toml::Value,
+    pub(crate) origins: Origins,
+}
+
+/// Merge the includes of the config at `config_path`, replace its variables
+/// and apply its conditional sections.
+pub(crate) fn preprocess(
+    config_path: &Utf8Path,
+    raw_config: &str,
+    defines: &BTreeMap<String, String>,
+) -> Result<Preprocessed> {
+    let mut origins = Origins::default();
+    let (mut table, values) =
+        load(config_path, raw_config, &mut origins, &mut Vec::new())?;
+    origins.values = values;
+
+    let variables = Variables::new(defines, table.remove(VARIABLES), &table)
+        .map_err(|problem| {
+            anyhow::anyhow!(origins.message(&[name(VARIABLES)], &problem))
+        })?;
+    let mut problems = Vec::new();
+    variables.substitute_table(
+        &mut table,
+        &mut Vec::new(),
+        &origins,
+        &mut problems,
+    );
+    if !problems.is_empty() {
+        bail!(
+            "Found {} problem(s) with variables:\n{}",
+            problems.len(),
+            problems.join("\n")
+        );
+    }
+
+    apply_conditions(&mut table, &mut origins)?;
+    Ok(Preprocessed {
+        value: toml::Value::Table(table),
+        origins,
+    })
+}
+
+/// Read a config and merge it on top of the configs it includes.
+/// `including` holds the configs that include it, to catch include cycles.
+fn load(
+    path: &Utf8Path,
+    text: &str,
+    origins: &mut Origins,
+    including: &mut Vec<Utf8PathBuf>,
+) -> Result<(toml::Table, Values)> {
+    let mut table: toml::Table = toml::from_str(text).with_context(|| {
+        format!("Failed to parse TOML data from config file {path}")
+    })?;
+    let includes = table.remove(INCLUDE);
+    let values = origins.add_file(path, text, &table);
+    let Some(includes) = includes else {
+        return Ok((table, values));
+    };
+    let Some(includes) = includes.as_array().and_then(|includes| {
+        includes
+            .iter()
+            .map(toml::Value::as_str)
+            .collect::<Option<Vec<_>>>()
+    }) else {
+        bail!("`include` in {} has to be an array of paths", path);
+    };
+
+    let canonical = path
+        .canonicalize_utf8()
+        .with_context(|| format!("Failed to resolve config path {path}"))?;
+    including.push(canonical);
+    let directory = path.parent().unwrap_or(path);
+    let mut merged = (toml::Table::new(), Values::new());
+    for include in includes {
+        let include_path = directory.join(include);
+        let text =
+            std::fs::read_to_string(&include_path).with_context(|| {
+                format!(
+                    "Failed to read config {include_path}, included by {path}"
+                )
+            })?;
+        if including.contains(&include_path.canonicalize_utf8()?) {
+            bail!("Config {} includes itself through {}", include_path, path);
+        }
+        let (included, included_values) =
+            load(&include_path, &text, origins, including)?;
+        merge_table(
+            &mut merged.0,
+            included,
+            &mut Vec::new(),
+            &mut merged.1,
+            &included_values,
+        );
+    }
+    including.pop();
+
+    merge_table(
+        &mut merged.0,
+        table,
+        &mut Vec::new(),
+        &mut merged.1,
+        &values,
+    );
+    Ok(merged)
+}
+
+/// Merge every `[[if]]` entry whose condition holds into the config.
+fn apply_conditions(
+    table: &mut toml::Table,
+    origins: &mut Origins,
+) -> Result<()> {
+    let Some(entries) = table.remove(IF) else {
+        return Ok(());
+    };
+    let entries = match entries {
+        toml::Value::Array(entries) => entries,
+        entry @ toml::Value::Table(_) => vec![entry],
+        _ => bail!(origins.message(&[name(IF)], "`if` has to be a table")),
+    };
+    for (index, entry) in entries.into_iter().enumerate() {
+        let path = [name(IF), Key::Index(index)];
+        let toml::Value::Table(mut entry) = entry else {
+            bail!(origins.message(&path, "An `if` entry has to be a table"));
+        };
+        let Some(condition) = entry.remove(CONDITION) else {
+            bail!(origins.message(&path, "An `if` entry needs a `condition`"));
+        };
+        let Some(condition) = condition.as_str() else {
+            bail!(origins.message(
+                &[name(IF), Key::Index(index), name(CONDITION)],
+                "The `condition` has to be a string"
+            ));
+        };
+        if holds(condition) {
+            let mut values = Values::new();
+            copy_values(&origins.values, &path, &mut values, &[]);
+            merge_table(
+                table,
+                entry,
+                &mut Vec::new(),
+                &mut origins.values,
+                &values,
+            );
+        }
+    }
+    remove_values(&mut origins.values, &[name(IF)]);
+    Ok(())
+}
+
+/// Whether a condition holds once its variables have been replaced.
+fn holds(condition: &str) -> bool {
+    let operand =
+        |value: &str| value.trim().trim_matches(['"', '\'']).to_owned();
+    if let Some((left, right)) = condition.split_once("!=") {
+        return operand(left) != operand(right);
+    }
+    if let Some((left, right)) = condition.split_once("==") {
+        return operand(left) == operand(right);
+    }
+    !matches!(operand(condition).as_str(), "" | "0" | "false")
+}
+
+/// Merge `overlay` on top of `base`, along with the origins of its values.
+fn merge_table(
+    base: &mut toml::Table,
+    overlay: toml::Table,
+    path: &mut Vec<Key>,
+    values: &mut Values,
+    overlay_values: &Values,
+) {
+    for (key, value) in overlay {
+        path.push(Key::Name(key.clone()));
+        match base.get_mut(&key) {
+            Some(existing) => {
+                merge_value(existing, value, path, values, overlay_values)
+            }
+            None => {
+                copy_values(overlay_values, path, values, path);
+                base.insert(key, value);
+            }
+        }
+        path.pop();
+    }
+}
+
+fn merge_value(
+    base: &mut toml::Value,
+    overlay: toml::Value,
+    path: &mut Vec<Key>,
+    values: &mut Values,
+    overlay_values: &Values,
+) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            merge_table(base, overlay, path, values, overlay_values)
+        }
+        (toml::Value::Array(base), toml::Value::Array(overlay))
+            if is_array_of_tables(base) && is_array_of_tables(&overlay) =>
+        {
+            for (index, item) in overlay.into_iter().enumerate() {
+                let from = [path.as_slice(), &[Key::Index(index)]].concat();
+                let to = [path.as_slice(), &[Key::Index(base.len())]].concat();
+                copy_values(overlay_values, &from, values, &to);
+                base.push(item);
+            }
+        }
+        (base, overlay) => {
+            remove_values(values, path);
+            copy_values(overlay_values, path, values, path);
+            *base = overlay;
+        }
+    }
+}
+
+fn is_array_of_tables(items: &[toml::Value]) -> bool {
+    !items.is_empty() && items.iter().all(toml::Value::is_table)
+}
+
+fn name(name: &str) -> Key {
+    Key::Name(name.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::helpers::test_dir::TestDir;
+
+    fn run(
+        dir: &TestDir,
+        path: &str,
+        defines: &[(&str, &str)],
+    ) -> Result<Preprocessed> {
+        let path = dir.path().join(path);
+        let text = std::fs::read_to_string(&path)?;
+        let defines = defines
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        preprocess(&path, &text, &defines)
+    }
+
+    fn get<'a>(value: &'a toml::Value, path: &str) -> &'a toml::Value {
+        path.split('.').fold(value, |value, key| &value[key])
+    }
+
+    #[test]
+    fn merges_includes_below_the_including_config() {
+        let dir = TestDir::new();
+        dir.file(
+            "first.toml",
+            r#"
+            [product_info]
+            product_name = "First"
+            manufacturer = "First Inc"
+            [[feature]]
+            id = "first"
+            "#,
+        );
+        dir.file(
+            "parts/second.toml",
+            r#"
+            [product_info]
+            manufacturer = "Second Inc"
+            [[feature]]
+            id = "second"
+            "#,
+        );
+        dir.file(
+            "main.toml",
+            r#"
+            include = ["first.toml", "parts/second.toml"]
+            [product_info]
+            product_name = "Main"
+            [[feature]]
+            id = "main"
+            "#,
+        );
+        let value = run(&dir, "main.toml", &[]).unwrap().value;
+        assert_eq!(
+            get(&value, "product_info.product_name").as_str(),
+            Some("Main")
+        );
+        assert_eq!(
+            get(&value, "product_info.manufacturer").as_str(),
+            Some("Second Inc")
+        );
+        let features = get(&value, "feature")
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|feature| feature["id"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(features, ["first", "second", "main"]);
+        assert!(value.get(INCLUDE).is_none());
+    }
+
+    #[test]
+    fn rejects_include_cycles() {
+        let dir = TestDir::new();
+        dir.file("main.toml", r#"include = ["part.toml"]"#);
+        dir.file("part.toml", r#"include = ["main.toml"]"#);
+        let err = run(&dir, "main.toml", &[]).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Config {} includes itself through {}",
+                dir.path().join("main.toml"),
+                dir.path().join("part.toml")
+            )
+        );
+
+        dir.file("self.toml", r#"include = ["self.toml"]"#);
+        let err = run(&dir, "self.toml", &[]).err().unwrap();
+        assert!(err.to_string().contains("includes itself"), "{err}");
+    }
+
+    #[test]
+    fn defines_win_over_the_variables_table_and_built_ins() {
+        let dir = TestDir::new();
+        dir.file(
+            "main.toml",
+            r#"
+            [product_info]
+            product_name = "App"
+            product_version = "1.2.3"
+            manufacturer = "Maker"
+            [variables]
+            version = "9.9.9"
+            edition = "home"
+            [strings]
+            name = "${product_name} by ${manufacturer}"
+            version = "${version}"
+            edition = "${edition}"
+            literal = "$${edition}"
+            "#,
+        );
+        let value = run(&dir, "main.toml", &[]).unwrap().value;
+        assert_eq!(get(&value, "strings.name").as_str(), Some("App by Maker"));
+        assert_eq!(get(&value, "strings.version").as_str(), Some("9.9.9"));
+        assert_eq!(get(&value, "strings.edition").as_str(), Some("home"));
+        assert_eq!(get(&value, "strings.literal").as_str(), Some("${edition}"));
+        assert!(value.get(VARIABLES).is_none());
+
+        let value =
+            run(&dir, "main.toml", &[("edition", "pro")]).unwrap().value;
+        assert_eq!(get(&value, "strings.edition").as_str(), Some("pro"));
+        assert_eq!(get(&value, "strings.version").as_str(), Some("9.9.9"));
+    }
+
+    #[test]
+    fn applies_the_conditions_that_hold() {
+        let dir = TestDir::new();
+        dir.file(
+            "main.toml",
+            r#"
+            [variables]
+            edition = "home"
+            [[feature]]
+            id = "main"
+            [[if]]
+            condition = "${edition} == pro"
+            [[if.feature]]
+            id = "pro"
+            [[if]]
+            condition = "${edition} != pro"
+            [[if.feature]]
+            id = "home"
+            "#,
+        );
+        let value = run(&dir, "main.toml", &[]).unwrap().value;
+        let features = get(&value, "feature")
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|feature| feature["id"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(features, ["main", "home"]);
+        assert!(value.get(IF).is_none());
+    }
+
+    #[test]
+    fn locates_problems_in_included_configs() {
+        let dir = TestDir::new();
+        let base = dir.file(
+            "base.toml",
+            "[product_info]\nmanufacturer = \"Maker\"\nproduct_name = \"${missing}\"\n",
+        );
+        dir.file(
+            "main.toml",
+            "include = [\"base.toml\"]\n[product_info]\nproduct_version = \"1.0.0\"\n",
+        );
+        let err = run(&dir, "main.toml", &[]).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Found 1 problem(s) with variables:\n{base}:3:16: Variable \
+                missing isn't defined, give it with `--define missing=VALUE` \
+                or in the [variables] table"
+            )
+        );
+
+        dir.file(
+            "base.toml",
+            "[product_info]\nmanufacturer = \"Maker\"\nproduct_nmae = \"App\"\n",
+        );
+        let preprocessed = run(&dir, "main.toml", &[]).unwrap();
+        let path = [name("product_info"), name("product_nmae")];
+        assert_eq!(
+            preprocessed.origins.message(&path, "Unknown"),
+            format!("{base}:3:16: Unknown")
+        );
+        let path = [name("product_info"), name("product_version")];
+        assert_eq!(
+            preprocessed.origins.message(&path, "Unknown"),
+            format!("{}:3:19: Unknown", dir.path().join("main.toml"))
+        );
+    }
+}
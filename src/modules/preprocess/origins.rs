@@ -0,0 +1,240 @@
+//! Where the values of a merged config were written, so problems with a
+//! value can point to the file and line it came from.
+
+use std::{collections::BTreeMap, fmt, ops::Range};
+
+use camino::{Utf8Path, Utf8PathBuf};
+use serde::de::DeserializeOwned;
+use serde_path_to_error::Segment;
+use toml_edit::ImDocument;
+
+use crate::modules::helpers::suggest::closest;
+
+/// Start of the serde messages about keys and values that aren't expected.
+const UNKNOWN_FIELD: &str = "unknown field `";
+const UNKNOWN_VARIANT: &str = "unknown variant `";
+
+/// One step of the path to a value, a key of a table or an index of an
+/// array.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Key {
+    Name(String),
+    Index(usize),
+}
+
+/// A file and the path of a value within it.
+#[derive(Clone)]
+pub(super) struct Origin {
+    file: usize,
+    path: Vec<Key>,
+}
+
+/// The origin of every value of a config, by the path of the value.
+pub(super) type Values = BTreeMap<Vec<Key>, Origin>;
+
+/// The files a config was read from and where each of its values was
+/// written.
+#[derive(Default)]
+pub(crate) struct Origins {
+    /// The path and text of every file.
+    files: Vec<(Utf8PathBuf, String)>,
+    pub(super) values: Values,
+}
+
+/// A line and column of a file, starting from 1.
+pub(crate) struct Location<'a> {
+    file: &'a Utf8Path,
+    line: usize,
+    column: usize,
+}
+
+impl fmt::Display for Location<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+impl Origins {
+    /// Keep the text of a file and record every value of `table` as written
+    /// in it.
+    pub(super) fn add_file(
+        &mut self,
+        path: &Utf8Path,
+        text: &str,
+        table: &toml::Table,
+    ) -> Values {
+        let file = self.files.len();
+        self.files.push((path.to_owned(), text.to_owned()));
+        let mut values = Values::new();
+        record_table(&mut values, file, &mut Vec::new(), table);
+        values
+    }
+
+    /// Where the value at `path` was written. Values that were made rather
+    /// than written point to the closest value above them that was written.
+    pub(crate) fn locate(&self, path: &[Key]) -> Option<Location<'_>> {
+        let (depth, origin) = (0..=path.len()).rev().find_map(|depth| {
+            Some((depth, self.values.get(&path[..depth])?))
+        })?;
+        let (file, text) = &self.files[origin.file];
+        let local = [origin.path.as_slice(), &path[depth..]].concat();
+        let offset = span(text, &local).map_or(0, |span| span.start);
+        let before = &text[..offset];
+        Some(Location {
+            file,
+            line: before.matches('\n').count() + 1,
+            column: before
+                .rsplit('\n')
+                .next()
+                .unwrap_or_default()
+                .chars()
+                .count()
+                + 1,
+        })
+    }
+
+    /// A message about the value at `path`, starting with where it was
+    /// written.
+    pub(crate) fn message(&self, path: &[Key], message: &str) -> String {
+        match self.locate(path) {
+            Some(location) => format!("{location}: {message}"),
+            None => message.to_owned(),
+        }
+    }
+
+    /// Read a config from its preprocessed value. Problems point to where the
+    /// value they are about was written, and unknown keys and values suggest
+    /// the expected one closest to them.
+    pub(crate) fn deserialize<T: DeserializeOwned>(
+        &self,
+        value: toml::Value,
+    ) -> anyhow::Result<T> {
+        serde_path_to_error::deserialize(value).map_err(|err| {
+            let mut path = err
+                .path()
+                .iter()
+                .map_while(|segment| match segment {
+                    Segment::Seq { index } => Some(Key::Index(*index)),
+                    Segment::Map { key } => Some(Key::Name(key.clone())),
+                    Segment::Enum { .. } | Segment::Unknown => None,
+                })
+                .collect::<Vec<_>>();
+            let message = err.inner().message();
+            let mut text = err.to_string();
+            if let Some(field) = message
+                .strip_prefix(UNKNOWN_FIELD)
+                .and_then(|rest| rest.split_once('`'))
+                .map(|(field, _)| field)
+            {
+                path.push(Key::Name(field.to_owned()));
+            }
+            if let Some(suggestion) = suggestion(message) {
+                text = format!("{text}, did you mean `{suggestion}`?");
+            }
+            anyhow::anyhow!(self.message(&path, &text))
+        })
+    }
+}
+
+/// The expected name closest to the unknown one of a serde message such as
+/// "unknown field `nmae`, expected one of `id`, `name`".
+fn suggestion(message: &str) -> Option<&str> {
+    let rest = message
+        .strip_prefix(UNKNOWN_FIELD)
+        .or_else(|| message.strip_prefix(UNKNOWN_VARIANT))?;
+    let (unknown, expected) = rest.split_once('`')?;
+    closest(unknown, expected.split('`').skip(1).step_by(2))
+}
+
+fn record_table(
+    values: &mut Values,
+    file: usize,
+    path: &mut Vec<Key>,
+    table: &toml::Table,
+) {
+    for (name, value) in table {
+        path.push(Key::Name(name.clone()));
+        record_value(values, file, path, value);
+        path.pop();
+    }
+}
+
+fn record_value(
+    values: &mut Values,
+    file: usize,
+    path: &mut Vec<Key>,
+    value: &toml::Value,
+) {
+    values.insert(
+        path.clone(),
+        Origin {
+            file,
+            path: path.clone(),
+        },
+    );
+    match value {
+        toml::Value::Table(table) => record_table(values, file, path, table),
+        toml::Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                path.push(Key::Index(index));
+                record_value(values, file, path, item);
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Copy the origins of the values below `from` in `source` to below `to` in
+/// `target`.
+pub(super) fn copy_values(
+    source: &Values,
+    from: &[Key],
+    target: &mut Values,
+    to: &[Key],
+) {
+    let copied = source
+        .range(from.to_vec()..)
+        .take_while(|(path, _)| path.starts_with(from))
+        .map(|(path, origin)| {
+            ([to, &path[from.len()..]].concat(), origin.clone())
+        })
+        .collect::<Vec<_>>();
+    target.extend(copied);
+}
+
+/// Forget the origins of the values below `path`.
+pub(super) fn remove_values(values: &mut Values, path: &[Key]) {
+    values.retain(|value, _| !value.starts_with(path));
+}
+
+/// The bytes of `text` that the value at `path` was written in, or the
+/// closest value above it that has a known span.
+fn span(text: &str, path: &[Key]) -> Option<Range<usize>> {
+    let document = ImDocument::parse(text).ok()?;
+    let mut item = document.as_item();
+    let mut span = None;
+    for key in path {
+        let next = match key {
+            Key::Name(name) => {
+                let Some((key, value)) = item
+                    .as_table_like()
+                    .and_then(|table| table.get_key_value(name))
+                else {
+                    break;
+                };
+                span = key.span().or(span);
+                value
+            }
+            Key::Index(index) => {
+                let Some(value) = item.get(*index) else {
+                    break;
+                };
+                value
+            }
+        };
+        span = next.span().or(span);
+        item = next;
+    }
+    span
+}
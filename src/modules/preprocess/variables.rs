@@ -0,0 +1,172 @@
+//! Replacing `${name}` variables in the strings of a config.
+
+use std::collections::BTreeMap;
+
+use super::origins::{Key, Origins};
+
+const START: &str = "${";
+/// Written for a literal `${`.
+const ESCAPED_START: &str = "$${";
+/// Prefix of variables that read an environment variable.
+const ENV_PREFIX: &str = "env.";
+
+/// Built-in variables and the `product_info` keys they are read from.
+const BUILT_INS: [(&str, &str); 3] = [
+    ("version", "product_version"),
+    ("product_name", "product_name"),
+    ("manufacturer", "manufacturer"),
+];
+
+/// The unresolved value of every variable. Values can use other variables,
+/// which are resolved when they are used.
+pub(super) struct Variables {
+    values: BTreeMap<String, String>,
+}
+
+impl Variables {
+    /// Collect the variables, where `--define` wins over the `[variables]`
+    /// table, which wins over the built-ins.
+    pub(super) fn new(
+        defines: &BTreeMap<String, String>,
+        table: Option<toml::Value>,
+        config: &toml::Table,
+    ) -> Result<Self, String> {
+        let mut values = BTreeMap::new();
+        let product_info = config.get("product_info");
+        for (name, key) in BUILT_INS {
+            if let Some(value) = product_info
+                .and_then(|product_info| product_info.get(key))
+                .and_then(toml::Value::as_str)
+            {
+                values.insert(name.to_owned(), value.to_owned());
+            }
+        }
+        match table {
+            None => {}
+            Some(toml::Value::Table(table)) => {
+                for (name, value) in table {
+                    let value = match value {
+                        toml::Value::String(value) => value,
+                        toml::Value::Table(_) | toml::Value::Array(_) => {
+                            return Err(format!(
+                                "Variable {name} has to be a string, number \
+                                or boolean"
+                            ));
+                        }
+                        value => value.to_string(),
+                    };
+                    values.insert(name, value);
+                }
+            }
+            Some(_) => return Err("`variables` has to be a table".to_owned()),
+        }
+        values.extend(defines.clone());
+        Ok(Variables { values })
+    }
+
+    /// Replace the variables in every string of `table`, adding a problem
+    /// for every string that uses a variable that can't be resolved.
+    pub(super) fn substitute_table(
+        &self,
+        table: &mut toml::Table,
+        path: &mut Vec<Key>,
+        origins: &Origins,
+        problems: &mut Vec<String>,
+    ) {
+        for (name, value) in table.iter_mut() {
+            path.push(Key::Name(name.clone()));
+            self.substitute_value(value, path, origins, problems);
+            path.pop();
+        }
+    }
+
+    fn substitute_value(
+        &self,
+        value: &mut toml::Value,
+        path: &mut Vec<Key>,
+        origins: &Origins,
+        problems: &mut Vec<String>,
+    ) {
+        match value {
+            toml::Value::String(text) => {
+                match self.substitute(text, &mut Vec::new()) {
+                    Ok(replaced) => *text = replaced,
+                    Err(problem) => {
+                        problems.push(origins.message(path, &problem))
+                    }
+                }
+            }
+            toml::Value::Array(items) => {
+                for (index, item) in items.iter_mut().enumerate() {
+                    path.push(Key::Index(index));
+                    self.substitute_value(item, path, origins, problems);
+                    path.pop();
+                }
+            }
+            toml::Value::Table(table) => {
+                self.substitute_table(table, path, origins, problems)
+            }
+            _ => {}
+        }
+    }
+
+    /// Replace the variables in `text`. `resolving` holds the variables whose
+    /// values are being replaced, to catch variables that use themselves.
+    fn substitute(
+        &self,
+        text: &str,
+        resolving: &mut Vec<String>,
+    ) -> Result<String, String> {
+        if !text.contains('$') {
+            return Ok(text.to_owned());
+        }
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('$') {
+            out.push_str(&rest[..start]);
+            let from_dollar = &rest[start..];
+            if let Some(after) = from_dollar.strip_prefix(ESCAPED_START) {
+                out.push_str(START);
+                rest = after;
+                continue;
+            }
+            let Some(after) = from_dollar.strip_prefix(START) else {
+                out.push('$');
+                rest = &from_dollar[1..];
+                continue;
+            };
+            let Some(end) = after.find('}') else {
+                return Err(format!("`{START}` without a closing `}}`"));
+            };
+            out.push_str(&self.lookup(after[..end].trim(), resolving)?);
+            rest = &after[end + 1..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    fn lookup(
+        &self,
+        name: &str,
+        resolving: &mut Vec<String>,
+    ) -> Result<String, String> {
+        if let Some(env_name) = name.strip_prefix(ENV_PREFIX) {
+            return std::env::var(env_name).map_err(|_| {
+                format!("Environment variable {env_name} isn't set")
+            });
+        }
+        let Some(value) = self.values.get(name) else {
+            return Err(format!(
+                "Variable {name} isn't defined, give it with `--define \
+                {name}=VALUE` or in the [variables] table"
+            ));
+        };
+        if resolving.iter().any(|variable| variable == name) {
+            return Err(format!("Variable {name} uses itself"));
+        }
+        resolving.push(name.to_owned());
+        let value = self.substitute(value, resolving);
+        resolving.pop();
+        value
+    }
+}
@@ -0,0 +1,256 @@
+use anyhow::{bail, Result};
+
+/// A single token of a Windows Installer SQL query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Token {
+    /// A bare or backtick quoted identifier. Keywords are identifiers too and
+    /// are told apart by the parser so that tables and columns can use names
+    /// such as `Condition`.
+    Ident { name: String, quoted: bool },
+    /// A string literal in single quotes.
+    Str(String),
+    Int(i32),
+    Comma,
+    Dot,
+    Star,
+    LParen,
+    RParen,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Token {
+    /// Whether this token is the given keyword. Quoted identifiers are never
+    /// keywords.
+    pub(crate) fn is_keyword(&self, keyword: &str) -> bool {
+        match self {
+            Token::Ident {
+                name,
+                quoted: false,
+            } => name.eq_ignore_ascii_case(keyword),
+            _ => false,
+        }
+    }
+}
+
+/// A token along with the character offset it started at, for error messages.
+#[derive(Clone, Debug)]
+pub(crate) struct Spanned {
+    pub(crate) token: Token,
+    pub(crate) offset: usize,
+}
+
+/// Split a query into tokens.
+pub(crate) fn tokenize(query: &str) -> Result<Vec<Spanned>> {
+    let chars = query.chars().collect::<Vec<char>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let offset = i;
+        let c = chars[i];
+        let token = match c {
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ',' => Token::Comma,
+            '.' => Token::Dot,
+            '*' => Token::Star,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '=' => Token::Eq,
+            '<' | '>' | '!' => {
+                let next = chars.get(i + 1).copied();
+                let (token, len) = match (c, next) {
+                    ('<', Some('=')) => (Token::Le, 2),
+                    ('<', Some('>')) => (Token::Ne, 2),
+                    ('>', Some('=')) => (Token::Ge, 2),
+                    ('!', Some('=')) => (Token::Ne, 2),
+                    ('<', _) => (Token::Lt, 1),
+                    ('>', _) => (Token::Gt, 1),
+                    _ => bail!("Unexpected character '!' at offset {}", i),
+                };
+                tokens.push(Spanned { token, offset });
+                i += len;
+                continue;
+            }
+            '\'' => {
+                let (value, end) = read_quoted(&chars, i, '\'')?;
+                tokens.push(Spanned {
+                    token: Token::Str(value),
+                    offset,
+                });
+                i = end;
+                continue;
+            }
+            '`' => {
+                let (name, end) = read_quoted(&chars, i, '`')?;
+                tokens.push(Spanned {
+                    token: Token::Ident { name, quoted: true },
+                    offset,
+                });
+                i = end;
+                continue;
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let text = chars[start..i].iter().collect::<String>();
+                let Ok(value) = text.parse::<i32>() else {
+                    bail!("Invalid integer {} at offset {}", text, start);
+                };
+                tokens.push(Spanned {
+                    token: Token::Int(value),
+                    offset,
+                });
+                continue;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '_')
+                {
+                    i += 1;
+                }
+                tokens.push(Spanned {
+                    token: Token::Ident {
+                        name: chars[start..i].iter().collect(),
+                        quoted: false,
+                    },
+                    offset,
+                });
+                continue;
+            }
+            c => bail!("Unexpected character '{}' at offset {}", c, i),
+        };
+        tokens.push(Spanned { token, offset });
+        i += 1;
+    }
+
+    Ok(tokens)
+}
+
+/// Read a quoted string that starts at `start`. Two quote characters in a row
+/// are read as a single literal quote. Returns the contents and the offset
+/// just past the closing quote.
+fn read_quoted(
+    chars: &[char],
+    start: usize,
+    quote: char,
+) -> Result<(String, usize)> {
+    let mut value = String::new();
+    let mut i = start + 1;
+    loop {
+        match chars.get(i) {
+            None => bail!("Unterminated {} quote at offset {}", quote, start),
+            Some(&c) if c == quote => {
+                if chars.get(i + 1) == Some(&quote) {
+                    value.push(quote);
+                    i += 2;
+                } else {
+                    return Ok((value, i + 1));
+                }
+            }
+            Some(&c) => {
+                value.push(c);
+                i += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tokenize, Token};
+
+    fn tokens(query: &str) -> Vec<Token> {
+        tokenize(query)
+            .unwrap()
+            .into_iter()
+            .map(|spanned| spanned.token)
+            .collect()
+    }
+
+    fn ident(name: &str, quoted: bool) -> Token {
+        Token::Ident {
+            name: name.to_owned(),
+            quoted,
+        }
+    }
+
+    #[test]
+    fn reads_names_literals_and_operators() {
+        assert_eq!(
+            tokens("SELECT `File`.* FROM File WHERE Size >= -12"),
+            vec![
+                ident("SELECT", false),
+                ident("File", true),
+                Token::Dot,
+                Token::Star,
+                ident("FROM", false),
+                ident("File", false),
+                ident("WHERE", false),
+                ident("Size", false),
+                Token::Ge,
+                Token::Int(-12),
+            ]
+        );
+        assert_eq!(
+            tokens("<> != <= < > ="),
+            vec![
+                Token::Ne,
+                Token::Ne,
+                Token::Le,
+                Token::Lt,
+                Token::Gt,
+                Token::Eq
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_doubled_quotes_as_one() {
+        assert_eq!(
+            tokens("'it''s' `a``b`"),
+            vec![Token::Str("it's".to_owned()), ident("a`b", true)]
+        );
+    }
+
+    #[test]
+    fn quoted_names_are_never_keywords() {
+        assert!(ident("select", false).is_keyword("SELECT"));
+        assert!(!ident("select", true).is_keyword("SELECT"));
+    }
+
+    #[test]
+    fn records_offsets() {
+        let offsets = tokenize("a  = 'b'")
+            .unwrap()
+            .into_iter()
+            .map(|spanned| spanned.offset)
+            .collect::<Vec<usize>>();
+        assert_eq!(offsets, vec![0, 3, 5]);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let error = |query: &str| tokenize(query).unwrap_err().to_string();
+        assert_eq!(error("'open"), "Unterminated ' quote at offset 0");
+        assert_eq!(error("a `b"), "Unterminated ` quote at offset 2");
+        assert_eq!(error("a ! b"), "Unexpected character '!' at offset 2");
+        assert_eq!(error("a ; b"), "Unexpected character ';' at offset 2");
+        assert_eq!(error("- 1"), "Invalid integer - at offset 0");
+        assert_eq!(
+            error("99999999999"),
+            "Invalid integer 99999999999 at offset 0"
+        );
+    }
+}
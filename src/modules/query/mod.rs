@@ -0,0 +1,74 @@
+pub(crate) mod lexer;
+pub(crate) mod parser;
+
+use anyhow::{Context, Result};
+use msi::{Expr, Select};
+
+use parser::{Comparison, Condition, Operand, Query};
+
+/// Parse a query and convert it into a `Select` that the `msi` crate can run.
+///
+/// `ORDER BY` has no equivalent in the `msi` crate, so it is returned as part
+/// of the parsed query for the caller to apply to the selected rows.
+pub(crate) fn compile(query: &str) -> Result<(Select, Query)> {
+    let tokens = lexer::tokenize(query)
+        .with_context(|| format!("Failed to read query [{query}]"))?;
+    let parsed = parser::parse(tokens)
+        .with_context(|| format!("Failed to parse query [{query}]"))?;
+    Ok((to_select(&parsed), parsed))
+}
+
+fn to_select(query: &Query) -> Select {
+    let mut select = Select::table(query.table.clone());
+    for join in &query.joins {
+        let rhs = Select::table(join.table.clone());
+        let on = to_expr(&join.on);
+        select = if join.left {
+            select.left_join(rhs, on)
+        } else {
+            select.inner_join(rhs, on)
+        };
+    }
+    if let Some(condition) = &query.condition {
+        select = select.with(to_expr(condition));
+    }
+    if let Some(columns) = &query.columns {
+        select = select.columns(columns);
+    }
+    select
+}
+
+fn to_expr(condition: &Condition) -> Expr {
+    match condition {
+        Condition::Compare(lhs, comparison, rhs) => {
+            let (lhs, rhs) = (operand_expr(lhs), operand_expr(rhs));
+            match comparison {
+                Comparison::Eq => lhs.eq(rhs),
+                Comparison::Ne => lhs.ne(rhs),
+                Comparison::Lt => lhs.lt(rhs),
+                Comparison::Le => lhs.le(rhs),
+                Comparison::Gt => lhs.gt(rhs),
+                Comparison::Ge => lhs.ge(rhs),
+            }
+        }
+        Condition::IsNull { operand, negated } => {
+            let operand = operand_expr(operand);
+            if *negated {
+                operand.ne(Expr::null())
+            } else {
+                operand.eq(Expr::null())
+            }
+        }
+        Condition::And(lhs, rhs) => to_expr(lhs).and(to_expr(rhs)),
+        Condition::Or(lhs, rhs) => to_expr(lhs).or(to_expr(rhs)),
+    }
+}
+
+fn operand_expr(operand: &Operand) -> Expr {
+    match operand {
+        Operand::Column(name) => Expr::col(name.clone()),
+        Operand::Str(value) => Expr::string(value.clone()),
+        Operand::Int(value) => Expr::integer(*value),
+        Operand::Null => Expr::null(),
+    }
+}
@@ -0,0 +1,445 @@
+use anyhow::{bail, Result};
+
+use super::lexer::{Spanned, Token};
+
+/// A comparison between two operands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// One side of a comparison.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Operand {
+    Column(String),
+    Str(String),
+    Int(i32),
+    Null,
+}
+
+/// A `WHERE` or `ON` condition.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Condition {
+    Compare(Operand, Comparison, Operand),
+    IsNull { operand: Operand, negated: bool },
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
+
+/// A table joined onto the selection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Join {
+    pub(crate) table: String,
+    pub(crate) left: bool,
+    pub(crate) on: Condition,
+}
+
+/// A column the result is sorted by.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct OrderBy {
+    pub(crate) column: String,
+    pub(crate) descending: bool,
+}
+
+/// A parsed `SELECT` statement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Query {
+    /// The selected columns. `None` when the query selects `*`.
+    pub(crate) columns: Option<Vec<String>>,
+    pub(crate) table: String,
+    pub(crate) joins: Vec<Join>,
+    pub(crate) condition: Option<Condition>,
+    pub(crate) order_by: Vec<OrderBy>,
+}
+
+/// Parse the subset of the Windows Installer SQL dialect that whimsi
+/// supports:
+///
+/// ```text
+/// SELECT { * | column [, column]... } FROM table
+///     [ [LEFT] JOIN table ON condition ]...
+///     [ WHERE condition ]
+///     [ ORDER BY column [ASC | DESC] [, column [ASC | DESC]]... ]
+/// ```
+pub(crate) fn parse(tokens: Vec<Spanned>) -> Result<Query> {
+    let mut parser = Parser { tokens, pos: 0 };
+    let query = parser.query()?;
+    if let Some(token) = parser.peek() {
+        bail!(
+            "Unexpected {:?} at offset {}",
+            token.token,
+            token.offset
+        );
+    }
+    Ok(query)
+}
+
+struct Parser {
+    tokens: Vec<Spanned>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Spanned> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Spanned> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        self.peek().is_some_and(|t| t.token.is_keyword(keyword))
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.peek().is_some_and(|t| &t.token == token);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<()> {
+        if self.eat_keyword(keyword) {
+            return Ok(());
+        }
+        match self.peek() {
+            Some(t) => bail!(
+                "Expected {} but found {:?} at offset {}",
+                keyword,
+                t.token,
+                t.offset
+            ),
+            None => bail!("Expected {} but the query ended", keyword),
+        }
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Spanned {
+                token: Token::Ident { name, .. },
+                ..
+            }) => Ok(name),
+            Some(t) => bail!(
+                "Expected a name but found {:?} at offset {}",
+                t.token,
+                t.offset
+            ),
+            None => bail!("Expected a name but the query ended"),
+        }
+    }
+
+    /// A column name, optionally qualified with its table as `Table.Column`.
+    fn column(&mut self) -> Result<String> {
+        let name = self.ident()?;
+        if self.eat(&Token::Dot) {
+            return Ok(format!("{}.{}", name, self.ident()?));
+        }
+        Ok(name)
+    }
+
+    fn query(&mut self) -> Result<Query> {
+        self.expect_keyword("SELECT")?;
+        let columns = if self.eat(&Token::Star) {
+            None
+        } else {
+            let mut columns = vec![self.column()?];
+            while self.eat(&Token::Comma) {
+                columns.push(self.column()?);
+            }
+            Some(columns)
+        };
+
+        self.expect_keyword("FROM")?;
+        let table = self.ident()?;
+        if let Some(t) = self.peek().filter(|t| t.token == Token::Comma) {
+            bail!(
+                "Joining tables with ',' at offset {} is not supported, \
+                use JOIN ... ON instead",
+                t.offset
+            );
+        }
+
+        let mut joins = Vec::new();
+        loop {
+            let left = self.eat_keyword("LEFT");
+            if left {
+                self.eat_keyword("OUTER");
+            } else {
+                self.eat_keyword("INNER");
+            }
+            if !self.eat_keyword("JOIN") {
+                if left {
+                    self.expect_keyword("JOIN")?;
+                }
+                break;
+            }
+            let table = self.ident()?;
+            self.expect_keyword("ON")?;
+            let on = self.condition()?;
+            joins.push(Join { table, left, on });
+        }
+
+        let condition = if self.eat_keyword("WHERE") {
+            Some(self.condition()?)
+        } else {
+            None
+        };
+
+        let mut order_by = Vec::new();
+        if self.eat_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                let column = self.column()?;
+                let descending = if self.eat_keyword("DESC") {
+                    true
+                } else {
+                    self.eat_keyword("ASC");
+                    false
+                };
+                order_by.push(OrderBy { column, descending });
+                if !self.eat(&Token::Comma) {
+                    break;
+                }
+            }
+        }
+
+        Ok(Query {
+            columns,
+            table,
+            joins,
+            condition,
+            order_by,
+        })
+    }
+
+    fn condition(&mut self) -> Result<Condition> {
+        let mut lhs = self.and_condition()?;
+        while self.eat_keyword("OR") {
+            let rhs = self.and_condition()?;
+            lhs = Condition::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn and_condition(&mut self) -> Result<Condition> {
+        let mut lhs = self.primary_condition()?;
+        while self.eat_keyword("AND") {
+            let rhs = self.primary_condition()?;
+            lhs = Condition::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn primary_condition(&mut self) -> Result<Condition> {
+        if self.eat(&Token::LParen) {
+            let condition = self.condition()?;
+            if !self.eat(&Token::RParen) {
+                bail!("Expected ')' to close condition");
+            }
+            return Ok(condition);
+        }
+
+        let lhs = self.operand()?;
+        if self.eat_keyword("IS") {
+            let negated = self.eat_keyword("NOT");
+            self.expect_keyword("NULL")?;
+            return Ok(Condition::IsNull {
+                operand: lhs,
+                negated,
+            });
+        }
+
+        let comparison = match self.next() {
+            Some(Spanned { token, offset }) => match token {
+                Token::Eq => Comparison::Eq,
+                Token::Ne => Comparison::Ne,
+                Token::Lt => Comparison::Lt,
+                Token::Le => Comparison::Le,
+                Token::Gt => Comparison::Gt,
+                Token::Ge => Comparison::Ge,
+                token => bail!(
+                    "Expected a comparison but found {:?} at offset {}",
+                    token,
+                    offset
+                ),
+            },
+            None => bail!("Expected a comparison but the query ended"),
+        };
+        let rhs = self.operand()?;
+        Ok(Condition::Compare(lhs, comparison, rhs))
+    }
+
+    fn operand(&mut self) -> Result<Operand> {
+        if self.eat_keyword("NULL") {
+            return Ok(Operand::Null);
+        }
+        match self.peek().map(|t| t.token.clone()) {
+            Some(Token::Str(value)) => {
+                self.pos += 1;
+                Ok(Operand::Str(value))
+            }
+            Some(Token::Int(value)) => {
+                self.pos += 1;
+                Ok(Operand::Int(value))
+            }
+            _ => Ok(Operand::Column(self.column()?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Comparison, Condition, Join, Operand, OrderBy, Query};
+    use crate::modules::query::lexer::tokenize;
+
+    fn query(text: &str) -> anyhow::Result<Query> {
+        parse(tokenize(text)?)
+    }
+
+    fn error(text: &str) -> String {
+        query(text).unwrap_err().to_string()
+    }
+
+    fn column(name: &str) -> Operand {
+        Operand::Column(name.to_owned())
+    }
+
+    #[test]
+    fn parses_a_full_select() {
+        let parsed = query(
+            "SELECT File.File, Component FROM File \
+            LEFT OUTER JOIN Component ON File.Component_ = Component.Component \
+            WHERE FileSize > 10 AND (Version IS NOT NULL OR Language = '0') \
+            ORDER BY FileSize DESC, File",
+        )
+        .unwrap();
+        assert_eq!(
+            parsed,
+            Query {
+                columns: Some(vec![
+                    "File.File".to_owned(),
+                    "Component".to_owned()
+                ]),
+                table: "File".to_owned(),
+                joins: vec![Join {
+                    table: "Component".to_owned(),
+                    left: true,
+                    on: Condition::Compare(
+                        column("File.Component_"),
+                        Comparison::Eq,
+                        column("Component.Component"),
+                    ),
+                }],
+                condition: Some(Condition::And(
+                    Box::new(Condition::Compare(
+                        column("FileSize"),
+                        Comparison::Gt,
+                        Operand::Int(10),
+                    )),
+                    Box::new(Condition::Or(
+                        Box::new(Condition::IsNull {
+                            operand: column("Version"),
+                            negated: true,
+                        }),
+                        Box::new(Condition::Compare(
+                            column("Language"),
+                            Comparison::Eq,
+                            Operand::Str("0".to_owned()),
+                        )),
+                    )),
+                )),
+                order_by: vec![
+                    OrderBy {
+                        column: "FileSize".to_owned(),
+                        descending: true,
+                    },
+                    OrderBy {
+                        column: "File".to_owned(),
+                        descending: false,
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let parsed = query("SELECT * FROM a WHERE b = 1 OR c = 2 AND d = 3")
+            .unwrap()
+            .condition
+            .unwrap();
+        let Condition::Or(_, rhs) = parsed else {
+            panic!("Expected OR at the top, got {parsed:?}");
+        };
+        assert!(matches!(*rhs, Condition::And(..)));
+    }
+
+    #[test]
+    fn quoted_keywords_are_names() {
+        let parsed = query("SELECT `Condition` FROM `Select`").unwrap();
+        assert_eq!(parsed.columns, Some(vec!["Condition".to_owned()]));
+        assert_eq!(parsed.table, "Select");
+    }
+
+    #[test]
+    fn rejects_malformed_queries() {
+        assert_eq!(error(""), "Expected SELECT but the query ended");
+        assert_eq!(
+            error("SELECT * File"),
+            "Expected FROM but found Ident { name: \"File\", quoted: false } \
+            at offset 9"
+        );
+        assert_eq!(
+            error("SELECT * FROM a, b"),
+            "Joining tables with ',' at offset 15 is not supported, use \
+            JOIN ... ON instead"
+        );
+        assert_eq!(
+            error("SELECT * FROM a LEFT b"),
+            "Expected JOIN but found Ident { name: \"b\", quoted: false } at \
+            offset 21"
+        );
+        assert_eq!(
+            error("SELECT * FROM a WHERE b"),
+            "Expected a comparison but the query ended"
+        );
+        assert_eq!(
+            error("SELECT * FROM a WHERE (b = 1"),
+            "Expected ')' to close condition"
+        );
+        assert_eq!(
+            error("SELECT * FROM a WHERE b IS 1"),
+            "Expected NULL but found Int(1) at offset 27"
+        );
+        assert_eq!(
+            error("SELECT * FROM a ORDER b"),
+            "Expected BY but found \
+            Ident { name: \"b\", quoted: false } at offset 22"
+        );
+        assert_eq!(
+            error("SELECT * FROM a )"),
+            "Unexpected RParen at offset 16"
+        );
+        assert_eq!(
+            error("SELECT , FROM a"),
+            "Expected a name but found Comma at offset 7"
+        );
+    }
+}
@@ -0,0 +1,343 @@
+//! Starting a new project with a commented config and an input directory.
+//!
+//! The config holds everything a package needs, with a fresh `UpgradeCode`
+//! that stays the same for every release of the product. When an existing
+//! directory is scanned, it becomes the input directory and every folder at
+//! its top gets a feature of its own, nested in a main feature that holds
+//! the remaining files.
+
+use std::{collections::HashSet, fmt::Write, fs};
+
+use anyhow::{bail, Context, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use uuid::Uuid;
+
+use crate::command::initializer::Platform;
+
+const CONFIG_FILE: &str = "whimsi.toml";
+/// Folder of the project that is the input directory, unless an existing
+/// directory is scanned.
+const FILES_DIR: &str = "files";
+/// Identifier of the feature the other features are nested in.
+const MAIN_FEATURE: &str = "Main";
+/// English (United States), used until the config is localized.
+const LANGUAGE: u16 = 1033;
+
+pub(crate) struct ScaffoldOptions {
+    /// Directory the config is written to.
+    pub(crate) directory: Utf8PathBuf,
+    /// Product name, which defaults to the name of the directory.
+    pub(crate) name: Option<String>,
+    /// Manufacturer, which defaults to the product name.
+    pub(crate) manufacturer: Option<String>,
+    pub(crate) platform: Platform,
+    /// Existing directory to use as the input directory.
+    pub(crate) scan: Option<Utf8PathBuf>,
+}
+
+/// The config and input directory of a new project.
+pub(crate) struct Scaffold {
+    pub(crate) config_path: Utf8PathBuf,
+    pub(crate) input_directory: Utf8PathBuf,
+    pub(crate) product_name: String,
+}
+
+/// Write the config of a new project and create its input directory.
+pub(crate) fn scaffold(options: ScaffoldOptions) -> Result<Scaffold> {
+    let config_path = options.directory.join(CONFIG_FILE);
+    if config_path.exists() {
+        bail!("Config {} already exists", config_path);
+    }
+    let product_name = match options.name {
+        Some(name) => name,
+        None => options
+            .directory
+            .file_name()
+            .map(str::to_owned)
+            .or_else(|| {
+                let directory = options.directory.canonicalize_utf8().ok()?;
+                directory.file_name().map(str::to_owned)
+            })
+            .unwrap_or_else(|| "Product".to_owned()),
+    };
+    let manufacturer =
+        options.manufacturer.unwrap_or_else(|| product_name.clone());
+
+    let features = match &options.scan {
+        Some(scan) => {
+            if !scan.is_dir() {
+                bail!("{} is not a directory", scan);
+            }
+            folder_features(scan)?
+        }
+        None => Vec::new(),
+    };
+    let input_directory = match options.scan {
+        Some(scan) => scan,
+        None => {
+            let files = options.directory.join(FILES_DIR);
+            fs::create_dir_all(&files).with_context(|| {
+                format!("Failed to create input directory {files}")
+            })?;
+            files
+        }
+    };
+
+    let config =
+        config_text(&product_name, &manufacturer, options.platform, &features);
+    fs::create_dir_all(&options.directory).with_context(|| {
+        format!("Failed to create directory {}", options.directory)
+    })?;
+    fs::write(&config_path, config)
+        .with_context(|| format!("Failed to write config {config_path}"))?;
+    Ok(Scaffold {
+        config_path,
+        input_directory,
+        product_name,
+    })
+}
+
+/// A feature for a folder at the top of the input directory.
+struct Feature {
+    id: String,
+    title: String,
+    /// Paths of its files, relative to the input directory.
+    files: Vec<String>,
+}
+
+/// A feature for every folder at the top of `input_directory` that holds
+/// files.
+fn folder_features(input_directory: &Utf8Path) -> Result<Vec<Feature>> {
+    let mut used = HashSet::from([MAIN_FEATURE.to_owned()]);
+    let mut features = Vec::new();
+    for folder in sorted_entries(input_directory)? {
+        if !folder.is_dir() {
+            continue;
+        }
+        let title = folder.file_name().unwrap_or_default().to_owned();
+        let mut files = Vec::new();
+        collect_files(&folder, &title, &mut files)?;
+        if files.is_empty() {
+            continue;
+        }
+        features.push(Feature {
+            id: feature_id(&title, &mut used),
+            title,
+            files,
+        });
+    }
+    Ok(features)
+}
+
+/// Add the paths of the files below `directory` to `files`, as `relative`
+/// joined with their path from `directory`.
+fn collect_files(
+    directory: &Utf8Path,
+    relative: &str,
+    files: &mut Vec<String>,
+) -> Result<()> {
+    for entry in sorted_entries(directory)? {
+        let name = entry.file_name().unwrap_or_default();
+        let path = format!("{relative}/{name}");
+        if entry.is_dir() {
+            collect_files(&entry, &path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn sorted_entries(directory: &Utf8Path) -> Result<Vec<Utf8PathBuf>> {
+    let mut entries = directory
+        .read_dir_utf8()
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.into_path()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .with_context(|| format!("Failed to read directory {directory}"))?;
+    entries.sort();
+    Ok(entries)
+}
+
+/// A feature identifier made of the letters, digits and underscores of
+/// `name`, numbered when it is already used.
+fn feature_id(name: &str, used: &mut HashSet<String>) -> String {
+    let mut id = name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect::<String>();
+    match id.chars().next() {
+        None => id = "Feature".to_owned(),
+        Some(c) if c.is_ascii_digit() => id.insert(0, '_'),
+        Some(_) => {}
+    }
+    let mut candidate = id.clone();
+    for n in 2.. {
+        if used.insert(candidate.clone()) {
+            break;
+        }
+        candidate = format!("{id}{n}");
+    }
+    candidate
+}
+
+fn config_text(
+    product_name: &str,
+    manufacturer: &str,
+    platform: Platform,
+    features: &[Feature],
+) -> String {
+    let upgrade_code = format!("{{{}}}", Uuid::new_v4()).to_uppercase();
+    let mut text = format!(
+        r#"# Config of {product_name}. Run `whimsi config schema` for a schema that
+# editors can complete and check this file with.
+
+[product_info]
+product_name = {name}
+product_version = "1.0.0"
+manufacturer = {manufacturer}
+# 1033 is English (United States).
+product_language = {LANGUAGE}
+# `*` makes a new product code for every build. Patches need a fixed one.
+product_code = "*"
+
+[summary_info]
+# The minimum Windows Installer version, times 100.
+page_count = {page_count}
+# `*` makes a new package code for every build.
+revision_number = "*"
+# The platform and language of the package.
+template = "{template};{LANGUAGE}"
+
+[property]
+# Ties every release of the product together. Keep it the same from now on.
+UpgradeCode = "{upgrade_code}"
+
+# Files that no feature lists are installed with the first feature.
+[[feature]]
+id = "{MAIN_FEATURE}"
+title = {name}
+display = "expand"
+configurable = true
+"#,
+        name = quoted(product_name),
+        manufacturer = quoted(manufacturer),
+        page_count = platform.page_count(),
+        template = platform.template(),
+    );
+    for feature in features {
+        let _ = write!(
+            text,
+            "\n[[feature]]\nid = {}\ntitle = {}\nparent = \"{MAIN_FEATURE}\"\n\
+            files = [\n",
+            quoted(&feature.id),
+            quoted(&feature.title),
+        );
+        for file in &feature.files {
+            let _ = writeln!(text, "    {},", quoted(file));
+        }
+        text.push_str("]\n");
+    }
+    text
+}
+
+/// `value` as a TOML string.
+fn quoted(value: &str) -> String {
+    toml::Value::String(value.to_owned()).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use camino::Utf8PathBuf;
+
+    use super::{feature_id, scaffold, ScaffoldOptions, MAIN_FEATURE};
+    use crate::{
+        command::initializer::Platform,
+        modules::{config::msi_config::MsiConfig, helpers::test_dir::TestDir},
+    };
+
+    fn options(
+        directory: Utf8PathBuf,
+        scan: Option<Utf8PathBuf>,
+    ) -> ScaffoldOptions {
+        ScaffoldOptions {
+            directory,
+            name: Some("Say \"Hi\"".to_owned()),
+            manufacturer: None,
+            platform: Platform::Intel,
+            scan,
+        }
+    }
+
+    #[test]
+    fn writes_a_config_that_parses() {
+        let dir = TestDir::new();
+        let scaffold = scaffold(options(dir.path().join("new"), None)).unwrap();
+        assert!(scaffold.input_directory.is_dir());
+
+        let text = std::fs::read_to_string(&scaffold.config_path).unwrap();
+        let config = toml::from_str::<MsiConfig>(&text).unwrap();
+        assert_eq!(config.product_info.product_name, "Say \"Hi\"");
+        assert_eq!(config.product_info.manufacturer, "Say \"Hi\"");
+        assert!(config.property.contains_key("UpgradeCode"));
+        assert_eq!(config.feature.len(), 1);
+        assert_eq!(config.feature[0].id, MAIN_FEATURE);
+
+        // A second run leaves the config alone.
+        let Err(err) = super::scaffold(options(dir.path().join("new"), None))
+        else {
+            panic!("The config was written again");
+        };
+        assert!(err.to_string().ends_with("already exists"));
+    }
+
+    #[test]
+    fn gives_scanned_folders_features() {
+        let dir = TestDir::new();
+        dir.file("input/1bin/tool.exe", "");
+        dir.file("input/Main/app.exe", "");
+        dir.file("input/my app!/sub/readme.txt", "");
+        dir.file("input/top.txt", "");
+        std::fs::create_dir_all(dir.path().join("input/empty")).unwrap();
+
+        let scaffold = scaffold(options(
+            dir.path().join("project"),
+            Some(dir.path().join("input")),
+        ))
+        .unwrap();
+        let text = std::fs::read_to_string(&scaffold.config_path).unwrap();
+        let config = toml::from_str::<MsiConfig>(&text).unwrap();
+        let features = config
+            .feature
+            .iter()
+            .map(|f| (f.id.as_str(), f.parent.as_deref(), f.files.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            features,
+            [
+                ("Main", None, 0),
+                ("_1bin", Some("Main"), 1),
+                ("Main2", Some("Main"), 1),
+                ("myapp", Some("Main"), 1),
+            ]
+        );
+        assert_eq!(
+            config.feature[3].files,
+            [Utf8PathBuf::from("my app!/sub/readme.txt")]
+        );
+    }
+
+    #[test]
+    fn feature_ids_are_identifiers_used_once() {
+        let mut used = HashSet::from([MAIN_FEATURE.to_owned()]);
+        assert_eq!(feature_id("1bin", &mut used), "_1bin");
+        assert_eq!(feature_id("Main", &mut used), "Main2");
+        assert_eq!(feature_id("Main", &mut used), "Main3");
+        assert_eq!(feature_id("!!!", &mut used), "Feature");
+        assert_eq!(feature_id("", &mut used), "Feature2");
+    }
+}
@@ -0,0 +1,163 @@
+//! The Authenticode digest of a package.
+//!
+//! Every stream is hashed in a fixed order, storages are hashed recursively,
+//! and each storage ends with its CLSID. Entries are ordered by comparing
+//! their UTF-16 names byte by byte as they are stored in the compound file.
+//! The signature streams themselves are left out.
+//!
+//! The extended signature also covers the metadata of every entry: names,
+//! sizes, CLSIDs, state bits and timestamps. That metadata is hashed first and
+//! the result is stored in `MsiDigitalSignatureEx` and fed into the main
+//! digest.
+
+use std::{
+    io::{Read, Seek},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+
+pub(crate) const SIGNATURE_STREAM: &str = "\u{5}DigitalSignature";
+pub(crate) const SIGNATURE_EX_STREAM: &str = "\u{5}MsiDigitalSignatureEx";
+
+/// 100 nanosecond intervals between 1601-01-01 and the Unix epoch.
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+
+/// The digest of the contents of every stream and storage, seeded with the
+/// metadata digest when the signature is extended.
+pub(crate) fn content_digest<F: Read + Seek>(
+    comp: &mut cfb::CompoundFile<F>,
+    metadata: Option<&[u8]>,
+) -> Result<Vec<u8>> {
+    let mut hasher = Sha256::new();
+    if let Some(metadata) = metadata {
+        hasher.update(metadata);
+    }
+    hash_storage(comp, Path::new("/"), &mut hasher)?;
+    Ok(hasher.finalize().to_vec())
+}
+
+/// The digest of the metadata of every entry, which is what the extended
+/// signature stream holds.
+pub(crate) fn metadata_digest<F: Read + Seek>(
+    comp: &cfb::CompoundFile<F>,
+) -> Result<Vec<u8>> {
+    let mut hasher = Sha256::new();
+    hash_metadata(comp, Path::new("/"), &mut hasher)?;
+    Ok(hasher.finalize().to_vec())
+}
+
+fn hash_storage<F: Read + Seek>(
+    comp: &mut cfb::CompoundFile<F>,
+    path: &Path,
+    hasher: &mut Sha256,
+) -> Result<()> {
+    for (child, is_stream) in sorted_children(comp, path)? {
+        if is_stream {
+            let mut bytes = Vec::new();
+            comp.open_stream(&child)
+                .and_then(|mut stream| stream.read_to_end(&mut bytes))
+                .with_context(|| {
+                    format!("Failed to read stream {}", child.display())
+                })?;
+            hasher.update(&bytes);
+        } else {
+            hash_storage(comp, &child, hasher)?;
+        }
+    }
+    let entry = comp
+        .entry(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    hasher.update(entry.clsid().to_bytes_le());
+    Ok(())
+}
+
+fn hash_metadata<F: Read + Seek>(
+    comp: &cfb::CompoundFile<F>,
+    path: &Path,
+    hasher: &mut Sha256,
+) -> Result<()> {
+    hash_entry(comp, path, hasher)?;
+    for (child, is_stream) in sorted_children(comp, path)? {
+        match is_stream {
+            true => hash_entry(comp, &child, hasher)?,
+            false => hash_metadata(comp, &child, hasher)?,
+        }
+    }
+    Ok(())
+}
+
+fn hash_entry<F: Read + Seek>(
+    comp: &cfb::CompoundFile<F>,
+    path: &Path,
+    hasher: &mut Sha256,
+) -> Result<()> {
+    let entry = comp
+        .entry(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    if !entry.is_root() {
+        hasher.update(utf16_name(entry.name()));
+    }
+    if entry.is_stream() {
+        // Only the low half of the size is included.
+        hasher.update((entry.len() as u32).to_le_bytes());
+    } else {
+        hasher.update(entry.clsid().to_bytes_le());
+    }
+    hasher.update(entry.state_bits().to_le_bytes());
+    if !entry.is_root() {
+        hasher.update(filetime(entry.created()).to_le_bytes());
+        hasher.update(filetime(entry.modified()).to_le_bytes());
+    }
+    Ok(())
+}
+
+/// The children of a storage in the order they are hashed, leaving out the
+/// signature streams at the root.
+fn sorted_children<F: Read + Seek>(
+    comp: &cfb::CompoundFile<F>,
+    path: &Path,
+) -> Result<Vec<(PathBuf, bool)>> {
+    let is_root = path == Path::new("/");
+    let mut children = comp
+        .read_storage(path)
+        .with_context(|| format!("Failed to read storage {}", path.display()))?
+        .filter(|entry| {
+            !is_root
+                || (entry.name() != SIGNATURE_STREAM
+                    && entry.name() != SIGNATURE_EX_STREAM)
+        })
+        .map(|entry| {
+            (
+                utf16_name(entry.name()),
+                entry.path().to_path_buf(),
+                entry.is_stream(),
+            )
+        })
+        .collect::<Vec<_>>();
+    // Slices compare element by element and put a prefix first, which is the
+    // same as comparing the names with `memcmp` and then by length.
+    children.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+    Ok(children
+        .into_iter()
+        .map(|(_, path, is_stream)| (path, is_stream))
+        .collect())
+}
+
+/// The name of an entry as it is stored in the compound file.
+fn utf16_name(name: &str) -> Vec<u8> {
+    name.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+/// Convert a timestamp back to the `FILETIME` it was read from.
+fn filetime(time: SystemTime) -> u64 {
+    let intervals = |d: Duration| (d.as_nanos() / 100) as u64;
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => FILETIME_UNIX_EPOCH + intervals(after),
+        Err(before) => {
+            FILETIME_UNIX_EPOCH.saturating_sub(intervals(before.duration()))
+        }
+    }
+}
@@ -0,0 +1,449 @@
+//! Authenticode signing and verification of packages.
+//!
+//! The signature is a PKCS#7 `SignedData` in the `\x05DigitalSignature`
+//! stream. Only RSA keys and SHA-256 digests are supported. Verifying checks
+//! that the package matches the signature and that the signature was made by
+//! the certificate it names, but not whether that certificate is trusted.
+
+mod digest;
+mod pkcs7;
+mod timestamp;
+
+use std::io::{Cursor, Read, Write};
+
+use anyhow::{bail, Context, Result};
+use camino::Utf8PathBuf;
+use log::info;
+use rsa::{
+    pkcs1::DecodeRsaPrivateKey,
+    pkcs1v15::{Signature, SigningKey, VerifyingKey},
+    pkcs8::DecodePrivateKey,
+    signature::{SignatureEncoding, Signer, Verifier},
+    traits::PublicKeyParts,
+    BigUint, RsaPrivateKey, RsaPublicKey,
+};
+use sha2::{Digest, Sha256};
+
+use digest::{SIGNATURE_EX_STREAM, SIGNATURE_STREAM};
+use pkcs7::Certificate;
+
+/// Everything needed to sign a package.
+pub(crate) struct SigningOptions {
+    /// PEM file with the signing certificate first, followed by any
+    /// intermediate certificates to include in the signature.
+    pub(crate) certificate: Utf8PathBuf,
+    /// PEM file with the RSA private key of the signing certificate.
+    pub(crate) key: Utf8PathBuf,
+    pub(crate) timestamp_url: Option<String>,
+    /// Also sign the metadata of the streams with `MsiDigitalSignatureEx`.
+    pub(crate) extended: bool,
+}
+
+/// What was found when checking a signature.
+pub(crate) struct Verified {
+    pub(crate) signer: String,
+    pub(crate) issuer: String,
+    pub(crate) extended: bool,
+    pub(crate) timestamped: bool,
+}
+
+/// Sign a package, replacing any signature it already has.
+pub(crate) fn sign(
+    package: Vec<u8>,
+    options: &SigningOptions,
+) -> Result<Vec<u8>> {
+    let certificates = read_certificates(&options.certificate)?;
+    let key = read_key(&options.key)?;
+    let signer = &certificates[0];
+    if key.n().to_bytes_be() != signer.modulus {
+        bail!(
+            "Key {} does not belong to certificate {}",
+            options.key,
+            options.certificate
+        );
+    }
+
+    let mut comp = cfb::CompoundFile::open(Cursor::new(package))
+        .with_context(|| "Failed to open the package")?;
+    for stream in [SIGNATURE_STREAM, SIGNATURE_EX_STREAM] {
+        let path = format!("/{stream}");
+        if comp.is_stream(&path) {
+            comp.remove_stream(&path)
+                .with_context(|| "Failed to remove the old signature")?;
+        }
+    }
+
+    let metadata = match options.extended {
+        true => Some(digest::metadata_digest(&comp)?),
+        false => None,
+    };
+    let digest = digest::content_digest(&mut comp, metadata.as_deref())?;
+    let indirect_data = pkcs7::indirect_data(&digest);
+    let signed_attributes = pkcs7::signed_attributes(&indirect_data)?;
+    let signature = SigningKey::<Sha256>::new(key)
+        .try_sign(&signed_attributes)
+        .with_context(|| "Failed to sign the package")?
+        .to_vec();
+    let timestamp = match &options.timestamp_url {
+        Some(url) => {
+            info!("Requesting timestamp from {}", url);
+            Some(timestamp::request(url, &signature)?)
+        }
+        None => None,
+    };
+    let signed_data = pkcs7::signed_data(
+        &indirect_data,
+        &signed_attributes,
+        &signature,
+        &certificates,
+        timestamp.as_deref(),
+    )?;
+
+    if let Some(metadata) = metadata {
+        write_stream(&mut comp, SIGNATURE_EX_STREAM, &metadata)?;
+    }
+    write_stream(&mut comp, SIGNATURE_STREAM, &signed_data)?;
+    comp.flush()
+        .with_context(|| "Failed to finish writing the signed package")?;
+    info!("Signed package as {}", signer.subject_name());
+    Ok(comp.into_inner().into_inner())
+}
+
+/// Check the signature of a package.
+pub(crate) fn verify(package: Vec<u8>) -> Result<Verified> {
+    let mut comp = cfb::CompoundFile::open(Cursor::new(package))
+        .with_context(|| "Failed to open the package")?;
+    let Some(signature) = read_stream(&mut comp, SIGNATURE_STREAM)? else {
+        bail!("Package is not signed");
+    };
+    let parsed = pkcs7::parse(&signature)
+        .with_context(|| "Failed to read the signature")?;
+
+    let metadata = read_stream(&mut comp, SIGNATURE_EX_STREAM)?;
+    if let Some(metadata) = &metadata {
+        if *metadata != digest::metadata_digest(&comp)? {
+            bail!("The stream metadata has changed since it was signed");
+        }
+    }
+    if parsed.digest != digest::content_digest(&mut comp, metadata.as_deref())?
+    {
+        bail!("The contents of the package have changed since it was signed");
+    }
+    let content = pkcs7::content(&parsed.indirect_data)?;
+    if parsed.message_digest != Sha256::digest(content).as_slice() {
+        bail!("The signed attributes don't match the signed content");
+    }
+
+    let signer = parsed
+        .signer()
+        .with_context(|| "The signing certificate is not in the signature")?;
+    let public_key = RsaPublicKey::new(
+        BigUint::from_bytes_be(&signer.modulus),
+        BigUint::from_bytes_be(&signer.exponent),
+    )
+    .with_context(|| "The signing certificate has an invalid key")?;
+    let signature = Signature::try_from(parsed.signature.as_slice())
+        .with_context(|| "The signature is malformed")?;
+    VerifyingKey::<Sha256>::new(public_key)
+        .verify(&parsed.signed_attributes, &signature)
+        .with_context(|| {
+            "The signature doesn't match the signing certificate"
+        })?;
+
+    Ok(Verified {
+        signer: signer.subject_name(),
+        issuer: signer.issuer_name(),
+        extended: metadata.is_some(),
+        timestamped: parsed.timestamped,
+    })
+}
+
+/// Read every certificate in a PEM file.
+fn read_certificates(path: &Utf8PathBuf) -> Result<Vec<Certificate>> {
+    let text = std::fs::read(path)
+        .with_context(|| format!("Failed to read certificate {path}"))?;
+    let certificates = pem::parse_many(&text)
+        .with_context(|| format!("Failed to parse certificate {path}"))?
+        .into_iter()
+        .filter(|block| block.tag() == "CERTIFICATE")
+        .map(|block| Certificate::parse(block.contents()))
+        .collect::<Result<Vec<Certificate>>>()
+        .with_context(|| format!("Failed to parse certificate {path}"))?;
+    if certificates.is_empty() {
+        bail!("No certificates found in {}", path);
+    }
+    Ok(certificates)
+}
+
+/// Read an RSA private key in either PKCS#8 or PKCS#1 PEM form.
+fn read_key(path: &Utf8PathBuf) -> Result<RsaPrivateKey> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read key {path}"))?;
+    RsaPrivateKey::from_pkcs8_pem(&text)
+        .or_else(|_| RsaPrivateKey::from_pkcs1_pem(&text))
+        .with_context(|| format!("Failed to parse RSA private key {path}"))
+}
+
+fn read_stream(
+    comp: &mut cfb::CompoundFile<Cursor<Vec<u8>>>,
+    name: &str,
+) -> Result<Option<Vec<u8>>> {
+    let path = format!("/{name}");
+    if !comp.is_stream(&path) {
+        return Ok(None);
+    }
+    let mut bytes = Vec::new();
+    comp.open_stream(&path)
+        .and_then(|mut stream| stream.read_to_end(&mut bytes))
+        .with_context(|| {
+            format!("Failed to read stream {}", name.escape_debug())
+        })?;
+    Ok(Some(bytes))
+}
+
+fn write_stream(
+    comp: &mut cfb::CompoundFile<Cursor<Vec<u8>>>,
+    name: &str,
+    bytes: &[u8],
+) -> Result<()> {
+    comp.create_stream(format!("/{name}"))
+        .and_then(|mut stream| stream.write_all(bytes))
+        .with_context(|| {
+            format!("Failed to write stream {}", name.escape_debug())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Cursor, Read, Write},
+        net::TcpListener,
+        sync::OnceLock,
+        thread::{self, JoinHandle},
+    };
+
+    use rsa::{
+        pkcs1::EncodeRsaPublicKey,
+        pkcs1v15::SigningKey,
+        pkcs8::{EncodePrivateKey, LineEnding},
+        rand_core::OsRng,
+        signature::{SignatureEncoding, Signer},
+        RsaPrivateKey,
+    };
+    use sha2::{Digest, Sha256};
+
+    use super::{
+        digest::SIGNATURE_STREAM, pkcs7, sign, verify, SigningOptions,
+    };
+    use crate::modules::helpers::{
+        der::{self, Tlv},
+        test_dir::TestDir,
+    };
+
+    const SHA256_WITH_RSA: &str = "1.2.840.113549.1.1.11";
+    const RSA_ENCRYPTION: &str = "1.2.840.113549.1.1.1";
+    const COMMON_NAME: &str = "2.5.4.3";
+    const UTF8_STRING: u8 = 0x0C;
+    const UTC_TIME: u8 = 0x17;
+
+    /// A `Name` with nothing but a common name.
+    fn name(common_name: &str) -> Vec<u8> {
+        der::sequence(&[der::set(&[der::sequence(&[
+            der::oid(COMMON_NAME),
+            der::tlv(UTF8_STRING, common_name.as_bytes()),
+        ])])])
+    }
+
+    /// A PEM key and a PEM certificate signed with it, made once since
+    /// generating the key is slow.
+    fn credentials() -> &'static (String, String) {
+        static CREDENTIALS: OnceLock<(String, String)> = OnceLock::new();
+        CREDENTIALS.get_or_init(|| {
+            let key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+            let public_key = key.to_public_key().to_pkcs1_der().unwrap();
+            let mut bit_string = vec![0];
+            bit_string.extend_from_slice(public_key.as_bytes());
+            let tbs = der::sequence(&[
+                der::context(0, &der::integer(2)),
+                der::integer(0x5EED),
+                der::algorithm(SHA256_WITH_RSA),
+                name("whimsi test CA"),
+                der::sequence(&[
+                    der::tlv(UTC_TIME, b"250101000000Z"),
+                    der::tlv(UTC_TIME, b"491231235959Z"),
+                ]),
+                name("whimsi test"),
+                der::sequence(&[
+                    der::algorithm(RSA_ENCRYPTION),
+                    der::tlv(der::BIT_STRING, &bit_string),
+                ]),
+            ]);
+            let signature = SigningKey::<Sha256>::new(key.clone()).sign(&tbs);
+            let mut signature_bits = vec![0];
+            signature_bits.extend_from_slice(&signature.to_vec());
+            let certificate = der::sequence(&[
+                tbs,
+                der::algorithm(SHA256_WITH_RSA),
+                der::tlv(der::BIT_STRING, &signature_bits),
+            ]);
+            (
+                key.to_pkcs8_pem(LineEnding::LF).unwrap().to_string(),
+                pem::encode(&pem::Pem::new("CERTIFICATE", certificate)),
+            )
+        })
+    }
+
+    fn options(dir: &TestDir, timestamp_url: Option<String>) -> SigningOptions {
+        let (key, certificate) = credentials();
+        SigningOptions {
+            certificate: dir.file("cert.pem", certificate),
+            key: dir.file("key.pem", key),
+            timestamp_url,
+            extended: false,
+        }
+    }
+
+    fn package() -> Vec<u8> {
+        std::fs::read("examples/example_2.msi").unwrap()
+    }
+
+    #[test]
+    fn verifies_a_signed_package() {
+        let dir = TestDir::new();
+        let signed = sign(package(), &options(&dir, None)).unwrap();
+        let verified = verify(signed).unwrap();
+        assert_eq!(verified.signer, "whimsi test");
+        assert_eq!(verified.issuer, "whimsi test CA");
+        assert!(!verified.extended);
+        assert!(!verified.timestamped);
+    }
+
+    #[test]
+    fn verifies_the_stream_metadata() {
+        let dir = TestDir::new();
+        let mut options = options(&dir, None);
+        options.extended = true;
+        let signed = sign(package(), &options).unwrap();
+        assert!(verify(signed).unwrap().extended);
+    }
+
+    #[test]
+    fn rejects_a_changed_stream() {
+        let dir = TestDir::new();
+        let signed = sign(package(), &options(&dir, None)).unwrap();
+
+        let mut comp = cfb::CompoundFile::open(Cursor::new(signed)).unwrap();
+        let stream = comp
+            .walk()
+            .find(|e| e.is_stream() && e.name() != SIGNATURE_STREAM)
+            .map(|e| e.path().to_owned())
+            .unwrap();
+        let mut contents = Vec::new();
+        comp.open_stream(&stream)
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        contents[0] ^= 0xFF;
+        comp.open_stream(&stream)
+            .unwrap()
+            .write_all(&contents)
+            .unwrap();
+        comp.flush().unwrap();
+        let tampered = comp.into_inner().into_inner();
+
+        let err = verify(tampered).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "The contents of the package have changed since it was signed"
+        );
+    }
+
+    /// A timestamp server on a local port that answers one request with
+    /// `status`, returning the request it got.
+    fn timestamp_server(status: u64) -> (String, JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end().to_ascii_lowercase();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some(value) = line.strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut request = vec![0; length];
+            reader.read_exact(&mut request).unwrap();
+
+            // Only the attribute the token is in is checked, so any value
+            // will do as the token.
+            let token = der::sequence(&[der::oid("1.2.840.113549.1.7.2")]);
+            let body =
+                der::sequence(&[der::sequence(&[der::integer(status)]), token]);
+            let mut stream = reader.into_inner();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: \
+                application/timestamp-reply\r\nContent-Length: {}\r\n\
+                Connection: close\r\n\r\n",
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(&body).unwrap();
+            request
+        });
+        (url, server)
+    }
+
+    #[test]
+    fn timestamps_the_signature() {
+        let dir = TestDir::new();
+        let (url, server) = timestamp_server(0);
+        let signed = sign(package(), &options(&dir, Some(url))).unwrap();
+        let request = server.join().unwrap();
+        assert!(verify(signed.clone()).unwrap().timestamped);
+
+        // The request is for the digest of the signature.
+        let mut comp = cfb::CompoundFile::open(Cursor::new(signed)).unwrap();
+        let mut signature = Vec::new();
+        comp.open_stream(format!("/{SIGNATURE_STREAM}"))
+            .unwrap()
+            .read_to_end(&mut signature)
+            .unwrap();
+        let signature = pkcs7::parse(&signature).unwrap().signature;
+        let mut request =
+            Tlv::parse(&request).unwrap().expect(der::SEQUENCE).unwrap();
+        assert_eq!(request.read().unwrap().raw, der::integer(1));
+        let mut imprint =
+            request.read().unwrap().expect(der::SEQUENCE).unwrap();
+        let _algorithm = imprint.read().unwrap();
+        assert_eq!(
+            imprint
+                .read()
+                .unwrap()
+                .expect_contents(der::OCTET_STRING)
+                .unwrap(),
+            Sha256::digest(&signature).as_slice()
+        );
+    }
+
+    #[test]
+    fn fails_when_the_timestamp_is_refused() {
+        let dir = TestDir::new();
+        let (url, server) = timestamp_server(2);
+        let err = sign(package(), &options(&dir, Some(url.clone())))
+            .err()
+            .unwrap();
+        server.join().unwrap();
+        assert_eq!(
+            err.to_string(),
+            format!("Timestamp server {url} refused the request")
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//! The PKCS#7 `SignedData` that Authenticode stores in `\x05DigitalSignature`.
+//!
+//! The signed content is an `SpcIndirectDataContent` holding the digest of
+//! the package together with the GUID of the MSI subject interface package,
+//! which tells Windows how the digest was computed.
+
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+
+use crate::modules::helpers::der::{self, Reader, Tlv};
+
+pub(crate) const SHA256: &str = "2.16.840.1.101.3.4.2.1";
+const RSA_ENCRYPTION: &str = "1.2.840.113549.1.1.1";
+const SIGNED_DATA: &str = "1.2.840.113549.1.7.2";
+const CONTENT_TYPE: &str = "1.2.840.113549.1.9.3";
+const MESSAGE_DIGEST: &str = "1.2.840.113549.1.9.4";
+const SPC_INDIRECT_DATA: &str = "1.3.6.1.4.1.311.2.1.4";
+const SPC_STATEMENT_TYPE: &str = "1.3.6.1.4.1.311.2.1.11";
+const SPC_SP_OPUS_INFO: &str = "1.3.6.1.4.1.311.2.1.12";
+const SPC_INDIVIDUAL_SP_KEY_PURPOSE: &str = "1.3.6.1.4.1.311.2.1.21";
+const SPC_SIPINFO: &str = "1.3.6.1.4.1.311.2.1.30";
+const RFC3161_COUNTER_SIGN: &str = "1.3.6.1.4.1.311.3.3.1";
+const COMMON_NAME: &str = "2.5.4.3";
+
+/// GUID of the subject interface package for MSI files, in the byte order it
+/// is stored in `SpcSipInfo`.
+const MSI_SIP: [u8; 16] = [
+    0xF1, 0x10, 0x0C, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x46,
+];
+
+/// An X.509 certificate and the parts of it a signature refers to.
+pub(crate) struct Certificate {
+    pub(crate) der: Vec<u8>,
+    issuer: Vec<u8>,
+    serial: Vec<u8>,
+    subject: Vec<u8>,
+    pub(crate) modulus: Vec<u8>,
+    pub(crate) exponent: Vec<u8>,
+}
+
+impl Certificate {
+    pub(crate) fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut cert = Tlv::parse(bytes)?.expect(der::SEQUENCE)?;
+        let mut tbs = cert.read()?.expect(der::SEQUENCE)?;
+        // Version
+        tbs.optional(0);
+        let serial = tbs.read()?;
+        serial.unsigned()?;
+        let _signature_algorithm = tbs.read()?;
+        let issuer = tbs.read()?;
+        let _validity = tbs.read()?;
+        let subject = tbs.read()?;
+        let mut spki = tbs.read()?.expect(der::SEQUENCE)?;
+        let mut algorithm = spki.read()?.expect(der::SEQUENCE)?;
+        if algorithm.read()?.oid()? != RSA_ENCRYPTION {
+            bail!("Only RSA certificates are supported");
+        }
+        let key = spki.read()?;
+        if key.tag != der::BIT_STRING || key.contents.first() != Some(&0) {
+            bail!("Certificate has a malformed public key");
+        }
+        let mut rsa_key =
+            Tlv::parse(&key.contents[1..])?.expect(der::SEQUENCE)?;
+        let modulus = rsa_key.read()?.unsigned()?.to_vec();
+        let exponent = rsa_key.read()?.unsigned()?.to_vec();
+        Ok(Certificate {
+            der: bytes.to_vec(),
+            issuer: issuer.raw.to_vec(),
+            serial: serial.raw.to_vec(),
+            subject: subject.raw.to_vec(),
+            modulus,
+            exponent,
+        })
+    }
+
+    /// The common name of the subject, for messages.
+    pub(crate) fn subject_name(&self) -> String {
+        common_name(&self.subject)
+    }
+
+    /// The common name of the issuer, for messages.
+    pub(crate) fn issuer_name(&self) -> String {
+        common_name(&self.issuer)
+    }
+}
+
+/// The first common name in an encoded `Name`, or `(unknown)`.
+fn common_name(name: &[u8]) -> String {
+    let find = || -> Result<Option<String>> {
+        let mut rdns = Tlv::parse(name)?.expect(der::SEQUENCE)?;
+        while !rdns.is_empty() {
+            let mut rdn = rdns.read()?.expect(der::SET)?;
+            while !rdn.is_empty() {
+                let mut attribute = rdn.read()?.expect(der::SEQUENCE)?;
+                let oid = attribute.read()?.oid()?;
+                let value = attribute.read()?;
+                if oid == COMMON_NAME {
+                    let text = String::from_utf8_lossy(value.contents);
+                    return Ok(Some(text.into_owned()));
+                }
+            }
+        }
+        Ok(None)
+    };
+    find()
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| "(unknown)".to_owned())
+}
+
+/// The `SpcIndirectDataContent` for a package with the given digest.
+pub(crate) fn indirect_data(digest: &[u8]) -> Vec<u8> {
+    let sip_info = der::sequence(&[
+        der::integer(1),
+        der::octet_string(&MSI_SIP),
+        der::integer(0),
+        der::integer(0),
+        der::integer(0),
+        der::integer(0),
+        der::integer(0),
+    ]);
+    der::sequence(&[
+        der::sequence(&[der::oid(SPC_SIPINFO), sip_info]),
+        der::sequence(&[der::algorithm(SHA256), der::octet_string(digest)]),
+    ])
+}
+
+/// The part of the indirect data that the message digest covers. Authenticode
+/// hashes its contents without the tag and length.
+pub(crate) fn content(indirect_data: &[u8]) -> Result<&[u8]> {
+    Ok(Tlv::parse(indirect_data)?.contents)
+}
+
+/// The authenticated attributes, encoded as the `SET` that gets signed.
+pub(crate) fn signed_attributes(indirect_data: &[u8]) -> Result<Vec<u8>> {
+    let content = content(indirect_data)?;
+    let attribute = |oid: &str, value: Vec<u8>| {
+        der::sequence(&[der::oid(oid), der::set(&[value])])
+    };
+    Ok(der::set(&[
+        attribute(CONTENT_TYPE, der::oid(SPC_INDIRECT_DATA)),
+        attribute(SPC_SP_OPUS_INFO, der::sequence(&[])),
+        attribute(
+            SPC_STATEMENT_TYPE,
+            der::sequence(&[der::oid(SPC_INDIVIDUAL_SP_KEY_PURPOSE)]),
+        ),
+        attribute(MESSAGE_DIGEST, der::octet_string(&Sha256::digest(content))),
+    ]))
+}
+
+/// Put the signature together. `certificates` starts with the signer.
+pub(crate) fn signed_data(
+    indirect_data: &[u8],
+    signed_attributes: &[u8],
+    signature: &[u8],
+    certificates: &[Certificate],
+    timestamp: Option<&[u8]>,
+) -> Result<Vec<u8>> {
+    let signer = certificates
+        .first()
+        .with_context(|| "No signing certificate was given")?;
+    let mut signer_info = vec![
+        der::integer(1),
+        der::sequence(&[signer.issuer.clone(), signer.serial.clone()]),
+        der::algorithm(SHA256),
+        der::context(0, Tlv::parse(signed_attributes)?.contents),
+        der::algorithm(RSA_ENCRYPTION),
+        der::octet_string(signature),
+    ];
+    if let Some(token) = timestamp {
+        let counter_sign = der::sequence(&[
+            der::oid(RFC3161_COUNTER_SIGN),
+            der::set(&[token.to_vec()]),
+        ]);
+        signer_info.push(der::context(1, &counter_sign));
+    }
+
+    let certificates = certificates
+        .iter()
+        .map(|cert| cert.der.clone())
+        .collect::<Vec<Vec<u8>>>()
+        .concat();
+    let signed_data = der::sequence(&[
+        der::integer(1),
+        der::set(&[der::algorithm(SHA256)]),
+        der::sequence(&[
+            der::oid(SPC_INDIRECT_DATA),
+            der::context(0, indirect_data),
+        ]),
+        der::context(0, &certificates),
+        der::set(&[der::sequence(&signer_info)]),
+    ]);
+    Ok(der::sequence(&[
+        der::oid(SIGNED_DATA),
+        der::context(0, &signed_data),
+    ]))
+}
+
+/// The parts of a signature that are needed to check it.
+pub(crate) struct ParsedSignature {
+    pub(crate) digest: Vec<u8>,
+    pub(crate) indirect_data: Vec<u8>,
+    pub(crate) certificates: Vec<Certificate>,
+    pub(crate) issuer: Vec<u8>,
+    pub(crate) serial: Vec<u8>,
+    /// The authenticated attributes re-encoded as the `SET` that was signed.
+    pub(crate) signed_attributes: Vec<u8>,
+    pub(crate) message_digest: Vec<u8>,
+    pub(crate) signature: Vec<u8>,
+    pub(crate) timestamped: bool,
+}
+
+impl ParsedSignature {
+    /// The certificate that made the signature.
+    pub(crate) fn signer(&self) -> Option<&Certificate> {
+        self.certificates
+            .iter()
+            .find(|c| c.issuer == self.issuer && c.serial == self.serial)
+    }
+}
+
+pub(crate) fn parse(bytes: &[u8]) -> Result<ParsedSignature> {
+    let mut content_info = Tlv::parse(bytes)?.expect(der::SEQUENCE)?;
+    if content_info.read()?.oid()? != SIGNED_DATA {
+        bail!("Signature is not PKCS#7 signed data");
+    }
+    let signed_data = content_info
+        .optional(0)
+        .with_context(|| "Signature has no content")?;
+    let mut signed_data =
+        Tlv::parse(signed_data.contents)?.expect(der::SEQUENCE)?;
+    let _version = signed_data.read()?;
+    let _digest_algorithms = signed_data.read()?;
+
+    let mut encap = signed_data.read()?.expect(der::SEQUENCE)?;
+    if encap.read()?.oid()? != SPC_INDIRECT_DATA {
+        bail!("Signature does not hold Authenticode content");
+    }
+    let indirect_data = encap
+        .optional(0)
+        .with_context(|| "Signature has no Authenticode content")?;
+    let indirect_data = Tlv::parse(indirect_data.contents)?;
+    let mut spc = indirect_data.expect(der::SEQUENCE)?;
+    let _sip_info = spc.read()?;
+    let mut digest_info = spc.read()?.expect(der::SEQUENCE)?;
+    let mut algorithm = digest_info.read()?.expect(der::SEQUENCE)?;
+    if algorithm.read()?.oid()? != SHA256 {
+        bail!("Only SHA-256 signatures are supported");
+    }
+    let digest = digest_info.read()?.expect_contents(der::OCTET_STRING)?;
+
+    let mut certificates = Vec::new();
+    if let Some(certs) = signed_data.optional(0) {
+        let mut certs = Reader::new(certs.contents);
+        while !certs.is_empty() {
+            certificates.push(Certificate::parse(certs.read()?.raw)?);
+        }
+    }
+    // Revocation lists aren't used.
+    signed_data.optional(1);
+
+    let mut signer_infos = signed_data.read()?.expect(der::SET)?;
+    let mut signer_info = signer_infos.read()?.expect(der::SEQUENCE)?;
+    let _version = signer_info.read()?;
+    let mut issuer_serial = signer_info.read()?.expect(der::SEQUENCE)?;
+    let issuer = issuer_serial.read()?.raw.to_vec();
+    let serial = issuer_serial.read()?.raw.to_vec();
+    let _digest_algorithm = signer_info.read()?;
+    let attributes = signer_info
+        .optional(0)
+        .with_context(|| "Signature has no authenticated attributes")?;
+    let mut signed_attributes = attributes.raw.to_vec();
+    signed_attributes[0] = der::SET;
+    let message_digest = find_message_digest(attributes.contents)?;
+    let _signature_algorithm = signer_info.read()?;
+    let signature = signer_info.read()?.expect_contents(der::OCTET_STRING)?;
+    let timestamped = match signer_info.optional(1) {
+        Some(unsigned) => {
+            let mut attributes = Reader::new(unsigned.contents);
+            let mut found = false;
+            while !attributes.is_empty() {
+                let mut attribute = attributes.read()?.expect(der::SEQUENCE)?;
+                found |= attribute.read()?.oid()? == RFC3161_COUNTER_SIGN;
+            }
+            found
+        }
+        None => false,
+    };
+
+    Ok(ParsedSignature {
+        digest: digest.to_vec(),
+        indirect_data: indirect_data.raw.to_vec(),
+        certificates,
+        issuer,
+        serial,
+        signed_attributes,
+        message_digest,
+        signature: signature.to_vec(),
+        timestamped,
+    })
+}
+
+fn find_message_digest(attributes: &[u8]) -> Result<Vec<u8>> {
+    let mut attributes = Reader::new(attributes);
+    while !attributes.is_empty() {
+        let mut attribute = attributes.read()?.expect(der::SEQUENCE)?;
+        if attribute.read()?.oid()? == MESSAGE_DIGEST {
+            let mut values = attribute.read()?.expect(der::SET)?;
+            let value = values.read()?.expect_contents(der::OCTET_STRING)?;
+            return Ok(value.to_vec());
+        }
+    }
+    bail!("Signature has no message digest attribute")
+}
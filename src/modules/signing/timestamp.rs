@@ -0,0 +1,52 @@
+//! RFC 3161 timestamps, which keep a signature valid after the signing
+//! certificate expires.
+
+use std::io::Read;
+
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+
+use super::pkcs7::SHA256;
+use crate::modules::helpers::der::{self, Tlv};
+
+/// Statuses that mean the timestamp was granted, possibly with changes.
+const GRANTED: [u8; 2] = [0, 1];
+
+/// Ask the timestamp server at `url` to countersign `signature` and return
+/// the timestamp token it sends back.
+pub(crate) fn request(url: &str, signature: &[u8]) -> Result<Vec<u8>> {
+    let request = der::sequence(&[
+        der::integer(1),
+        der::sequence(&[
+            der::algorithm(SHA256),
+            der::octet_string(&Sha256::digest(signature)),
+        ]),
+        // Ask for the certificate of the server to be included in the token.
+        der::boolean(true),
+    ]);
+
+    let response = ureq::post(url)
+        .set("Content-Type", "application/timestamp-query")
+        .send_bytes(&request)
+        .with_context(|| format!("Failed to reach timestamp server {url}"))?;
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut body)
+        .with_context(|| format!("Failed to read response from {url}"))?;
+
+    let mut response = Tlv::parse(&body)
+        .and_then(|r| r.expect(der::SEQUENCE))
+        .with_context(|| {
+            format!("Timestamp server {url} sent a bad response")
+        })?;
+    let mut status = response.read()?.expect(der::SEQUENCE)?;
+    let code = status.read()?.unsigned()?;
+    if code.len() != 1 || !GRANTED.contains(&code[0]) {
+        bail!("Timestamp server {} refused the request", url);
+    }
+    let token = response
+        .read()
+        .with_context(|| format!("Timestamp server {url} sent no token"))?;
+    Ok(token.raw.to_vec())
+}
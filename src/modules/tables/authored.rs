@@ -0,0 +1,338 @@
+// Populates the tables that are written from authored rows, such as the
+// features, registry values and custom actions of a config or WiX source
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+};
+
+use anyhow::{Context, Result};
+use camino::Utf8PathBuf;
+use msi::{Category, Column, Select, Value};
+
+use crate::{
+    command::builder::Msi,
+    modules::tables::{
+        component, sequence,
+        value_check::{insert_rows, SourcedRow},
+    },
+};
+
+/// Rows for the tables that aren't written from the directories and files,
+/// by the name of their table.
+#[derive(Default)]
+pub(crate) struct AuthoredTables {
+    pub(crate) rows: BTreeMap<&'static str, Vec<SourcedRow>>,
+    /// Names of `Binary` rows and the files their streams are read from.
+    pub(crate) binaries: Vec<(String, Utf8PathBuf)>,
+}
+
+impl AuthoredTables {
+    pub(crate) fn add(&mut self, table: &'static str, row: SourcedRow) {
+        self.rows.entry(table).or_default().push(row);
+    }
+}
+
+/// Write the rows and the streams of the binaries. Tables that the UI or
+/// another part of the build already created are added to, keeping the
+/// actions they already sequence.
+pub(crate) fn populate_authored_tables(
+    package: &mut Msi,
+    tables: AuthoredTables,
+) -> Result<()> {
+    let mut rows = tables.rows;
+    for (name, source) in tables.binaries {
+        let bytes = std::fs::read(&source)
+            .with_context(|| format!("Failed to read binary {source}"))?;
+        let stream = format!("Binary.{name}");
+        package
+            .write_stream(&stream)
+            .and_then(|mut out| out.write_all(&bytes))
+            .with_context(|| format!("Failed to write stream {stream}"))?;
+        rows.entry("Binary").or_default().push(SourcedRow::new(
+            vec![Value::from(name), Value::from(stream)],
+            Some(source),
+        ));
+    }
+
+    for (table, mut rows) in rows {
+        let columns = columns(table);
+        if !package.has_table(table) {
+            package
+                .create_table(table, columns.clone())
+                .with_context(|| format!("Failed to create {table} table"))?;
+        } else if sequence::is_sequence_table(table) {
+            let scheduled = package
+                .select_rows(Select::table(table))
+                .with_context(|| format!("Failed to read the {table} table"))?
+                .filter_map(|row| row["Action"].as_str().map(str::to_owned))
+                .collect::<BTreeSet<_>>();
+            rows.retain(|row| {
+                row.values[0]
+                    .as_str()
+                    .is_some_and(|action| !scheduled.contains(action))
+            });
+        }
+        insert_rows(package, table, &columns, rows)?;
+    }
+    Ok(())
+}
+
+/// The columns of a table there are authored rows for.
+fn columns(table: &str) -> Vec<Column> {
+    match table {
+        "Component" => component::columns(),
+        sequence::EXECUTE_SEQUENCE | sequence::UI_SEQUENCE => {
+            sequence::columns()
+        }
+        "Feature" => feature(),
+        "FeatureComponents" => feature_components(),
+        "Condition" => condition(),
+        "Registry" => registry(),
+        "Shortcut" => shortcut(),
+        "ServiceInstall" => service_install(),
+        "ServiceControl" => service_control(),
+        "CreateFolder" => create_folder(),
+        "RemoveFile" => remove_file(),
+        "CustomAction" => custom_action(),
+        "Binary" => binary(),
+        "Upgrade" => upgrade(),
+        "LaunchCondition" => launch_condition(),
+        _ => unreachable!("No columns for table {table}"),
+    }
+}
+
+fn feature() -> Vec<Column> {
+    vec![
+        Column::build("Feature").primary_key().id_string(38),
+        Column::build("Feature_Parent").nullable().id_string(38),
+        Column::build("Title")
+            .nullable()
+            .localizable()
+            .category(Category::Text)
+            .string(64),
+        Column::build("Description")
+            .nullable()
+            .localizable()
+            .category(Category::Text)
+            .string(255),
+        Column::build("Display").nullable().int16(),
+        Column::build("Level").int16(),
+        Column::build("Directory_").nullable().id_string(72),
+        Column::build("Attributes").int16(),
+    ]
+}
+
+pub(crate) fn feature_components() -> Vec<Column> {
+    vec![
+        Column::build("Feature_").primary_key().id_string(38),
+        Column::build("Component_").primary_key().id_string(72),
+    ]
+}
+
+fn condition() -> Vec<Column> {
+    vec![
+        Column::build("Feature_").primary_key().id_string(38),
+        Column::build("Level").primary_key().int16(),
+        Column::build("Condition")
+            .nullable()
+            .category(Category::Condition)
+            .string(255),
+    ]
+}
+
+fn registry() -> Vec<Column> {
+    vec![
+        Column::build("Registry").primary_key().id_string(72),
+        Column::build("Root").int16(),
+        Column::build("Key")
+            .localizable()
+            .category(Category::RegPath)
+            .string(255),
+        Column::build("Name")
+            .nullable()
+            .localizable()
+            .category(Category::Formatted)
+            .string(255),
+        Column::build("Value")
+            .nullable()
+            .localizable()
+            .category(Category::Formatted)
+            .string(0),
+        Column::build("Component_").id_string(72),
+    ]
+}
+
+fn shortcut() -> Vec<Column> {
+    vec![
+        Column::build("Shortcut").primary_key().id_string(72),
+        Column::build("Directory_").id_string(72),
+        Column::build("Name")
+            .localizable()
+            .category(Category::Filename)
+            .string(128),
+        Column::build("Component_").id_string(72),
+        Column::build("Target")
+            .category(Category::Shortcut)
+            .string(72),
+        Column::build("Arguments")
+            .nullable()
+            .category(Category::Formatted)
+            .string(255),
+        Column::build("Description")
+            .nullable()
+            .localizable()
+            .category(Category::Text)
+            .string(255),
+        Column::build("Hotkey").nullable().int16(),
+        Column::build("Icon_").nullable().id_string(72),
+        Column::build("IconIndex").nullable().int16(),
+        Column::build("ShowCmd").nullable().int16(),
+        Column::build("WkDir").nullable().id_string(72),
+    ]
+}
+
+fn service_install() -> Vec<Column> {
+    let formatted = |name| {
+        Column::build(name)
+            .nullable()
+            .category(Category::Formatted)
+            .string(255)
+    };
+    vec![
+        Column::build("ServiceInstall").primary_key().id_string(72),
+        Column::build("Name")
+            .category(Category::Formatted)
+            .string(255),
+        Column::build("DisplayName")
+            .nullable()
+            .localizable()
+            .category(Category::Formatted)
+            .string(255),
+        Column::build("ServiceType")
+            .category(Category::DoubleInteger)
+            .int32(),
+        Column::build("StartType")
+            .category(Category::DoubleInteger)
+            .int32(),
+        Column::build("ErrorControl")
+            .category(Category::DoubleInteger)
+            .int32(),
+        formatted("LoadOrderGroup"),
+        formatted("Dependencies"),
+        formatted("StartName"),
+        formatted("Password"),
+        formatted("Arguments"),
+        Column::build("Component_").id_string(72),
+        Column::build("Description")
+            .nullable()
+            .localizable()
+            .category(Category::Formatted)
+            .string(255),
+    ]
+}
+
+fn service_control() -> Vec<Column> {
+    vec![
+        Column::build("ServiceControl").primary_key().id_string(72),
+        Column::build("Name")
+            .localizable()
+            .category(Category::Formatted)
+            .string(255),
+        Column::build("Event").int16(),
+        Column::build("Arguments")
+            .nullable()
+            .category(Category::Formatted)
+            .string(255),
+        Column::build("Wait").nullable().int16(),
+        Column::build("Component_").id_string(72),
+    ]
+}
+
+fn create_folder() -> Vec<Column> {
+    vec![
+        Column::build("Directory_").primary_key().id_string(72),
+        Column::build("Component_").primary_key().id_string(72),
+    ]
+}
+
+fn remove_file() -> Vec<Column> {
+    vec![
+        Column::build("FileKey").primary_key().id_string(72),
+        Column::build("Component_").id_string(72),
+        Column::build("FileName")
+            .nullable()
+            .localizable()
+            .category(Category::WildCardFilename)
+            .string(255),
+        Column::build("DirProperty").id_string(72),
+        Column::build("InstallMode").int16(),
+    ]
+}
+
+fn custom_action() -> Vec<Column> {
+    vec![
+        Column::build("Action").primary_key().id_string(72),
+        Column::build("Type").int16(),
+        Column::build("Source")
+            .nullable()
+            .category(Category::CustomSource)
+            .string(72),
+        Column::build("Target")
+            .nullable()
+            .category(Category::Formatted)
+            .string(255),
+    ]
+}
+
+fn binary() -> Vec<Column> {
+    vec![
+        Column::build("Name").primary_key().id_string(72),
+        Column::build("Data").binary(),
+    ]
+}
+
+fn upgrade() -> Vec<Column> {
+    vec![
+        Column::build("UpgradeCode")
+            .primary_key()
+            .category(Category::Guid)
+            .string(38),
+        Column::build("VersionMin")
+            .primary_key()
+            .nullable()
+            .category(Category::Text)
+            .string(20),
+        Column::build("VersionMax")
+            .primary_key()
+            .nullable()
+            .category(Category::Text)
+            .string(20),
+        Column::build("Language")
+            .primary_key()
+            .nullable()
+            .category(Category::Language)
+            .string(255),
+        Column::build("Attributes").primary_key().int32(),
+        Column::build("Remove")
+            .nullable()
+            .category(Category::Formatted)
+            .string(255),
+        Column::build("ActionProperty")
+            .category(Category::UpperCase)
+            .string(72),
+    ]
+}
+
+fn launch_condition() -> Vec<Column> {
+    vec![
+        Column::build("Condition")
+            .primary_key()
+            .category(Category::Condition)
+            .string(255),
+        Column::build("Description")
+            .localizable()
+            .category(Category::Formatted)
+            .string(255),
+    ]
+}
@@ -1,61 +1,104 @@
-// Populates the `File` table
+// Populates the `Component` table
 
-use msi::{Category, Column, Insert, Value};
+use msi::{Category, Column, Value};
 use uuid::Uuid;
 
 use crate::{
-    builder::Msi,
-    error,
-    models::{error::MsiError, file::File},
+    command::builder::Msi,
+    modules::{
+        component::file::File,
+        config::msi_config::MsiConfig,
+        helpers::{error::MsiError, log_return::error},
+        tables::value_check::{insert_rows, SourcedRow},
+        traits::identifier::WHIMSI_NAMESPACE,
+    },
 };
 
 const TABLE_NAME: &str = "Component";
 
+/// Component GUIDs are derived from the product's [`guid_seed`], the
+/// directory the file is installed to and the file's key, unless the config
+/// gives the file's component a GUID. A component keeps its GUID between
+/// versions of a product as the component rules require.
 pub fn populate_component_table(
     package: &mut Msi,
-    files: &Vec<File>,
+    files: &[File],
+    seed: &str,
 ) -> Result<(), MsiError> {
     create_component_table(package)?;
 
-    let query = Insert::into(TABLE_NAME).rows(
-        files
-            .iter()
-            .map(|file| {
+    let rows = files
+        .iter()
+        .map(|file| {
+            SourcedRow::new(
                 vec![
                     Value::from(file.component_id().to_string()),
-                    Value::from(Uuid::new_v4().to_string()),
-                    Value::from(file.name().to_string()),
-                    Value::from(file.vital() & 16),
-                ]
-            })
-            .collect(),
-    );
+                    Value::from(file.guid().clone().unwrap_or_else(|| {
+                        component_guid(
+                            seed,
+                            file.directory_id(),
+                            file.file_id(),
+                        )
+                    })),
+                    Value::from(file.directory_id().to_string()),
+                    Value::from(0),
+                    Value::Null,
+                    // Each file gets its own component so the file is always
+                    // the key path.
+                    Value::from(file.file_id().to_string()),
+                ],
+                Some(file.source().clone()),
+            )
+        })
+        .collect();
 
-    if let Err(err) = package.insert_rows(query) {
-        return Err(MsiError::nested("Failed to insert row into table", err));
-    };
+    insert_rows(package, TABLE_NAME, &columns(), rows)
+}
 
-    Ok(())
+/// What identifies the product across versions and languages. That is the
+/// `UpgradeCode` if there is one, or else a product code that isn't
+/// generated for each build. The product name is only used when neither is
+/// set, as renaming the product then changes every component GUID.
+pub(crate) fn guid_seed(config: &MsiConfig) -> String {
+    if let Some(upgrade_code) = config.property.get("UpgradeCode") {
+        return upgrade_code.to_uppercase();
+    }
+    match config.product_info.product_code.as_str() {
+        "*" => config.product_info.product_name.to_string(),
+        code => code.to_uppercase(),
+    }
+}
+
+/// The GUID of the component in `directory` whose key path is `key_path`.
+pub(crate) fn component_guid(
+    seed: &str,
+    directory: &str,
+    key_path: &str,
+) -> String {
+    let seed = format!("{}/{}/{}", seed, directory, key_path);
+    let guid = Uuid::new_v5(&WHIMSI_NAMESPACE, seed.as_bytes());
+    format!("{{{}}}", guid).to_uppercase()
+}
+
+pub(crate) fn columns() -> Vec<Column> {
+    vec![
+        Column::build("Component").primary_key().id_string(72),
+        Column::build("ComponentId")
+            .category(Category::Guid)
+            .nullable()
+            .string(38),
+        Column::build("Directory_").id_string(72),
+        Column::build("Attributes").int16(),
+        Column::build("Condition")
+            .nullable()
+            .category(Category::Condition)
+            .string(255),
+        Column::build("KeyPath").nullable().id_string(72),
+    ]
 }
 
 fn create_component_table(package: &mut Msi) -> Result<(), MsiError> {
-    let result = package.create_table(
-        TABLE_NAME,
-        vec![
-            Column::build("Component").primary_key().id_string(72),
-            Column::build("ComponentId")
-                .category(Category::Guid)
-                .nullable()
-                .string(38),
-            Column::build("Directory_").id_string(72),
-            Column::build("Attributes").int16(),
-            Column::build("Condition")
-                .nullable()
-                .category(Category::Condition)
-                .string(255),
-            Column::build("KeyPath").nullable().id_string(72),
-        ],
-    );
+    let result = package.create_table(TABLE_NAME, columns());
 
     if let Err(e) = result {
         let err = error!("Failed to create {} table: {}", TABLE_NAME, e);
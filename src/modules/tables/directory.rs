@@ -1,53 +1,70 @@
 // Populates the `Directory` table
 
-use msi::{Category, Column, Insert, Value};
+use std::collections::HashMap;
+
+use msi::{Category, Column, Value};
 
 use crate::{
-    builder::Msi,
-    error,
-    models::{directory::Directory, error::MsiError},
+    command::builder::Msi,
+    modules::{
+        component::directory::Directory,
+        helpers::{error::MsiError, filename::ShortNames, log_return::error},
+        tables::value_check::{insert_rows, SourcedRow},
+    },
 };
 
+const TABLE_NAME: &str = "Directory";
+
 pub fn populate_directory_table(
     package: &mut Msi,
-    directories: &Vec<Directory>,
+    directories: &[Directory],
 ) -> Result<(), MsiError> {
     create_directory_table(package)?;
 
-    let query = Insert::into("Directory").rows(
-        directories
-            .iter()
-            .map(|dir| {
+    // Short names only have to be unique between siblings.
+    let mut short_names: HashMap<Option<String>, ShortNames> = HashMap::new();
+    let rows = directories
+        .iter()
+        .map(|dir| {
+            let parent = dir.parent_id().as_ref().map(|p| p.to_string());
+            // Standard folders such as `TARGETDIR` are resolved by Windows
+            // Installer and only have a placeholder name.
+            let name = match dir.source() {
+                Some(_) => short_names
+                    .entry(parent.clone())
+                    .or_default()
+                    .msi_name(dir.name()),
+                None => dir.name().to_string(),
+            };
+            SourcedRow::new(
                 vec![
                     Value::from(dir.id().to_string()),
-                    match &dir.parent_id() {
-                        Some(p) => Value::from(p.to_string()),
+                    match parent {
+                        Some(p) => Value::from(p),
                         None => Value::Null,
                     },
-                    Value::from(dir.name().to_string()),
-                ]
-            })
-            .collect(),
-    );
+                    Value::from(name),
+                ],
+                dir.source().clone(),
+            )
+        })
+        .collect();
 
-    if let Err(err) = package.insert_rows(query) {
-        return Err(MsiError::nested("Failed to insert row into table", err));
-    };
+    insert_rows(package, TABLE_NAME, &columns(), rows)
+}
 
-    Ok(())
+fn columns() -> Vec<Column> {
+    vec![
+        Column::build("Directory").primary_key().id_string(72),
+        Column::build("Directory_Parent").nullable().id_string(72),
+        Column::build("DefaultDir")
+            .category(Category::DefaultDir)
+            .string(255),
+    ]
 }
 
 fn create_directory_table(package: &mut Msi) -> Result<(), MsiError> {
-    let result = package.create_table(
-        "Directory",
-        vec![
-            Column::build("Directory").primary_key().id_string(72),
-            Column::build("Directory_Parent").nullable().id_string(72),
-            Column::build("DefaultDir")
-                .category(Category::DefaultDir)
-                .string(255),
-        ],
-    );
+    let result = package.create_table(TABLE_NAME, columns());
 
     if let Err(e) = result {
         let err = error!("Failed to create Directory table: {}", e);
@@ -1,67 +1,98 @@
 // Populates the `File` table
 
-use msi::{Category, Column};
+use std::collections::HashMap;
+
+use msi::{Category, Column, Value};
 
 use crate::{
-    builder::Msi,
-    error,
-    models::{error::MsiError, file::File},
+    command::builder::Msi,
+    modules::{
+        component::file::File,
+        helpers::{error::MsiError, filename::ShortNames, log_return::error},
+        tables::value_check::{insert_rows, SourcedRow},
+    },
 };
 
+const TABLE_NAME: &str = "File";
+
+/// [`msidbFileAttributesVital`](https://learn.microsoft.com/en-us/windows/win32/msi/file-table)
+const VITAL: i32 = 0x200;
+
 pub fn populate_file_table(
     package: &mut Msi,
-    files: &Vec<File>,
+    files: &[File],
 ) -> Result<(), MsiError> {
     create_file_table(package)?;
-    unimplemented!();
 
-    // let query = Insert::into("File").rows(
-    //     files
-    //         .iter()
-    //         .map(|file| {
-    //             vec![
-    //                 Value::from(file.id().to_string()),
-    //                 match &dir.parent_id() {
-    //                     Some(p) => Value::from(p.to_string()),
-    //                     None => Value::Null,
-    //                 },
-    //                 Value::from(dir.name().to_string()),
-    //             ]
-    //         })
-    //         .collect(),
-    // );
+    // Short names only have to be unique within the directory they are
+    // installed into.
+    let mut short_names: HashMap<String, ShortNames> = HashMap::new();
+    let mut rows = Vec::new();
+    for file in files {
+        let Ok(size) = i32::try_from(*file.size()) else {
+            let msg = error!(
+                "File {} is {} bytes which is too large for an MSI",
+                file.source(),
+                file.size()
+            );
+            return Err(MsiError::short(msg));
+        };
+        let name = short_names
+            .entry(file.directory_id().to_string())
+            .or_default()
+            .msi_name(file.name());
+        rows.push(SourcedRow::new(
+            vec![
+                Value::from(file.file_id().to_string()),
+                Value::from(file.component_id().to_string()),
+                Value::from(name),
+                Value::from(size),
+                match file.version() {
+                    Some(v) => Value::from(v.clone()),
+                    None => Value::Null,
+                },
+                match file.language() {
+                    Some(l) => Value::from(l.clone()),
+                    None => Value::Null,
+                },
+                match file.vital() {
+                    true => Value::from(VITAL),
+                    false => Value::Null,
+                },
+                Value::from(*file.sequence() as i32),
+            ],
+            Some(file.source().clone()),
+        ));
+    }
 
-    // if let Err(err) = package.insert_rows(query) {
-    //     return Err(MsiError::nested("Failed to insert row into table", err));
-    // };
+    insert_rows(package, TABLE_NAME, &columns(), rows)
+}
 
-    Ok(())
+fn columns() -> Vec<Column> {
+    vec![
+        Column::build("File").primary_key().id_string(72),
+        Column::build("Component_").id_string(72),
+        Column::build("FileName")
+            .category(Category::Filename)
+            .string(255),
+        Column::build("FileSize")
+            .category(Category::DoubleInteger)
+            .int32(),
+        Column::build("Version")
+            .nullable()
+            .category(Category::Version)
+            .string(72),
+        Column::build("Language")
+            .nullable()
+            .category(Category::Language)
+            .string(20),
+        Column::build("Attributes").nullable().int16(),
+        Column::build("Sequence").int16(),
+    ]
 }
 
 fn create_file_table(package: &mut Msi) -> Result<(), MsiError> {
-    let result = package.create_table(
-        "File",
-        vec![
-            Column::build("File").primary_key().id_string(72),
-            Column::build("Component_").id_string(72),
-            Column::build("FileName")
-                .category(Category::Filename)
-                .string(255),
-            Column::build("FileSize")
-                .category(Category::DoubleInteger)
-                .int16(),
-            Column::build("Version")
-                .nullable()
-                .category(Category::Version)
-                .string(72),
-            Column::build("Language")
-                .nullable()
-                .category(Category::Language)
-                .string(20),
-            Column::build("Attributes").nullable().int16(),
-            Column::build("Sequence").int16(),
-        ],
-    );
+    let result = package.create_table(TABLE_NAME, columns());
 
     if let Err(e) = result {
         let err = error!("Failed to create File table: {}", e);
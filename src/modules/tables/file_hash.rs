@@ -0,0 +1,68 @@
+// Populates the `MsiFileHash` table
+
+use msi::{Column, Value};
+
+use crate::{
+    command::builder::Msi,
+    modules::{
+        component::file::File,
+        helpers::{error::MsiError, log_return::error},
+        tables::value_check::{insert_rows, SourcedRow},
+    },
+};
+
+const TABLE_NAME: &str = "MsiFileHash";
+
+/// Record the MD5 hash of every unversioned file. Windows Installer uses it to
+/// skip files that are already up to date, and patches use it to find the
+/// files that changed between two builds.
+pub fn populate_file_hash_table(
+    package: &mut Msi,
+    files: &[File],
+) -> Result<(), MsiError> {
+    create_file_hash_table(package)?;
+
+    let mut rows = Vec::new();
+    // Versioned files are compared by version instead and must not be
+    // hashed.
+    for file in files.iter().filter(|f| f.version().is_none()) {
+        let contents = match std::fs::read(file.source()) {
+            Ok(contents) => contents,
+            Err(e) => {
+                let msg = error!("Failed to read file {}", file.source());
+                return Err(MsiError::nested(msg, Box::new(e)));
+            }
+        };
+        let mut values =
+            vec![Value::from(file.file_id().to_string()), Value::from(0)];
+        // The hash is stored as four little endian 32-bit integers.
+        values.extend(md5::compute(&contents).0.chunks_exact(4).map(|c| {
+            Value::from(i32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        }));
+        rows.push(SourcedRow::new(values, Some(file.source().clone())));
+    }
+
+    insert_rows(package, TABLE_NAME, &columns(), rows)
+}
+
+fn columns() -> Vec<Column> {
+    vec![
+        Column::build("File_").primary_key().id_string(72),
+        Column::build("Options").int16(),
+        Column::build("HashPart1").int32(),
+        Column::build("HashPart2").int32(),
+        Column::build("HashPart3").int32(),
+        Column::build("HashPart4").int32(),
+    ]
+}
+
+fn create_file_hash_table(package: &mut Msi) -> Result<(), MsiError> {
+    let result = package.create_table(TABLE_NAME, columns());
+
+    if let Err(e) = result {
+        let err = error!("Failed to create {} table: {}", TABLE_NAME, e);
+        return Err(MsiError::nested(err, Box::new(e)));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,49 @@
+// Describes the `Media` table
+
+use msi::{Category, Column};
+
+use crate::modules::validation::database::Database;
+
+pub(crate) const TABLE_NAME: &str = "Media";
+
+pub(crate) fn columns() -> Vec<Column> {
+    vec![
+        Column::build("DiskId").primary_key().int16(),
+        Column::build("LastSequence").int16(),
+        Column::build("DiskPrompt")
+            .nullable()
+            .localizable()
+            .category(Category::Text)
+            .string(64),
+        Column::build("Cabinet")
+            .nullable()
+            .category(Category::Cabinet)
+            .string(255),
+        Column::build("VolumeLabel")
+            .nullable()
+            .category(Category::Text)
+            .string(32),
+        Column::build("Source")
+            .nullable()
+            .category(Category::Property)
+            .string(72),
+    ]
+}
+
+/// The disk ID and first file sequence number for a new `Media` row that
+/// comes after everything already in the package.
+pub(crate) fn next_media(db: &Database) -> (i32, i32) {
+    let media = db.table(TABLE_NAME);
+    let disk_id = media
+        .and_then(|m| m.rows.iter().filter_map(|r| m.int(r, "DiskId")).max())
+        .unwrap_or(0)
+        + 1;
+    let last_file_sequence = db.table("File").and_then(|f| {
+        f.rows.iter().filter_map(|r| f.int(r, "Sequence")).max()
+    });
+    let last_media_sequence = media.and_then(|m| {
+        m.rows.iter().filter_map(|r| m.int(r, "LastSequence")).max()
+    });
+    let first = last_file_sequence.max(last_media_sequence).unwrap_or(0) + 1;
+    (disk_id, first)
+}
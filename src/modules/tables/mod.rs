@@ -1,3 +1,10 @@
+pub(crate) mod authored;
 pub mod component;
 pub mod directory;
 pub mod file;
+pub mod file_hash;
+pub(crate) mod media;
+pub mod module_signature;
+pub mod property;
+pub(crate) mod sequence;
+pub(crate) mod value_check;
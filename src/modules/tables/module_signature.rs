@@ -0,0 +1,113 @@
+// Populates the `ModuleSignature` and `ModuleComponents` tables
+
+use msi::{Category, Column, Value};
+use uuid::Uuid;
+
+use crate::{
+    command::builder::Msi,
+    modules::{
+        component::file::File,
+        config::product_information::ProductInformationProperties,
+        helpers::{error::MsiError, log_return::error},
+        tables::value_check::{insert_rows, SourcedRow},
+    },
+};
+
+const SIGNATURE_TABLE: &str = "ModuleSignature";
+const COMPONENTS_TABLE: &str = "ModuleComponents";
+
+/// The ModuleID of a module built from `product_info`. It is the product
+/// name with every character that isn't allowed in an identifier replaced,
+/// followed by the product code with its dashes replaced by underscores.
+pub fn module_id(product_info: &ProductInformationProperties) -> String {
+    let guid = match product_info.product_code.as_str() {
+        "*" => Uuid::new_v4().to_string(),
+        code => code.trim_matches(['{', '}']).to_owned(),
+    };
+    let name = product_info
+        .product_name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() || c == '_' {
+            true => c,
+            false => '_',
+        })
+        .collect::<String>();
+    format!("{}.{}", name, guid.replace('-', "_").to_uppercase())
+}
+
+/// Sign the module so packages it gets merged into can refer to it.
+pub fn populate_module_signature_table(
+    package: &mut Msi,
+    module_id: &str,
+    product_info: &ProductInformationProperties,
+) -> Result<(), MsiError> {
+    create_table(package, SIGNATURE_TABLE, signature_columns())?;
+
+    let row = SourcedRow::new(
+        vec![
+            Value::from(module_id),
+            Value::from(product_info.product_language as i32),
+            Value::from(product_info.product_version.to_string()),
+        ],
+        None,
+    );
+    insert_rows(package, SIGNATURE_TABLE, &signature_columns(), vec![row])
+}
+
+/// List every component of the module.
+pub fn populate_module_components_table(
+    package: &mut Msi,
+    files: &[File],
+    module_id: &str,
+    language: u16,
+) -> Result<(), MsiError> {
+    create_table(package, COMPONENTS_TABLE, components_columns())?;
+
+    let rows = files
+        .iter()
+        .map(|file| {
+            SourcedRow::new(
+                vec![
+                    Value::from(file.component_id().to_string()),
+                    Value::from(module_id),
+                    Value::from(language as i32),
+                ],
+                Some(file.source().clone()),
+            )
+        })
+        .collect();
+    insert_rows(package, COMPONENTS_TABLE, &components_columns(), rows)
+}
+
+fn signature_columns() -> Vec<Column> {
+    vec![
+        Column::build("ModuleID").primary_key().id_string(72),
+        Column::build("Language").primary_key().int16(),
+        Column::build("Version")
+            .category(Category::Version)
+            .string(32),
+    ]
+}
+
+fn components_columns() -> Vec<Column> {
+    vec![
+        Column::build("Component").primary_key().id_string(72),
+        Column::build("ModuleID").primary_key().id_string(72),
+        Column::build("Language").primary_key().int16(),
+    ]
+}
+
+fn create_table(
+    package: &mut Msi,
+    table: &str,
+    columns: Vec<Column>,
+) -> Result<(), MsiError> {
+    let result = package.create_table(table, columns);
+
+    if let Err(e) = result {
+        let err = error!("Failed to create {} table: {}", table, e);
+        return Err(MsiError::nested(err, Box::new(e)));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+// Populates the `Property` table
+
+use msi::{Category, Column, Value};
+use uuid::Uuid;
+
+use crate::{
+    command::builder::Msi,
+    modules::{
+        config::product_information::ProductInformationProperties,
+        helpers::{error::MsiError, log_return::error},
+        tables::value_check::{insert_rows, SourcedRow},
+    },
+};
+
+const TABLE_NAME: &str = "Property";
+
+/// Add the required product information properties. A `product_code` of `*`
+/// gets a new GUID on every build.
+pub fn populate_property_table(
+    package: &mut Msi,
+    product_info: &ProductInformationProperties,
+) -> Result<(), MsiError> {
+    create_property_table(package)?;
+
+    let product_code = match product_info.product_code.as_str() {
+        "*" => format!("{{{}}}", Uuid::new_v4()).to_uppercase(),
+        code => code.to_owned(),
+    };
+    let properties = [
+        ("ProductName", product_info.product_name.to_string()),
+        ("ProductVersion", product_info.product_version.to_string()),
+        ("Manufacturer", product_info.manufacturer.to_string()),
+        ("ProductLanguage", product_info.product_language.to_string()),
+        ("ProductCode", product_code),
+    ];
+    insert_properties(package, properties)
+}
+
+/// Add properties to a `Property` table that has already been created.
+pub(crate) fn insert_properties<K: Into<Value>>(
+    package: &mut Msi,
+    properties: impl IntoIterator<Item = (K, String)>,
+) -> Result<(), MsiError> {
+    let rows = properties
+        .into_iter()
+        .map(|(property, value)| {
+            SourcedRow::new(vec![property.into(), Value::from(value)], None)
+        })
+        .collect();
+
+    insert_rows(package, TABLE_NAME, &columns(), rows)
+}
+
+fn columns() -> Vec<Column> {
+    vec![
+        Column::build("Property").primary_key().id_string(72),
+        Column::build("Value")
+            .localizable()
+            .category(Category::Text)
+            .string(0),
+    ]
+}
+
+fn create_property_table(package: &mut Msi) -> Result<(), MsiError> {
+    let result = package.create_table(TABLE_NAME, columns());
+
+    if let Err(e) = result {
+        let err = error!("Failed to create {} table: {}", TABLE_NAME, e);
+        return Err(MsiError::nested(err, Box::new(e)));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,147 @@
+// Describes the standard action sequence tables, such as `InstallUISequence`
+
+use msi::{Category, Column};
+
+pub(crate) const EXECUTE_SEQUENCE: &str = "InstallExecuteSequence";
+pub(crate) const UI_SEQUENCE: &str = "InstallUISequence";
+
+/// The standard actions of the `InstallExecuteSequence`, at the sequence
+/// numbers WiX gives them.
+pub(crate) const EXECUTE_ACTIONS: [(&str, i32); 26] = [
+    ("LaunchConditions", 100),
+    ("ValidateProductID", 700),
+    ("CostInitialize", 800),
+    ("FileCost", 900),
+    ("CostFinalize", 1000),
+    ("InstallValidate", 1400),
+    ("InstallInitialize", 1500),
+    ("ProcessComponents", 1600),
+    ("UnpublishFeatures", 1800),
+    ("StopServices", 1900),
+    ("DeleteServices", 2000),
+    ("RemoveRegistryValues", 2600),
+    ("RemoveShortcuts", 3200),
+    ("RemoveFiles", 3500),
+    ("RemoveFolders", 3600),
+    ("CreateFolders", 3700),
+    ("InstallFiles", 4000),
+    ("CreateShortcuts", 4500),
+    ("WriteRegistryValues", 5000),
+    ("InstallServices", 5800),
+    ("StartServices", 5900),
+    ("RegisterUser", 6000),
+    ("RegisterProduct", 6100),
+    ("PublishFeatures", 6300),
+    ("PublishProduct", 6400),
+    ("InstallFinalize", 6600),
+];
+
+/// The sequence numbers of the standard actions in the sequence tables they
+/// can be scheduled in, as suggested by Windows Installer.
+const STANDARD_ACTIONS: [(&str, i32); 67] = [
+    ("LaunchConditions", 100),
+    ("FindRelatedProducts", 200),
+    ("AppSearch", 400),
+    ("CCPSearch", 500),
+    ("RMCCPSearch", 600),
+    ("ValidateProductID", 700),
+    ("CostInitialize", 800),
+    ("FileCost", 900),
+    ("IsolateComponents", 950),
+    ("CostFinalize", 1000),
+    ("SetODBCFolders", 1100),
+    ("MigrateFeatureStates", 1200),
+    ("ExecuteAction", 1300),
+    ("InstallValidate", 1400),
+    ("InstallInitialize", 1500),
+    ("AllocateRegistrySpace", 1550),
+    ("ProcessComponents", 1600),
+    ("UnpublishComponents", 1700),
+    ("MsiUnpublishAssemblies", 1750),
+    ("UnpublishFeatures", 1800),
+    ("StopServices", 1900),
+    ("DeleteServices", 2000),
+    ("UnregisterComPlus", 2100),
+    ("SelfUnregModules", 2200),
+    ("UnregisterTypeLibraries", 2300),
+    ("RemoveODBC", 2400),
+    ("UnregisterFonts", 2500),
+    ("RemoveRegistryValues", 2600),
+    ("UnregisterClassInfo", 2700),
+    ("UnregisterExtensionInfo", 2800),
+    ("UnregisterProgIdInfo", 2900),
+    ("UnregisterMIMEInfo", 3000),
+    ("RemoveIniValues", 3100),
+    ("RemoveShortcuts", 3200),
+    ("RemoveEnvironmentStrings", 3300),
+    ("RemoveDuplicateFiles", 3400),
+    ("RemoveFiles", 3500),
+    ("RemoveFolders", 3600),
+    ("CreateFolders", 3700),
+    ("MoveFiles", 3800),
+    ("InstallAdminPackage", 3900),
+    ("InstallFiles", 4000),
+    ("PatchFiles", 4090),
+    ("DuplicateFiles", 4210),
+    ("BindImage", 4300),
+    ("CreateShortcuts", 4500),
+    ("RegisterClassInfo", 4600),
+    ("RegisterExtensionInfo", 4700),
+    ("RegisterProgIdInfo", 4800),
+    ("RegisterMIMEInfo", 4900),
+    ("WriteRegistryValues", 5000),
+    ("WriteIniValues", 5100),
+    ("WriteEnvironmentStrings", 5200),
+    ("RegisterFonts", 5300),
+    ("InstallODBC", 5400),
+    ("RegisterTypeLibraries", 5500),
+    ("SelfRegModules", 5600),
+    ("RegisterComPlus", 5700),
+    ("InstallServices", 5800),
+    ("StartServices", 5900),
+    ("RegisterUser", 6000),
+    ("RegisterProduct", 6100),
+    ("PublishComponents", 6200),
+    ("MsiPublishAssemblies", 6250),
+    ("PublishFeatures", 6300),
+    ("PublishProduct", 6400),
+    ("InstallFinalize", 6600),
+];
+
+/// The standard actions of the `InstallUISequence`. A UI set adds its
+/// dialogs to these.
+pub(crate) const UI_ACTIONS: [(&str, i32); 6] = [
+    ("LaunchConditions", 100),
+    ("ValidateProductID", 700),
+    ("CostInitialize", 800),
+    ("FileCost", 900),
+    ("CostFinalize", 1000),
+    ("ExecuteAction", 1300),
+];
+
+/// The sequence number of a standard action, or `None` if `action` isn't
+/// one.
+pub(crate) fn standard_sequence(action: &str) -> Option<i32> {
+    STANDARD_ACTIONS
+        .iter()
+        .find(|(standard, _)| *standard == action)
+        .map(|(_, sequence)| *sequence)
+}
+
+pub(crate) fn is_sequence_table(table: &str) -> bool {
+    table == EXECUTE_SEQUENCE || table == UI_SEQUENCE
+}
+
+pub(crate) fn columns() -> Vec<Column> {
+    vec![
+        Column::build("Action")
+            .primary_key()
+            .category(Category::Identifier)
+            .string(72),
+        Column::build("Condition")
+            .nullable()
+            .category(Category::Condition)
+            .string(255),
+        Column::build("Sequence").nullable().int16(),
+    ]
+}
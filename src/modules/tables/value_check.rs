@@ -0,0 +1,443 @@
+// Checks values against the columns they are inserted into
+
+use std::collections::HashSet;
+
+use camino::Utf8PathBuf;
+use msi::{Category, Column, ColumnType, Insert, Select, Value};
+
+use crate::{
+    command::builder::Msi,
+    modules::helpers::{
+        error::MsiError,
+        filename::{is_valid_long_name, is_valid_short_name},
+    },
+};
+
+/// A row to insert along with the file or directory it was generated from,
+/// so problems can be traced back to the input.
+pub(crate) struct SourcedRow {
+    pub(crate) values: Vec<Value>,
+    pub(crate) source: Option<Utf8PathBuf>,
+}
+
+impl SourcedRow {
+    pub(crate) fn new(values: Vec<Value>, source: Option<Utf8PathBuf>) -> Self {
+        SourcedRow { values, source }
+    }
+}
+
+/// Check every value against the column it is being inserted into and insert
+/// the rows if they are all valid.
+///
+/// Every invalid value is reported, not just the first one, so a build only
+/// has to be rerun once to see everything that needs fixing.
+pub(crate) fn insert_rows(
+    package: &mut Msi,
+    table: &str,
+    columns: &[Column],
+    rows: Vec<SourcedRow>,
+) -> Result<(), MsiError> {
+    let file_keys = file_keys(package, table, &rows);
+    let problems = rows
+        .iter()
+        .flat_map(|row| check_row(table, columns, row, &file_keys))
+        .collect::<Vec<String>>();
+    if !problems.is_empty() {
+        // The caller reports the error, so it isn't logged here as well.
+        let msg = format!(
+            "Found {} invalid value(s) for table {}:\n{}",
+            problems.len(),
+            table,
+            problems.join("\n")
+        );
+        return Err(MsiError::short(msg));
+    }
+
+    let query =
+        Insert::into(table).rows(rows.into_iter().map(|r| r.values).collect());
+    if let Err(err) = package.insert_rows(query) {
+        return Err(MsiError::nested(
+            format!("Failed to insert rows into table {table}"),
+            err,
+        ));
+    };
+    Ok(())
+}
+
+/// The keys of the files a `File.Version` can name as its companion, which
+/// are those being inserted and those already in the package.
+fn file_keys(
+    package: &mut Msi,
+    table: &str,
+    rows: &[SourcedRow],
+) -> HashSet<String> {
+    if table != "File" {
+        return HashSet::new();
+    }
+    let key = |value: &Value| match value {
+        Value::Str(key) => Some(key.clone()),
+        _ => None,
+    };
+    let mut keys = rows
+        .iter()
+        .filter_map(|row| key(row.values.first()?))
+        .collect::<HashSet<String>>();
+    if package.has_table(table) {
+        if let Ok(existing) = package.select_rows(Select::table(table)) {
+            keys.extend(existing.filter_map(|row| key(&row[0])));
+        }
+    }
+    keys
+}
+
+fn check_row(
+    table: &str,
+    columns: &[Column],
+    row: &SourcedRow,
+    file_keys: &HashSet<String>,
+) -> Vec<String> {
+    if row.values.len() != columns.len() {
+        return vec![format!(
+            "{}: Row has {} values but the table has {} columns",
+            table,
+            row.values.len(),
+            columns.len()
+        )];
+    }
+
+    columns
+        .iter()
+        .zip(&row.values)
+        .filter_map(|(column, value)| {
+            if is_companion_key(table, column, value, file_keys) {
+                return None;
+            }
+            let problem = check_value(column, value).err()?;
+            let source = match &row.source {
+                Some(source) => format!(" (from {source})"),
+                None => String::new(),
+            };
+            Some(format!(
+                "{}.{}: {}{}",
+                table,
+                column.name(),
+                problem,
+                source
+            ))
+        })
+        .collect()
+}
+
+/// `File.Version` holds the key of another file instead of a version for a
+/// [companion file](https://learn.microsoft.com/en-us/windows/win32/msi/companion-files).
+fn is_companion_key(
+    table: &str,
+    column: &Column,
+    value: &Value,
+    file_keys: &HashSet<String>,
+) -> bool {
+    table == "File"
+        && column.name() == "Version"
+        && matches!(value, Value::Str(key) if file_keys.contains(key))
+}
+
+/// Check a single value against the type, width and category of its column.
+pub(crate) fn check_value(
+    column: &Column,
+    value: &Value,
+) -> Result<(), String> {
+    let string = match (value, column.coltype()) {
+        (Value::Null, _) if column.is_nullable() => return Ok(()),
+        (Value::Null, _) => return Err("Value can't be null".to_owned()),
+        (Value::Int(i), ColumnType::Int16) => {
+            // -32768 is reserved to represent null.
+            if !(-32767..=32767).contains(i) {
+                return Err(format!(
+                    "Value {i} does not fit in a 16-bit column"
+                ));
+            }
+            return Ok(());
+        }
+        (Value::Int(i), ColumnType::Int32) => {
+            if *i == i32::MIN {
+                return Err(format!("Value {i} is reserved for null"));
+            }
+            return Ok(());
+        }
+        (Value::Str(s), ColumnType::Str(width)) => {
+            let length = s.chars().count();
+            if width > 0 && length > width {
+                return Err(format!(
+                    "Value [{s}] is {length} characters long but the column \
+                    only holds {width}"
+                ));
+            }
+            s
+        }
+        (Value::Int(i), _) => {
+            return Err(format!("Integer {i} given for a string column"))
+        }
+        (Value::Str(s), _) => {
+            return Err(format!("String [{s}] given for an integer column"))
+        }
+    };
+
+    match column.category() {
+        Some(Category::Guid) => check_guid(string),
+        Some(Category::Filename) => check_filename(string),
+        Some(Category::DefaultDir) => check_default_dir(string),
+        Some(Category::Version) => check_version(string),
+        Some(Category::Identifier) => check_identifier(string),
+        Some(Category::Language) => check_language(string),
+        Some(Category::Formatted) | Some(Category::Condition) => {
+            check_brackets(string)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// GUIDs must be uppercase and wrapped in braces, such as
+/// `{12345678-ABCD-ABCD-ABCD-123456789ABC}`.
+fn check_guid(value: &str) -> Result<(), String> {
+    let valid = value.len() == 38
+        && value.is_ascii()
+        && value.starts_with('{')
+        && value.ends_with('}')
+        && value[1..37].char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_digit() || ('A'..='F').contains(&c),
+        });
+    if valid {
+        return Ok(());
+    }
+    Err(format!(
+        "[{value}] is not an uppercase GUID in the form \
+        {{XXXXXXXX-XXXX-XXXX-XXXX-XXXXXXXXXXXX}}"
+    ))
+}
+
+/// File names are either a single name or `short|long`.
+fn check_filename(value: &str) -> Result<(), String> {
+    match value.split_once('|') {
+        Some((short, long)) => {
+            if !is_valid_short_name(short) {
+                return Err(format!("[{short}] is not a valid 8.3 short name"));
+            }
+            if !is_valid_long_name(long) {
+                return Err(format!("[{long}] is not a valid file name"));
+            }
+        }
+        None => {
+            if !is_valid_long_name(value) {
+                return Err(format!("[{value}] is not a valid file name"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Directory names are `target[:source]` where each half is a file name or
+/// `.` to use the parent directory.
+fn check_default_dir(value: &str) -> Result<(), String> {
+    for part in value.splitn(2, ':') {
+        if part != "." {
+            check_filename(part)?;
+        }
+    }
+    Ok(())
+}
+
+/// Versions are one to four numbers from 0 to 65535 separated by periods.
+fn check_version(value: &str) -> Result<(), String> {
+    let parts = value.split('.').collect::<Vec<&str>>();
+    let valid = (1..=4).contains(&parts.len())
+        && parts.iter().all(|p| {
+            !p.is_empty()
+                && p.chars().all(|c| c.is_ascii_digit())
+                && p.parse::<u16>().is_ok()
+        });
+    if valid {
+        return Ok(());
+    }
+    Err(format!(
+        "[{value}] is not a version of up to four numbers between 0 and 65535"
+    ))
+}
+
+/// Identifiers start with a letter or underscore and contain only ASCII
+/// letters, digits, underscores and periods.
+fn check_identifier(value: &str) -> Result<(), String> {
+    let mut chars = value.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+    if valid {
+        return Ok(());
+    }
+    Err(format!("[{value}] is not a valid identifier"))
+}
+
+/// Languages are a comma separated list of numeric language identifiers.
+fn check_language(value: &str) -> Result<(), String> {
+    if value.split(',').all(|l| l.trim().parse::<u16>().is_ok()) {
+        return Ok(());
+    }
+    Err(format!(
+        "[{value}] is not a list of numeric language identifiers"
+    ))
+}
+
+/// Formatted strings and conditions must close every `[` and `{` they open.
+/// Escaped characters such as `[\[]` are skipped.
+fn check_brackets(value: &str) -> Result<(), String> {
+    let chars = value.chars().collect::<Vec<char>>();
+    let mut open = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        i += 1;
+        if c == '['
+            && chars.get(i) == Some(&'\\')
+            && chars.get(i + 2) == Some(&']')
+        {
+            i += 3;
+            continue;
+        }
+        match c {
+            '[' | '{' => open.push(c),
+            ']' | '}' => {
+                let expected = if c == ']' { '[' } else { '{' };
+                if open.pop() != Some(expected) {
+                    return Err(format!("[{value}] has an unmatched '{c}'"));
+                }
+            }
+            _ => {}
+        }
+    }
+    match open.last() {
+        Some(c) => Err(format!("[{value}] has an unclosed '{c}'")),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use msi::{Category, Column, Value};
+
+    use super::{
+        check_brackets, check_default_dir, check_filename, check_guid,
+        check_identifier, check_row, check_value, check_version, SourcedRow,
+    };
+
+    fn columns() -> Vec<Column> {
+        vec![
+            Column::build("File").primary_key().id_string(72),
+            Column::build("Version")
+                .nullable()
+                .category(Category::Version)
+                .string(72),
+        ]
+    }
+
+    fn problems(table: &str, version: &str) -> Vec<String> {
+        let row = SourcedRow::new(
+            vec![Value::from("Readme"), Value::from(version)],
+            None,
+        );
+        let file_keys = HashSet::from(["App".to_owned(), "Readme".to_owned()]);
+        check_row(table, &columns(), &row, &file_keys)
+    }
+
+    #[test]
+    fn accepts_versions_and_companion_keys() {
+        assert!(problems("File", "1.2.3.4").is_empty());
+        assert!(problems("File", "App").is_empty());
+    }
+
+    #[test]
+    fn rejects_keys_that_are_not_files() {
+        assert_eq!(problems("File", "Missing").len(), 1);
+        assert!(problems("File", "Missing")[0].starts_with("File.Version: "));
+        // Only the `File` table has companion files.
+        assert_eq!(problems("Other", "App").len(), 1);
+    }
+
+    #[test]
+    fn guids_are_uppercase_and_braced() {
+        assert!(check_guid("{12345678-ABCD-ABCD-ABCD-123456789ABC}").is_ok());
+        assert!(check_guid("{12345678-abcd-abcd-abcd-123456789abc}").is_err());
+        assert!(check_guid("12345678-ABCD-ABCD-ABCD-123456789ABC").is_err());
+    }
+
+    #[test]
+    fn filenames_can_have_a_short_and_long_name() {
+        assert!(check_filename("README~1.TXT|Readme file.txt").is_ok());
+        assert!(check_filename("Readme file.txt").is_ok());
+        let err = check_filename("Readme file.txt|Readme.txt").unwrap_err();
+        assert_eq!(err, "[Readme file.txt] is not a valid 8.3 short name");
+    }
+
+    #[test]
+    fn default_dirs_have_a_target_and_source() {
+        assert!(check_default_dir(".").is_ok());
+        assert!(check_default_dir(".:Source").is_ok());
+        assert!(check_default_dir("PROGRA~1|Program Files:.").is_ok());
+        assert_eq!(
+            check_default_dir("Target:So*rce").unwrap_err(),
+            "[So*rce] is not a valid file name"
+        );
+    }
+
+    #[test]
+    fn versions_have_up_to_four_parts_of_16_bits() {
+        assert!(check_version("65535.0.0.1").is_ok());
+        assert!(check_version("1.65536").is_err());
+        assert!(check_version("1.2.3.4.5").is_err());
+        assert!(check_version("1..2").is_err());
+    }
+
+    #[test]
+    fn identifiers_start_with_a_letter_or_underscore() {
+        assert!(check_identifier("_1bin.x64").is_ok());
+        assert_eq!(
+            check_identifier("1bin").unwrap_err(),
+            "[1bin] is not a valid identifier"
+        );
+    }
+
+    #[test]
+    fn brackets_are_closed_unless_escaped() {
+        assert!(check_brackets("[\\[]INSTALLDIR[\\]]").is_ok());
+        assert!(check_brackets("{[INSTALLDIR]}").is_ok());
+        assert_eq!(
+            check_brackets("INSTALLDIR]").unwrap_err(),
+            "[INSTALLDIR]] has an unmatched ']'"
+        );
+        assert_eq!(
+            check_brackets("{[INSTALLDIR]").unwrap_err(),
+            "[{[INSTALLDIR]] has an unclosed '{'"
+        );
+    }
+
+    #[test]
+    fn int16_values_leave_out_the_null_value() {
+        let column = Column::build("Attributes").int16();
+        assert!(check_value(&column, &Value::Int(-32767)).is_ok());
+        assert!(check_value(&column, &Value::Int(32767)).is_ok());
+        assert!(check_value(&column, &Value::Int(-32768)).is_err());
+        assert!(check_value(&column, &Value::Int(32768)).is_err());
+    }
+
+    #[test]
+    fn strings_fit_their_column() {
+        let column = Column::build("Name").string(4);
+        assert!(check_value(&column, &Value::from("Four")).is_ok());
+        assert_eq!(
+            check_value(&column, &Value::from("Fives")).unwrap_err(),
+            "Value [Fives] is 5 characters long but the column only holds 4"
+        );
+    }
+}
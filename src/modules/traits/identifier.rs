@@ -1,15 +1,21 @@
 use flexstr::LocalStr;
 use uuid::Uuid;
 
+/// Namespace for the name based UUIDs that whimsi derives identifiers and
+/// component GUIDs from.
+pub(crate) const WHIMSI_NAMESPACE: Uuid =
+    Uuid::from_u128(0xf1f62e56_97ea_488a_87ce_4142715d5e49);
+
 pub(crate) trait Identifier {
-    fn as_identifier() -> LocalStr;
+    /// An identifier that is always the same for the same `seed`, so rows
+    /// keep their keys between builds. Patches rely on this to match rows
+    /// between two versions of a package.
+    fn from_seed(seed: &str) -> LocalStr;
 }
 
 impl Identifier for Uuid {
-    fn as_identifier() -> LocalStr {
-        // We make all identifiers start with an underscore so we can ignore the
-        // case when the UUID starts with a number, which is invalid for
-        // identifiers.
-        ("_".to_string() + Uuid::new_v4().simple().to_string().as_str()).into()
+    fn from_seed(seed: &str) -> LocalStr {
+        let uuid = Uuid::new_v5(&WHIMSI_NAMESPACE, seed.as_bytes());
+        ("_".to_string() + uuid.simple().to_string().as_str()).into()
     }
 }
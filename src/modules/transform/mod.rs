@@ -0,0 +1,792 @@
+//! Creating and applying transforms (`.mst`).
+//!
+//! A transform is a compound file laid out like an MSI, except that its table
+//! streams hold row changes instead of rows. Each row starts with a mask
+//! saying whether it is an insert, a delete or which columns it updates. The
+//! summary information records the packages the transform was made from and
+//! what has to match before it may be applied.
+//!
+//! Rows can be added, removed and changed, whole tables can be added and
+//! removed, and streams can be added or changed. Changing the columns of a
+//! table that is in both packages is not supported.
+
+pub(crate) mod rows;
+pub(crate) mod schema;
+pub(crate) mod string_pool;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{Cursor, Read, Seek, Write},
+    path::Path,
+    time::SystemTime,
+};
+
+use anyhow::{bail, Context, Result};
+use log::{debug, warn};
+use msi::{Delete, Expr, Insert, Package, Select, Update, Value};
+use uuid::Uuid;
+
+use self::{
+    rows::{EncodedRows, RowOp},
+    schema::{COLUMNS, TABLES},
+    string_pool::StringPool,
+};
+use crate::{
+    command::{format::CellValue, lister::ColumnListing},
+    modules::{
+        helpers::{
+            property_set::{
+                Property, PropertySet, PID_APPNAME, PID_CHARCOUNT,
+                PID_CODEPAGE, PID_CREATE_DTM, PID_LASTAUTHOR, PID_REVNUMBER,
+                PID_TEMPLATE, PID_TITLE,
+            },
+            stream_name,
+        },
+        validation::database::{Database, TableData},
+    },
+};
+
+/// CLSID that marks the root storage of a compound file as a transform.
+const TRANSFORM_CLSID: u128 = 0x000C1082_0000_0000_C000_000000000046;
+
+pub(crate) const SUMMARY_INFORMATION: &str = "\u{5}SummaryInformation";
+const STRING_POOL: &str = "_StringPool";
+const STRING_DATA: &str = "_StringData";
+const VALIDATION: &str = "_Validation";
+
+/// The base package must have the same `ProductLanguage`.
+pub(crate) const VALIDATE_LANGUAGE: i32 = 0x0001;
+/// The base package must have the same `ProductCode`.
+pub(crate) const VALIDATE_PRODUCT: i32 = 0x0002;
+/// The base package must have the same `UpgradeCode`.
+pub(crate) const VALIDATE_UPGRADECODE: i32 = 0x0800;
+
+/// Conditions whimsi writes into the transforms it creates.
+const DEFAULT_VALIDATION: i32 = VALIDATE_PRODUCT | VALIDATE_UPGRADECODE;
+
+/// The primary key of a row, used to match rows between two packages.
+type RowKey = Vec<Option<CellValue>>;
+
+/// Compute the changes that turn `base` into `modified` and return the bytes
+/// of a transform holding them.
+pub(crate) fn create<F, G>(
+    base: &mut Package<F>,
+    modified: &mut Package<G>,
+) -> Result<Vec<u8>>
+where
+    F: Read + Seek,
+    G: Read + Seek,
+{
+    let base_db = Database::load(base)?;
+    let modified_db = Database::load(modified)?;
+
+    let names = base_db
+        .tables()
+        .chain(modified_db.tables())
+        .map(|t| t.name.as_str())
+        .filter(|name| ![TABLES, COLUMNS].contains(name))
+        .collect::<BTreeSet<&str>>();
+
+    // The strings are those of the modified package, which can use another
+    // codepage than the base, such as when it is in another language.
+    let mut pool = StringPool::new(modified.database_codepage());
+    let mut tables = Vec::new();
+    let mut table_ops = Vec::new();
+    let mut column_ops = Vec::new();
+    for name in names {
+        let (old, new) = (base_db.table(name), modified_db.table(name));
+        let (columns, ops) = match (old, new) {
+            (Some(old), Some(new)) => {
+                let old_columns = old
+                    .columns
+                    .iter()
+                    .map(ColumnListing::from)
+                    .collect::<Vec<_>>();
+                let new_columns = new
+                    .columns
+                    .iter()
+                    .map(ColumnListing::from)
+                    .collect::<Vec<_>>();
+                if old_columns != new_columns {
+                    bail!(
+                        "Columns of table {} differ between the packages. \
+                        Transforms that change columns are not supported",
+                        name
+                    );
+                }
+                (&old.columns, table_changes(old, new))
+            }
+            (Some(_), None) => {
+                debug!("Table {} was removed", name);
+                table_ops.push(RowOp::Delete(vec![Value::from(name)]));
+                continue;
+            }
+            (None, Some(new)) => {
+                debug!("Table {} was added", name);
+                let (table_row, mut column_rows) =
+                    schema::add_table(name, &new.columns);
+                table_ops.push(table_row);
+                column_ops.append(&mut column_rows);
+                let ops = new
+                    .rows
+                    .iter()
+                    .map(|row| RowOp::Insert(row.clone()))
+                    .collect();
+                (&new.columns, ops)
+            }
+            (None, None) => continue,
+        };
+        if ops.is_empty() {
+            continue;
+        }
+        debug!("Table {} has {} changed row(s)", name, ops.len());
+        let encoded = rows::encode(name, columns, &ops, &mut pool)?;
+        tables.push((name.to_owned(), encoded));
+    }
+    if !table_ops.is_empty() {
+        let encoded = rows::encode(
+            TABLES,
+            &schema::tables_columns(),
+            &table_ops,
+            &mut pool,
+        )?;
+        tables.push((TABLES.to_owned(), encoded));
+    }
+    if !column_ops.is_empty() {
+        let encoded = rows::encode(
+            COLUMNS,
+            &schema::columns_columns(),
+            &column_ops,
+            &mut pool,
+        )?;
+        tables.push((COLUMNS.to_owned(), encoded));
+    }
+
+    let streams = changed_streams(base, modified)?;
+    let summary = transform_summary(base, &base_db, modified, &modified_db);
+    write_transform(&pool, &tables, &streams, &summary)
+}
+
+/// The inserts, deletes and updates that turn the rows of `old` into the rows
+/// of `new`.
+fn table_changes(old: &TableData, new: &TableData) -> Vec<RowOp> {
+    let old_rows = keyed_rows(old);
+    let new_rows = keyed_rows(new);
+    let keys = rows::key_count(&old.columns);
+
+    let mut ops = Vec::new();
+    for (key, row) in &old_rows {
+        if !new_rows.contains_key(key) {
+            ops.push(RowOp::Delete(row[..keys].to_vec()));
+        }
+    }
+    for (key, row) in &new_rows {
+        let Some(old_row) = old_rows.get(key) else {
+            ops.push(RowOp::Insert(row.to_vec()));
+            continue;
+        };
+        let changes = row
+            .iter()
+            .zip(old_row.iter())
+            .enumerate()
+            .skip(keys)
+            .filter(|(_, (new, old))| new != old)
+            .map(|(index, (new, _))| (index, new.clone()))
+            .collect::<Vec<_>>();
+        if !changes.is_empty() {
+            ops.push(RowOp::Update {
+                key: row[..keys].to_vec(),
+                changes,
+            });
+        }
+    }
+    ops
+}
+
+fn row_key(row: &[Value], keys: usize) -> RowKey {
+    row[..keys].iter().map(CellValue::from_value).collect()
+}
+
+fn keyed_rows(table: &TableData) -> BTreeMap<RowKey, &[Value]> {
+    let keys = rows::key_count(&table.columns);
+    table
+        .rows
+        .iter()
+        .map(|row| (row_key(row, keys), row.as_slice()))
+        .collect()
+}
+
+/// Streams that are new or have different contents in `modified`.
+fn changed_streams<F, G>(
+    base: &mut Package<F>,
+    modified: &mut Package<G>,
+) -> Result<Vec<(String, Vec<u8>)>>
+where
+    F: Read + Seek,
+    G: Read + Seek,
+{
+    let base_names = base.streams().collect::<BTreeSet<String>>();
+    let modified_names = modified.streams().collect::<BTreeSet<String>>();
+    if let Some(name) = base_names.difference(&modified_names).next() {
+        bail!(
+            "Stream {} was removed. Transforms that remove streams are not \
+            supported",
+            name
+        );
+    }
+
+    let mut changed = Vec::new();
+    for name in modified_names {
+        let new = read_package_stream(modified, &name)?;
+        if base_names.contains(&name)
+            && read_package_stream(base, &name)? == new
+        {
+            continue;
+        }
+        debug!("Stream {} changed", name);
+        changed.push((name, new));
+    }
+    Ok(changed)
+}
+
+fn read_package_stream<F: Read + Seek>(
+    package: &mut Package<F>,
+    name: &str,
+) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    package
+        .read_stream(name)
+        .with_context(|| format!("Failed to open stream {name}"))?
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("Failed to read stream {name}"))?;
+    Ok(bytes)
+}
+
+/// The value of a property in the `Property` table.
+pub(crate) fn property<'a>(db: &'a Database, name: &str) -> Option<&'a str> {
+    let table = db.table("Property")?;
+    table
+        .rows
+        .iter()
+        .find(|row| table.str(row, "Property") == Some(name))
+        .and_then(|row| table.str(row, "Value"))
+}
+
+/// The `platform;languages` template of a package.
+pub(crate) fn template<F>(package: &Package<F>) -> String {
+    let info = package.summary_info();
+    let languages = info
+        .languages()
+        .iter()
+        .map(|l| l.code().to_string())
+        .collect::<Vec<String>>()
+        .join(",");
+    format!("{};{}", info.arch().unwrap_or_default(), languages)
+}
+
+fn transform_summary<F, G>(
+    base: &Package<F>,
+    base_db: &Database,
+    modified: &Package<G>,
+    modified_db: &Database,
+) -> PropertySet {
+    let product = |db: &Database| {
+        format!(
+            "{}{}",
+            property(db, "ProductCode").unwrap_or_default(),
+            property(db, "ProductVersion").unwrap_or_default()
+        )
+    };
+    let revision = format!(
+        "{};{};{}",
+        product(base_db),
+        product(modified_db),
+        property(base_db, "UpgradeCode").unwrap_or_default()
+    );
+
+    let mut summary = PropertySet::default();
+    summary
+        .set(PID_CODEPAGE, Property::I2(1252))
+        .set_str(PID_TITLE, "Transform")
+        .set_str(PID_TEMPLATE, &template(base))
+        .set_str(PID_LASTAUTHOR, &template(modified))
+        .set_str(PID_REVNUMBER, &revision)
+        .set(PID_CREATE_DTM, Property::Time(SystemTime::now()))
+        // The high word holds the validation conditions and the low word the
+        // errors to ignore, of which there are none.
+        .set(PID_CHARCOUNT, Property::I4(DEFAULT_VALIDATION << 16))
+        .set_str(PID_APPNAME, "whimsi");
+    summary
+}
+
+fn write_transform(
+    pool: &StringPool,
+    tables: &[(String, EncodedRows)],
+    streams: &[(String, Vec<u8>)],
+    summary: &PropertySet,
+) -> Result<Vec<u8>> {
+    let mut comp = cfb::CompoundFile::create(Cursor::new(Vec::new()))
+        .with_context(|| "Failed to create transform storage")?;
+    comp.set_storage_clsid("/", Uuid::from_u128(TRANSFORM_CLSID))
+        .with_context(|| "Failed to mark storage as a transform")?;
+
+    let ref_size = pool.ref_size();
+    let (pool_bytes, data_bytes) = pool.write();
+    let mut contents = vec![
+        (stream_name::encode(STRING_POOL, true), pool_bytes),
+        (stream_name::encode(STRING_DATA, true), data_bytes),
+        (SUMMARY_INFORMATION.to_owned(), summary.to_bytes()),
+    ];
+    for (name, rows) in tables.iter().filter(|(_, rows)| !rows.is_empty()) {
+        contents
+            .push((stream_name::encode(name, true), rows.to_bytes(ref_size)));
+    }
+    for (name, bytes) in streams {
+        contents.push((stream_name::encode(name, false), bytes.clone()));
+    }
+
+    for (name, bytes) in contents {
+        let path = format!("/{name}");
+        comp.create_stream(&path)
+            .and_then(|mut stream| stream.write_all(&bytes))
+            .with_context(|| {
+                format!(
+                    "Failed to write stream {}",
+                    stream_name::decode(&name).0
+                )
+            })?;
+    }
+    comp.flush()
+        .with_context(|| "Failed to finish transform storage")?;
+    Ok(comp.into_inner().into_inner())
+}
+
+/// Apply a transform to `package` in place.
+pub(crate) fn apply<F, G>(
+    package: &mut Package<F>,
+    transform: &mut cfb::CompoundFile<G>,
+) -> Result<()>
+where
+    F: Read + Write + Seek,
+    G: Read + Seek,
+{
+    let db = Database::load(package)?;
+    check_validation(transform, package, &db)?;
+
+    let pool = StringPool::read(
+        &read_transform_stream(transform, STRING_POOL, true)?
+            .unwrap_or_default(),
+        &read_transform_stream(transform, STRING_DATA, true)?
+            .unwrap_or_default(),
+    )?;
+
+    let (removed, added) = apply_schema(package, transform, &pool)?;
+    let tables = db
+        .tables()
+        .filter(|t| !removed.contains(&t.name))
+        .chain(added.iter());
+    for table in tables {
+        let Some(bytes) = read_transform_stream(transform, &table.name, true)?
+        else {
+            continue;
+        };
+        let ops = rows::decode(&table.name, &table.columns, &bytes, &pool)?;
+        debug!("Applying {} change(s) to table {}", ops.len(), table.name);
+        apply_table(package, table, ops)?;
+    }
+
+    for name in transform_streams(transform) {
+        let (decoded, is_table) = stream_name::decode(&name);
+        if is_table {
+            let known = [STRING_POOL, STRING_DATA, TABLES, COLUMNS]
+                .contains(&decoded.as_str())
+                || db.table(&decoded).is_some()
+                || added.iter().any(|t| t.name == decoded);
+            if !known {
+                warn!("Ignoring changes to unknown table {}", decoded);
+            }
+            continue;
+        }
+        if decoded.starts_with('\u{5}') {
+            continue;
+        }
+        let bytes = read_transform_stream(transform, &decoded, false)?
+            .unwrap_or_default();
+        debug!("Writing stream {}", decoded);
+        package
+            .write_stream(&decoded)
+            .and_then(|mut stream| stream.write_all(&bytes))
+            .with_context(|| format!("Failed to write stream {decoded}"))?;
+    }
+    Ok(())
+}
+
+/// Drop and create the tables listed in `_Tables` and `_Columns`. Returns the
+/// names of the dropped tables and the empty tables that were created.
+fn apply_schema<F, G>(
+    package: &mut Package<F>,
+    transform: &mut cfb::CompoundFile<G>,
+    pool: &StringPool,
+) -> Result<(BTreeSet<String>, Vec<TableData>)>
+where
+    F: Read + Write + Seek,
+    G: Read + Seek,
+{
+    let mut removed = BTreeSet::new();
+    let mut added_names = BTreeSet::new();
+    if let Some(bytes) = read_transform_stream(transform, TABLES, true)? {
+        let ops =
+            rows::decode(TABLES, &schema::tables_columns(), &bytes, pool)?;
+        for op in ops {
+            match op {
+                RowOp::Delete(key) => {
+                    let name = table_name(&key)?;
+                    debug!("Dropping table {}", name);
+                    package
+                        .drop_table(&name)
+                        .with_context(|| format!("Failed to drop {name}"))?;
+                    removed.insert(name);
+                }
+                RowOp::Insert(values) => {
+                    added_names.insert(table_name(&values)?);
+                }
+                RowOp::Update { .. } => {
+                    bail!("Transform has an update to {}", TABLES)
+                }
+            }
+        }
+    }
+
+    let column_ops = match read_transform_stream(transform, COLUMNS, true)? {
+        Some(bytes) => {
+            rows::decode(COLUMNS, &schema::columns_columns(), &bytes, pool)?
+        }
+        None => Vec::new(),
+    };
+    let mut added = Vec::new();
+    for (name, columns) in schema::added_columns(column_ops)? {
+        if !added_names.contains(&name) {
+            bail!(
+                "Transform adds columns to existing table {}, which is not \
+                supported",
+                name
+            );
+        }
+        debug!("Creating table {}", name);
+        package
+            .create_table(name.clone(), columns.clone())
+            .with_context(|| format!("Failed to create table {name}"))?;
+        added.push(TableData {
+            name,
+            columns,
+            rows: Vec::new(),
+        });
+    }
+    Ok((removed, added))
+}
+
+/// The table name in a row of `_Tables`.
+fn table_name(row: &[Value]) -> Result<String> {
+    match row.first() {
+        Some(Value::Str(name)) => Ok(name.clone()),
+        _ => bail!("Transform has a malformed row in {}", TABLES),
+    }
+}
+
+/// Names of the streams at the root of the transform.
+fn transform_streams<G: Read + Seek>(
+    transform: &cfb::CompoundFile<G>,
+) -> Vec<String> {
+    transform
+        .walk()
+        .filter(|e| e.is_stream() && e.path().parent() == Some(Path::new("/")))
+        .map(|e| e.name().to_owned())
+        .collect()
+}
+
+fn read_transform_stream<G: Read + Seek>(
+    transform: &mut cfb::CompoundFile<G>,
+    name: &str,
+    is_table: bool,
+) -> Result<Option<Vec<u8>>> {
+    let raw = match name.starts_with('\u{5}') {
+        true => name.to_owned(),
+        false => stream_name::encode(name, is_table),
+    };
+    let path = format!("/{raw}");
+    if !transform.is_stream(&path) {
+        return Ok(None);
+    }
+    let mut bytes = Vec::new();
+    transform
+        .open_stream(&path)
+        .and_then(|mut stream| stream.read_to_end(&mut bytes))
+        .with_context(|| format!("Failed to read transform stream {name}"))?;
+    Ok(Some(bytes))
+}
+
+/// Check the conditions stored in the summary information of the transform
+/// against the package it is being applied to.
+fn check_validation<F, G: Read + Seek>(
+    transform: &mut cfb::CompoundFile<G>,
+    package: &Package<F>,
+    db: &Database,
+) -> Result<()> {
+    let Some(summary) =
+        read_transform_stream(transform, SUMMARY_INFORMATION, false)?
+            .and_then(|bytes| PropertySet::read(&bytes))
+    else {
+        bail!("Transform has no readable summary information");
+    };
+    let validation = summary.get_int(PID_CHARCOUNT).unwrap_or_default() >> 16;
+    let revision = summary.get_str(PID_REVNUMBER).unwrap_or_default();
+    let mut parts = revision.split(';');
+    let base_product = parts.next().unwrap_or_default();
+    let upgrade_code = parts.nth(1).unwrap_or_default();
+
+    if validation & VALIDATE_PRODUCT != 0 {
+        let expected = base_product.get(..38).unwrap_or(base_product);
+        let actual = property(db, "ProductCode").unwrap_or_default();
+        if !expected.eq_ignore_ascii_case(actual) {
+            bail!(
+                "Transform is for product {} but the package is {}",
+                expected,
+                actual
+            );
+        }
+    }
+    if validation & VALIDATE_UPGRADECODE != 0 {
+        let actual = property(db, "UpgradeCode").unwrap_or_default();
+        if !upgrade_code.eq_ignore_ascii_case(actual) {
+            bail!(
+                "Transform is for upgrade code {} but the package has {}",
+                upgrade_code,
+                actual
+            );
+        }
+    }
+    if validation & VALIDATE_LANGUAGE != 0 {
+        let expected = summary.get_str(PID_TEMPLATE).unwrap_or_default();
+        let actual = template(package);
+        let language = |t: &str| t.split_once(';').map(|(_, l)| l.to_owned());
+        if language(&expected) != language(&actual) {
+            bail!(
+                "Transform is for language {} but the package is {}",
+                expected,
+                actual
+            );
+        }
+    }
+    Ok(())
+}
+
+fn key_condition(table: &TableData, key: &[Value]) -> Result<Expr> {
+    let mut condition: Option<Expr> = None;
+    for (column, value) in table.columns.iter().zip(key) {
+        let value = match value {
+            Value::Null => Expr::null(),
+            Value::Int(i) => Expr::integer(*i),
+            Value::Str(s) => Expr::string(s.clone()),
+        };
+        let term = Expr::col(column.name().to_owned()).eq(value);
+        condition = Some(match condition {
+            Some(condition) => condition.and(term),
+            None => term,
+        });
+    }
+    condition.with_context(|| format!("Table {} has no key", table.name))
+}
+
+fn apply_table<F: Read + Write + Seek>(
+    package: &mut Package<F>,
+    table: &TableData,
+    ops: Vec<RowOp>,
+) -> Result<()> {
+    let keys = rows::key_count(&table.columns);
+    // Read the rows again because creating a table also adds rows to
+    // `_Validation`.
+    let mut existing = package
+        .select_rows(Select::table(table.name.clone()))
+        .with_context(|| format!("Failed to read table {}", table.name))?
+        .map(|row| {
+            let row =
+                (0..row.len()).map(|i| row[i].clone()).collect::<Vec<_>>();
+            (row_key(&row, keys), row)
+        })
+        .collect::<BTreeMap<RowKey, Vec<Value>>>();
+    let describe = |key: &[Value]| {
+        key.iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<String>>()
+            .join("/")
+    };
+
+    for op in ops {
+        match op {
+            RowOp::Insert(values) => {
+                let key = row_key(&values, keys);
+                if table.name == VALIDATION
+                    && existing.get(&key) == Some(&values)
+                {
+                    continue;
+                }
+                if existing.insert(key, values.clone()).is_some() {
+                    bail!(
+                        "Transform adds row {} to table {} which already \
+                        exists",
+                        describe(&values[..keys]),
+                        table.name
+                    );
+                }
+                package
+                    .insert_rows(Insert::into(table.name.clone()).row(values))
+                    .with_context(|| {
+                        format!("Failed to insert row into {}", table.name)
+                    })?;
+            }
+            RowOp::Delete(key) => {
+                if existing.remove(&row_key(&key, key.len())).is_none() {
+                    bail!(
+                        "Transform deletes row {} from table {} which doesn't \
+                        exist",
+                        describe(&key),
+                        table.name
+                    );
+                }
+                package
+                    .delete_rows(
+                        Delete::from(table.name.clone())
+                            .with(key_condition(table, &key)?),
+                    )
+                    .with_context(|| {
+                        format!("Failed to delete row from {}", table.name)
+                    })?;
+            }
+            RowOp::Update { key, changes } => {
+                if !existing.contains_key(&row_key(&key, key.len())) {
+                    bail!(
+                        "Transform updates row {} in table {} which doesn't \
+                        exist",
+                        describe(&key),
+                        table.name
+                    );
+                }
+                let mut update = Update::table(table.name.clone());
+                for (index, value) in changes {
+                    let column = table.columns[index].name().to_owned();
+                    update = update.set(column, value);
+                }
+                package
+                    .update_rows(update.with(key_condition(table, &key)?))
+                    .with_context(|| {
+                        format!("Failed to update row in {}", table.name)
+                    })?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use msi::Package;
+
+    use super::{apply, create};
+    use crate::{
+        command::{differ::diff_packages, transformer::MemoryPackage},
+        modules::helpers::test_dir::TestDir,
+    };
+
+    const PRODUCT_CODE: &str = "{3F2B8C41-7D5E-4A96-B0C3-9E1D2F4A6B57}";
+
+    /// A config of version `version` of the product `product_code`, which
+    /// installs the files in the folder named after the version.
+    fn config(version: &str, product_code: &str) -> String {
+        format!(
+            r#"
+            [product_info]
+            product_name = "Transformed"
+            product_version = "{version}"
+            manufacturer = "Whimsi"
+            product_language = 1033
+            product_code = "{product_code}"
+
+            [summary_info]
+            page_count = 200
+            revision_number = "*"
+            template = "x64;1033"
+            author = "Whimsi"
+
+            [[files]]
+            source = "{version}"
+            target = "INSTALLDIR"
+            "#
+        )
+    }
+
+    fn open(bytes: Vec<u8>) -> MemoryPackage {
+        Package::open(Cursor::new(bytes)).unwrap()
+    }
+
+    /// Build versions 1.0.0 and 1.0.1 of the product, where 1.0.1 changes a
+    /// file and adds another.
+    fn versions(dir: &TestDir) -> (Vec<u8>, Vec<u8>) {
+        dir.file("v1.toml", &config("1.0.0", PRODUCT_CODE));
+        dir.file("v2.toml", &config("1.0.1", PRODUCT_CODE));
+        dir.file("1.0.0/app.txt", "one");
+        dir.file("1.0.1/app.txt", "two, which is longer");
+        dir.file("1.0.1/new.txt", "new");
+        (dir.build("v1.toml", ""), dir.build("v2.toml", ""))
+    }
+
+    #[test]
+    fn applying_a_transform_gives_the_modified_package() {
+        let dir = TestDir::new();
+        let (v1, v2) = versions(&dir);
+        let transform =
+            create(&mut open(v1.clone()), &mut open(v2.clone())).unwrap();
+
+        let mut package = open(v1);
+        let mut comp = cfb::CompoundFile::open(Cursor::new(transform)).unwrap();
+        apply(&mut package, &mut comp).unwrap();
+        let applied = open(package.into_inner().unwrap().into_inner());
+
+        // Transforms leave the summary information alone, so only the tables
+        // and streams have to match.
+        let diff = diff_packages(applied, open(v2)).unwrap();
+        let tables = diff
+            .tables
+            .iter()
+            .map(|t| t.table.as_str())
+            .collect::<Vec<_>>();
+        let streams = diff
+            .streams
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect::<Vec<_>>();
+        assert!(tables.is_empty(), "Tables differ: {tables:?}");
+        assert!(streams.is_empty(), "Streams differ: {streams:?}");
+    }
+
+    #[test]
+    fn rejects_a_package_of_another_product() {
+        let dir = TestDir::new();
+        let (v1, v2) = versions(&dir);
+        let transform = create(&mut open(v1), &mut open(v2)).unwrap();
+
+        dir.file(
+            "other.toml",
+            &config("1.0.0", "{D6E0A7B2-1C4F-4E83-9A5D-2B7F8C3E1A60}"),
+        );
+        let mut other = open(dir.build("other.toml", ""));
+        let mut comp = cfb::CompoundFile::open(Cursor::new(transform)).unwrap();
+        let Err(err) = apply(&mut other, &mut comp) else {
+            panic!("Applied a transform to another product");
+        };
+        assert!(err.to_string().starts_with(&format!(
+            "Transform is for product {PRODUCT_CODE} but the package is"
+        )));
+    }
+}
@@ -0,0 +1,250 @@
+use anyhow::{bail, Result};
+use msi::{Column, ColumnType, Value};
+
+use super::string_pool::StringPool;
+
+/// A single change to a table that a transform records.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum RowOp {
+    /// A new row with a value for every column.
+    Insert(Vec<Value>),
+    /// Remove the row with these primary key values.
+    Delete(Vec<Value>),
+    /// Change some of the columns of the row with these primary key values.
+    Update {
+        key: Vec<Value>,
+        changes: Vec<(usize, Value)>,
+    },
+}
+
+/// A value ready to be written to a table stream. Strings have already been
+/// added to the string pool because the width of a string reference depends
+/// on how many strings the pool ends up holding.
+enum Cell {
+    Int16(u16),
+    Int32(u32),
+    Str(u32),
+}
+
+/// The encoded rows of a single table stream, waiting for the string pool to
+/// be finished.
+pub(crate) struct EncodedRows(Vec<(u16, Vec<Cell>)>);
+
+/// Transform rows start with a 16 bit mask with one bit per column, so only
+/// tables with up to 16 columns can be transformed.
+const MAX_COLUMNS: usize = 16;
+
+/// The number of leading primary key columns. Rows of tables without a
+/// primary key are identified by every column.
+pub(crate) fn key_count(columns: &[Column]) -> usize {
+    match columns.iter().take_while(|c| c.is_primary_key()).count() {
+        0 => columns.len(),
+        n => n,
+    }
+}
+
+/// Encode the changes to a table. Strings are added to `pool`.
+pub(crate) fn encode(
+    table: &str,
+    columns: &[Column],
+    ops: &[RowOp],
+    pool: &mut StringPool,
+) -> Result<EncodedRows> {
+    if columns.len() > MAX_COLUMNS {
+        bail!(
+            "Table {} has {} columns but transforms support at most {}",
+            table,
+            columns.len(),
+            MAX_COLUMNS
+        );
+    }
+    let keys = key_count(columns);
+
+    let mut rows = Vec::new();
+    for op in ops {
+        let (mask, values): (u16, Vec<(usize, &Value)>) = match op {
+            // Inserts set the low bit and store the column count in the high
+            // byte of the mask.
+            RowOp::Insert(values) => (
+                ((columns.len() as u16) << 8) | 1,
+                values.iter().enumerate().collect(),
+            ),
+            RowOp::Delete(key) => (0, key.iter().enumerate().collect()),
+            RowOp::Update { key, changes } => {
+                let mut mask = 0u16;
+                let mut values = key.iter().enumerate().collect::<Vec<_>>();
+                for (index, value) in changes {
+                    if *index < keys {
+                        bail!(
+                            "Table {} can't update a primary key column",
+                            table
+                        );
+                    }
+                    mask |= 1 << index;
+                    values.push((*index, value));
+                }
+                values.sort_by_key(|(index, _)| *index);
+                if mask & 1 != 0 || mask == 0 {
+                    bail!(
+                        "Table {} has an update that can't be encoded",
+                        table
+                    );
+                }
+                (mask, values)
+            }
+        };
+
+        let mut cells = Vec::new();
+        for (index, value) in values {
+            let Some(column) = columns.get(index) else {
+                bail!("Table {} has no column {}", table, index);
+            };
+            cells.push(encode_cell(table, column, value, pool)?);
+        }
+        rows.push((mask, cells));
+    }
+    Ok(EncodedRows(rows))
+}
+
+fn encode_cell(
+    table: &str,
+    column: &Column,
+    value: &Value,
+    pool: &mut StringPool,
+) -> Result<Cell> {
+    // Integers are stored offset by half their range so that zero can
+    // represent null.
+    Ok(match (column.coltype(), value) {
+        (ColumnType::Int16, Value::Null) => Cell::Int16(0),
+        (ColumnType::Int16, Value::Int(i)) => Cell::Int16((*i as u16) ^ 0x8000),
+        (ColumnType::Int32, Value::Null) => Cell::Int32(0),
+        (ColumnType::Int32, Value::Int(i)) => {
+            Cell::Int32((*i as u32) ^ 0x8000_0000)
+        }
+        (ColumnType::Str(_), Value::Null) => Cell::Str(0),
+        (ColumnType::Str(_), Value::Str(s)) => Cell::Str(pool.intern(s)),
+        (_, value) => bail!(
+            "Value {} doesn't match the type of column {}.{}",
+            value,
+            table,
+            column.name()
+        ),
+    })
+}
+
+impl EncodedRows {
+    /// Write the rows using string references of `ref_size` bytes.
+    pub(crate) fn to_bytes(&self, ref_size: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (mask, cells) in &self.0 {
+            out.extend_from_slice(&mask.to_le_bytes());
+            for cell in cells {
+                match cell {
+                    Cell::Int16(v) => out.extend_from_slice(&v.to_le_bytes()),
+                    Cell::Int32(v) => out.extend_from_slice(&v.to_le_bytes()),
+                    Cell::Str(v) => {
+                        out.extend_from_slice(&v.to_le_bytes()[..ref_size])
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Decode the rows of a transform table stream.
+pub(crate) fn decode(
+    table: &str,
+    columns: &[Column],
+    bytes: &[u8],
+    pool: &StringPool,
+) -> Result<Vec<RowOp>> {
+    let keys = key_count(columns);
+    let ref_size = pool.ref_size();
+    let mut reader = Reader { bytes, pos: 0 };
+    let mut ops = Vec::new();
+
+    while !reader.is_empty() {
+        let mask = reader.read(2)? as u16;
+        if mask & 1 != 0 {
+            let count = (mask >> 8) as usize;
+            if count > columns.len() {
+                bail!("Row of table {} has too many columns", table);
+            }
+            let mut values = columns[..count]
+                .iter()
+                .map(|c| read_value(&mut reader, c, pool, ref_size))
+                .collect::<Result<Vec<Value>>>()?;
+            // Columns left out of an insert are null.
+            values.resize(columns.len(), Value::Null);
+            ops.push(RowOp::Insert(values));
+            continue;
+        }
+
+        let mut key = Vec::new();
+        let mut changes = Vec::new();
+        for (index, column) in columns.iter().enumerate() {
+            if index < keys {
+                key.push(read_value(&mut reader, column, pool, ref_size)?);
+            } else if index < 16 && mask & (1 << index) != 0 {
+                let value = read_value(&mut reader, column, pool, ref_size)?;
+                changes.push((index, value));
+            }
+        }
+        if mask == 0 {
+            ops.push(RowOp::Delete(key));
+        } else {
+            ops.push(RowOp::Update { key, changes });
+        }
+    }
+    Ok(ops)
+}
+
+fn read_value(
+    reader: &mut Reader,
+    column: &Column,
+    pool: &StringPool,
+    ref_size: usize,
+) -> Result<Value> {
+    Ok(match column.coltype() {
+        ColumnType::Int16 => match reader.read(2)? as u16 {
+            0 => Value::Null,
+            v => Value::Int((v ^ 0x8000) as i16 as i32),
+        },
+        ColumnType::Int32 => match reader.read(4)? {
+            0 => Value::Null,
+            v => Value::Int((v ^ 0x8000_0000) as i32),
+        },
+        ColumnType::Str(_) => match pool.get(reader.read(ref_size)?) {
+            Some(s) => Value::Str(s.to_owned()),
+            None => Value::Null,
+        },
+    })
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    /// Read a little endian integer of `size` bytes.
+    fn read(&mut self, size: usize) -> Result<u32> {
+        let Some(bytes) = self.bytes.get(self.pos..self.pos + size) else {
+            bail!("Transform data ends in the middle of a row");
+        };
+        self.pos += size;
+        Ok(bytes
+            .iter()
+            .rev()
+            .fold(0u32, |acc, b| (acc << 8) | *b as u32))
+    }
+}
@@ -0,0 +1,138 @@
+//! The `_Tables` and `_Columns` streams of a transform, which record tables
+//! that are added or removed.
+
+use anyhow::{bail, Result};
+use msi::{Category, Column, ColumnType, Value};
+
+use super::rows::RowOp;
+
+pub(crate) const TABLES: &str = "_Tables";
+pub(crate) const COLUMNS: &str = "_Columns";
+
+const COL_FIELD_SIZE_MASK: i32 = 0xFF;
+const COL_VALID_BIT: i32 = 0x100;
+const COL_LOCALIZABLE_BIT: i32 = 0x200;
+const COL_NONBINARY_BIT: i32 = 0x400;
+const COL_STRING_BIT: i32 = 0x800;
+const COL_NULLABLE_BIT: i32 = 0x1000;
+const COL_PRIMARY_KEY_BIT: i32 = 0x2000;
+
+/// Columns of the `_Tables` table.
+pub(crate) fn tables_columns() -> Vec<Column> {
+    vec![Column::build("Name").primary_key().string(64)]
+}
+
+/// Columns of the `_Columns` table.
+pub(crate) fn columns_columns() -> Vec<Column> {
+    vec![
+        Column::build("Table").primary_key().string(64),
+        Column::build("Number").primary_key().int16(),
+        Column::build("Name").string(64),
+        Column::build("Type").int16(),
+    ]
+}
+
+/// The rows that add a table to `_Tables` and its columns to `_Columns`.
+pub(crate) fn add_table(
+    table: &str,
+    columns: &[Column],
+) -> (RowOp, Vec<RowOp>) {
+    let column_rows = columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            RowOp::Insert(vec![
+                Value::from(table),
+                Value::Int(index as i32 + 1),
+                Value::from(column.name()),
+                Value::Int(type_bits(column)),
+            ])
+        })
+        .collect();
+    (RowOp::Insert(vec![Value::from(table)]), column_rows)
+}
+
+/// The type of a column as stored in `_Columns`.
+fn type_bits(column: &Column) -> i32 {
+    let mut bits = COL_VALID_BIT;
+    bits |= match column.coltype() {
+        ColumnType::Int16 => COL_NONBINARY_BIT | 2,
+        ColumnType::Int32 => 4,
+        ColumnType::Str(_) if column.category() == Some(Category::Binary) => {
+            COL_STRING_BIT
+        }
+        ColumnType::Str(width) => {
+            COL_STRING_BIT | COL_NONBINARY_BIT | width as i32
+        }
+    };
+    if column.is_localizable() {
+        bits |= COL_LOCALIZABLE_BIT;
+    }
+    if column.is_nullable() {
+        bits |= COL_NULLABLE_BIT;
+    }
+    if column.is_primary_key() {
+        bits |= COL_PRIMARY_KEY_BIT;
+    }
+    bits
+}
+
+/// Build a column from its name and the type stored in `_Columns`.
+pub(crate) fn column_from_bits(name: &str, bits: i32) -> Column {
+    let mut builder = Column::build(name.to_owned());
+    if bits & COL_PRIMARY_KEY_BIT != 0 {
+        builder = builder.primary_key();
+    }
+    if bits & COL_NULLABLE_BIT != 0 {
+        builder = builder.nullable();
+    }
+    if bits & COL_LOCALIZABLE_BIT != 0 {
+        builder = builder.localizable();
+    }
+    let size = bits & COL_FIELD_SIZE_MASK;
+    if bits & COL_STRING_BIT == 0 {
+        match size {
+            2 => builder.int16(),
+            _ => builder.int32(),
+        }
+    } else if bits & COL_NONBINARY_BIT == 0 {
+        builder.binary()
+    } else {
+        builder.string(size as usize)
+    }
+}
+
+/// Collect the columns of added tables from the rows of `_Columns`, ordered
+/// by their column number.
+pub(crate) fn added_columns(
+    ops: Vec<RowOp>,
+) -> Result<Vec<(String, Vec<Column>)>> {
+    let mut tables: Vec<(String, Vec<(i32, Column)>)> = Vec::new();
+    for op in ops {
+        let values = match op {
+            RowOp::Insert(values) => values,
+            // Columns are removed along with the table they belong to.
+            RowOp::Delete(_) => continue,
+            RowOp::Update { .. } => {
+                bail!("Transform changes columns, which is not supported")
+            }
+        };
+        let [Value::Str(table), Value::Int(number), Value::Str(name), Value::Int(bits)] =
+            values.as_slice()
+        else {
+            bail!("Transform has a malformed row in {}", COLUMNS);
+        };
+        let column = (*number, column_from_bits(name, *bits));
+        match tables.iter_mut().find(|(t, _)| t == table) {
+            Some((_, columns)) => columns.push(column),
+            None => tables.push((table.clone(), vec![column])),
+        }
+    }
+    Ok(tables
+        .into_iter()
+        .map(|(table, mut columns)| {
+            columns.sort_by_key(|(number, _)| *number);
+            (table, columns.into_iter().map(|(_, c)| c).collect())
+        })
+        .collect())
+}
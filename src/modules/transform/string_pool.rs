@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use msi::CodePage;
+
+/// Flag set on the codepage in `_StringPool` when string references take
+/// three bytes instead of two.
+const LONG_STRING_REFS: u32 = 0x8000_0000;
+
+/// The strings referenced by the rows of a transform.
+///
+/// Every storage in an MSI has its own `_StringPool` and `_StringData`
+/// streams, and table rows refer to strings by their one-based index into
+/// the pool. Index zero is a null string.
+pub(crate) struct StringPool {
+    codepage: CodePage,
+    strings: Vec<String>,
+    refcounts: Vec<u32>,
+    index: HashMap<String, u32>,
+    /// Set when a pool that was read back used three byte references.
+    read_long_refs: bool,
+}
+
+impl StringPool {
+    pub(crate) fn new(codepage: CodePage) -> Self {
+        StringPool {
+            codepage,
+            strings: Vec::new(),
+            refcounts: Vec::new(),
+            index: HashMap::new(),
+            read_long_refs: false,
+        }
+    }
+
+    /// Add a reference to `string` and return its index.
+    pub(crate) fn intern(&mut self, string: &str) -> u32 {
+        if string.is_empty() {
+            return 0;
+        }
+        if let Some(&index) = self.index.get(string) {
+            self.refcounts[index as usize - 1] += 1;
+            return index;
+        }
+        self.strings.push(string.to_owned());
+        self.refcounts.push(1);
+        let index = self.strings.len() as u32;
+        self.index.insert(string.to_owned(), index);
+        index
+    }
+
+    /// The string at a one-based index.
+    pub(crate) fn get(&self, index: u32) -> Option<&str> {
+        match index {
+            0 => None,
+            i => self.strings.get(i as usize - 1).map(String::as_str),
+        }
+    }
+
+    /// Whether string references need three bytes.
+    pub(crate) fn long_refs(&self) -> bool {
+        self.read_long_refs || self.strings.len() > 0xFFFF
+    }
+
+    /// The number of bytes every string reference takes in a table stream.
+    pub(crate) fn ref_size(&self) -> usize {
+        if self.long_refs() {
+            3
+        } else {
+            2
+        }
+    }
+
+    /// Serialize the pool into the contents of the `_StringPool` and
+    /// `_StringData` streams.
+    pub(crate) fn write(&self) -> (Vec<u8>, Vec<u8>) {
+        let mut pool = Vec::new();
+        let mut data = Vec::new();
+
+        let mut codepage = self.codepage.id() as u32;
+        if self.long_refs() {
+            codepage |= LONG_STRING_REFS;
+        }
+        pool.extend_from_slice(&codepage.to_le_bytes());
+
+        for (string, refcount) in self.strings.iter().zip(&self.refcounts) {
+            let bytes = self.codepage.encode(string);
+            let length = bytes.len() as u32;
+            let refcount = (*refcount).min(u16::MAX as u32) as u16;
+            if length > 0xFFFF {
+                // Long strings take two entries, the first of which has a
+                // length of zero and the high half of the real length.
+                pool.extend_from_slice(&0u16.to_le_bytes());
+                pool.extend_from_slice(&((length >> 16) as u16).to_le_bytes());
+            }
+            pool.extend_from_slice(&(length as u16).to_le_bytes());
+            pool.extend_from_slice(&refcount.to_le_bytes());
+            data.extend_from_slice(&bytes);
+        }
+
+        (pool, data)
+    }
+
+    /// Read a pool back from the contents of the `_StringPool` and
+    /// `_StringData` streams.
+    pub(crate) fn read(pool: &[u8], data: &[u8]) -> Result<Self> {
+        if pool.len() < 4 {
+            bail!("String pool is too short to hold a codepage");
+        }
+        let codepage_id =
+            u32::from_le_bytes([pool[0], pool[1], pool[2], pool[3]]);
+        let codepage =
+            CodePage::from_id((codepage_id & !LONG_STRING_REFS) as i32)
+                .unwrap_or(CodePage::Windows1252);
+
+        let entries = pool[4..]
+            .chunks_exact(4)
+            .map(|c| {
+                (
+                    u16::from_le_bytes([c[0], c[1]]) as u32,
+                    u16::from_le_bytes([c[2], c[3]]) as u32,
+                )
+            })
+            .collect::<Vec<(u32, u32)>>();
+
+        let mut result = StringPool::new(codepage);
+        result.read_long_refs = codepage_id & LONG_STRING_REFS != 0;
+        let mut offset = 0;
+        let mut i = 0;
+        while i < entries.len() {
+            let (mut length, mut refcount) = entries[i];
+            if length == 0 && refcount > 0 && i + 1 < entries.len() {
+                length = (refcount << 16) | entries[i + 1].0;
+                refcount = entries[i + 1].1;
+                i += 1;
+            }
+            i += 1;
+
+            let end = offset + length as usize;
+            let Some(bytes) = data.get(offset..end) else {
+                bail!(
+                    "String data ends before string {}",
+                    result.strings.len() + 1
+                );
+            };
+            offset = end;
+
+            // Unused entries keep their slot so later indices still line up.
+            let string = result.codepage.decode(bytes);
+            result.strings.push(string.clone());
+            result.refcounts.push(refcount);
+            result
+                .index
+                .entry(string)
+                .or_insert(result.strings.len() as u32);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use msi::CodePage;
+
+    use super::StringPool;
+
+    #[test]
+    fn round_trips_strings_and_refcounts() {
+        let mut pool = StringPool::new(CodePage::Windows1252);
+        assert_eq!(pool.intern(""), 0);
+        assert_eq!(pool.intern("File"), 1);
+        assert_eq!(pool.intern("Component"), 2);
+        assert_eq!(pool.intern("File"), 1);
+
+        let (bytes, data) = pool.write();
+        assert_eq!(&bytes[..4], &1252u32.to_le_bytes());
+        assert_eq!(&bytes[4..], &[4, 0, 2, 0, 9, 0, 1, 0]);
+        assert_eq!(data, b"FileComponent");
+
+        let read = StringPool::read(&bytes, &data).unwrap();
+        assert_eq!(read.get(0), None);
+        assert_eq!(read.get(1), Some("File"));
+        assert_eq!(read.get(2), Some("Component"));
+        assert_eq!(read.get(3), None);
+        assert_eq!(read.ref_size(), 2);
+        assert_eq!(read.write(), (bytes, data));
+    }
+
+    #[test]
+    fn round_trips_strings_longer_than_a_length_entry() {
+        let long = "x".repeat(0x1_0002);
+        let mut pool = StringPool::new(CodePage::Windows1252);
+        pool.intern(&long);
+        pool.intern("after");
+
+        let (bytes, data) = pool.write();
+        // A zero length entry holds the high half of the length.
+        assert_eq!(&bytes[4..12], &[0, 0, 1, 0, 2, 0, 1, 0]);
+        let read = StringPool::read(&bytes, &data).unwrap();
+        assert_eq!(read.get(1), Some(long.as_str()));
+        assert_eq!(read.get(2), Some("after"));
+    }
+
+    #[test]
+    fn keeps_the_long_reference_flag() {
+        let mut bytes = (1252u32 | 0x8000_0000).to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[1, 0, 1, 0]);
+        let read = StringPool::read(&bytes, b"a").unwrap();
+        assert_eq!(read.ref_size(), 3);
+        assert_eq!(read.write().0, bytes);
+    }
+
+    #[test]
+    fn rejects_truncated_streams() {
+        let error =
+            |pool: &[u8], data: &[u8]| match StringPool::read(pool, data) {
+                Ok(_) => panic!("Read a truncated string pool"),
+                Err(err) => err.to_string(),
+            };
+        assert_eq!(
+            error(&[0xe4, 0x04], b""),
+            "String pool is too short to hold a codepage"
+        );
+        let mut bytes = 1252u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[3, 0, 1, 0, 5, 0, 1, 0]);
+        assert_eq!(error(&bytes, b"abcde"), "String data ends before string 2");
+    }
+}
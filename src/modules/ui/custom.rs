@@ -0,0 +1,438 @@
+//! Dialogs of the user's own, from the `[[dialog]]` entries of the config.
+
+use std::collections::BTreeSet;
+
+use anyhow::{bail, Context, Result};
+use msi::Value;
+
+use crate::modules::{
+    config::dialog::{
+        ControlConfig, ControlKind, DialogConfig, EventKind, OptionConfig,
+    },
+    tables::value_check::SourcedRow,
+};
+
+use super::{
+    dialog::{Control, Dialog, Event, ENABLED, SUNKEN, VISIBLE},
+    wizard::{self, Wizard},
+};
+
+/// Height of a radio button that doesn't give its own.
+const RADIO_BUTTON_HEIGHT: i32 = 15;
+
+/// Rows for the tables other than the dialog tables that the dialogs need.
+#[derive(Default)]
+pub(crate) struct CustomRows {
+    pub(crate) radio_buttons: Vec<SourcedRow>,
+    pub(crate) combo_boxes: Vec<SourcedRow>,
+    /// Initial values of the properties the controls are bound to.
+    pub(crate) properties: Vec<(String, String)>,
+}
+
+/// Add the dialogs to `wizard`. `properties` are the properties the package
+/// already has a value for.
+pub(crate) fn add_dialogs(
+    wizard: &mut Wizard,
+    configs: &[DialogConfig],
+    properties: &BTreeSet<String>,
+) -> Result<CustomRows> {
+    let mut dialogs = wizard.ids().map(str::to_owned).collect::<BTreeSet<_>>();
+    for config in configs {
+        if !dialogs.insert(config.id.clone()) {
+            bail!("Dialog {} is defined more than once", config.id);
+        }
+    }
+    // Events can set any property that a control takes its value from,
+    // whichever dialog the control is on.
+    let mut known = properties.clone();
+    known.extend(
+        configs
+            .iter()
+            .flat_map(|d| &d.control)
+            .filter(|c| takes_value(c.kind))
+            .filter_map(|c| c.property.clone()),
+    );
+
+    let mut rows = CustomRows::default();
+    for config in configs {
+        let dialog = build_dialog(config, &dialogs, &known, &mut rows)
+            .with_context(|| format!("Invalid dialog {}", config.id))?;
+        match (&config.after, &config.before) {
+            (Some(_), Some(_)) => bail!(
+                "Dialog {} can't be placed both after and before a page",
+                config.id
+            ),
+            (Some(anchor), None) => wizard.insert(dialog, anchor, true)?,
+            (None, Some(anchor)) => wizard.insert(dialog, anchor, false)?,
+            (None, None) => wizard.dialogs.push(dialog),
+        }
+    }
+
+    let mut valued = BTreeSet::new();
+    for (property, _) in &rows.properties {
+        if properties.contains(property) || !valued.insert(property) {
+            bail!("Property {} is given more than one value", property);
+        }
+    }
+    Ok(rows)
+}
+
+fn build_dialog(
+    config: &DialogConfig,
+    dialogs: &BTreeSet<String>,
+    properties: &BTreeSet<String>,
+    rows: &mut CustomRows,
+) -> Result<Dialog> {
+    let is_page = config.after.is_some() || config.before.is_some();
+    let title = config.title.as_deref().unwrap_or("[ProductName] Setup");
+    let mut dialog = if is_page {
+        if config.width.is_some() || config.height.is_some() {
+            bail!("Pages have the size of the wizard");
+        }
+        if config.default_control.is_some() || config.cancel_control.is_some() {
+            bail!("Pages use the Next and Cancel buttons of the wizard");
+        }
+        let description = config.description.as_deref().unwrap_or_default();
+        wizard::page(&config.id, title, description)
+    } else {
+        let mut dialog = Dialog::wizard(&config.id, title);
+        dialog.width = config.width.unwrap_or(dialog.width);
+        dialog.height = config.height.unwrap_or(dialog.height);
+        dialog.default = config.default_control.clone();
+        dialog.cancel = config.cancel_control.clone();
+        dialog
+    };
+
+    for control in &config.control {
+        let control = build_control(control, dialogs, properties, rows)
+            .with_context(|| format!("Invalid control {}", control.id))?;
+        dialog.add(control);
+    }
+    if is_page {
+        wizard::navigation(&mut dialog, "&Next");
+    }
+
+    let mut ids = BTreeSet::new();
+    for control in &dialog.controls {
+        if !ids.insert(control.id.as_str()) {
+            bail!(
+                "Control {} is defined more than once or has the name of a \
+                control the wizard adds",
+                control.id
+            );
+        }
+    }
+    if ids.is_empty() {
+        bail!("Dialog has no controls");
+    }
+    for id in [&dialog.default, &dialog.cancel].into_iter().flatten() {
+        if !ids.contains(id.as_str()) {
+            bail!("Control {} is not on the dialog", id);
+        }
+    }
+    Ok(dialog)
+}
+
+fn build_control(
+    config: &ControlConfig,
+    dialogs: &BTreeSet<String>,
+    properties: &BTreeSet<String>,
+    rows: &mut CustomRows,
+) -> Result<Control> {
+    let mut attributes = 0;
+    for (set, attribute) in [
+        (config.visible, VISIBLE),
+        (config.enabled, ENABLED),
+        (config.sunken, SUNKEN),
+    ] {
+        if set {
+            attributes |= attribute;
+        }
+    }
+    let geometry = (config.x, config.y, config.width, config.height);
+    // The control types are named as in the `Control` table.
+    let kind = format!("{:?}", config.kind);
+    let mut control = Control::new(&config.id, &kind, geometry, attributes);
+    if let Some(text) = &config.text {
+        control = control.text(text);
+    }
+
+    match (&config.property, &config.value) {
+        (None, _) if takes_value(config.kind) => {
+            bail!("{} controls need a property", kind)
+        }
+        (None, Some(_)) => bail!("A value can only be given with a property"),
+        (None, None) => {}
+        (Some(property), value) => {
+            control = control.property(property);
+            match value.as_deref() {
+                Some("") => {
+                    bail!("The value of property {} is empty", property)
+                }
+                Some(value) => {
+                    rows.properties.push((property.clone(), value.to_owned()))
+                }
+                None => {}
+            }
+        }
+    }
+
+    let property = config.property.as_deref().unwrap_or_default();
+    match config.kind {
+        ControlKind::RadioButtonGroup | ControlKind::ComboBox
+            if config.option.is_empty() =>
+        {
+            bail!("{} controls need at least one option", kind)
+        }
+        ControlKind::RadioButtonGroup => rows
+            .radio_buttons
+            .extend(radio_button_rows(config, property)),
+        ControlKind::ComboBox => rows
+            .combo_boxes
+            .extend(combo_box_rows(&config.option, property)),
+        _ if !config.option.is_empty() => {
+            bail!("Only RadioButtonGroup and ComboBox controls have options")
+        }
+        _ => {}
+    }
+    add_events(control, config, dialogs, properties)
+}
+
+/// Whether the user sets the property of a control of this kind. `Text`
+/// and `PushButton` controls never change their property.
+fn takes_value(kind: ControlKind) -> bool {
+    !matches!(kind, ControlKind::Text | ControlKind::PushButton)
+}
+
+/// Add the events and conditions of a control, checking that the dialogs
+/// and properties they name exist.
+fn add_events(
+    mut control: Control,
+    config: &ControlConfig,
+    dialogs: &BTreeSet<String>,
+    properties: &BTreeSet<String>,
+) -> Result<Control> {
+    if !config.event.is_empty()
+        && !matches!(
+            config.kind,
+            ControlKind::PushButton | ControlKind::CheckBox
+        )
+    {
+        bail!("Only PushButton and CheckBox controls publish events");
+    }
+    for event in &config.event {
+        let (name, argument) = match &event.kind {
+            EventKind::NewDialog { dialog } => {
+                if !dialogs.contains(dialog) {
+                    bail!(
+                        "NewDialog names dialog {} which doesn't exist",
+                        dialog
+                    );
+                }
+                ("NewDialog".to_owned(), dialog.clone())
+            }
+            EventKind::EndDialog { action } => {
+                ("EndDialog".to_owned(), format!("{action:?}"))
+            }
+            EventKind::DoAction { action } => {
+                ("DoAction".to_owned(), action.clone())
+            }
+            EventKind::SetProperty { property, value } => {
+                if !properties.contains(property) {
+                    bail!(
+                        "SetProperty sets property {} which has no value in \
+                        the Property table and isn't set by a control",
+                        property
+                    );
+                }
+                // An argument of `{}` clears the property.
+                let value = match value.as_str() {
+                    "" => "{}".to_owned(),
+                    value => value.to_owned(),
+                };
+                (format!("[{property}]"), value)
+            }
+        };
+        let mut published = Event::new(&name, &argument);
+        if let Some(condition) = &event.condition {
+            published.condition = condition.clone();
+        }
+        control = control.event(published);
+    }
+    for condition in &config.condition {
+        control = control.condition(
+            &format!("{:?}", condition.action),
+            &condition.condition,
+        );
+    }
+    Ok(control)
+}
+
+fn radio_button_rows(
+    config: &ControlConfig,
+    property: &str,
+) -> Vec<SourcedRow> {
+    config
+        .option
+        .iter()
+        .enumerate()
+        .map(|(index, option)| {
+            let height = option.height.unwrap_or(RADIO_BUTTON_HEIGHT);
+            SourcedRow::new(
+                vec![
+                    Value::from(property),
+                    Value::from(index as i32 + 1),
+                    Value::from(option.value.as_str()),
+                    Value::from(option.x.unwrap_or(0)),
+                    Value::from(option.y.unwrap_or(index as i32 * height)),
+                    Value::from(option.width.unwrap_or(config.width)),
+                    Value::from(height),
+                    Value::from(option.text.as_str()),
+                    Value::Null,
+                ],
+                None,
+            )
+        })
+        .collect()
+}
+
+fn combo_box_rows(options: &[OptionConfig], property: &str) -> Vec<SourcedRow> {
+    options
+        .iter()
+        .enumerate()
+        .map(|(index, option)| {
+            SourcedRow::new(
+                vec![
+                    Value::from(property),
+                    Value::from(index as i32 + 1),
+                    Value::from(option.value.as_str()),
+                    Value::from(option.text.as_str()),
+                ],
+                None,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use serde::Deserialize;
+
+    use super::add_dialogs;
+    use crate::modules::{
+        config::{dialog::DialogConfig, ui::UiSet},
+        ui::wizard::Wizard,
+    };
+
+    #[derive(Deserialize)]
+    struct Config {
+        dialog: Vec<DialogConfig>,
+    }
+
+    /// A page asking for a server URL, which can be cleared by a button or
+    /// checked in a dialog of its own.
+    const SERVER_PAGE: &str = r#"
+        [[dialog]]
+        id = "ServerDlg"
+        after = "WelcomeDlg"
+
+        [[dialog.control]]
+        id = "Url"
+        type = "Edit"
+        x = 20
+        y = 60
+        width = 320
+        height = 18
+        property = "SERVER_URL"
+        value = "https://example.com"
+
+        [[dialog.control]]
+        id = "Clear"
+        type = "PushButton"
+        x = 20
+        y = 80
+        width = 56
+        height = 17
+        text = "Clear"
+        event = [
+            { event = "SetProperty", property = "SERVER_URL", value = "" },
+            { event = "NewDialog", dialog = "CheckDlg" },
+        ]
+
+        [[dialog]]
+        id = "CheckDlg"
+        default_control = "Ok"
+
+        [[dialog.control]]
+        id = "Ok"
+        type = "PushButton"
+        x = 20
+        y = 20
+        width = 56
+        height = 17
+        event = [{ event = "EndDialog", action = "Return" }]
+    "#;
+
+    /// Add the dialogs of `config` to the minimal wizard and return the
+    /// identifiers of its pages.
+    fn add(config: &str) -> anyhow::Result<Vec<String>> {
+        let config = toml::from_str::<Config>(config).unwrap();
+        let mut wizard = Wizard::standard(UiSet::Minimal, None);
+        let known = BTreeSet::from(["ProductName".to_owned()]);
+        let rows = add_dialogs(&mut wizard, &config.dialog, &known)?;
+        assert_eq!(
+            rows.properties,
+            [("SERVER_URL".to_owned(), "https://example.com".to_owned())]
+        );
+        Ok(wizard.pages.iter().map(|p| p.id.clone()).collect())
+    }
+
+    fn error(config: &str) -> String {
+        format!("{:#}", add(config).unwrap_err())
+    }
+
+    #[test]
+    fn adds_pages_and_dialogs() {
+        assert_eq!(
+            add(SERVER_PAGE).unwrap(),
+            ["WelcomeDlg", "ServerDlg", "VerifyReadyDlg"]
+        );
+    }
+
+    #[test]
+    fn new_dialog_needs_an_existing_dialog() {
+        let config = SERVER_PAGE
+            .replace(r#"dialog = "CheckDlg""#, r#"dialog = "MissingDlg""#);
+        assert_eq!(
+            error(&config),
+            "Invalid dialog ServerDlg: Invalid control Clear: NewDialog names \
+            dialog MissingDlg which doesn't exist"
+        );
+    }
+
+    #[test]
+    fn set_property_needs_a_control_that_sets_it() {
+        // A Text control only shows its property.
+        let config =
+            SERVER_PAGE.replace(r#"type = "Edit""#, r#"type = "Text""#);
+        assert_eq!(
+            error(&config),
+            "Invalid dialog ServerDlg: Invalid control Clear: SetProperty \
+            sets property SERVER_URL which has no value in the Property table \
+            and isn't set by a control"
+        );
+    }
+
+    #[test]
+    fn pages_go_next_to_pages_of_the_wizard() {
+        let config = SERVER_PAGE
+            .replace(r#"after = "WelcomeDlg""#, r#"before = "ExitDlg""#);
+        assert_eq!(
+            error(&config),
+            "Page ServerDlg can't be placed next to ExitDlg, which is not a \
+            page of the wizard"
+        );
+    }
+}
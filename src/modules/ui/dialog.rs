@@ -0,0 +1,303 @@
+//! A description of dialogs that compiles into the `Dialog`, `Control`,
+//! `ControlEvent`, `ControlCondition` and `EventMapping` tables.
+
+use msi::Value;
+
+use crate::modules::tables::value_check::SourcedRow;
+
+/// `msidbDialogAttributesVisible | msidbDialogAttributesModal`
+pub(crate) const DIALOG_MODAL: i32 = 0x3;
+/// `msidbDialogAttributesError`
+pub(crate) const DIALOG_ERROR: i32 = 0x10000;
+
+/// `msidbControlAttributesVisible`
+pub(crate) const VISIBLE: i32 = 0x1;
+/// `msidbControlAttributesEnabled`
+pub(crate) const ENABLED: i32 = 0x2;
+/// `msidbControlAttributesSunken`
+pub(crate) const SUNKEN: i32 = 0x4;
+/// `msidbControlAttributesIndirect`
+pub(crate) const INDIRECT: i32 = 0x8;
+/// `msidbControlAttributesTransparent`
+pub(crate) const TRANSPARENT: i32 = 0x10000;
+/// `msidbControlAttributesNoPrefix`
+pub(crate) const NO_PREFIX: i32 = 0x20000;
+/// `msidbControlAttributesProgress95`
+pub(crate) const PROGRESS_95: i32 = 0x10000;
+/// `msidbControlAttributesFixedVolume | msidbControlAttributesRemoteVolume`
+pub(crate) const FIXED_AND_REMOTE: i32 = 0x60000;
+
+/// Size of every wizard dialog, in dialog units.
+pub(crate) const WIDTH: i32 = 370;
+pub(crate) const HEIGHT: i32 = 270;
+
+/// A dialog and all of its controls.
+pub(crate) struct Dialog {
+    pub(crate) id: String,
+    pub(crate) title: String,
+    pub(crate) width: i32,
+    pub(crate) height: i32,
+    pub(crate) attributes: i32,
+    pub(crate) controls: Vec<Control>,
+    /// Control activated by the Enter key.
+    pub(crate) default: Option<String>,
+    /// Control activated by the Escape key.
+    pub(crate) cancel: Option<String>,
+}
+
+/// A control on a dialog. Controls that take focus are linked into the tab
+/// order in the order they are listed.
+pub(crate) struct Control {
+    pub(crate) id: String,
+    pub(crate) kind: String,
+    pub(crate) x: i32,
+    pub(crate) y: i32,
+    pub(crate) width: i32,
+    pub(crate) height: i32,
+    pub(crate) attributes: i32,
+    pub(crate) property: Option<String>,
+    pub(crate) text: Option<String>,
+    pub(crate) events: Vec<Event>,
+    /// Pairs of an action, such as `Disable` or `Hide`, and the condition
+    /// under which it is taken.
+    pub(crate) conditions: Vec<(String, String)>,
+    /// Pairs of a control event the control subscribes to and the attribute
+    /// that the event updates.
+    pub(crate) subscriptions: Vec<(String, String)>,
+}
+
+/// An event published when a push button, check box or selection tree is
+/// used. Events run in the order they are listed.
+pub(crate) struct Event {
+    pub(crate) event: String,
+    pub(crate) argument: String,
+    pub(crate) condition: String,
+}
+
+impl Event {
+    /// An event that is always published.
+    pub(crate) fn new(event: &str, argument: &str) -> Self {
+        Event {
+            event: event.to_owned(),
+            argument: argument.to_owned(),
+            condition: "1".to_owned(),
+        }
+    }
+}
+
+impl Dialog {
+    /// A modal wizard dialog of the standard size.
+    pub(crate) fn wizard(id: &str, title: &str) -> Self {
+        Dialog {
+            id: id.to_owned(),
+            title: title.to_owned(),
+            width: WIDTH,
+            height: HEIGHT,
+            attributes: DIALOG_MODAL,
+            controls: Vec::new(),
+            default: None,
+            cancel: None,
+        }
+    }
+
+    pub(crate) fn control_mut(&mut self, id: &str) -> Option<&mut Control> {
+        self.controls.iter_mut().find(|c| c.id == id)
+    }
+
+    pub(crate) fn add(&mut self, control: Control) -> &mut Control {
+        self.controls.push(control);
+        self.controls.last_mut().expect("A control was just added")
+    }
+
+    /// The row of this dialog in the `Dialog` table.
+    pub(crate) fn dialog_row(&self) -> SourcedRow {
+        let tab_order = self.tab_order();
+        // A dialog without a control that takes focus still has to name
+        // one, so fall back to the first control.
+        let first = tab_order
+            .first()
+            .copied()
+            .or_else(|| self.controls.first().map(|c| c.id.as_str()))
+            .unwrap_or_default();
+        SourcedRow::new(
+            vec![
+                Value::from(self.id.as_str()),
+                Value::from(50),
+                Value::from(50),
+                Value::from(self.width),
+                Value::from(self.height),
+                Value::from(self.attributes),
+                Value::from(self.title.as_str()),
+                Value::from(first),
+                optional(self.default.as_deref()),
+                optional(self.cancel.as_deref()),
+            ],
+            None,
+        )
+    }
+
+    /// The rows of this dialog's controls in the `Control` table.
+    pub(crate) fn control_rows(&self) -> Vec<SourcedRow> {
+        let tab_order = self.tab_order();
+        self.controls
+            .iter()
+            .map(|control| {
+                let next = tab_order
+                    .iter()
+                    .position(|id| *id == control.id)
+                    .filter(|_| tab_order.len() > 1)
+                    .map(|i| tab_order[(i + 1) % tab_order.len()]);
+                SourcedRow::new(
+                    vec![
+                        Value::from(self.id.as_str()),
+                        Value::from(control.id.as_str()),
+                        Value::from(control.kind.as_str()),
+                        Value::from(control.x),
+                        Value::from(control.y),
+                        Value::from(control.width),
+                        Value::from(control.height),
+                        Value::from(control.attributes),
+                        optional(control.property.as_deref()),
+                        optional(control.text.as_deref()),
+                        optional(next),
+                        Value::Null,
+                    ],
+                    None,
+                )
+            })
+            .collect()
+    }
+
+    pub(crate) fn event_rows(&self) -> Vec<SourcedRow> {
+        let mut rows = Vec::new();
+        for control in &self.controls {
+            for (ordering, event) in control.events.iter().enumerate() {
+                rows.push(SourcedRow::new(
+                    vec![
+                        Value::from(self.id.as_str()),
+                        Value::from(control.id.as_str()),
+                        Value::from(event.event.as_str()),
+                        Value::from(event.argument.as_str()),
+                        Value::from(event.condition.as_str()),
+                        Value::from(ordering as i32 + 1),
+                    ],
+                    None,
+                ));
+            }
+        }
+        rows
+    }
+
+    pub(crate) fn condition_rows(&self) -> Vec<SourcedRow> {
+        self.controls
+            .iter()
+            .flat_map(|control| {
+                control.conditions.iter().map(|(action, condition)| {
+                    SourcedRow::new(
+                        vec![
+                            Value::from(self.id.as_str()),
+                            Value::from(control.id.as_str()),
+                            Value::from(action.as_str()),
+                            Value::from(condition.as_str()),
+                        ],
+                        None,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    pub(crate) fn mapping_rows(&self) -> Vec<SourcedRow> {
+        self.controls
+            .iter()
+            .flat_map(|control| {
+                control.subscriptions.iter().map(|(event, attribute)| {
+                    SourcedRow::new(
+                        vec![
+                            Value::from(self.id.as_str()),
+                            Value::from(control.id.as_str()),
+                            Value::from(event.as_str()),
+                            Value::from(attribute.as_str()),
+                        ],
+                        None,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// The controls that take focus, in the order they are listed.
+    fn tab_order(&self) -> Vec<&str> {
+        self.controls
+            .iter()
+            .filter(|c| c.takes_focus())
+            .map(|c| c.id.as_str())
+            .collect()
+    }
+}
+
+impl Control {
+    pub(crate) fn new(
+        id: &str,
+        kind: &str,
+        (x, y, width, height): (i32, i32, i32, i32),
+        attributes: i32,
+    ) -> Self {
+        Control {
+            id: id.to_owned(),
+            kind: kind.to_owned(),
+            x,
+            y,
+            width,
+            height,
+            attributes,
+            property: None,
+            text: None,
+            events: Vec::new(),
+            conditions: Vec::new(),
+            subscriptions: Vec::new(),
+        }
+    }
+
+    pub(crate) fn text(mut self, text: &str) -> Self {
+        self.text = Some(text.to_owned());
+        self
+    }
+
+    pub(crate) fn property(mut self, property: &str) -> Self {
+        self.property = Some(property.to_owned());
+        self
+    }
+
+    pub(crate) fn event(mut self, event: Event) -> Self {
+        self.events.push(event);
+        self
+    }
+
+    pub(crate) fn condition(mut self, action: &str, condition: &str) -> Self {
+        self.conditions
+            .push((action.to_owned(), condition.to_owned()));
+        self
+    }
+
+    pub(crate) fn subscribe(mut self, event: &str, attribute: &str) -> Self {
+        self.subscriptions
+            .push((event.to_owned(), attribute.to_owned()));
+        self
+    }
+
+    /// Whether the control can be reached with the Tab key.
+    fn takes_focus(&self) -> bool {
+        !matches!(
+            self.kind.as_str(),
+            "Text" | "Line" | "Bitmap" | "Icon" | "GroupBox" | "ProgressBar"
+        ) && self.attributes & VISIBLE != 0
+    }
+}
+
+fn optional(value: Option<&str>) -> Value {
+    match value {
+        Some(value) => Value::from(value),
+        None => Value::Null,
+    }
+}
@@ -0,0 +1,453 @@
+//! The tables of the built-in wizard UI.
+//!
+//! Each UI set is described as a list of [`dialog::Dialog`]s by the
+//! [`wizard`] module, joined by the dialogs from the config in [`custom`],
+//! and then written out to the UI tables along with the text styles, strings
+//! and bitmaps the dialogs use.
+
+mod custom;
+mod dialog;
+mod resources;
+mod wizard;
+
+use std::{collections::BTreeSet, io::Write};
+
+use anyhow::{bail, Context, Result};
+use msi::{Category, Column, Select, Value};
+
+use crate::{
+    command::builder::Msi,
+    modules::{
+        config::{dialog::DialogConfig, ui::UiSet},
+        tables::{
+            property, sequence,
+            value_check::{insert_rows, SourcedRow},
+        },
+    },
+};
+
+use dialog::Dialog;
+use resources::DEFAULT_FONT;
+use wizard::{Wizard, INSTALLDIR_PROPERTY, LICENSE_PROPERTY};
+
+const SEQUENCE_TABLE: &str = "InstallUISequence";
+
+/// Add the dialogs of `set` and the dialogs from the config to a package
+/// that already has a `Property` table. `license` is the license agreement
+/// as plain text or RTF.
+pub(crate) fn populate_ui_tables(
+    package: &mut Msi,
+    set: UiSet,
+    license: Option<&str>,
+    custom_dialogs: &[DialogConfig],
+) -> Result<()> {
+    if set == UiSet::None {
+        if !custom_dialogs.is_empty() {
+            bail!("Dialogs can only be added to a UI set other than none");
+        }
+        return Ok(());
+    }
+    let license = license.map(resources::license_rtf);
+
+    let mut properties = vec![
+        ("DefaultUIFont".to_owned(), DEFAULT_FONT.to_owned()),
+        ("ErrorDialog".to_owned(), "ErrorDlg".to_owned()),
+    ];
+    if license.is_some() {
+        properties.push((LICENSE_PROPERTY.to_owned(), "0".to_owned()));
+    }
+    if matches!(set, UiSet::InstallDir | UiSet::FeatureTree) {
+        properties
+            .push((INSTALLDIR_PROPERTY.to_owned(), "INSTALLDIR".to_owned()));
+    }
+    let mut known = package
+        .select_rows(Select::table("Property"))
+        .with_context(|| "Failed to read the Property table")?
+        .filter_map(|row| row["Property"].as_str().map(str::to_owned))
+        .collect::<BTreeSet<String>>();
+    known.extend(properties.iter().map(|(property, _)| property.clone()));
+
+    let mut wizard = Wizard::standard(set, license.as_deref());
+    let custom = custom::add_dialogs(&mut wizard, custom_dialogs, &known)?;
+    let dialogs = wizard.finish();
+
+    let tables: [(&str, Vec<Column>, Vec<SourcedRow>); 8] = [
+        (
+            "Dialog",
+            dialog_columns(),
+            dialogs.iter().map(Dialog::dialog_row).collect(),
+        ),
+        (
+            "Control",
+            control_columns(),
+            dialogs.iter().flat_map(Dialog::control_rows).collect(),
+        ),
+        (
+            "ControlEvent",
+            control_event_columns(),
+            dialogs.iter().flat_map(Dialog::event_rows).collect(),
+        ),
+        (
+            "ControlCondition",
+            control_condition_columns(),
+            dialogs.iter().flat_map(Dialog::condition_rows).collect(),
+        ),
+        (
+            "EventMapping",
+            event_mapping_columns(),
+            dialogs.iter().flat_map(Dialog::mapping_rows).collect(),
+        ),
+        (
+            "TextStyle",
+            text_style_columns(),
+            resources::text_style_rows(),
+        ),
+        ("UIText", ui_text_columns(), resources::ui_text_rows()),
+        (
+            "ActionText",
+            action_text_columns(),
+            resources::action_text_rows(),
+        ),
+    ];
+    for (table, columns, rows) in tables {
+        create_and_insert(package, table, columns, rows)?;
+    }
+
+    let mut radio_buttons = custom.radio_buttons;
+    if license.is_some() {
+        radio_buttons.extend(resources::radio_button_rows());
+    }
+    if !radio_buttons.is_empty() {
+        create_and_insert(
+            package,
+            "RadioButton",
+            radio_button_columns(),
+            radio_buttons,
+        )?;
+    }
+    if !custom.combo_boxes.is_empty() {
+        create_and_insert(
+            package,
+            "ComboBox",
+            combo_box_columns(),
+            custom.combo_boxes,
+        )?;
+    }
+    populate_binary_table(package)?;
+
+    properties.extend(custom.properties);
+    property::insert_properties(package, properties)?;
+
+    populate_sequence_table(package, set)
+}
+
+fn create_and_insert(
+    package: &mut Msi,
+    table: &str,
+    columns: Vec<Column>,
+    rows: Vec<SourcedRow>,
+) -> Result<()> {
+    if package.has_table(table) {
+        bail!("Table {} already exists", table);
+    }
+    package
+        .create_table(table, columns.clone())
+        .with_context(|| format!("Failed to create {table} table"))?;
+    insert_rows(package, table, &columns, rows)?;
+    Ok(())
+}
+
+/// Store the bitmaps as streams named after their `Binary` table rows.
+fn populate_binary_table(package: &mut Msi) -> Result<()> {
+    let mut rows = Vec::new();
+    for (name, bytes) in resources::bitmaps() {
+        let stream = format!("Binary.{name}");
+        package
+            .write_stream(&stream)
+            .and_then(|mut out| out.write_all(&bytes))
+            .with_context(|| format!("Failed to write stream {stream}"))?;
+        rows.push(SourcedRow::new(
+            vec![Value::from(name), Value::from(stream)],
+            None,
+        ));
+    }
+    create_and_insert(package, "Binary", binary_columns(), rows)
+}
+
+/// Show the wizard before the installation starts and the exit dialogs
+/// after it ends.
+fn populate_sequence_table(package: &mut Msi, set: UiSet) -> Result<()> {
+    let mut actions = vec![
+        ("FatalErrorDlg", None, -3),
+        ("UserExitDlg", None, -2),
+        ("ExitDlg", None, -1),
+        ("CostInitialize", None, 800),
+        ("FileCost", None, 900),
+        ("CostFinalize", None, 1000),
+        ("WelcomeDlg", Some("NOT Installed"), 1230),
+        ("ProgressDlg", None, 1280),
+        ("ExecuteAction", None, 1300),
+    ];
+    if set == UiSet::FeatureTree {
+        actions.push(("MigrateFeatureStates", None, 1200));
+    }
+    let rows = actions
+        .into_iter()
+        .map(|(action, condition, sequence)| {
+            SourcedRow::new(
+                vec![
+                    Value::from(action),
+                    match condition {
+                        Some(condition) => Value::from(condition),
+                        None => Value::Null,
+                    },
+                    Value::from(sequence),
+                ],
+                None,
+            )
+        })
+        .collect();
+    create_and_insert(package, SEQUENCE_TABLE, sequence::columns(), rows)
+}
+
+fn dialog_columns() -> Vec<Column> {
+    vec![
+        Column::build("Dialog")
+            .primary_key()
+            .category(Category::Identifier)
+            .string(72),
+        Column::build("HCentering").int16(),
+        Column::build("VCentering").int16(),
+        Column::build("Width").int16(),
+        Column::build("Height").int16(),
+        Column::build("Attributes").nullable().int32(),
+        Column::build("Title")
+            .nullable()
+            .localizable()
+            .category(Category::Formatted)
+            .string(128),
+        Column::build("Control_First")
+            .category(Category::Identifier)
+            .string(50),
+        Column::build("Control_Default")
+            .nullable()
+            .category(Category::Identifier)
+            .string(50),
+        Column::build("Control_Cancel")
+            .nullable()
+            .category(Category::Identifier)
+            .string(50),
+    ]
+}
+
+fn control_columns() -> Vec<Column> {
+    vec![
+        Column::build("Dialog_")
+            .primary_key()
+            .category(Category::Identifier)
+            .string(72),
+        Column::build("Control")
+            .primary_key()
+            .category(Category::Identifier)
+            .string(50),
+        Column::build("Type")
+            .category(Category::Identifier)
+            .string(20),
+        Column::build("X").int16(),
+        Column::build("Y").int16(),
+        Column::build("Width").int16(),
+        Column::build("Height").int16(),
+        Column::build("Attributes").nullable().int32(),
+        Column::build("Property")
+            .nullable()
+            .category(Category::Identifier)
+            .string(72),
+        // Formatted for most controls, but the RTF of a ScrollableText
+        // control is shown as it is and may hold unbalanced brackets.
+        Column::build("Text")
+            .nullable()
+            .localizable()
+            .category(Category::Text)
+            .string(0),
+        Column::build("Control_Next")
+            .nullable()
+            .category(Category::Identifier)
+            .string(50),
+        Column::build("Help")
+            .nullable()
+            .localizable()
+            .category(Category::Text)
+            .string(50),
+    ]
+}
+
+fn control_event_columns() -> Vec<Column> {
+    vec![
+        Column::build("Dialog_")
+            .primary_key()
+            .category(Category::Identifier)
+            .string(72),
+        Column::build("Control_")
+            .primary_key()
+            .category(Category::Identifier)
+            .string(50),
+        Column::build("Event")
+            .primary_key()
+            .category(Category::Formatted)
+            .string(50),
+        Column::build("Argument")
+            .primary_key()
+            .category(Category::Formatted)
+            .string(255),
+        Column::build("Condition")
+            .primary_key()
+            .nullable()
+            .category(Category::Condition)
+            .string(255),
+        Column::build("Ordering").nullable().int16(),
+    ]
+}
+
+fn control_condition_columns() -> Vec<Column> {
+    vec![
+        Column::build("Dialog_")
+            .primary_key()
+            .category(Category::Identifier)
+            .string(72),
+        Column::build("Control_")
+            .primary_key()
+            .category(Category::Identifier)
+            .string(50),
+        Column::build("Action")
+            .primary_key()
+            .category(Category::Text)
+            .string(50),
+        Column::build("Condition")
+            .primary_key()
+            .category(Category::Condition)
+            .string(255),
+    ]
+}
+
+fn event_mapping_columns() -> Vec<Column> {
+    vec![
+        Column::build("Dialog_")
+            .primary_key()
+            .category(Category::Identifier)
+            .string(72),
+        Column::build("Control_")
+            .primary_key()
+            .category(Category::Identifier)
+            .string(50),
+        Column::build("Event")
+            .primary_key()
+            .category(Category::Identifier)
+            .string(50),
+        Column::build("Attribute")
+            .category(Category::Identifier)
+            .string(50),
+    ]
+}
+
+fn text_style_columns() -> Vec<Column> {
+    vec![
+        Column::build("TextStyle")
+            .primary_key()
+            .category(Category::Text)
+            .string(72),
+        Column::build("FaceName")
+            .category(Category::Text)
+            .string(32),
+        Column::build("Size").int16(),
+        Column::build("Color").nullable().int32(),
+        Column::build("StyleBits").nullable().int16(),
+    ]
+}
+
+fn radio_button_columns() -> Vec<Column> {
+    vec![
+        Column::build("Property")
+            .primary_key()
+            .category(Category::Identifier)
+            .string(72),
+        Column::build("Order").primary_key().int16(),
+        Column::build("Value")
+            .category(Category::Formatted)
+            .string(64),
+        Column::build("X").int16(),
+        Column::build("Y").int16(),
+        Column::build("Width").int16(),
+        Column::build("Height").int16(),
+        Column::build("Text")
+            .nullable()
+            .localizable()
+            .category(Category::Text)
+            .string(0),
+        Column::build("Help")
+            .nullable()
+            .localizable()
+            .category(Category::Text)
+            .string(50),
+    ]
+}
+
+fn combo_box_columns() -> Vec<Column> {
+    vec![
+        Column::build("Property")
+            .primary_key()
+            .category(Category::Identifier)
+            .string(72),
+        Column::build("Order").primary_key().int16(),
+        Column::build("Value")
+            .category(Category::Formatted)
+            .string(64),
+        Column::build("Text")
+            .nullable()
+            .localizable()
+            .category(Category::Formatted)
+            .string(64),
+    ]
+}
+
+fn ui_text_columns() -> Vec<Column> {
+    vec![
+        Column::build("Key")
+            .primary_key()
+            .category(Category::Identifier)
+            .string(72),
+        Column::build("Text")
+            .nullable()
+            .localizable()
+            .category(Category::Text)
+            .string(255),
+    ]
+}
+
+fn action_text_columns() -> Vec<Column> {
+    vec![
+        Column::build("Action")
+            .primary_key()
+            .category(Category::Identifier)
+            .string(72),
+        Column::build("Description")
+            .nullable()
+            .localizable()
+            .category(Category::Text)
+            .string(0),
+        Column::build("Template")
+            .nullable()
+            .localizable()
+            .category(Category::Template)
+            .string(0),
+    ]
+}
+
+fn binary_columns() -> Vec<Column> {
+    vec![
+        Column::build("Name")
+            .primary_key()
+            .category(Category::Identifier)
+            .string(72),
+        Column::build("Data").binary(),
+    ]
+}
@@ -0,0 +1,325 @@
+//! Text styles, strings, bitmaps and the license text used by the dialogs.
+
+use msi::Value;
+
+use crate::modules::tables::value_check::SourcedRow;
+
+use super::wizard::LICENSE_PROPERTY;
+
+/// Keys of the bitmaps in the `Binary` table.
+pub(crate) const BANNER_BITMAP: &str = "WhimsiUIBannerBmp";
+pub(crate) const DIALOG_BITMAP: &str = "WhimsiUIDialogBmp";
+
+/// Text style used for text without a style of its own.
+pub(crate) const DEFAULT_FONT: &str = "Whimsi_Font_Normal";
+
+/// The accent colour of the bitmaps, as blue, green and red.
+const ACCENT: [u8; 3] = [0x79, 0x4E, 0x1F];
+const WHITE: [u8; 3] = [0xFF, 0xFF, 0xFF];
+
+pub(crate) fn text_style_rows() -> Vec<SourcedRow> {
+    [
+        (DEFAULT_FONT, 8, 0),
+        ("Whimsi_Font_Bigger", 12, 0),
+        // Bold
+        ("Whimsi_Font_Title", 9, 1),
+    ]
+    .into_iter()
+    .map(|(style, size, bits)| {
+        SourcedRow::new(
+            vec![
+                Value::from(style),
+                Value::from("Tahoma"),
+                Value::from(size),
+                Value::Null,
+                Value::from(bits),
+            ],
+            None,
+        )
+    })
+    .collect()
+}
+
+/// The choices of the license agreement radio button group.
+pub(crate) fn radio_button_rows() -> Vec<SourcedRow> {
+    [
+        (1, "1", 0, "I &accept the terms in the License Agreement"),
+        (
+            2,
+            "0",
+            15,
+            "I &do not accept the terms in the License Agreement",
+        ),
+    ]
+    .into_iter()
+    .map(|(order, value, y, text)| {
+        SourcedRow::new(
+            vec![
+                Value::from(LICENSE_PROPERTY),
+                Value::from(order),
+                Value::from(value),
+                Value::from(5),
+                Value::from(y),
+                Value::from(300),
+                Value::from(15),
+                Value::from(text),
+                Value::Null,
+            ],
+            None,
+        )
+    })
+    .collect()
+}
+
+/// Strings Windows Installer shows in the dialogs, such as units of disk
+/// space and the states of a feature in the selection tree.
+pub(crate) fn ui_text_rows() -> Vec<SourcedRow> {
+    [
+        ("AbsentPath", ""),
+        ("bytes", "bytes"),
+        ("GB", "GB"),
+        ("KB", "KB"),
+        ("MB", "MB"),
+        ("MenuAbsent", "Entire feature will be unavailable"),
+        (
+            "MenuAllLocal",
+            "Entire feature will be installed on local hard drive",
+        ),
+        ("MenuLocal", "Will be installed on local hard drive"),
+        ("NewFolder", "Folder|New Folder"),
+        ("SelAbsentAbsent", "This feature will remain uninstalled"),
+        (
+            "SelAbsentLocal",
+            "This feature will be installed on the local hard drive",
+        ),
+        (
+            "SelChildCostNeg",
+            "This feature frees up [1] on your hard drive.",
+        ),
+        (
+            "SelChildCostPos",
+            "This feature requires [1] on your hard drive.",
+        ),
+        ("SelCostPending", "Compiling cost for this feature..."),
+        ("SelLocalAbsent", "This feature will be completely removed"),
+        (
+            "SelLocalLocal",
+            "This feature will remain on your local hard drive",
+        ),
+        (
+            "SelParentCostNegNeg",
+            "This feature frees up [1] on your hard drive. It has [2] of [3] \
+            subfeatures selected. The subfeatures free up [4] on your hard \
+            drive.",
+        ),
+        (
+            "SelParentCostNegPos",
+            "This feature frees up [1] on your hard drive. It has [2] of [3] \
+            subfeatures selected. The subfeatures require [4] on your hard \
+            drive.",
+        ),
+        (
+            "SelParentCostPosNeg",
+            "This feature requires [1] on your hard drive. It has [2] of [3] \
+            subfeatures selected. The subfeatures free up [4] on your hard \
+            drive.",
+        ),
+        (
+            "SelParentCostPosPos",
+            "This feature requires [1] on your hard drive. It has [2] of [3] \
+            subfeatures selected. The subfeatures require [4] on your hard \
+            drive.",
+        ),
+        (
+            "TimeRemaining",
+            "Time remaining: {[1] minutes }{[2] seconds}",
+        ),
+        ("VolumeCostAvailable", "Available"),
+        ("VolumeCostDifference", "Difference"),
+        ("VolumeCostRequired", "Required"),
+        ("VolumeCostSize", "Disk Size"),
+        ("VolumeCostVolume", "Volume"),
+    ]
+    .into_iter()
+    .map(|(key, text)| {
+        let text = match text {
+            "" => Value::Null,
+            text => Value::from(text),
+        };
+        SourcedRow::new(vec![Value::from(key), text], None)
+    })
+    .collect()
+}
+
+/// Descriptions of the standard actions shown by the progress dialog.
+pub(crate) fn action_text_rows() -> Vec<SourcedRow> {
+    [
+        ("CostFinalize", "Computing space requirements", None),
+        ("CostInitialize", "Computing space requirements", None),
+        ("FileCost", "Computing space requirements", None),
+        ("InstallValidate", "Validating install", None),
+        (
+            "InstallFiles",
+            "Copying new files",
+            Some("File: [1],  Directory: [9],  Size: [6]"),
+        ),
+        (
+            "RemoveFiles",
+            "Removing files",
+            Some("File: [1], Directory: [9]"),
+        ),
+        ("CreateFolders", "Creating folders", Some("Folder: [1]")),
+        ("RemoveFolders", "Removing folders", Some("Folder: [1]")),
+        (
+            "WriteRegistryValues",
+            "Writing system registry values",
+            Some("Key: [1], Name: [2], Value: [3]"),
+        ),
+        (
+            "CreateShortcuts",
+            "Creating shortcuts",
+            Some("Shortcut: [1]"),
+        ),
+        ("RegisterProduct", "Registering product", Some("[1]")),
+        (
+            "PublishFeatures",
+            "Publishing Product Features",
+            Some("Feature: [1]"),
+        ),
+        ("PublishProduct", "Publishing product information", None),
+        ("Rollback", "Rolling back action:", Some("[1]")),
+    ]
+    .into_iter()
+    .map(|(action, description, template)| {
+        SourcedRow::new(
+            vec![
+                Value::from(action),
+                Value::from(description),
+                match template {
+                    Some(template) => Value::from(template),
+                    None => Value::Null,
+                },
+            ],
+            None,
+        )
+    })
+    .collect()
+}
+
+/// The bitmaps in the `Binary` table as pairs of a key and the contents.
+pub(crate) fn bitmaps() -> Vec<(&'static str, Vec<u8>)> {
+    // A white banner with a line of the accent colour along the bottom.
+    let banner = bitmap(493, 58, |_, y| if y < 3 { ACCENT } else { WHITE });
+    // A band of the accent colour down the left side, behind nothing but
+    // the space the text leaves free.
+    let dialog = bitmap(493, 312, |x, _| if x < 164 { ACCENT } else { WHITE });
+    vec![(BANNER_BITMAP, banner), (DIALOG_BITMAP, dialog)]
+}
+
+/// An uncompressed 24-bit bitmap. `pixel` is given the column and the row,
+/// counted from the bottom.
+fn bitmap(
+    width: u32,
+    height: u32,
+    pixel: impl Fn(u32, u32) -> [u8; 3],
+) -> Vec<u8> {
+    // Rows are padded to a multiple of four bytes.
+    let stride = (width * 3).div_ceil(4) * 4;
+    let pixels_size = stride * height;
+    let mut out = Vec::with_capacity((54 + pixels_size) as usize);
+    out.extend_from_slice(b"BM");
+    out.extend_from_slice(&(54 + pixels_size).to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&54u32.to_le_bytes());
+    // BITMAPINFOHEADER
+    out.extend_from_slice(&40u32.to_le_bytes());
+    out.extend_from_slice(&(width as i32).to_le_bytes());
+    out.extend_from_slice(&(height as i32).to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&24u16.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&pixels_size.to_le_bytes());
+    // 96 DPI
+    out.extend_from_slice(&3780i32.to_le_bytes());
+    out.extend_from_slice(&3780i32.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    for y in 0..height {
+        let row_start = out.len();
+        for x in 0..width {
+            out.extend_from_slice(&pixel(x, y));
+        }
+        out.resize(row_start + stride as usize, 0);
+    }
+    out
+}
+
+/// The license text as RTF. Text that is already RTF is used as it is and
+/// plain text is converted with every line as its own paragraph.
+pub(crate) fn license_rtf(text: &str) -> String {
+    if text.trim_start().starts_with("{\\rtf") {
+        return text.to_owned();
+    }
+    let mut rtf = String::from(
+        "{\\rtf1\\ansi\\ansicpg1252\\deff0{\\fonttbl{\\f0\\fnil Tahoma;}}\
+        \\viewkind4\\uc1\\pard\\f0\\fs16 ",
+    );
+    for c in text.chars() {
+        match c {
+            '\\' | '{' | '}' => {
+                rtf.push('\\');
+                rtf.push(c);
+            }
+            '\r' => {}
+            '\n' => rtf.push_str("\\par\n"),
+            c if c.is_ascii() => rtf.push(c),
+            c => {
+                // RTF takes signed 16-bit code units, followed by the
+                // character to show where Unicode isn't supported.
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    rtf.push_str(&format!("\\u{}?", *unit as i16));
+                }
+            }
+        }
+    }
+    rtf.push('}');
+    rtf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::license_rtf;
+
+    /// The RTF of `text` without the header and the closing brace.
+    fn body(text: &str) -> String {
+        let rtf = license_rtf(text);
+        let start = rtf.find("\\fs16 ").expect("RTF has a header") + 6;
+        rtf[start..rtf.len() - 1].to_owned()
+    }
+
+    #[test]
+    fn escapes_rtf_control_characters() {
+        assert_eq!(body("C:\\{x}"), "C:\\\\\\{x\\}");
+    }
+
+    #[test]
+    fn makes_every_line_a_paragraph() {
+        assert_eq!(body("One\r\nTwo\nThree"), "One\\par\nTwo\\par\nThree");
+    }
+
+    #[test]
+    fn writes_non_ascii_characters_as_utf16_units() {
+        assert_eq!(body("é"), "\\u233?");
+        assert_eq!(body("語"), "\\u-30050?");
+        // Characters outside the BMP are written as a surrogate pair.
+        assert_eq!(body("😀"), "\\u-10179?\\u-8704?");
+    }
+
+    #[test]
+    fn keeps_rtf_as_it_is() {
+        let rtf = "{\\rtf1\\ansi {\\b Bold}}";
+        assert_eq!(license_rtf(rtf), rtf);
+        assert!(license_rtf("Plain").starts_with("{\\rtf1"));
+    }
+}
@@ -0,0 +1,644 @@
+//! The dialogs of the built-in UI sets.
+//!
+//! A wizard is a list of pages, each with Back, Next and Cancel buttons that
+//! are linked to the neighbouring pages once every page is known. The other
+//! dialogs, such as the progress and exit dialogs, are shown by the
+//! `InstallUISequence` or spawned from the pages.
+
+use anyhow::{bail, Result};
+
+use super::{
+    dialog::{
+        Control, Dialog, Event, DIALOG_ERROR, DIALOG_MODAL, ENABLED,
+        FIXED_AND_REMOTE, INDIRECT, NO_PREFIX, PROGRESS_95, SUNKEN,
+        TRANSPARENT, VISIBLE, WIDTH,
+    },
+    resources::{BANNER_BITMAP, DIALOG_BITMAP},
+};
+use crate::modules::config::ui::UiSet;
+
+/// Property holding the name of the directory the user picks.
+pub(crate) const INSTALLDIR_PROPERTY: &str = "WHIMSI_INSTALLDIR";
+/// Property set by the license agreement radio buttons.
+pub(crate) const LICENSE_PROPERTY: &str = "LicenseAccepted";
+
+const TEXT: i32 = VISIBLE | TRANSPARENT | NO_PREFIX;
+const BUTTON: i32 = VISIBLE | ENABLED;
+const BUTTON_Y: i32 = 243;
+
+/// The pages of a wizard and the dialogs that go with it.
+pub(crate) struct Wizard {
+    pub(crate) pages: Vec<Dialog>,
+    pub(crate) dialogs: Vec<Dialog>,
+}
+
+impl Wizard {
+    /// The dialogs of a built-in UI set. `license` is the license agreement
+    /// as RTF; the license page is left out without one.
+    pub(crate) fn standard(set: UiSet, license: Option<&str>) -> Self {
+        let mut pages = vec![welcome()];
+        if let Some(license) = license {
+            pages.push(license_agreement(license));
+        }
+        match set {
+            UiSet::InstallDir => pages.push(install_dir()),
+            UiSet::FeatureTree => pages.push(customize()),
+            UiSet::None | UiSet::Minimal => {}
+        }
+        pages.push(verify_ready());
+
+        let exit = finished(
+            "ExitDlg",
+            "Completed the [ProductName] Setup Wizard",
+            "Click the Finish button to exit the Setup Wizard.",
+            "Return",
+        );
+        let user_exit = finished(
+            "UserExitDlg",
+            "[ProductName] Setup Wizard was interrupted",
+            "[ProductName] setup was interrupted. Your system has not been \
+            modified. To install this program at a later time, run the Setup \
+            Wizard again.",
+            "Exit",
+        );
+        let fatal_error = finished(
+            "FatalErrorDlg",
+            "[ProductName] Setup Wizard ended prematurely",
+            "[ProductName] Setup Wizard ended prematurely because of an \
+            error. Your system has not been modified.",
+            "Exit",
+        );
+        let mut dialogs =
+            vec![progress(), exit, user_exit, fatal_error, cancel(), error()];
+        if matches!(set, UiSet::InstallDir | UiSet::FeatureTree) {
+            dialogs.push(browse());
+        }
+        Wizard { pages, dialogs }
+    }
+
+    /// Every dialog identifier, pages first.
+    pub(crate) fn ids(&self) -> impl Iterator<Item = &str> {
+        self.pages
+            .iter()
+            .chain(&self.dialogs)
+            .map(|d| d.id.as_str())
+    }
+
+    /// Insert `page` before or after the page with the identifier `anchor`.
+    pub(crate) fn insert(
+        &mut self,
+        page: Dialog,
+        anchor: &str,
+        after: bool,
+    ) -> Result<()> {
+        let Some(index) = self.pages.iter().position(|p| p.id == anchor) else {
+            bail!(
+                "Page {} can't be placed next to {}, which is not a page of \
+                the wizard",
+                page.id,
+                anchor
+            );
+        };
+        self.pages.insert(index + usize::from(after), page);
+        Ok(())
+    }
+
+    /// Link the Back and Next buttons of the pages and return every dialog.
+    /// The Next button of the last page ends the wizard and starts the
+    /// installation.
+    pub(crate) fn finish(mut self) -> Vec<Dialog> {
+        let ids = self.pages.iter().map(|p| p.id.clone()).collect::<Vec<_>>();
+        for (index, page) in self.pages.iter_mut().enumerate() {
+            if let Some(back) = page.control_mut("Back") {
+                match index.checked_sub(1) {
+                    Some(previous) => back
+                        .events
+                        .push(Event::new("NewDialog", &ids[previous])),
+                    None => back.attributes &= !ENABLED,
+                }
+            }
+            if let Some(next) = page.control_mut("Next") {
+                match ids.get(index + 1) {
+                    Some(following) => {
+                        next.events.push(Event::new("NewDialog", following))
+                    }
+                    None => next.events.push(Event::new("EndDialog", "Return")),
+                }
+            }
+        }
+        self.pages.append(&mut self.dialogs);
+        self.pages
+    }
+}
+
+/// A wizard page with a banner across the top.
+pub(crate) fn page(id: &str, title: &str, description: &str) -> Dialog {
+    let mut dialog = Dialog::wizard(id, "[ProductName] Setup");
+    dialog.add(
+        Control::new("BannerBitmap", "Bitmap", (0, 0, WIDTH, 44), VISIBLE)
+            .text(BANNER_BITMAP),
+    );
+    dialog.add(Control::new(
+        "BannerLine",
+        "Line",
+        (0, 44, WIDTH, 0),
+        VISIBLE,
+    ));
+    dialog.add(
+        Control::new("Title", "Text", (15, 6, 200, 15), TEXT)
+            .text(&format!("{{\\Whimsi_Font_Title}}{title}")),
+    );
+    dialog.add(
+        Control::new("Description", "Text", (25, 23, 280, 15), TEXT)
+            .text(description),
+    );
+    dialog
+}
+
+/// A page with the large bitmap down the left side, used at the start and
+/// end of the wizard.
+fn cover(id: &str, title: &str, description: &str) -> Dialog {
+    let mut dialog = Dialog::wizard(id, "[ProductName] Setup");
+    dialog.add(
+        Control::new("Bitmap", "Bitmap", (0, 0, WIDTH, 234), VISIBLE)
+            .text(DIALOG_BITMAP),
+    );
+    dialog.add(
+        Control::new("Title", "Text", (135, 20, 220, 60), TEXT)
+            .text(&format!("{{\\Whimsi_Font_Bigger}}{title}")),
+    );
+    dialog.add(
+        Control::new("Description", "Text", (135, 80, 220, 60), TEXT)
+            .text(description),
+    );
+    dialog
+}
+
+/// Add the line and the Back, Next and Cancel buttons along the bottom.
+pub(crate) fn navigation(dialog: &mut Dialog, next_text: &str) {
+    dialog.add(Control::new(
+        "BottomLine",
+        "Line",
+        (0, 234, WIDTH, 0),
+        VISIBLE,
+    ));
+    dialog.add(
+        Control::new("Back", "PushButton", (180, BUTTON_Y, 56, 17), BUTTON)
+            .text("&Back"),
+    );
+    dialog.add(
+        Control::new("Next", "PushButton", (236, BUTTON_Y, 56, 17), BUTTON)
+            .text(next_text),
+    );
+    dialog.add(
+        Control::new("Cancel", "PushButton", (304, BUTTON_Y, 56, 17), BUTTON)
+            .text("Cancel")
+            .event(Event::new("SpawnDialog", "CancelDlg")),
+    );
+    dialog.default = Some("Next".to_owned());
+    dialog.cancel = Some("Cancel".to_owned());
+}
+
+fn welcome() -> Dialog {
+    let mut dialog = cover(
+        "WelcomeDlg",
+        "Welcome to the [ProductName] Setup Wizard",
+        "The Setup Wizard will install [ProductName] on your computer. \
+        Click Next to continue or Cancel to exit the Setup Wizard.",
+    );
+    navigation(&mut dialog, "&Next");
+    dialog
+}
+
+fn license_agreement(license: &str) -> Dialog {
+    let mut dialog = page(
+        "LicenseAgreementDlg",
+        "End-User License Agreement",
+        "Please read the following license agreement carefully",
+    );
+    dialog.add(
+        Control::new(
+            "LicenseText",
+            "ScrollableText",
+            (20, 60, 330, 130),
+            VISIBLE | ENABLED | SUNKEN,
+        )
+        .text(license),
+    );
+    dialog.add(
+        Control::new(
+            "Buttons",
+            "RadioButtonGroup",
+            (20, 195, 330, 35),
+            VISIBLE | ENABLED,
+        )
+        .property(LICENSE_PROPERTY),
+    );
+    navigation(&mut dialog, "&Next");
+    if let Some(next) = dialog.control_mut("Next") {
+        let accepted = format!("{LICENSE_PROPERTY} = \"1\"");
+        next.conditions = vec![
+            ("Disable".to_owned(), format!("NOT ({accepted})")),
+            ("Enable".to_owned(), accepted),
+        ];
+    }
+    dialog
+}
+
+fn install_dir() -> Dialog {
+    let mut dialog = page(
+        "InstallDirDlg",
+        "Destination Folder",
+        "Click Next to install to the default folder or click Change to \
+        choose another.",
+    );
+    dialog.add(
+        Control::new("FolderLabel", "Text", (20, 60, 290, 30), TEXT)
+            .text("Install [ProductName] to:"),
+    );
+    dialog.add(
+        Control::new(
+            "Folder",
+            "PathEdit",
+            (20, 100, 320, 18),
+            VISIBLE | ENABLED | INDIRECT,
+        )
+        .property(INSTALLDIR_PROPERTY),
+    );
+    dialog.add(
+        Control::new("ChangeFolder", "PushButton", (20, 120, 56, 17), BUTTON)
+            .text("&Change...")
+            .event(Event::new(
+                "[_BrowseProperty]",
+                &format!("[{INSTALLDIR_PROPERTY}]"),
+            ))
+            .event(Event::new("SpawnDialog", "BrowseDlg")),
+    );
+    navigation(&mut dialog, "&Next");
+    if let Some(next) = dialog.control_mut("Next") {
+        next.events.push(Event::new(
+            "SetTargetPath",
+            &format!("[{INSTALLDIR_PROPERTY}]"),
+        ));
+    }
+    dialog
+}
+
+fn customize() -> Dialog {
+    let mut dialog = page(
+        "CustomizeDlg",
+        "Custom Setup",
+        "Select the way you want features to be installed.",
+    );
+    dialog.add(
+        Control::new(
+            "Tree",
+            "SelectionTree",
+            (20, 55, 175, 135),
+            VISIBLE | ENABLED | SUNKEN,
+        )
+        .property("_BrowseProperty")
+        .text("Tree of selections"),
+    );
+    dialog.add(
+        Control::new("ItemDescription", "Text", (205, 55, 140, 80), TEXT)
+            .subscribe("SelectionDescription", "Text"),
+    );
+    dialog.add(
+        Control::new("ItemSize", "Text", (205, 140, 140, 50), TEXT)
+            .subscribe("SelectionSize", "Text"),
+    );
+    dialog.add(
+        Control::new("Location", "Text", (20, 195, 270, 20), TEXT)
+            .subscribe("SelectionPath", "Text")
+            .subscribe("SelectionPathOn", "Visible"),
+    );
+    dialog.add(
+        Control::new("Browse", "PushButton", (294, 195, 56, 17), BUTTON)
+            .text("B&rowse...")
+            .event(Event::new("SelectionBrowse", "BrowseDlg"))
+            .condition("Hide", "Installed"),
+    );
+    navigation(&mut dialog, "&Next");
+    dialog
+}
+
+fn verify_ready() -> Dialog {
+    let mut dialog = page(
+        "VerifyReadyDlg",
+        "Ready to install [ProductName]",
+        "The Setup Wizard is ready to begin the installation",
+    );
+    dialog.add(Control::new("Text", "Text", (25, 70, 320, 80), TEXT).text(
+        "Click Install to begin the installation. Click Back to review \
+            or change any of your installation settings. Click Cancel to \
+            exit the wizard.",
+    ));
+    navigation(&mut dialog, "&Install");
+    dialog
+}
+
+fn progress() -> Dialog {
+    let mut dialog = page(
+        "ProgressDlg",
+        "Installing [ProductName]",
+        "Please wait while the Setup Wizard installs [ProductName].",
+    );
+    // The progress dialog stays up while the installation runs.
+    dialog.attributes = VISIBLE;
+    dialog.add(
+        Control::new("ActionText", "Text", (70, 100, 285, 10), TEXT)
+            .subscribe("ActionText", "Text"),
+    );
+    dialog.add(
+        Control::new(
+            "ProgressBar",
+            "ProgressBar",
+            (20, 115, 330, 10),
+            VISIBLE | PROGRESS_95,
+        )
+        .text("Progress done")
+        .subscribe("SetProgress", "Progress"),
+    );
+    dialog.add(
+        Control::new("StatusLabel", "Text", (20, 100, 50, 10), TEXT)
+            .text("Status:"),
+    );
+    navigation(&mut dialog, "&Next");
+    for id in ["Back", "Next"] {
+        if let Some(button) = dialog.control_mut(id) {
+            button.attributes &= !ENABLED;
+        }
+    }
+    dialog
+}
+
+/// One of the dialogs shown when the installation ends. The Finish button
+/// ends the dialog with `end`.
+fn finished(id: &str, title: &str, description: &str, end: &str) -> Dialog {
+    let mut dialog = cover(id, title, description);
+    navigation(&mut dialog, "&Finish");
+    for id in ["Back", "Cancel"] {
+        if let Some(button) = dialog.control_mut(id) {
+            button.attributes &= !ENABLED;
+            button.events.clear();
+        }
+    }
+    if let Some(next) = dialog.control_mut("Next") {
+        next.events.push(Event::new("EndDialog", end));
+    }
+    dialog.cancel = Some("Next".to_owned());
+    dialog
+}
+
+fn cancel() -> Dialog {
+    let mut dialog = Dialog::wizard("CancelDlg", "[ProductName] Setup");
+    dialog.width = 260;
+    dialog.height = 85;
+    dialog.add(
+        Control::new("Text", "Text", (48, 15, 194, 30), TEXT).text(
+            "Are you sure you want to cancel [ProductName] installation?",
+        ),
+    );
+    dialog.add(
+        Control::new("No", "PushButton", (132, 57, 56, 17), BUTTON)
+            .text("&No")
+            .event(Event::new("EndDialog", "Return")),
+    );
+    dialog.add(
+        Control::new("Yes", "PushButton", (72, 57, 56, 17), BUTTON)
+            .text("&Yes")
+            .event(Event::new("EndDialog", "Exit")),
+    );
+    dialog.default = Some("No".to_owned());
+    dialog.cancel = Some("No".to_owned());
+    dialog
+}
+
+/// The dialog Windows Installer uses for error messages. The installer shows
+/// only the buttons that fit the message.
+fn error() -> Dialog {
+    let mut dialog = Dialog::wizard("ErrorDlg", "Installer Information");
+    dialog.width = 270;
+    dialog.height = 105;
+    dialog.attributes = DIALOG_MODAL | DIALOG_ERROR;
+    dialog.add(
+        Control::new("ErrorText", "Text", (48, 15, 205, 60), TEXT)
+            .text("Information text"),
+    );
+    let buttons = [
+        ("A", "&Abort", "ErrorAbort"),
+        ("C", "Cancel", "ErrorCancel"),
+        ("I", "&Ignore", "ErrorIgnore"),
+        ("N", "&No", "ErrorNo"),
+        ("O", "&OK", "ErrorOk"),
+        ("R", "&Retry", "ErrorRetry"),
+        ("Y", "&Yes", "ErrorYes"),
+    ];
+    for (id, text, end) in buttons {
+        dialog.add(
+            Control::new(id, "PushButton", (100, 80, 56, 17), BUTTON)
+                .text(text)
+                .event(Event::new("EndDialog", end)),
+        );
+    }
+    dialog
+}
+
+fn browse() -> Dialog {
+    let mut dialog = page(
+        "BrowseDlg",
+        "Change destination folder",
+        "Browse to the destination folder",
+    );
+    dialog.add(
+        Control::new("ComboLabel", "Text", (25, 58, 44, 10), TEXT)
+            .text("&Look in:"),
+    );
+    dialog.add(
+        Control::new(
+            "DirectoryCombo",
+            "DirectoryCombo",
+            (70, 55, 220, 80),
+            VISIBLE | ENABLED | INDIRECT | FIXED_AND_REMOTE,
+        )
+        .property("_BrowseProperty"),
+    );
+    dialog.add(
+        Control::new("Up", "PushButton", (298, 55, 19, 19), BUTTON)
+            .text("Up")
+            .event(Event::new("DirectoryListUp", "0")),
+    );
+    dialog.add(
+        Control::new("NewFolder", "PushButton", (325, 55, 19, 19), BUTTON)
+            .text("New")
+            .event(Event::new("DirectoryListNew", "0")),
+    );
+    dialog.add(
+        Control::new(
+            "DirectoryList",
+            "DirectoryList",
+            (25, 83, 320, 98),
+            VISIBLE | ENABLED | SUNKEN | INDIRECT,
+        )
+        .property("_BrowseProperty"),
+    );
+    dialog.add(
+        Control::new("PathLabel", "Text", (25, 190, 320, 10), TEXT)
+            .text("&Folder name:"),
+    );
+    dialog.add(
+        Control::new(
+            "PathEdit",
+            "PathEdit",
+            (25, 202, 320, 18),
+            VISIBLE | ENABLED | INDIRECT,
+        )
+        .property("_BrowseProperty"),
+    );
+    dialog.add(Control::new(
+        "BottomLine",
+        "Line",
+        (0, 234, WIDTH, 0),
+        VISIBLE,
+    ));
+    dialog.add(
+        Control::new("OK", "PushButton", (240, BUTTON_Y, 56, 17), BUTTON)
+            .text("OK")
+            .event(Event::new("SetTargetPath", "[_BrowseProperty]"))
+            .event(Event::new("EndDialog", "Return")),
+    );
+    dialog.add(
+        Control::new("Cancel", "PushButton", (304, BUTTON_Y, 56, 17), BUTTON)
+            .text("Cancel")
+            .event(Event::new("Reset", "0"))
+            .event(Event::new("EndDialog", "Return")),
+    );
+    dialog.default = Some("OK".to_owned());
+    dialog.cancel = Some("Cancel".to_owned());
+    dialog
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Wizard, LICENSE_PROPERTY};
+    use crate::modules::{
+        config::ui::UiSet,
+        ui::dialog::{Control, Dialog, ENABLED},
+    };
+
+    fn control<'a>(
+        dialogs: &'a [Dialog],
+        dialog: &str,
+        id: &str,
+    ) -> &'a Control {
+        dialogs
+            .iter()
+            .find(|d| d.id == dialog)
+            .and_then(|d| d.controls.iter().find(|c| c.id == id))
+            .unwrap_or_else(|| panic!("{dialog} has no control {id}"))
+    }
+
+    fn events(control: &Control) -> Vec<String> {
+        control
+            .events
+            .iter()
+            .map(|e| format!("{} {}", e.event, e.argument))
+            .collect()
+    }
+
+    /// Check that the Back and Next buttons of `pages` lead to the page
+    /// before and after them.
+    fn assert_chain(set: UiSet, license: Option<&str>, pages: &[&str]) {
+        let dialogs = Wizard::standard(set, license).finish();
+        let ids = dialogs.iter().map(|d| d.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(&ids[..pages.len()], pages);
+
+        for (index, page) in pages.iter().enumerate() {
+            let back = control(&dialogs, page, "Back");
+            match index.checked_sub(1) {
+                Some(previous) => assert_eq!(
+                    events(back),
+                    [format!("NewDialog {}", pages[previous])]
+                ),
+                None => {
+                    assert!(back.events.is_empty());
+                    assert_eq!(back.attributes & ENABLED, 0);
+                }
+            }
+            let next = events(control(&dialogs, page, "Next"));
+            let expected = match pages.get(index + 1) {
+                Some(following) => format!("NewDialog {following}"),
+                None => "EndDialog Return".to_owned(),
+            };
+            assert_eq!(next.last(), Some(&expected), "Next of {page}");
+        }
+    }
+
+    #[test]
+    fn links_the_pages_of_every_set() {
+        let license = Some("{\\rtf1 License}");
+        assert_chain(
+            UiSet::Minimal,
+            license,
+            &["WelcomeDlg", "LicenseAgreementDlg", "VerifyReadyDlg"],
+        );
+        assert_chain(
+            UiSet::InstallDir,
+            license,
+            &[
+                "WelcomeDlg",
+                "LicenseAgreementDlg",
+                "InstallDirDlg",
+                "VerifyReadyDlg",
+            ],
+        );
+        assert_chain(
+            UiSet::FeatureTree,
+            license,
+            &[
+                "WelcomeDlg",
+                "LicenseAgreementDlg",
+                "CustomizeDlg",
+                "VerifyReadyDlg",
+            ],
+        );
+    }
+
+    #[test]
+    fn leaves_out_the_license_page_without_a_license() {
+        assert_chain(UiSet::Minimal, None, &["WelcomeDlg", "VerifyReadyDlg"]);
+        assert_chain(
+            UiSet::InstallDir,
+            None,
+            &["WelcomeDlg", "InstallDirDlg", "VerifyReadyDlg"],
+        );
+    }
+
+    #[test]
+    fn next_waits_for_the_license_to_be_accepted() {
+        let dialogs =
+            Wizard::standard(UiSet::Minimal, Some("{\\rtf1 License}")).finish();
+        assert_eq!(
+            control(&dialogs, "LicenseAgreementDlg", "Buttons")
+                .property
+                .as_deref(),
+            Some(LICENSE_PROPERTY)
+        );
+        let next = control(&dialogs, "LicenseAgreementDlg", "Next");
+        assert_eq!(
+            next.conditions,
+            [
+                (
+                    "Disable".to_owned(),
+                    "NOT (LicenseAccepted = \"1\")".to_owned()
+                ),
+                ("Enable".to_owned(), "LicenseAccepted = \"1\"".to_owned()),
+            ]
+        );
+        // The other pages can always be left.
+        assert!(control(&dialogs, "WelcomeDlg", "Next")
+            .conditions
+            .is_empty());
+    }
+}
@@ -0,0 +1,223 @@
+use std::{
+    cell::OnceCell,
+    collections::{BTreeMap, HashMap},
+    io::{Read, Seek},
+};
+
+use anyhow::{Context, Result};
+use msi::{Column, Package, Select, Value};
+
+/// An in-memory copy of every table in a package.
+///
+/// The checks look at many tables at once and the `msi` crate needs mutable
+/// access to the package for every query, so everything is read up front.
+pub(crate) struct Database {
+    tables: BTreeMap<String, TableData>,
+    /// The row of every primary key of every table, built on first use.
+    keys: OnceCell<HashMap<String, HashMap<String, usize>>>,
+    /// The directory of every component, built on first use.
+    component_directories: OnceCell<HashMap<String, String>>,
+}
+
+/// The columns and rows of a single table.
+pub(crate) struct TableData {
+    pub(crate) name: String,
+    pub(crate) columns: Vec<Column>,
+    pub(crate) rows: Vec<Vec<Value>>,
+}
+
+impl Database {
+    /// Read every table of the package into memory.
+    pub(crate) fn load<F: Read + Seek>(
+        package: &mut Package<F>,
+    ) -> Result<Self> {
+        let definitions = package
+            .tables()
+            .map(|t| (t.name().to_owned(), t.columns().to_vec()))
+            .collect::<Vec<_>>();
+
+        let mut tables = BTreeMap::new();
+        for (name, columns) in definitions {
+            let rows = package
+                .select_rows(Select::table(name.clone()))
+                .with_context(|| format!("Failed to read table {name}"))?
+                .map(|row| (0..row.len()).map(|i| row[i].clone()).collect())
+                .collect();
+            tables.insert(
+                name.clone(),
+                TableData {
+                    name,
+                    columns,
+                    rows,
+                },
+            );
+        }
+        Ok(Database::new(tables))
+    }
+
+    fn new(tables: BTreeMap<String, TableData>) -> Self {
+        Database {
+            tables,
+            keys: OnceCell::new(),
+            component_directories: OnceCell::new(),
+        }
+    }
+
+    pub(crate) fn tables(&self) -> impl Iterator<Item = &TableData> {
+        self.tables.values()
+    }
+
+    pub(crate) fn table(&self, name: &str) -> Option<&TableData> {
+        self.tables.get(name)
+    }
+
+    pub(crate) fn table_mut(&mut self, name: &str) -> Option<&mut TableData> {
+        // The rows may change, so the lookups are built again when needed.
+        self.keys.take();
+        self.component_directories.take();
+        self.tables.get_mut(name)
+    }
+
+    /// The rows of a table, or nothing when the table is not in the package.
+    pub(crate) fn rows(&self, name: &str) -> &[Vec<Value>] {
+        self.tables
+            .get(name)
+            .map(|t| t.rows.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The row of a table with the given primary key, or the first one when
+    /// the key is duplicated.
+    pub(crate) fn row(&self, table: &str, key: &str) -> Option<&[Value]> {
+        let index = *self.keys().get(table)?.get(key)?;
+        Some(self.tables.get(table)?.rows[index].as_slice())
+    }
+
+    pub(crate) fn has_key(&self, table: &str, key: &str) -> bool {
+        self.keys().get(table).is_some_and(|k| k.contains_key(key))
+    }
+
+    /// The `Directory_` of a component.
+    pub(crate) fn component_directory(&self, component: &str) -> Option<&str> {
+        self.component_directories
+            .get_or_init(|| {
+                let Some(table) = self.table("Component") else {
+                    return HashMap::new();
+                };
+                let mut directories = HashMap::new();
+                for row in &table.rows {
+                    if let (Some(component), Some(directory)) = (
+                        table.str(row, "Component"),
+                        table.str(row, "Directory_"),
+                    ) {
+                        directories
+                            .entry(component.to_owned())
+                            .or_insert_with(|| directory.to_owned());
+                    }
+                }
+                directories
+            })
+            .get(component)
+            .map(String::as_str)
+    }
+
+    fn keys(&self) -> &HashMap<String, HashMap<String, usize>> {
+        self.keys.get_or_init(|| {
+            self.tables
+                .iter()
+                .map(|(name, table)| {
+                    let mut keys = HashMap::new();
+                    for (index, row) in table.rows.iter().enumerate() {
+                        keys.entry(table.key(row)).or_insert(index);
+                    }
+                    (name.clone(), keys)
+                })
+                .collect()
+        })
+    }
+}
+
+impl TableData {
+    pub(crate) fn column_index(&self, column: &str) -> Option<usize> {
+        self.columns.iter().position(|c| c.name() == column)
+    }
+
+    /// The string value of a column, or `None` if it is null or missing.
+    pub(crate) fn str<'a>(
+        &self,
+        row: &'a [Value],
+        column: &str,
+    ) -> Option<&'a str> {
+        match row.get(self.column_index(column)?)? {
+            Value::Str(s) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    /// The integer value of a column, or `None` if it is null or missing.
+    pub(crate) fn int(&self, row: &[Value], column: &str) -> Option<i32> {
+        match row.get(self.column_index(column)?)? {
+            Value::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// The primary key of a row, with multiple key columns joined by `/`.
+    pub(crate) fn key(&self, row: &[Value]) -> String {
+        self.columns
+            .iter()
+            .zip(row)
+            .filter(|(c, _)| c.is_primary_key())
+            .map(|(_, v)| match v {
+                // The display form of a string is quoted.
+                Value::Str(s) => s.clone(),
+                v => v.to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join("/")
+    }
+}
+
+#[cfg(test)]
+impl Database {
+    /// A database of the given tables, which are named after their first
+    /// column, keyed by it and hold nothing but strings. Empty strings are
+    /// null.
+    pub(crate) fn of_strings(tables: &[(&[&str], &[&[&str]])]) -> Self {
+        let tables = tables
+            .iter()
+            .map(|(columns, rows)| {
+                let name = columns[0].to_owned();
+                let columns = columns
+                    .iter()
+                    .enumerate()
+                    .map(|(i, c)| match i {
+                        0 => Column::build(*c).primary_key().string(255),
+                        _ => Column::build(*c).nullable().string(255),
+                    })
+                    .collect();
+                let rows = rows
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|v| {
+                                if v.is_empty() {
+                                    Value::Null
+                                } else {
+                                    Value::from(*v)
+                                }
+                            })
+                            .collect()
+                    })
+                    .collect();
+                let table = TableData {
+                    name: name.clone(),
+                    columns,
+                    rows,
+                };
+                (name, table)
+            })
+            .collect();
+        Database::new(tables)
+    }
+}
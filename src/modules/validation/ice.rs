@@ -0,0 +1,625 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use msi::{ColumnType, Value};
+
+use super::{
+    database::{Database, TableData},
+    schema::{
+        standard_table, RESERVED_PROPERTIES, SYSTEM_FOLDERS, SYSTEM_TABLES,
+        USER_PROFILE_FOLDERS,
+    },
+    Finding, Severity,
+};
+
+/// `Component.Attributes` bit marking the `KeyPath` as a `Registry` key.
+const REGISTRY_KEY_PATH: i32 = 0x4;
+/// `Registry.Root` value for `HKEY_CURRENT_USER`.
+const HKCU: i32 = 1;
+/// `Registry.Root` value for `HKEY_LOCAL_MACHINE`.
+const HKLM: i32 = 2;
+
+/// # [ICE03](https://learn.microsoft.com/en-us/windows/win32/msi/ice03)
+///
+/// Checks every value against the type, width, nullability, category and
+/// foreign key of its column. The system tables are left out.
+pub(crate) fn ice03(db: &Database) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for table in db.tables() {
+        if SYSTEM_TABLES.contains(&table.name.as_str()) {
+            continue;
+        }
+        let standard = standard_table(&table.name);
+        for row in &table.rows {
+            for (column, value) in table.columns.iter().zip(row) {
+                let message = match value {
+                    Value::Null if !column.is_nullable() => Some(
+                        "Null value in a column that is not nullable"
+                            .to_owned(),
+                    ),
+                    Value::Null => None,
+                    Value::Str(s) => check_string(column, s),
+                    Value::Int(i) => match column.value_range() {
+                        Some((min, max)) if *i < min || *i > max => {
+                            Some(format!(
+                                "Value {i} is outside the range {min}..={max}"
+                            ))
+                        }
+                        _ => None,
+                    },
+                };
+                if let Some(message) = message {
+                    findings.push(Finding::new(
+                        3,
+                        Severity::Error,
+                        table,
+                        row,
+                        format!("{}: {}", column.name(), message),
+                    ));
+                }
+
+                let foreign_table = standard
+                    .and_then(|s| s.iter().find(|c| c.name == column.name()))
+                    .and_then(|c| c.foreign_table);
+                if let (Some(foreign), Value::Str(key)) = (foreign_table, value)
+                {
+                    if !db.has_key(foreign, key) {
+                        findings.push(Finding::new(
+                            3,
+                            Severity::Error,
+                            table,
+                            row,
+                            format!(
+                                "{}: Key {} is not in the {} table",
+                                column.name(),
+                                key,
+                                foreign
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    findings
+}
+
+fn check_string(column: &msi::Column, value: &str) -> Option<String> {
+    if let ColumnType::Str(width) = column.coltype() {
+        let length = value.chars().count();
+        if width > 0 && length > width {
+            return Some(format!(
+                "String of length {length} is longer than the column width {width}"
+            ));
+        }
+    }
+    match column.category() {
+        Some(category) if !category.validate(value) => {
+            Some(format!("Value [{value}] is not a valid {category:?}"))
+        }
+        _ => None,
+    }
+}
+
+/// # [ICE06](https://learn.microsoft.com/en-us/windows/win32/msi/ice06)
+///
+/// Checks that the standard tables contain every column they are defined
+/// with.
+pub(crate) fn ice06(db: &Database) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    for table in db.tables() {
+        let Some(standard) = standard_table(&table.name) else {
+            continue;
+        };
+        for column in standard {
+            if table.column_index(column.name).is_none() {
+                findings.push(Finding {
+                    ice: 6,
+                    severity: Severity::Error,
+                    table: Some(table.name.clone()),
+                    key: None,
+                    message: format!("Column {} is missing", column.name),
+                });
+            }
+        }
+    }
+    findings
+}
+
+/// # [ICE18](https://learn.microsoft.com/en-us/windows/win32/msi/ice18)
+///
+/// A component with a null `KeyPath` uses its directory as the key path, so
+/// that directory has to be created through the `CreateFolder` table.
+pub(crate) fn ice18(db: &Database) -> Vec<Finding> {
+    let Some(components) = db.table("Component") else {
+        return Vec::new();
+    };
+    let create_folder = db.table("CreateFolder");
+    let mut findings = Vec::new();
+    for row in &components.rows {
+        if components.str(row, "KeyPath").is_some() {
+            continue;
+        }
+        let component = components.str(row, "Component").unwrap_or_default();
+        let directory = components.str(row, "Directory_").unwrap_or_default();
+        let created = create_folder.is_some_and(|t| {
+            t.rows.iter().any(|r| {
+                t.str(r, "Component_") == Some(component)
+                    && t.str(r, "Directory_") == Some(directory)
+            })
+        });
+        if !created {
+            findings.push(Finding::new(
+                18,
+                Severity::Error,
+                components,
+                row,
+                format!(
+                    "KeyPath is null but directory {directory} is not in the \
+                    CreateFolder table for this component"
+                ),
+            ));
+        }
+    }
+    findings
+}
+
+/// # [ICE30](https://learn.microsoft.com/en-us/windows/win32/msi/ice30)
+///
+/// Checks that no two components install a file to the same path.
+pub(crate) fn ice30(db: &Database) -> Vec<Finding> {
+    let Some(files) = db.table("File") else {
+        return Vec::new();
+    };
+    let directories = DirectoryTree::new(db);
+
+    let mut seen: BTreeMap<String, (String, String)> = BTreeMap::new();
+    let mut findings = Vec::new();
+    for row in &files.rows {
+        let component = files.str(row, "Component_").unwrap_or_default();
+        let Some(directory) = db.component_directory(component) else {
+            continue;
+        };
+        let name = long_name(files.str(row, "FileName").unwrap_or_default());
+        let path =
+            format!("{}/{}", directories.path(directory), name).to_lowercase();
+
+        let file = files.str(row, "File").unwrap_or_default().to_owned();
+        match seen.get(&path) {
+            Some((other_file, other_component))
+                if other_component != component =>
+            {
+                findings.push(Finding::new(
+                    30,
+                    Severity::Error,
+                    files,
+                    row,
+                    format!(
+                        "Installs to {path}, which file {other_file} of \
+                        component {other_component} also installs to"
+                    ),
+                ));
+            }
+            Some(_) => {}
+            None => {
+                seen.insert(path, (file, component.to_owned()));
+            }
+        }
+    }
+    findings
+}
+
+/// # [ICE33](https://learn.microsoft.com/en-us/windows/win32/msi/ice33)
+///
+/// COM registration written directly to the `Registry` table is not
+/// advertised and isn't repaired. It should be authored in the `Class`,
+/// `ProgId`, `TypeLib` and related tables instead.
+pub(crate) fn ice33(db: &Database) -> Vec<Finding> {
+    const COM_KEYS: &[&str] =
+        &["clsid\\", "interface\\", "typelib\\", "appid\\"];
+    let Some(registry) = db.table("Registry") else {
+        return Vec::new();
+    };
+    let mut findings = Vec::new();
+    for row in &registry.rows {
+        let key = registry.str(row, "Key").unwrap_or_default().to_lowercase();
+        let class_key = match registry.int(row, "Root") {
+            Some(0) => Some(key.as_str()),
+            _ => key.strip_prefix("software\\classes\\"),
+        };
+        if class_key.is_some_and(|k| COM_KEYS.iter().any(|c| k.starts_with(c)))
+        {
+            findings.push(Finding::new(
+                33,
+                Severity::Warning,
+                registry,
+                row,
+                "COM registration should be authored in the Class, ProgId \
+                or TypeLib tables instead of the Registry table",
+            ));
+        }
+    }
+    findings
+}
+
+/// # [ICE38](https://learn.microsoft.com/en-us/windows/win32/msi/ice38)
+///
+/// Components installed into the user profile must use a registry key under
+/// `HKEY_CURRENT_USER` as their key path.
+pub(crate) fn ice38(db: &Database) -> Vec<Finding> {
+    let Some(components) = db.table("Component") else {
+        return Vec::new();
+    };
+    let directories = DirectoryTree::new(db);
+    let mut findings = Vec::new();
+    for row in &components.rows {
+        let directory = components.str(row, "Directory_").unwrap_or_default();
+        if !directories.in_user_profile(directory) {
+            continue;
+        }
+        if key_path_root(db, components, row) != Some(HKCU) {
+            findings.push(Finding::new(
+                38,
+                Severity::Error,
+                components,
+                row,
+                format!(
+                    "Installs to the user profile through {directory} but its \
+                    KeyPath is not a registry key under HKCU"
+                ),
+            ));
+        }
+    }
+    findings
+}
+
+/// # [ICE43](https://learn.microsoft.com/en-us/windows/win32/msi/ice43)
+///
+/// Components holding non-advertised shortcuts must use a registry key under
+/// `HKEY_CURRENT_USER` as their key path.
+pub(crate) fn ice43(db: &Database) -> Vec<Finding> {
+    let (Some(shortcuts), Some(components)) =
+        (db.table("Shortcut"), db.table("Component"))
+    else {
+        return Vec::new();
+    };
+    let mut findings = Vec::new();
+    for row in &shortcuts.rows {
+        // Advertised shortcuts point at a feature, non-advertised shortcuts
+        // use a formatted target such as `[#File]`.
+        let target = shortcuts.str(row, "Target").unwrap_or_default();
+        if !target.starts_with('[') {
+            continue;
+        }
+        let component = shortcuts.str(row, "Component_").unwrap_or_default();
+        let Some(component_row) = db.row("Component", component) else {
+            continue;
+        };
+        if key_path_root(db, components, component_row) != Some(HKCU) {
+            findings.push(Finding::new(
+                43,
+                Severity::Error,
+                shortcuts,
+                row,
+                format!(
+                    "Non-advertised shortcut belongs to component {component} \
+                    whose KeyPath is not a registry key under HKCU"
+                ),
+            ));
+        }
+    }
+    findings
+}
+
+/// # [ICE57](https://learn.microsoft.com/en-us/windows/win32/msi/ice57)
+///
+/// A component must not mix per-user and per-machine data.
+pub(crate) fn ice57(db: &Database) -> Vec<Finding> {
+    let (Some(components), Some(registry)) =
+        (db.table("Component"), db.table("Registry"))
+    else {
+        return Vec::new();
+    };
+    let mut roots: HashMap<&str, BTreeSet<i32>> = HashMap::new();
+    for row in &registry.rows {
+        if let (Some(component), Some(root)) =
+            (registry.str(row, "Component_"), registry.int(row, "Root"))
+        {
+            roots.entry(component).or_default().insert(root);
+        }
+    }
+
+    let mut findings = Vec::new();
+    for row in &components.rows {
+        let component = components.str(row, "Component").unwrap_or_default();
+        let Some(roots) = roots.get(component) else {
+            continue;
+        };
+        let message = if roots.contains(&HKCU) && roots.contains(&HKLM) {
+            "Writes registry values under both HKCU and HKLM"
+        } else if roots.contains(&HKCU)
+            && key_path_root(db, components, row) != Some(HKCU)
+        {
+            "Writes registry values under HKCU but has a per-machine KeyPath"
+        } else {
+            continue;
+        };
+        findings.push(Finding::new(
+            57,
+            Severity::Error,
+            components,
+            row,
+            message,
+        ));
+    }
+    findings
+}
+
+/// # [ICE64](https://learn.microsoft.com/en-us/windows/win32/msi/ice64)
+///
+/// Directories created in the user profile must be removed on uninstall
+/// through the `RemoveFile` table.
+pub(crate) fn ice64(db: &Database) -> Vec<Finding> {
+    let Some(directories) = db.table("Directory") else {
+        return Vec::new();
+    };
+    let tree = DirectoryTree::new(db);
+    let remove_file = db.table("RemoveFile");
+    let mut findings = Vec::new();
+    for row in &directories.rows {
+        let directory = directories.str(row, "Directory").unwrap_or_default();
+        if USER_PROFILE_FOLDERS.contains(&directory)
+            || !tree.in_user_profile(directory)
+        {
+            continue;
+        }
+        let removed = remove_file.is_some_and(|t| {
+            t.rows.iter().any(|r| {
+                t.str(r, "DirProperty") == Some(directory)
+                    && t.str(r, "FileName").is_none()
+            })
+        });
+        if !removed {
+            findings.push(Finding::new(
+                64,
+                Severity::Error,
+                directories,
+                row,
+                "Directory is in the user profile but is not removed by the \
+                RemoveFile table",
+            ));
+        }
+    }
+    findings
+}
+
+/// # [ICE99](https://learn.microsoft.com/en-us/windows/win32/msi/ice99)
+///
+/// Directory names must not collide with properties that Windows Installer
+/// sets itself.
+pub(crate) fn ice99(db: &Database) -> Vec<Finding> {
+    let Some(directories) = db.table("Directory") else {
+        return Vec::new();
+    };
+    directories
+        .rows
+        .iter()
+        .filter(|row| {
+            directories
+                .str(row, "Directory")
+                .is_some_and(|d| RESERVED_PROPERTIES.contains(&d))
+        })
+        .map(|row| {
+            Finding::new(
+                99,
+                Severity::Error,
+                directories,
+                row,
+                "Directory name is a property reserved by Windows Installer",
+            )
+        })
+        .collect()
+}
+
+/// The registry root of a component's key path, if its key path is a
+/// registry key.
+fn key_path_root(
+    db: &Database,
+    components: &TableData,
+    row: &[Value],
+) -> Option<i32> {
+    let attributes = components.int(row, "Attributes").unwrap_or_default();
+    if attributes & REGISTRY_KEY_PATH == 0 {
+        return None;
+    }
+    let key_path = components.str(row, "KeyPath")?;
+    let registry = db.table("Registry")?;
+    registry.int(db.row("Registry", key_path)?, "Root")
+}
+
+/// The long form of a `short|long` file or directory name.
+fn long_name(name: &str) -> &str {
+    name.rsplit_once('|').map(|(_, long)| long).unwrap_or(name)
+}
+
+/// The parent and target name of every directory, used to resolve where
+/// things are installed.
+struct DirectoryTree<'a> {
+    entries: HashMap<&'a str, (Option<&'a str>, &'a str)>,
+}
+
+impl<'a> DirectoryTree<'a> {
+    fn new(db: &'a Database) -> Self {
+        let mut entries = HashMap::new();
+        if let Some(table) = db.table("Directory") {
+            for row in &table.rows {
+                let Some(id) = table.str(row, "Directory") else {
+                    continue;
+                };
+                let parent = table.str(row, "Directory_Parent");
+                // `DefaultDir` is `target[:source]` where each half may be
+                // `short|long`.
+                let default_dir = table.str(row, "DefaultDir").unwrap_or(".");
+                let target = default_dir.split(':').next().unwrap_or(".");
+                entries.insert(id, (parent, long_name(target)));
+            }
+        }
+        DirectoryTree { entries }
+    }
+
+    /// The identifiers of a directory and each of its ancestors.
+    fn ancestry(&self, directory: &'a str) -> Vec<&'a str> {
+        let mut chain = vec![directory];
+        let mut current = directory;
+        while let Some((Some(parent), _)) = self.entries.get(current) {
+            // A directory that is its own parent is a root. Guard against
+            // longer cycles as well so bad data can't hang validation.
+            if *parent == current || chain.contains(parent) {
+                break;
+            }
+            chain.push(parent);
+            current = parent;
+        }
+        chain
+    }
+
+    /// The install path of a directory, starting with the key of the
+    /// system folder or root it is in. Those are resolved by Windows
+    /// Installer, so two of them are never taken to be the same folder even
+    /// when their `DefaultDir` is the same.
+    fn path(&self, directory: &str) -> String {
+        let Some((&id, _)) = self.entries.get_key_value(directory) else {
+            return directory.to_owned();
+        };
+        let chain = self.ancestry(id);
+        let mut segments = Vec::new();
+        for (index, d) in chain.iter().enumerate() {
+            if SYSTEM_FOLDERS.contains(d) || index == chain.len() - 1 {
+                segments.push(*d);
+                break;
+            }
+            match self.entries.get(d) {
+                Some((_, name)) if *name != "." => segments.push(*name),
+                _ => {}
+            }
+        }
+        segments.reverse();
+        segments.join("/")
+    }
+
+    fn in_user_profile(&self, directory: &'a str) -> bool {
+        self.ancestry(directory)
+            .iter()
+            .any(|d| USER_PROFILE_FOLDERS.contains(d))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use msi::{Category, Column};
+
+    use super::{ice03, ice30, Database};
+
+    const DIRECTORY: &[&str] = &["Directory", "Directory_Parent", "DefaultDir"];
+    const COMPONENT: &[&str] = &["Component", "Directory_"];
+    const FILE: &[&str] = &["File", "Component_", "FileName"];
+
+    fn database(directories: &[&[&str]], files: &[&[&str]]) -> Database {
+        let components = files
+            .iter()
+            .map(|file| [file[1], file[3]])
+            .collect::<Vec<[&str; 2]>>();
+        let components = components
+            .iter()
+            .map(|c| c.as_slice())
+            .collect::<Vec<&[&str]>>();
+        let files = files
+            .iter()
+            .map(|file| &file[..3])
+            .collect::<Vec<&[&str]>>();
+        Database::of_strings(&[
+            (DIRECTORY, directories),
+            (COMPONENT, &components),
+            (FILE, &files),
+        ])
+    }
+
+    /// Files that are the only file of their component, as file key,
+    /// component key, file name and directory.
+    const CONFIGS: &[&[&str]] = &[
+        &["InstallCfg", "InstallCfgC", "app.cfg", "INSTALLDIR"],
+        &["DataCfg", "DataCfgC", "app.cfg", "DataDir"],
+    ];
+
+    #[test]
+    fn different_roots_are_different_folders() {
+        let db = database(
+            &[
+                &["TARGETDIR", "", "SourceDir"],
+                &["ProgramFilesFolder", "TARGETDIR", "."],
+                &["INSTALLDIR", "ProgramFilesFolder", "Product"],
+                &["CommonAppDataFolder", "TARGETDIR", "."],
+                &["DataDir", "CommonAppDataFolder", "Product"],
+            ],
+            CONFIGS,
+        );
+        assert!(ice30(&db).is_empty());
+    }
+
+    #[test]
+    fn finds_files_installed_to_the_same_path() {
+        let db = database(
+            &[
+                &["TARGETDIR", "", "SourceDir"],
+                &["ProgramFilesFolder", "TARGETDIR", "."],
+                &["INSTALLDIR", "ProgramFilesFolder", "PRODUCT|Product"],
+                // A `.` directory that isn't a system folder is the same
+                // folder as its parent.
+                &["Same", "INSTALLDIR", "."],
+                &["DataDir", "Same", "."],
+            ],
+            CONFIGS,
+        );
+        let findings = ice30(&db);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].key.as_deref(), Some("DataCfg"));
+        assert!(findings[0]
+            .message
+            .contains("programfilesfolder/product/app.cfg"));
+    }
+
+    #[test]
+    fn finds_keys_that_are_not_in_the_foreign_table() {
+        let db = Database::of_strings(&[
+            (DIRECTORY, &[&["INSTALLDIR", "", "SourceDir"]]),
+            (COMPONENT, &[&["App", "INSTALLDIR"], &["Data", "DataDir"]]),
+        ]);
+        let findings = ice03(&db);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].key.as_deref(), Some("Data"));
+        assert_eq!(
+            findings[0].message,
+            "Directory_: Key DataDir is not in the Directory table"
+        );
+    }
+
+    #[test]
+    fn leaves_out_the_system_tables() {
+        let mut db = Database::of_strings(&[
+            (DIRECTORY, &[&["INSTALLDIR", "", "SourceDir"]]),
+            (
+                &["_Validation", "Column", "KeyTable"],
+                &[&[
+                    "Signature",
+                    "Signature",
+                    "Signature;RegLocator;IniLocator;DrLocator;CompLocator",
+                ]],
+            ),
+        ]);
+        let validation = db.table_mut("_Validation").unwrap();
+        validation.columns[2] = Column::build("KeyTable")
+            .nullable()
+            .category(Category::Identifier)
+            .string(255);
+        assert!(ice03(&db).is_empty());
+    }
+}
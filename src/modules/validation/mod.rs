@@ -0,0 +1,113 @@
+pub(crate) mod database;
+pub(crate) mod ice;
+pub(crate) mod schema;
+
+use std::io::{Read, Seek};
+
+use anyhow::Result;
+use log::debug;
+use msi::{Package, Value};
+use serde::Serialize;
+
+use database::{Database, TableData};
+
+/// How serious a validation finding is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Severity {
+    Error,
+    Warning,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A single problem found by one of the Internal Consistency Evaluators.
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct Finding {
+    pub(crate) ice: u16,
+    pub(crate) severity: Severity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) table: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) key: Option<String>,
+    pub(crate) message: String,
+}
+
+impl Finding {
+    /// Create a finding about a single row of a table.
+    pub(crate) fn new(
+        ice: u16,
+        severity: Severity,
+        table: &TableData,
+        row: &[Value],
+        message: impl Into<String>,
+    ) -> Finding {
+        Finding {
+            ice,
+            severity,
+            table: Some(table.name.clone()),
+            key: Some(table.key(row)),
+            message: message.into(),
+        }
+    }
+
+    /// The `ICE00` style name of the evaluator that produced this finding.
+    pub(crate) fn ice_name(&self) -> String {
+        format!("ICE{:02}", self.ice)
+    }
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.ice_name(), self.severity)?;
+        if let Some(table) = &self.table {
+            write!(f, " [{}", table)?;
+            if let Some(key) = &self.key {
+                write!(f, ".{}", key)?;
+            }
+            write!(f, "]")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+type Check = fn(&Database) -> Vec<Finding>;
+
+/// Every evaluator that whimsi implements, in the order they are run.
+const CHECKS: &[(u16, Check)] = &[
+    (3, ice::ice03),
+    (6, ice::ice06),
+    (18, ice::ice18),
+    (30, ice::ice30),
+    (33, ice::ice33),
+    (38, ice::ice38),
+    (43, ice::ice43),
+    (57, ice::ice57),
+    (64, ice::ice64),
+    (99, ice::ice99),
+];
+
+/// Run every evaluator against the package.
+pub(crate) fn validate<F: Read + Seek>(
+    package: &mut Package<F>,
+) -> Result<Vec<Finding>> {
+    let db = Database::load(package)?;
+    let mut findings = Vec::new();
+    for (ice, check) in CHECKS {
+        debug!("Running ICE{:02}", ice);
+        findings.extend(check(&db));
+    }
+    Ok(findings)
+}
+
+/// Whether any of the findings should fail a build.
+pub(crate) fn has_errors(findings: &[Finding]) -> bool {
+    findings.iter().any(|f| f.severity == Severity::Error)
+}
@@ -0,0 +1,212 @@
+/// A column of a standard Windows Installer table and, if the column refers
+/// to another table, the name of that table.
+pub(crate) struct StandardColumn {
+    pub(crate) name: &'static str,
+    pub(crate) foreign_table: Option<&'static str>,
+}
+
+const fn col(name: &'static str) -> StandardColumn {
+    StandardColumn {
+        name,
+        foreign_table: None,
+    }
+}
+
+const fn fk(name: &'static str, table: &'static str) -> StandardColumn {
+    StandardColumn {
+        name,
+        foreign_table: Some(table),
+    }
+}
+
+/// Tables that describe the database itself. Their values follow their own
+/// rules, such as `_Validation.KeyTable` holding a `;` separated list of
+/// tables, so they are not checked against their categories.
+pub(crate) const SYSTEM_TABLES: &[&str] =
+    &["_Tables", "_Columns", "_Validation"];
+
+/// The columns of the standard tables that whimsi authors or validates.
+///
+/// This stands in for the `_Validation` table that Orca ships in its `.cub`
+/// files. Only the tables the checks rely on are listed.
+pub(crate) const STANDARD_TABLES: &[(&str, &[StandardColumn])] = &[
+    (
+        "Component",
+        &[
+            col("Component"),
+            col("ComponentId"),
+            fk("Directory_", "Directory"),
+            col("Attributes"),
+            col("Condition"),
+            col("KeyPath"),
+        ],
+    ),
+    (
+        "CreateFolder",
+        &[fk("Directory_", "Directory"), fk("Component_", "Component")],
+    ),
+    (
+        "Directory",
+        &[
+            col("Directory"),
+            fk("Directory_Parent", "Directory"),
+            col("DefaultDir"),
+        ],
+    ),
+    (
+        "Feature",
+        &[
+            col("Feature"),
+            fk("Feature_Parent", "Feature"),
+            col("Title"),
+            col("Description"),
+            col("Display"),
+            col("Level"),
+            fk("Directory_", "Directory"),
+            col("Attributes"),
+        ],
+    ),
+    (
+        "FeatureComponents",
+        &[fk("Feature_", "Feature"), fk("Component_", "Component")],
+    ),
+    (
+        "File",
+        &[
+            col("File"),
+            fk("Component_", "Component"),
+            col("FileName"),
+            col("FileSize"),
+            col("Version"),
+            col("Language"),
+            col("Attributes"),
+            col("Sequence"),
+        ],
+    ),
+    (
+        "Registry",
+        &[
+            col("Registry"),
+            col("Root"),
+            col("Key"),
+            col("Name"),
+            col("Value"),
+            fk("Component_", "Component"),
+        ],
+    ),
+    (
+        "RemoveFile",
+        &[
+            col("FileKey"),
+            fk("Component_", "Component"),
+            col("FileName"),
+            col("DirProperty"),
+            col("InstallMode"),
+        ],
+    ),
+    (
+        "Shortcut",
+        &[
+            col("Shortcut"),
+            fk("Directory_", "Directory"),
+            col("Name"),
+            fk("Component_", "Component"),
+            col("Target"),
+            col("Arguments"),
+            col("Description"),
+            col("Hotkey"),
+            col("Icon_"),
+            col("IconIndex"),
+            col("ShowCmd"),
+            col("WkDir"),
+        ],
+    ),
+];
+
+/// Find the standard definition of a table.
+pub(crate) fn standard_table(name: &str) -> Option<&'static [StandardColumn]> {
+    STANDARD_TABLES
+        .iter()
+        .find(|(table, _)| *table == name)
+        .map(|(_, columns)| *columns)
+}
+
+/// Folders that resolve to a location inside the profile of the installing
+/// user.
+pub(crate) const USER_PROFILE_FOLDERS: &[&str] = &[
+    "AppDataFolder",
+    "DesktopFolder",
+    "FavoritesFolder",
+    "LocalAppDataFolder",
+    "MyPicturesFolder",
+    "NetHoodFolder",
+    "PersonalFolder",
+    "PrintHoodFolder",
+    "ProgramMenuFolder",
+    "RecentFolder",
+    "SendToFolder",
+    "StartMenuFolder",
+    "StartupFolder",
+    "TemplateFolder",
+];
+
+/// [System folder](https://learn.microsoft.com/en-us/windows/win32/msi/property-reference#system-folder-properties)
+/// properties, which Windows Installer sets to the path of a folder of the
+/// target system.
+pub(crate) const SYSTEM_FOLDERS: &[&str] = &[
+    "AdminToolsFolder",
+    "AppDataFolder",
+    "CommonAppDataFolder",
+    "CommonFiles64Folder",
+    "CommonFilesFolder",
+    "DesktopFolder",
+    "FavoritesFolder",
+    "FontsFolder",
+    "LocalAppDataFolder",
+    "MyPicturesFolder",
+    "NetHoodFolder",
+    "PersonalFolder",
+    "PrintHoodFolder",
+    "ProgramFiles64Folder",
+    "ProgramFilesFolder",
+    "ProgramMenuFolder",
+    "RecentFolder",
+    "SendToFolder",
+    "StartMenuFolder",
+    "StartupFolder",
+    "System16Folder",
+    "System64Folder",
+    "SystemFolder",
+    "TempFolder",
+    "TemplateFolder",
+    "WindowsFolder",
+    "WindowsVolume",
+];
+
+/// Properties that Windows Installer sets itself. A directory with one of
+/// these names would have its path overwritten at install time.
+pub(crate) const RESERVED_PROPERTIES: &[&str] = &[
+    "AdminUser",
+    "ComputerName",
+    "DATE",
+    "LogonUser",
+    "MsiNTProductType",
+    "MsiNTSuiteBackOffice",
+    "MsiNTSuiteDataCenter",
+    "MsiNTSuiteEnterprise",
+    "MsiNTSuitePersonal",
+    "MsiNTSuiteSmallBusiness",
+    "Privileged",
+    "RemoteAdminTS",
+    "ServicePackLevel",
+    "ServicePackLevelMinor",
+    "TIME",
+    "UserLanguageID",
+    "UserSID",
+    "Version9X",
+    "VersionDatabase",
+    "VersionMsi",
+    "VersionNT",
+    "VersionNT64",
+    "WindowsBuild",
+];
@@ -0,0 +1,355 @@
+//! Custom actions, the binaries they run and where they are scheduled.
+
+use std::collections::BTreeMap;
+
+use camino::Utf8PathBuf;
+use msi::Value;
+use roxmltree::Node;
+
+use crate::modules::tables::{
+    sequence::{EXECUTE_ACTIONS, EXECUTE_SEQUENCE, UI_ACTIONS, UI_SEQUENCE},
+    value_check::SourcedRow,
+};
+
+use super::{elements, inner_text, optional, Reader};
+
+/// The actions a major upgrade adds to both sequences.
+const UPGRADE_ACTIONS: [(&str, i32); 2] =
+    [("FindRelatedProducts", 25), ("MigrateFeatureStates", 1200)];
+
+/// [`CustomAction.Type`](https://learn.microsoft.com/en-us/windows/win32/msi/summary-list-of-all-custom-action-types)
+/// bits for how the action is run.
+const NO_IMPERSONATE: i32 = 0x800;
+const HIDE_TARGET: i32 = 0x2000;
+
+#[derive(Default)]
+pub(super) struct Actions {
+    /// Names of `Binary` rows and the files their streams are read from.
+    pub(super) binaries: Vec<(String, Utf8PathBuf)>,
+    scheduled: Vec<Scheduled>,
+    /// Sequence number of `RemoveExistingProducts` if there is a major
+    /// upgrade.
+    pub(super) upgrade_schedule: Option<i32>,
+}
+
+/// A `Custom` element, scheduling a custom action in a sequence.
+struct Scheduled {
+    table: &'static str,
+    action: String,
+    condition: Option<String>,
+    anchor: Anchor,
+    location: String,
+    source: Option<Utf8PathBuf>,
+}
+
+enum Anchor {
+    Sequence(i32),
+    After(String),
+    Before(String),
+}
+
+impl Reader<'_, '_> {
+    pub(super) fn custom_action(&mut self, node: Node) {
+        let Some(id) = self.required(node, "Id") else {
+            return;
+        };
+        for child in elements(node) {
+            self.unsupported(child);
+        }
+        for script in ["Script", "JScriptCall", "VBScriptCall"] {
+            if node.attribute(script).is_some() {
+                self.problem(node, "Script custom actions are not supported");
+                return;
+            }
+        }
+        if self.is_defined("CustomAction", id) {
+            self.problem(
+                node,
+                format!("CustomAction {id} is defined more than once"),
+            );
+            return;
+        }
+
+        // v4 renamed the attributes that refer to rows of other tables.
+        let binary =
+            node.attribute("BinaryRef").or(node.attribute("BinaryKey"));
+        let file = node.attribute("FileRef").or(node.attribute("FileKey"));
+        let property = node.attribute("Property");
+        let directory = node.attribute("Directory");
+        let command = node.attribute("ExeCommand");
+        let value = node.attribute("Value");
+        let action = match (binary, file, property, directory) {
+            (Some(binary), None, None, None) => {
+                self.reference("Binary", binary, node);
+                match (node.attribute("DllEntry"), command) {
+                    (Some(entry), None) => Some((1, binary, entry)),
+                    (None, Some(command)) => Some((2, binary, command)),
+                    _ => None,
+                }
+            }
+            (None, Some(file), None, None) => {
+                self.reference("File", file, node);
+                command.map(|command| (18, file, command))
+            }
+            (None, None, Some(property), None) => match (value, command) {
+                (Some(value), None) => Some((51, property, value)),
+                (None, Some(command)) => Some((50, property, command)),
+                _ => None,
+            },
+            (None, None, None, Some(directory)) => {
+                self.reference("Directory", directory, node);
+                match (value, command) {
+                    (Some(value), None) => Some((35, directory, value)),
+                    (None, Some(command)) => Some((34, directory, command)),
+                    _ => None,
+                }
+            }
+            (None, None, None, None) => {
+                node.attribute("Error").map(|error| (19, "", error))
+            }
+            _ => None,
+        };
+        let Some((mut kind, source, target)) = action else {
+            self.problem(
+                node,
+                "CustomAction needs a binary with a DllEntry or ExeCommand, a \
+                file with an ExeCommand, a Property or Directory with a Value \
+                or ExeCommand, or an Error",
+            );
+            return;
+        };
+
+        kind |= self
+            .choice(
+                node,
+                "Execute",
+                &[
+                    ("immediate", 0),
+                    ("firstSequence", 0x100),
+                    ("oncePerProcess", 0x200),
+                    ("secondSequence", 0x300),
+                    ("deferred", 0x400),
+                    ("rollback", 0x500),
+                    ("commit", 0x600),
+                ],
+            )
+            .unwrap_or(0);
+        kind |= self
+            .choice(
+                node,
+                "Return",
+                &[
+                    ("check", 0),
+                    ("ignore", 0x40),
+                    ("asyncWait", 0x80),
+                    ("asyncNoWait", 0xC0),
+                ],
+            )
+            .unwrap_or(0);
+        if !self.yes_no(node, "Impersonate", true) {
+            kind |= NO_IMPERSONATE;
+        }
+        if self.yes_no(node, "HideTarget", false) {
+            kind |= HIDE_TARGET;
+        }
+        self.add_row(
+            "CustomAction",
+            node,
+            vec![
+                Value::from(id),
+                Value::from(kind),
+                optional(Some(source).filter(|source| !source.is_empty())),
+                Value::from(target),
+            ],
+        );
+    }
+
+    /// A `Binary` that custom actions can run, read from the input
+    /// directory.
+    pub(super) fn binary(&mut self, node: Node) {
+        let Some(id) = self.required(node, "Id") else {
+            return;
+        };
+        for child in elements(node) {
+            self.unsupported(child);
+        }
+        let Some(source) =
+            node.attribute("SourceFile").or(node.attribute("src"))
+        else {
+            self.required(node, "SourceFile");
+            return;
+        };
+        let path = self.input_directory.join(source.replace('\\', "/"));
+        if !path.is_file() {
+            self.problem(
+                node,
+                format!(
+                    "There is no file {} in {}",
+                    source, self.input_directory
+                ),
+            );
+            return;
+        }
+        if self.is_defined("Binary", id) {
+            self.problem(
+                node,
+                format!("Binary {id} is defined more than once"),
+            );
+            return;
+        }
+        self.actions.binaries.push((id.to_owned(), path));
+    }
+
+    /// An `InstallExecuteSequence` or `InstallUISequence`, which can only
+    /// schedule custom actions. The standard actions are always scheduled
+    /// where WiX schedules them.
+    pub(super) fn sequence(&mut self, node: Node) {
+        let table = match node.tag_name().name() {
+            UI_SEQUENCE => UI_SEQUENCE,
+            _ => EXECUTE_SEQUENCE,
+        };
+        for child in elements(node) {
+            if self.wix_name(child) != Some("Custom") {
+                self.unsupported(child);
+                continue;
+            }
+            let Some(action) = self.required(child, "Action") else {
+                continue;
+            };
+            self.reference("CustomAction", action, child);
+            let sequence = self.number(child, "Sequence");
+            let anchor = match (
+                sequence,
+                child.attribute("After"),
+                child.attribute("Before"),
+            ) {
+                (Some(sequence), None, None) => Anchor::Sequence(sequence),
+                (None, Some(after), None) => Anchor::After(after.to_owned()),
+                (None, None, Some(before)) => Anchor::Before(before.to_owned()),
+                _ => {
+                    self.problem(
+                        child,
+                        "Custom needs exactly one of Sequence, After or Before",
+                    );
+                    continue;
+                }
+            };
+            let condition = match self.v4 {
+                true => child.attribute("Condition"),
+                false => inner_text(child),
+            };
+            self.actions.scheduled.push(Scheduled {
+                table,
+                action: action.to_owned(),
+                condition: condition.map(str::to_owned),
+                anchor,
+                location: self.location(child),
+                source: self.source(child),
+            });
+        }
+    }
+
+    /// Write both sequences with the standard actions and the custom
+    /// actions placed around them. An action placed after or before another
+    /// gets the nearest free sequence number on that side of it.
+    pub(super) fn sequence_rows(&mut self) {
+        let mut execute = EXECUTE_ACTIONS.to_vec();
+        let mut ui = UI_ACTIONS.to_vec();
+        if let Some(schedule) = self.actions.upgrade_schedule {
+            execute.extend(UPGRADE_ACTIONS);
+            execute.push(("RemoveExistingProducts", schedule));
+            ui.extend(UPGRADE_ACTIONS);
+        }
+        let mut sequences: BTreeMap<&'static str, BTreeMap<String, i32>> =
+            [(EXECUTE_SEQUENCE, execute), (UI_SEQUENCE, ui)]
+                .into_iter()
+                .map(|(table, actions)| {
+                    let actions = actions
+                        .into_iter()
+                        .map(|(action, sequence)| (action.to_owned(), sequence))
+                        .collect();
+                    (table, actions)
+                })
+                .collect();
+        let mut rows: BTreeMap<&'static str, Vec<SourcedRow>> = BTreeMap::new();
+        for (table, actions) in &sequences {
+            let standard = actions.iter().map(|(action, sequence)| {
+                SourcedRow::new(
+                    vec![
+                        Value::from(action.as_str()),
+                        Value::Null,
+                        Value::from(*sequence),
+                    ],
+                    None,
+                )
+            });
+            rows.insert(*table, standard.collect());
+        }
+
+        // Actions can be placed relative to custom actions that are placed
+        // later in the source, so keep going while anything gets placed.
+        let mut pending = std::mem::take(&mut self.actions.scheduled);
+        loop {
+            let count = pending.len();
+            let mut waiting = Vec::new();
+            for scheduled in pending {
+                let actions = sequences.get_mut(scheduled.table).unwrap();
+                if actions.contains_key(&scheduled.action) {
+                    self.problems.push(format!(
+                        "{}: {} is already in the {}",
+                        scheduled.location, scheduled.action, scheduled.table
+                    ));
+                    continue;
+                }
+                let Some(sequence) = place(actions, &scheduled.anchor) else {
+                    waiting.push(scheduled);
+                    continue;
+                };
+                actions.insert(scheduled.action.clone(), sequence);
+                rows.entry(scheduled.table)
+                    .or_default()
+                    .push(SourcedRow::new(
+                        vec![
+                            Value::from(scheduled.action),
+                            optional(scheduled.condition.as_deref()),
+                            Value::from(sequence),
+                        ],
+                        scheduled.source,
+                    ));
+            }
+            pending = waiting;
+            if pending.is_empty() || pending.len() == count {
+                break;
+            }
+        }
+        for scheduled in pending {
+            let (Anchor::After(anchor) | Anchor::Before(anchor)) =
+                &scheduled.anchor
+            else {
+                continue;
+            };
+            self.problems.push(format!(
+                "{}: {} is scheduled next to {}, which isn't in the {}",
+                scheduled.location, scheduled.action, anchor, scheduled.table
+            ));
+        }
+        self.rows.extend(rows);
+    }
+}
+
+/// The sequence number for an action placed at `anchor`, or `None` if the
+/// action it is placed next to isn't in `actions` yet.
+fn place(actions: &BTreeMap<String, i32>, anchor: &Anchor) -> Option<i32> {
+    let taken = |sequence: &i32| actions.values().any(|s| s == sequence);
+    match anchor {
+        Anchor::Sequence(sequence) => Some(*sequence),
+        Anchor::After(action) => {
+            let start = actions.get(action)? + 1;
+            (start..).find(|sequence| !taken(sequence))
+        }
+        Anchor::Before(action) => {
+            let end = *actions.get(action)?;
+            (1..end).rev().find(|sequence| !taken(sequence))
+        }
+    }
+}
@@ -0,0 +1,1077 @@
+//! The elements that describe what is installed and where: directories,
+//! components with their files, registry values, shortcuts and services,
+//! and the features the components belong to.
+
+use std::collections::BTreeSet;
+
+use camino::Utf8PathBuf;
+use flexstr::LocalStr;
+use msi::Value;
+use roxmltree::Node;
+use uuid::Uuid;
+
+use crate::modules::{
+    component::{directory::Directory, file::File},
+    tables::{component::component_guid, value_check::SourcedRow},
+    traits::identifier::Identifier,
+};
+
+use super::{elements, inner_text, optional, Reader};
+
+pub(super) const TARGETDIR: &str = "TARGETDIR";
+
+/// [`Component.Attributes`](https://learn.microsoft.com/en-us/windows/win32/msi/component-table)
+/// bits.
+const REGISTRY_KEY_PATH: i32 = 0x4;
+const PERMANENT: i32 = 0x10;
+const NEVER_OVERWRITE: i32 = 0x80;
+const BITNESS_64: i32 = 0x100;
+
+/// [`Feature.Attributes`](https://learn.microsoft.com/en-us/windows/win32/msi/feature-table)
+/// bits.
+const DISALLOW_ADVERTISE: i32 = 0x8;
+const UI_DISALLOW_ABSENT: i32 = 0x10;
+
+/// [`ServiceInstall.ErrorControl`](https://learn.microsoft.com/en-us/windows/win32/msi/serviceinstall-table)
+/// bit that fails the installation if the service can't be installed.
+const SERVICE_VITAL: i32 = 0x8000;
+const SERVICE_INTERACTIVE: i32 = 0x100;
+
+/// The root of the directory tree, which every source has whether or not it
+/// defines it.
+pub(super) fn target_dir() -> Directory {
+    Directory::new(TARGETDIR, None::<LocalStr>, "SourceDir", None)
+}
+
+/// A component, with what is needed to pick its key path once all of its
+/// children have been read.
+pub(super) struct Component {
+    pub(super) id: String,
+    directory: String,
+    /// `None` to derive the GUID as for the components whimsi makes itself.
+    guid: Option<String>,
+    attributes: i32,
+    /// Set by `Win64` or `Bitness`, otherwise taken from the platform.
+    is_64bit: Option<bool>,
+    condition: Option<String>,
+    /// The explicit key path and whether it is a registry value.
+    key_path: Option<(String, bool)>,
+    first_file: Option<String>,
+    first_registry: Option<String>,
+    location: String,
+    source: Option<Utf8PathBuf>,
+}
+
+/// A component or component group included in a feature or group.
+pub(super) enum Member {
+    Component(String),
+    Group(String, String),
+}
+
+pub(super) struct Feature {
+    id: String,
+    parent: Option<String>,
+    title: Option<String>,
+    description: Option<String>,
+    display: Display,
+    level: i32,
+    directory: Option<String>,
+    attributes: i32,
+    members: Vec<Member>,
+    source: Option<Utf8PathBuf>,
+}
+
+#[derive(Clone, Copy)]
+enum Display {
+    Collapse,
+    Expand,
+    Hidden,
+}
+
+impl Reader<'_, '_> {
+    /// A `Directory` under the directory `parent`. A directory without a
+    /// name, or named `.`, is the same folder as its parent.
+    pub(super) fn directory(&mut self, node: Node, parent: &str) {
+        let name = node.attribute("Name").unwrap_or(".");
+        let id = match node.attribute("Id") {
+            Some(id) => id.to_owned(),
+            None => Uuid::from_seed(&format!("directory:{parent}/{name}"))
+                .to_string(),
+        };
+        // TARGETDIR is always defined, but v3 sources have to define it
+        // themselves.
+        if id != TARGETDIR {
+            if !self.directory_ids.insert(id.clone()) {
+                self.problem(
+                    node,
+                    format!("Directory {id} is defined more than once"),
+                );
+            }
+            let source = (name != ".").then(|| self.source(node)).flatten();
+            self.directories.push(Directory::new(
+                id.as_str(),
+                Some(LocalStr::from(parent)),
+                name,
+                source,
+            ));
+        }
+        self.directory_children(node, &id);
+    }
+
+    /// A v4 `StandardDirectory`, one of the folders Windows Installer
+    /// resolves itself, such as `ProgramFiles64Folder`.
+    pub(super) fn standard_directory(&mut self, node: Node) {
+        let Some(id) = self.required(node, "Id") else {
+            return;
+        };
+        if id != TARGETDIR && self.directory_ids.insert(id.to_owned()) {
+            self.directories.push(Directory::new(
+                id,
+                Some(LocalStr::from(TARGETDIR)),
+                ".",
+                None,
+            ));
+        }
+        self.directory_children(node, id);
+    }
+
+    pub(super) fn directory_ref(&mut self, node: Node) {
+        let Some(id) = self.required(node, "Id") else {
+            return;
+        };
+        self.reference("Directory", id, node);
+        self.directory_children(node, id);
+    }
+
+    fn directory_children(&mut self, node: Node, id: &str) {
+        for child in elements(node) {
+            match self.wix_name(child) {
+                Some("Directory") => self.directory(child, id),
+                Some("Component") => {
+                    self.component(child, Some(id));
+                }
+                _ => self.unsupported(child),
+            }
+        }
+    }
+
+    /// A `Component` installed into `directory` unless it names its own.
+    /// Returns its identifier so features and groups can include it.
+    pub(super) fn component(
+        &mut self,
+        node: Node,
+        directory: Option<&str>,
+    ) -> Option<String> {
+        let directory = match node.attribute("Directory") {
+            Some(directory) => {
+                self.reference("Directory", directory, node);
+                directory
+            }
+            None => match directory {
+                Some(directory) => directory,
+                None => {
+                    self.problem(node, "Component needs a Directory");
+                    return None;
+                }
+            },
+        };
+        // Components of v4 sources can leave out the Id, in which case they
+        // take the Id of their first file.
+        let id = match node.attribute("Id") {
+            Some(id) => id.to_owned(),
+            None => match elements(node)
+                .find(|child| self.wix_name(*child) == Some("File"))
+            {
+                Some(file) => self.file_id(file, directory),
+                None => {
+                    self.problem(node, "Component needs an Id");
+                    return None;
+                }
+            },
+        };
+        if self.components.iter().any(|component| component.id == id) {
+            self.problem(
+                node,
+                format!("Component {id} is defined more than once"),
+            );
+            return None;
+        }
+
+        let guid = match node.attribute("Guid") {
+            // An empty GUID leaves the component unmanaged.
+            Some("") => Some(String::new()),
+            _ => self.guid(node, "Guid").filter(|guid| guid != "*"),
+        };
+        let mut attributes = 0;
+        for (name, attribute) in [
+            ("Permanent", PERMANENT),
+            ("NeverOverwrite", NEVER_OVERWRITE),
+        ] {
+            if self.yes_no(node, name, false) {
+                attributes |= attribute;
+            }
+        }
+        let is_64bit = match self.v4 {
+            true => self
+                .choice(
+                    node,
+                    "Bitness",
+                    &[
+                        ("default", None),
+                        ("always32", Some(false)),
+                        ("always64", Some(true)),
+                    ],
+                )
+                .flatten(),
+            false => node
+                .attribute("Win64")
+                .map(|_| self.yes_no(node, "Win64", false)),
+        };
+        let mut component = Component {
+            id: id.clone(),
+            directory: directory.to_owned(),
+            guid,
+            attributes,
+            is_64bit,
+            condition: node.attribute("Condition").map(str::to_owned),
+            key_path: None,
+            first_file: None,
+            first_registry: None,
+            location: self.location(node),
+            source: self.source(node),
+        };
+
+        for child in elements(node) {
+            match self.wix_name(child) {
+                Some("File") => self.file(child, &mut component),
+                Some("RegistryValue") => {
+                    self.registry_value(child, &mut component, None)
+                }
+                Some("RegistryKey") => self.registry_key(child, &mut component),
+                Some("Shortcut") => self.shortcut(child, &component, None),
+                Some("ServiceInstall") => {
+                    self.service_install(child, &component)
+                }
+                Some("ServiceControl") => {
+                    self.service_control(child, &component)
+                }
+                Some("CreateFolder") => self.create_folder(child, &component),
+                Some("RemoveFolder") => self.remove_folder(child, &component),
+                Some("Condition") if !self.v4 => {
+                    component.condition = inner_text(child).map(str::to_owned)
+                }
+                _ => self.unsupported(child),
+            }
+        }
+        self.components.push(component);
+        Some(id)
+    }
+
+    /// The Id of a `File`, which is derived from where it is installed if
+    /// it isn't given, like the Ids of the files whimsi finds itself.
+    fn file_id(&self, node: Node, directory: &str) -> String {
+        match node.attribute("Id") {
+            Some(id) => id.to_owned(),
+            None => {
+                let name = self.file_name(node).unwrap_or_default();
+                Uuid::from_seed(&format!("file:{directory}/{name}")).to_string()
+            }
+        }
+    }
+
+    fn file_name(&self, node: Node) -> Option<String> {
+        match node.attribute("Name") {
+            Some(name) => Some(name.to_owned()),
+            None => {
+                let source = node.attribute("Source")?.replace('\\', "/");
+                source.rsplit('/').next().map(str::to_owned)
+            }
+        }
+    }
+
+    fn file(&mut self, node: Node, component: &mut Component) {
+        let Some(source) = self.required(node, "Source") else {
+            return;
+        };
+        // WiX v3 sources often start their paths with the folder they are
+        // built from.
+        let source = source.replace('\\', "/");
+        let source = source.strip_prefix("SourceDir/").unwrap_or(&source);
+        let path = self.input_directory.join(source);
+        let size = match path.metadata() {
+            Ok(metadata) if metadata.is_file() => metadata.len(),
+            _ => {
+                self.problem(
+                    node,
+                    format!(
+                        "There is no file {} in {}",
+                        source, self.input_directory
+                    ),
+                );
+                return;
+            }
+        };
+        let id = self.file_id(node, &component.directory);
+        if self.files.iter().any(|file| file.file_id().as_str() == id) {
+            self.problem(node, format!("File {id} is defined more than once"));
+            return;
+        }
+        let name = self.file_name(node).unwrap_or_default();
+        let file =
+            File::new(&path, &component.directory, self.sequencer.get(), size)
+                .with_ids(&id, &component.id)
+                .with_name(&name)
+                .with_vital(self.yes_no(node, "Vital", true));
+        self.files.push(file);
+
+        if self.yes_no(node, "KeyPath", false) {
+            self.key_path(node, component, &id, false);
+        }
+        component.first_file.get_or_insert_with(|| id.clone());
+        for child in elements(node) {
+            match self.wix_name(child) {
+                Some("Shortcut") => {
+                    self.shortcut(child, component, Some(&format!("[#{id}]")))
+                }
+                _ => self.unsupported(child),
+            }
+        }
+    }
+
+    fn key_path(
+        &mut self,
+        node: Node,
+        component: &mut Component,
+        id: &str,
+        is_registry: bool,
+    ) {
+        if component.key_path.is_some() {
+            self.problem(
+                node,
+                format!(
+                    "Component {} has more than one key path",
+                    component.id
+                ),
+            );
+        }
+        component.key_path = Some((id.to_owned(), is_registry));
+    }
+
+    /// A v3 `RegistryKey`, which gives the root and key of the values in
+    /// it.
+    fn registry_key(&mut self, node: Node, component: &mut Component) {
+        let (Some(root), Some(key)) =
+            (self.registry_root(node), self.required(node, "Key"))
+        else {
+            return;
+        };
+        for child in elements(node) {
+            match self.wix_name(child) {
+                Some("RegistryValue") => {
+                    self.registry_value(child, component, Some((root, key)))
+                }
+                _ => self.unsupported(child),
+            }
+        }
+    }
+
+    fn registry_root(&mut self, node: Node) -> Option<i32> {
+        let root = self.choice(
+            node,
+            "Root",
+            &[
+                ("HKMU", -1),
+                ("HKCR", 0),
+                ("HKCU", 1),
+                ("HKLM", 2),
+                ("HKU", 3),
+            ],
+        );
+        if node.attribute("Root").is_none() {
+            self.required(node, "Root");
+        }
+        root
+    }
+
+    /// A `RegistryValue`, with the root and key of its `RegistryKey` if it
+    /// is in one.
+    fn registry_value(
+        &mut self,
+        node: Node,
+        component: &mut Component,
+        parent: Option<(i32, &str)>,
+    ) {
+        let (root, key) = match parent {
+            Some((root, key)) => (
+                node.attribute("Root")
+                    .map_or(Some(root), |_| self.registry_root(node)),
+                node.attribute("Key").unwrap_or(key).to_owned(),
+            ),
+            None => (
+                self.registry_root(node),
+                self.required(node, "Key").unwrap_or_default().to_owned(),
+            ),
+        };
+        let Some(root) = root else {
+            return;
+        };
+        let name = node.attribute("Name");
+        let kind = self
+            .choice(
+                node,
+                "Type",
+                &[
+                    ("string", "string"),
+                    ("integer", "integer"),
+                    ("expandable", "expandable"),
+                    ("binary", "binary"),
+                    ("multiString", "multiString"),
+                ],
+            )
+            .unwrap_or("string");
+        let action = self
+            .choice(
+                node,
+                "Action",
+                &[
+                    ("write", "write"),
+                    ("append", "append"),
+                    ("prepend", "prepend"),
+                ],
+            )
+            .unwrap_or("write");
+        let mut values = Vec::new();
+        if let Some(value) = node.attribute("Value") {
+            values.push(value.to_owned());
+        }
+        for child in elements(node) {
+            match self.wix_name(child) {
+                Some("MultiStringValue") if kind == "multiString" => {
+                    let value =
+                        child.attribute("Value").or_else(|| inner_text(child));
+                    values.push(value.unwrap_or_default().to_owned());
+                }
+                _ => self.unsupported(child),
+            }
+        }
+        if action != "write" && kind != "multiString" {
+            self.problem(
+                node,
+                "Only multiString values can be appended or prepended",
+            );
+        }
+        let value = match (kind, values.as_slice()) {
+            (_, []) => None,
+            ("multiString", values) => {
+                let joined = values.join("[~]");
+                // A `[~]` at the start appends, at the end prepends, and at
+                // both ends replaces.
+                Some(match action {
+                    "append" => format!("[~]{joined}"),
+                    "prepend" => format!("{joined}[~]"),
+                    _ => format!("[~]{joined}[~]"),
+                })
+            }
+            (_, [_, _, ..]) => {
+                self.problem(
+                    node,
+                    "Only multiString values can have more than one value",
+                );
+                None
+            }
+            ("integer", [value]) => Some(format!("#{value}")),
+            ("expandable", [value]) => Some(format!("#%{value}")),
+            ("binary", [value]) => Some(format!("#x{value}")),
+            (_, [value]) if value.starts_with('#') => Some(format!("#{value}")),
+            (_, [value]) => Some(value.clone()),
+        };
+
+        let id = match node.attribute("Id") {
+            Some(id) => id.to_owned(),
+            None => Uuid::from_seed(&format!(
+                "registry:{}/{}/{}/{}",
+                component.id,
+                root,
+                key,
+                name.unwrap_or_default()
+            ))
+            .to_string(),
+        };
+        if self.yes_no(node, "KeyPath", false) {
+            self.key_path(node, component, &id, true);
+        }
+        component.first_registry.get_or_insert_with(|| id.clone());
+        self.add_row(
+            "Registry",
+            node,
+            vec![
+                Value::from(id.as_str()),
+                Value::from(root),
+                Value::from(key),
+                optional(name),
+                optional(value.as_deref()),
+                Value::from(component.id.as_str()),
+            ],
+        );
+    }
+
+    /// A non-advertised `Shortcut`. `target` is the file the shortcut is
+    /// in, if it is in one.
+    fn shortcut(
+        &mut self,
+        node: Node,
+        component: &Component,
+        target: Option<&str>,
+    ) {
+        if self.yes_no(node, "Advertise", false) {
+            self.problem(node, "Advertised shortcuts are not supported");
+            return;
+        }
+        for unsupported in ["Icon", "Hotkey"] {
+            if node.attribute(unsupported).is_some() {
+                self.problem(
+                    node,
+                    format!("{unsupported} is not supported on shortcuts"),
+                );
+            }
+        }
+        for child in elements(node) {
+            self.unsupported(child);
+        }
+        let target = match (node.attribute("Target"), target) {
+            (Some(target), _) => target.to_owned(),
+            (None, Some(target)) => target.to_owned(),
+            (None, None) => {
+                self.problem(node, "Shortcut needs a Target");
+                return;
+            }
+        };
+        let (Some(id), Some(name)) =
+            (self.required(node, "Id"), self.required(node, "Name"))
+        else {
+            return;
+        };
+        let directory = match node.attribute("Directory") {
+            Some(directory) => {
+                self.reference("Directory", directory, node);
+                directory
+            }
+            None => component.directory.as_str(),
+        };
+        let name = self
+            .shortcut_names
+            .entry(directory.to_owned())
+            .or_default()
+            .msi_name(name);
+        let show = self.choice(
+            node,
+            "Show",
+            &[("normal", 1), ("maximized", 3), ("minimized", 7)],
+        );
+        let working_directory = node.attribute("WorkingDirectory");
+        if let Some(working_directory) = working_directory {
+            self.reference("Directory", working_directory, node);
+        }
+        self.add_row(
+            "Shortcut",
+            node,
+            vec![
+                Value::from(id),
+                Value::from(directory),
+                Value::from(name),
+                Value::from(component.id.as_str()),
+                Value::from(target),
+                optional(node.attribute("Arguments")),
+                optional(node.attribute("Description")),
+                Value::Null,
+                Value::Null,
+                Value::Null,
+                show.map_or(Value::Null, Value::from),
+                optional(working_directory),
+            ],
+        );
+    }
+
+    fn service_install(&mut self, node: Node, component: &Component) {
+        let (Some(id), Some(name)) =
+            (self.required(node, "Id"), self.required(node, "Name"))
+        else {
+            return;
+        };
+        let mut service_type = self
+            .choice(
+                node,
+                "Type",
+                &[("ownProcess", 0x10), ("shareProcess", 0x20)],
+            )
+            .unwrap_or_else(|| {
+                self.required(node, "Type");
+                0x10
+            });
+        if self.yes_no(node, "Interactive", false) {
+            service_type |= SERVICE_INTERACTIVE;
+        }
+        let start = self
+            .choice(
+                node,
+                "Start",
+                &[("auto", 2), ("demand", 3), ("disabled", 4)],
+            )
+            .unwrap_or_else(|| {
+                self.required(node, "Start");
+                3
+            });
+        let mut error_control = self
+            .choice(
+                node,
+                "ErrorControl",
+                &[("ignore", 0), ("normal", 1), ("critical", 3)],
+            )
+            .unwrap_or_else(|| {
+                self.required(node, "ErrorControl");
+                1
+            });
+        if self.yes_no(node, "Vital", false) {
+            error_control |= SERVICE_VITAL;
+        }
+        let mut dependencies = Vec::new();
+        for child in elements(node) {
+            match self.wix_name(child) {
+                Some("ServiceDependency") => {
+                    if let Some(dependency) = self.required(child, "Id") {
+                        dependencies.push(dependency);
+                    }
+                }
+                _ => self.unsupported(child),
+            }
+        }
+        // Dependencies are separated and ended by `[~]`.
+        let dependencies = (!dependencies.is_empty())
+            .then(|| format!("{}[~][~]", dependencies.join("[~]")));
+        self.add_row(
+            "ServiceInstall",
+            node,
+            vec![
+                Value::from(id),
+                Value::from(name),
+                optional(node.attribute("DisplayName")),
+                Value::from(service_type),
+                Value::from(start),
+                Value::from(error_control),
+                optional(node.attribute("LoadOrderGroup")),
+                optional(dependencies.as_deref()),
+                optional(node.attribute("Account")),
+                optional(node.attribute("Password")),
+                optional(node.attribute("Arguments")),
+                Value::from(component.id.as_str()),
+                optional(node.attribute("Description")),
+            ],
+        );
+    }
+
+    fn service_control(&mut self, node: Node, component: &Component) {
+        let (Some(id), Some(name)) =
+            (self.required(node, "Id"), self.required(node, "Name"))
+        else {
+            return;
+        };
+        for child in elements(node) {
+            self.unsupported(child);
+        }
+        // The install bit of each event, with the uninstall bit four bits
+        // higher.
+        let mut event = 0;
+        for (name, install) in [("Start", 0x1), ("Stop", 0x2), ("Remove", 0x8)]
+        {
+            event |= self
+                .choice(
+                    node,
+                    name,
+                    &[
+                        ("install", install),
+                        ("uninstall", install << 4),
+                        ("both", install | install << 4),
+                    ],
+                )
+                .unwrap_or(0);
+        }
+        let wait = node
+            .attribute("Wait")
+            .map(|_| self.yes_no(node, "Wait", true) as i32);
+        self.add_row(
+            "ServiceControl",
+            node,
+            vec![
+                Value::from(id),
+                Value::from(name),
+                Value::from(event),
+                Value::Null,
+                wait.map_or(Value::Null, Value::from),
+                Value::from(component.id.as_str()),
+            ],
+        );
+    }
+
+    fn create_folder(&mut self, node: Node, component: &Component) {
+        for child in elements(node) {
+            self.unsupported(child);
+        }
+        let directory = match node.attribute("Directory") {
+            Some(directory) => {
+                self.reference("Directory", directory, node);
+                directory
+            }
+            None => component.directory.as_str(),
+        };
+        self.add_row(
+            "CreateFolder",
+            node,
+            vec![Value::from(directory), Value::from(component.id.as_str())],
+        );
+    }
+
+    /// A `RemoveFolder`, which removes an empty folder such as the one a
+    /// shortcut is made in.
+    fn remove_folder(&mut self, node: Node, component: &Component) {
+        let mode = self.choice(
+            node,
+            "On",
+            &[("install", 1), ("uninstall", 2), ("both", 3)],
+        );
+        let Some(mode) = mode else {
+            self.required(node, "On");
+            return;
+        };
+        let directory = match node.attribute("Directory") {
+            Some(directory) => {
+                self.reference("Directory", directory, node);
+                directory
+            }
+            None => component.directory.as_str(),
+        };
+        let id = match node.attribute("Id") {
+            Some(id) => id.to_owned(),
+            None => Uuid::from_seed(&format!(
+                "removefolder:{}/{}",
+                component.id, directory
+            ))
+            .to_string(),
+        };
+        self.add_row(
+            "RemoveFile",
+            node,
+            vec![
+                Value::from(id),
+                Value::from(component.id.as_str()),
+                Value::Null,
+                Value::from(directory),
+                Value::from(mode),
+            ],
+        );
+    }
+
+    pub(super) fn component_group(&mut self, node: Node) {
+        let Some(id) = self.required(node, "Id") else {
+            return;
+        };
+        if self.groups.contains_key(id) {
+            self.problem(
+                node,
+                format!("ComponentGroup {id} is defined more than once"),
+            );
+            return;
+        }
+        let directory = node.attribute("Directory");
+        if let Some(directory) = directory {
+            self.reference("Directory", directory, node);
+        }
+        let members = self.members(node, directory, &[]);
+        self.groups.insert(id.to_owned(), members);
+    }
+
+    /// The components and groups in a feature or group. `allowed` are the
+    /// other elements the caller reads itself.
+    fn members(
+        &mut self,
+        node: Node,
+        directory: Option<&str>,
+        allowed: &[&str],
+    ) -> Vec<Member> {
+        let mut members = Vec::new();
+        for child in elements(node) {
+            let location = self.location(child);
+            match self.wix_name(child) {
+                Some("Component") => {
+                    if let Some(id) = self.component(child, directory) {
+                        members.push(Member::Component(id));
+                    }
+                }
+                Some("ComponentRef") => {
+                    if let Some(id) = self.required(child, "Id") {
+                        self.reference("Component", id, child);
+                        members.push(Member::Component(id.to_owned()));
+                    }
+                }
+                Some("ComponentGroupRef") => {
+                    if let Some(id) = self.required(child, "Id") {
+                        members.push(Member::Group(id.to_owned(), location));
+                    }
+                }
+                Some(name) if allowed.contains(&name) => {}
+                _ => self.unsupported(child),
+            }
+        }
+        members
+    }
+
+    /// A `Feature` and the features nested in it.
+    pub(super) fn feature(&mut self, node: Node, parent: Option<&str>) {
+        let Some(id) = self.required(node, "Id") else {
+            return;
+        };
+        if self.features.iter().any(|feature| feature.id == id) {
+            self.problem(
+                node,
+                format!("Feature {id} is defined more than once"),
+            );
+            return;
+        }
+        let display = self
+            .choice(
+                node,
+                "Display",
+                &[
+                    ("collapse", Display::Collapse),
+                    ("expand", Display::Expand),
+                    ("hidden", Display::Hidden),
+                ],
+            )
+            .unwrap_or(Display::Collapse);
+        let directory = node.attribute("ConfigurableDirectory");
+        if let Some(directory) = directory {
+            self.reference("Directory", directory, node);
+        }
+        let mut attributes = 0;
+        if !self.yes_no(node, "AllowAdvertise", true) {
+            attributes |= DISALLOW_ADVERTISE;
+        }
+        if self.choice(node, "Absent", &[("allow", false), ("disallow", true)])
+            == Some(true)
+        {
+            attributes |= UI_DISALLOW_ABSENT;
+        }
+        let level = self.number(node, "Level").unwrap_or(1);
+        let index = self.features.len();
+        self.features.push(Feature {
+            id: id.to_owned(),
+            parent: parent.map(str::to_owned),
+            title: node.attribute("Title").map(str::to_owned),
+            description: node.attribute("Description").map(str::to_owned),
+            display,
+            level,
+            directory: directory.map(str::to_owned),
+            attributes,
+            members: Vec::new(),
+            source: self.source(node),
+        });
+
+        let condition = match self.v4 {
+            true => "Level",
+            false => "Condition",
+        };
+        let members = self.members(node, None, &["Feature", condition]);
+        self.features[index].members = members;
+        for child in elements(node) {
+            match self.wix_name(child) {
+                Some("Feature") => self.feature(child, Some(id)),
+                Some("Condition") if !self.v4 => {
+                    let condition = inner_text(child);
+                    self.feature_level(child, id, condition)
+                }
+                Some("Level") if self.v4 => {
+                    let condition = self.required(child, "Condition");
+                    self.feature_level(child, id, condition)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// A condition that sets the install level of a feature when it is
+    /// true.
+    fn feature_level(
+        &mut self,
+        node: Node,
+        feature: &str,
+        condition: Option<&str>,
+    ) {
+        let level = match self.v4 {
+            true => self.number::<i32>(node, "Value"),
+            false => self.number::<i32>(node, "Level"),
+        };
+        let (Some(level), Some(condition)) = (level, condition) else {
+            self.problem(
+                node,
+                "A feature condition needs a level and a condition",
+            );
+            return;
+        };
+        self.add_row(
+            "Condition",
+            node,
+            vec![
+                Value::from(feature),
+                Value::from(level),
+                Value::from(condition),
+            ],
+        );
+    }
+
+    /// Write the `Feature` and `FeatureComponents` rows, checking that
+    /// every component is in a feature.
+    pub(super) fn feature_rows(&mut self) {
+        let mut included = BTreeSet::new();
+        let mut rows = Vec::new();
+        let mut feature_components = Vec::new();
+        let mut problems = Vec::new();
+        for (index, feature) in self.features.iter().enumerate() {
+            // Features are shown in the order they are defined, collapsed
+            // with odd values and expanded with even ones.
+            let display = match feature.display {
+                Display::Collapse => index as i32 * 2 + 1,
+                Display::Expand => index as i32 * 2 + 2,
+                Display::Hidden => 0,
+            };
+            rows.push(SourcedRow::new(
+                vec![
+                    Value::from(feature.id.as_str()),
+                    optional(feature.parent.as_deref()),
+                    optional(feature.title.as_deref()),
+                    optional(feature.description.as_deref()),
+                    Value::from(display),
+                    Value::from(feature.level),
+                    optional(feature.directory.as_deref()),
+                    Value::from(feature.attributes),
+                ],
+                feature.source.clone(),
+            ));
+            let mut components = BTreeSet::new();
+            self.expand(
+                &feature.members,
+                &mut Vec::new(),
+                &mut components,
+                &mut problems,
+            );
+            for component in components {
+                feature_components.push(SourcedRow::new(
+                    vec![
+                        Value::from(feature.id.as_str()),
+                        Value::from(component.as_str()),
+                    ],
+                    feature.source.clone(),
+                ));
+                included.insert(component);
+            }
+        }
+        for component in &self.components {
+            if !included.contains(&component.id) {
+                problems.push(format!(
+                    "{}: Component {} is not in any feature",
+                    component.location, component.id
+                ));
+            }
+        }
+        self.problems.extend(problems);
+        self.rows.insert("Feature", rows);
+        self.rows.insert("FeatureComponents", feature_components);
+    }
+
+    /// Collect the components of `members`, following groups. `groups` is
+    /// the chain of groups being expanded, to catch groups that include
+    /// themselves.
+    fn expand(
+        &self,
+        members: &[Member],
+        groups: &mut Vec<String>,
+        components: &mut BTreeSet<String>,
+        problems: &mut Vec<String>,
+    ) {
+        for member in members {
+            match member {
+                Member::Component(id) => {
+                    components.insert(id.clone());
+                }
+                Member::Group(id, location) => {
+                    let Some(group) = self.groups.get(id) else {
+                        problems.push(format!(
+                            "{location}: There is no ComponentGroup {id}"
+                        ));
+                        continue;
+                    };
+                    if groups.contains(id) {
+                        problems.push(format!(
+                            "{location}: ComponentGroup {id} includes itself"
+                        ));
+                        continue;
+                    }
+                    groups.push(id.clone());
+                    self.expand(group, groups, components, problems);
+                    groups.pop();
+                }
+            }
+        }
+    }
+
+    /// Write the `Component` rows. A component without an explicit key
+    /// path uses its first file, or else its first registry value, or else
+    /// the folder it is in.
+    pub(super) fn component_rows(&mut self, seed: &str, is_64bit: bool) {
+        let rows = self
+            .components
+            .iter()
+            .map(|component| {
+                let key_path = component.key_path.clone().or_else(|| {
+                    match (&component.first_file, &component.first_registry) {
+                        (Some(file), _) => Some((file.clone(), false)),
+                        (None, Some(registry)) => {
+                            Some((registry.clone(), true))
+                        }
+                        (None, None) => None,
+                    }
+                });
+                let mut attributes = component.attributes;
+                if let Some((_, true)) = key_path {
+                    attributes |= REGISTRY_KEY_PATH;
+                }
+                if component.is_64bit.unwrap_or(is_64bit) {
+                    attributes |= BITNESS_64;
+                }
+                let guid = match &component.guid {
+                    Some(guid) => guid.clone(),
+                    // Components keyed by their folder are told apart by
+                    // their id instead.
+                    None => component_guid(
+                        seed,
+                        &component.directory,
+                        key_path
+                            .as_ref()
+                            .map_or(component.id.as_str(), |(id, _)| id),
+                    ),
+                };
+                SourcedRow::new(
+                    vec![
+                        Value::from(component.id.as_str()),
+                        // An empty GUID leaves the component unmanaged.
+                        optional(Some(guid.as_str()).filter(|g| !g.is_empty())),
+                        Value::from(component.directory.as_str()),
+                        Value::from(attributes),
+                        optional(component.condition.as_deref()),
+                        optional(key_path.as_ref().map(|(id, _)| id.as_str())),
+                    ],
+                    component.source.clone(),
+                )
+            })
+            .collect();
+        self.rows.insert("Component", rows);
+    }
+}
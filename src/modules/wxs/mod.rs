@@ -0,0 +1,1203 @@
+//! Importing WiX v3 and v4 `.wxs` sources.
+//!
+//! The common subset of the WiX schema is read into the same models a TOML
+//! config is: the product and summary information become an [`MsiConfig`],
+//! and the `Directory`, `Component` and `File` elements become the
+//! directories and files that otherwise come from scanning the input
+//! directory. Everything else that is supported, such as features, registry
+//! values, shortcuts, services, custom actions and major upgrades, is turned
+//! into rows for the tables whimsi doesn't author from a TOML config.
+//!
+//! Sources are read without a preprocessor, so `<?define?>`, `<?include?>`
+//! and `$(var.Name)` can't be used. Every element and construct that isn't
+//! supported is reported with its line and column, and all of them are
+//! reported together so a source only has to be fixed once.
+
+mod actions;
+mod layout;
+mod product;
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Display,
+    str::FromStr,
+};
+
+use anyhow::{bail, Context, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use msi::Value;
+use roxmltree::{Document, Node};
+use uuid::Uuid;
+
+use crate::modules::{
+    component::{directory::Directory, file::File},
+    config::{msi_config::MsiConfig, ui::UiSet},
+    helpers::{filename::ShortNames, sequencer::Sequencer},
+    tables::{
+        authored::AuthoredTables, component::guid_seed, value_check::SourcedRow,
+    },
+};
+
+const WIX_V3_NAMESPACE: &str = "http://schemas.microsoft.com/wix/2006/wi";
+const WIX_V4_NAMESPACE: &str = "http://wixtoolset.org/schemas/v4/wxs";
+
+/// The directories, files and authored rows of a WiX source. The rows
+/// include the `Component` table, which is written from the `Component`
+/// elements instead of having one component per file.
+pub(crate) struct Layout {
+    pub(crate) directories: Vec<Directory>,
+    pub(crate) files: Vec<File>,
+    pub(crate) tables: AuthoredTables,
+}
+
+/// Read the WiX source at `path`. The `Source` of files and the
+/// `SourceFile` of binaries are relative to `input_directory`, which plays
+/// the part of a WiX bind path.
+pub(crate) fn import(
+    path: &Utf8Path,
+    text: &str,
+    input_directory: &Utf8Path,
+) -> Result<(MsiConfig, Layout)> {
+    let document = Document::parse(text)
+        .with_context(|| format!("Failed to parse WiX source {path}"))?;
+    let root = document.root_element();
+    let v4 = match root.tag_name().namespace() {
+        Some(WIX_V3_NAMESPACE) => false,
+        Some(WIX_V4_NAMESPACE) => true,
+        _ => bail!(
+            "{} is not a WiX v3 or v4 source, its root element must be Wix in \
+            the namespace {} or {}",
+            path,
+            WIX_V3_NAMESPACE,
+            WIX_V4_NAMESPACE
+        ),
+    };
+
+    let mut reader = Reader::new(path, &document, input_directory, v4);
+    reader.check_preprocessor();
+    reader.wix(root);
+    let imported = reader.finish();
+    match imported {
+        Some(imported) if reader.problems.is_empty() => Ok(imported),
+        _ => bail!(
+            "Found {} problem(s) in WiX source {}:\n{}",
+            reader.problems.len(),
+            path,
+            reader.problems.join("\n")
+        ),
+    }
+}
+
+/// Walks the elements of a source, collecting what it finds and the
+/// problems with it.
+struct Reader<'a, 'input> {
+    path: &'a Utf8Path,
+    document: &'a Document<'input>,
+    input_directory: &'a Utf8Path,
+    v4: bool,
+    problems: Vec<String>,
+
+    product: product::Product,
+    properties: BTreeMap<String, String>,
+    secure_properties: BTreeSet<String>,
+    ui: Option<(UiSet, String)>,
+    license: Option<Utf8PathBuf>,
+
+    directories: Vec<Directory>,
+    directory_ids: BTreeSet<String>,
+    files: Vec<File>,
+    sequencer: Sequencer,
+    components: Vec<layout::Component>,
+    groups: BTreeMap<String, Vec<layout::Member>>,
+    features: Vec<layout::Feature>,
+    /// Short names of the shortcuts in each directory.
+    shortcut_names: HashMap<String, ShortNames>,
+    actions: actions::Actions,
+
+    /// Rows of the tables the elements map to directly.
+    rows: BTreeMap<&'static str, Vec<SourcedRow>>,
+    /// Identifiers that have to be defined somewhere in the source, by the
+    /// table that defines them, with the location of the reference.
+    references: Vec<(&'static str, String, String)>,
+}
+
+impl<'a, 'input> Reader<'a, 'input> {
+    fn new(
+        path: &'a Utf8Path,
+        document: &'a Document<'input>,
+        input_directory: &'a Utf8Path,
+        v4: bool,
+    ) -> Self {
+        Reader {
+            path,
+            document,
+            input_directory,
+            v4,
+            problems: Vec::new(),
+            product: product::Product::default(),
+            properties: BTreeMap::new(),
+            secure_properties: BTreeSet::new(),
+            ui: None,
+            license: None,
+            directories: vec![layout::target_dir()],
+            directory_ids: BTreeSet::from([layout::TARGETDIR.to_owned()]),
+            files: Vec::new(),
+            sequencer: Sequencer::new(1),
+            components: Vec::new(),
+            groups: BTreeMap::new(),
+            features: Vec::new(),
+            shortcut_names: HashMap::new(),
+            actions: actions::Actions::default(),
+            rows: BTreeMap::new(),
+            references: Vec::new(),
+        }
+    }
+
+    /// Report the preprocessor, which isn't run, wherever it is used.
+    fn check_preprocessor(&mut self) {
+        for node in self.document.descendants() {
+            if let Some(pi) = node.pi() {
+                self.problem(
+                    node,
+                    format!(
+                        "Preprocessor instruction <?{}?> is not supported",
+                        pi.target
+                    ),
+                );
+            }
+            for attribute in node.attributes() {
+                let value = attribute.value();
+                if value.contains("$(") || value.contains("!(") {
+                    self.problem(
+                        node,
+                        format!(
+                            "Preprocessor and binder variables in {} are not supported",
+                            attribute.name()
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    fn wix(&mut self, root: Node) {
+        for node in elements(root) {
+            match self.wix_name(node) {
+                Some("Product") if !self.v4 => self.product_v3(node),
+                Some("Package") if self.v4 => self.package_v4(node),
+                Some("Fragment") => {
+                    for child in elements(node) {
+                        self.package_child(child);
+                    }
+                }
+                _ => self.unsupported(node),
+            }
+        }
+    }
+
+    /// Read an element that can be a child of a v3 `Product`, a v4
+    /// `Package` or a `Fragment`.
+    fn package_child(&mut self, node: Node) {
+        match self.wix_name(node) {
+            Some("Property") => self.property(node),
+            Some("Directory") => self.directory(node, layout::TARGETDIR),
+            Some("StandardDirectory") if self.v4 => {
+                self.standard_directory(node)
+            }
+            Some("DirectoryRef") => self.directory_ref(node),
+            Some("Component") => {
+                self.component(node, None);
+            }
+            Some("ComponentGroup") => self.component_group(node),
+            Some("Feature") => self.feature(node, None),
+            Some("CustomAction") => self.custom_action(node),
+            Some("Binary") => self.binary(node),
+            Some("InstallExecuteSequence" | "InstallUISequence") => {
+                self.sequence(node)
+            }
+            Some("MajorUpgrade") => self.major_upgrade(node),
+            Some("Condition") if !self.v4 => self.launch_condition(node),
+            Some("Launch") if self.v4 => self.launch_condition(node),
+            Some("UIRef") => self.ui_ref(node),
+            Some("WixVariable") => self.wix_variable(node),
+            Some("Media" | "MediaTemplate") => log::warn!(
+                "{}: {} is ignored, whimsi decides how files are stored",
+                self.location(node),
+                node.tag_name().name()
+            ),
+            _ => self.unsupported(node),
+        }
+    }
+
+    /// The local name of an element in the WiX namespace of the source.
+    /// Elements of extensions are in other namespaces and have no name
+    /// here.
+    fn wix_name<'n>(&self, node: Node<'n, '_>) -> Option<&'n str> {
+        let namespace = match self.v4 {
+            true => WIX_V4_NAMESPACE,
+            false => WIX_V3_NAMESPACE,
+        };
+        (node.tag_name().namespace() == Some(namespace))
+            .then(|| node.tag_name().name())
+    }
+
+    fn location(&self, node: Node) -> String {
+        let position = self.document.text_pos_at(node.range().start);
+        format!("{}:{}:{}", self.path, position.row, position.col)
+    }
+
+    /// The location of an element as the source of the rows made from it.
+    fn source(&self, node: Node) -> Option<Utf8PathBuf> {
+        // Sources are only ever shown, so the location of the element can
+        // stand in for a path.
+        Some(Utf8PathBuf::from(self.location(node)))
+    }
+
+    fn problem(&mut self, node: Node, message: impl Display) {
+        let location = self.location(node);
+        self.problems.push(format!("{location}: {message}"));
+    }
+
+    fn unsupported(&mut self, node: Node) {
+        let tag = node.tag_name();
+        let name = match tag.namespace().and_then(|ns| node.lookup_prefix(ns)) {
+            Some(prefix) if self.wix_name(node).is_none() => {
+                format!("{prefix}:{}", tag.name())
+            }
+            _ => tag.name().to_owned(),
+        };
+        let parent = node
+            .parent_element()
+            .map(|parent| parent.tag_name().name())
+            .unwrap_or_default();
+        self.problem(node, format!("Unsupported element {name} in {parent}"));
+    }
+
+    /// An attribute that has to be given.
+    fn required<'n>(
+        &mut self,
+        node: Node<'n, '_>,
+        name: &str,
+    ) -> Option<&'n str> {
+        let value = node.attribute(name);
+        if value.is_none() {
+            self.problem(
+                node,
+                format!(
+                    "{} is missing the {} attribute",
+                    node.tag_name().name(),
+                    name
+                ),
+            );
+        }
+        value
+    }
+
+    fn yes_no(&mut self, node: Node, name: &str, default: bool) -> bool {
+        match node.attribute(name) {
+            None => default,
+            Some("yes") => true,
+            Some("no") => false,
+            Some(value) => {
+                self.problem(
+                    node,
+                    format!("{name} is {value} instead of yes or no"),
+                );
+                default
+            }
+        }
+    }
+
+    fn number<T: FromStr>(&mut self, node: Node, name: &str) -> Option<T> {
+        let value = node.attribute(name)?;
+        let number = value.parse().ok();
+        if number.is_none() {
+            self.problem(
+                node,
+                format!("{name} is {value} instead of a number"),
+            );
+        }
+        number
+    }
+
+    /// A GUID written the way the MSI tables need it, in uppercase between
+    /// braces. WiX also accepts GUIDs without braces and in lowercase. `*`,
+    /// which has WiX generate the GUID, is kept as it is.
+    fn guid(&mut self, node: Node, name: &str) -> Option<String> {
+        let value = node.attribute(name)?;
+        if value == "*" {
+            return Some(value.to_owned());
+        }
+        match Uuid::parse_str(value) {
+            Ok(uuid) => Some(format!("{{{uuid}}}").to_uppercase()),
+            Err(_) => {
+                self.problem(
+                    node,
+                    format!("{name} is {value} instead of a GUID"),
+                );
+                // The value is still returned so the attribute isn't also
+                // reported as missing.
+                Some(value.to_owned())
+            }
+        }
+    }
+
+    /// Pick the value of an attribute from a list of the values it can
+    /// have.
+    fn choice<T: Copy>(
+        &mut self,
+        node: Node,
+        name: &str,
+        choices: &[(&str, T)],
+    ) -> Option<T> {
+        let value = node.attribute(name)?;
+        let choice = choices
+            .iter()
+            .find(|(choice, _)| *choice == value)
+            .map(|(_, choice)| *choice);
+        if choice.is_none() {
+            let names = choices.iter().map(|(choice, _)| *choice);
+            self.problem(
+                node,
+                format!(
+                    "{} is {} instead of one of {}",
+                    name,
+                    value,
+                    names.collect::<Vec<_>>().join(", ")
+                ),
+            );
+        }
+        choice
+    }
+
+    fn add_row(&mut self, table: &'static str, node: Node, values: Vec<Value>) {
+        let row = SourcedRow::new(values, self.source(node));
+        self.rows.entry(table).or_default().push(row);
+    }
+
+    /// Note that `id` has to be defined in `table`, which is checked once
+    /// the whole source has been read.
+    fn reference(&mut self, table: &'static str, id: &str, node: Node) {
+        let location = self.location(node);
+        self.references.push((table, id.to_owned(), location));
+    }
+
+    /// Turn what was read into a config and layout and check that
+    /// everything referenced is defined. Returns `None` if anything needed
+    /// is missing, which has been reported as a problem.
+    fn finish(&mut self) -> Option<(MsiConfig, Layout)> {
+        let config = self.config();
+        let (seed, is_64bit) = match &config {
+            Some(config) => (
+                guid_seed(config),
+                product::is_64bit(&config.summary_info.template),
+            ),
+            None => (String::new(), false),
+        };
+        self.feature_rows();
+        self.component_rows(&seed, is_64bit);
+        self.sequence_rows();
+        self.check_references();
+
+        Some((
+            config?,
+            Layout {
+                directories: std::mem::take(&mut self.directories),
+                files: std::mem::take(&mut self.files),
+                tables: AuthoredTables {
+                    rows: std::mem::take(&mut self.rows),
+                    binaries: std::mem::take(&mut self.actions.binaries),
+                },
+            },
+        ))
+    }
+
+    fn check_references(&mut self) {
+        let problems = self
+            .references
+            .iter()
+            .filter(|(table, id, _)| !self.is_defined(table, id))
+            .map(|(table, id, location)| {
+                format!("{location}: There is no {table} {id}")
+            })
+            .collect::<Vec<_>>();
+        self.problems.extend(problems);
+    }
+
+    fn is_defined(&self, table: &str, id: &str) -> bool {
+        match table {
+            "Directory" => self.directory_ids.contains(id),
+            "File" => {
+                self.files.iter().any(|file| file.file_id().as_str() == id)
+            }
+            "Component" => self.components.iter().any(|c| c.id == id),
+            "Binary" => {
+                self.actions.binaries.iter().any(|(binary, _)| binary == id)
+            }
+            _ => self.rows.get(table).is_some_and(|rows| {
+                rows.iter().any(|row| row.values[0].as_str() == Some(id))
+            }),
+        }
+    }
+}
+
+/// The child elements of `node`, skipping text and comments.
+fn elements<'a, 'input>(
+    node: Node<'a, 'input>,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(Node::is_element)
+}
+
+/// The text of an element, for the elements that hold a condition or value
+/// as text in WiX v3.
+fn inner_text<'a>(node: Node<'a, '_>) -> Option<&'a str> {
+    node.text().map(str::trim).filter(|text| !text.is_empty())
+}
+
+/// A value for a column that can be null.
+fn optional(value: Option<&str>) -> Value {
+    match value {
+        Some(value) => Value::from(value),
+        None => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use camino::Utf8Path;
+    use msi::Value;
+
+    use super::{import, Layout, WIX_V3_NAMESPACE, WIX_V4_NAMESPACE};
+    use crate::modules::{
+        config::{msi_config::MsiConfig, ui::UiSet},
+        helpers::test_dir::TestDir,
+    };
+
+    /// The directories and component most of the tests install into.
+    const LAYOUT: &str = r#"
+    <Directory Id="TARGETDIR" Name="SourceDir">
+      <Directory Id="ProgramFilesFolder">
+        <Directory Id="INSTALLDIR" Name="App">
+          <Component Id="Main" Guid="5a2b3c4d-1111-2222-3333-444455556666">
+            <File Id="AppExe" Source="app.exe" KeyPath="yes" />
+          </Component>
+        </Directory>
+      </Directory>
+    </Directory>
+    <Feature Id="Complete">
+      <ComponentRef Id="Main" />
+    </Feature>"#;
+
+    /// A v3 source with a product that has `body` in it.
+    fn v3(body: &str) -> String {
+        format!(
+            r#"<Wix xmlns="{WIX_V3_NAMESPACE}">
+  <Product Id="*" Name="App" Version="1.0.0" Manufacturer="Me"
+           Language="1033" UpgradeCode="1d4b9f5e-7a43-4e37-8f0a-2c7b1e6f9d10">
+    <Package InstallerVersion="500" Compressed="yes" />
+    {body}
+  </Product>
+</Wix>"#
+        )
+    }
+
+    /// A v4 source with a package that has `body` in it.
+    fn v4(body: &str) -> String {
+        format!(
+            r#"<Wix xmlns="{WIX_V4_NAMESPACE}">
+  <Package Name="App" Version="1.0.0" Manufacturer="Me" Language="1033"
+           UpgradeCode="1d4b9f5e-7a43-4e37-8f0a-2c7b1e6f9d10">
+    {body}
+  </Package>
+</Wix>"#
+        )
+    }
+
+    /// Import `text` with a bind path that has the files the tests
+    /// install.
+    fn read(text: &str) -> Result<(MsiConfig, Layout)> {
+        let dir = TestDir::new();
+        for file in ["app.exe", "bin/readme.txt", "setup.dll"] {
+            dir.file(file, file);
+        }
+        import(Utf8Path::new("app.wxs"), text, dir.path())
+    }
+
+    fn problems(text: &str) -> String {
+        match read(text) {
+            Ok(_) => panic!("The source was read without problems"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    fn rows(layout: &Layout, table: &str) -> Vec<Vec<Value>> {
+        layout.tables.rows[table]
+            .iter()
+            .map(|row| row.values.clone())
+            .collect()
+    }
+
+    fn row(layout: &Layout, table: &str, key: &str) -> Vec<Value> {
+        rows(layout, table)
+            .into_iter()
+            .find(|row| row[0].as_str() == Some(key))
+            .unwrap_or_else(|| panic!("There is no {table} row {key}"))
+    }
+
+    #[test]
+    fn reads_the_product_and_package_of_a_v3_source() {
+        let text = format!(
+            r#"<Wix xmlns="{WIX_V3_NAMESPACE}">
+  <Product Id="1d4b9f5e-7a43-4e37-8f0a-2c7b1e6f9d10" Name="App"
+           Version="1.2.3" Manufacturer="Me" Language="1033"
+           UpgradeCode="{{8a1c0b2e-3d4f-4a5b-9c6d-7e8f90a1b2c3}}">
+    <Package Id="2f3e4d5c-6b7a-4988-a7b6-c5d4e3f2a1b0" Platform="x64"
+             InstallerVersion="200" Compressed="yes" Comments="Hi"
+             InstallScope="perMachine" />
+  </Product>
+</Wix>"#
+        );
+        let (config, _) = read(&text).unwrap();
+
+        let product = &config.product_info;
+        assert_eq!(product.product_name, "App");
+        assert_eq!(product.product_version, "1.2.3");
+        assert_eq!(product.product_language, 1033);
+        assert_eq!(
+            product.product_code,
+            "{1D4B9F5E-7A43-4E37-8F0A-2C7B1E6F9D10}"
+        );
+        assert_eq!(
+            config.property["UpgradeCode"],
+            "{8A1C0B2E-3D4F-4A5B-9C6D-7E8F90A1B2C3}"
+        );
+        assert_eq!(config.property["ALLUSERS"], "1");
+        let summary = &config.summary_info;
+        assert_eq!(
+            summary.revision_number,
+            "{2F3E4D5C-6B7A-4988-A7B6-C5D4E3F2A1B0}"
+        );
+        assert_eq!(summary.template, "x64;1033");
+        assert_eq!(summary.page_count, 200);
+        assert_eq!(summary.word_count, Some(2));
+        assert_eq!(summary.author.as_deref(), Some("Me"));
+        assert_eq!(summary.comments.as_deref(), Some("Hi"));
+    }
+
+    #[test]
+    fn reads_the_package_and_summary_information_of_a_v4_source() {
+        let text = format!(
+            r#"<Wix xmlns="{WIX_V4_NAMESPACE}">
+  <Package Name="App" Version="1.0.0" Manufacturer="Me" Language="1033"
+           ProductCode="1D4B9F5E7A434E378F0A2C7B1E6F9D10" Scope="perUser">
+    <SummaryInformation Manufacturer="Vendor" Codepage="1252" />
+  </Package>
+</Wix>"#
+        );
+        let (config, _) = read(&text).unwrap();
+
+        assert_eq!(
+            config.product_info.product_code,
+            "{1D4B9F5E-7A43-4E37-8F0A-2C7B1E6F9D10}"
+        );
+        assert_eq!(config.property["ALLUSERS"], "2");
+        assert_eq!(config.property["MSIINSTALLPERUSER"], "1");
+        let summary = &config.summary_info;
+        assert_eq!(summary.revision_number, "*");
+        assert_eq!(summary.template, "Intel;1033");
+        assert_eq!(summary.word_count, Some(2));
+        assert_eq!(summary.author.as_deref(), Some("Vendor"));
+        assert_eq!(summary.code_page.as_deref(), Some("1252"));
+
+        // Without a ProductCode every build gets a new one.
+        let (config, _) = read(&v4("")).unwrap();
+        assert_eq!(config.product_info.product_code, "*");
+    }
+
+    #[test]
+    fn reports_invalid_guids_once_where_they_are() {
+        let text = v3(LAYOUT)
+            .replace(r#"Id="*""#, r#"Id="not-a-guid""#)
+            .replace("5a2b3c4d-1111", "5a2b3c4d-zzzz");
+        let problems = problems(&text);
+
+        assert!(problems.starts_with("Found 2 problem(s)"), "{problems}");
+        assert!(problems
+            .contains("app.wxs:2:3: Id is not-a-guid instead of a GUID"));
+        assert!(problems.contains(
+            "app.wxs:9:11: Guid is 5a2b3c4d-zzzz-2222-3333-444455556666 \
+            instead of a GUID"
+        ));
+        assert!(!problems.contains("missing"), "{problems}");
+    }
+
+    #[test]
+    fn reads_properties() {
+        let (config, _) = read(&v3(r#"
+    <Property Id="MODE" Value="quiet" Secure="yes" />
+    <Property Id="TEXT">inner</Property>
+    <Property Id="DECLARED" />"#))
+        .unwrap();
+
+        assert_eq!(config.property["MODE"], "quiet");
+        assert_eq!(config.property["TEXT"], "inner");
+        assert!(!config.property.contains_key("DECLARED"));
+        assert_eq!(config.property["SecureCustomProperties"], "MODE");
+
+        let problems =
+            problems(&v3(r#"<Property Id="ProductName" Value="Other" />"#));
+        assert!(problems
+            .contains("ProductName is set from the attributes of the product"));
+    }
+
+    #[test]
+    fn reads_directories_and_directory_refs() {
+        let (_, layout) = read(&v3(&format!(
+            r#"{LAYOUT}
+    <DirectoryRef Id="INSTALLDIR">
+      <Directory Id="DOCS" Name="docs" />
+    </DirectoryRef>"#
+        )))
+        .unwrap();
+
+        let directories = layout
+            .directories
+            .iter()
+            .map(|directory| {
+                (
+                    directory.id().as_str(),
+                    directory.parent_id().as_deref(),
+                    directory.name().as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            directories,
+            [
+                ("TARGETDIR", None, "SourceDir"),
+                ("ProgramFilesFolder", Some("TARGETDIR"), "."),
+                ("INSTALLDIR", Some("ProgramFilesFolder"), "App"),
+                ("DOCS", Some("INSTALLDIR"), "docs"),
+            ]
+        );
+
+        let problems =
+            problems(&v3(&format!(r#"{LAYOUT}<DirectoryRef Id="NOWHERE" />"#)));
+        assert!(problems.contains("There is no Directory NOWHERE"));
+    }
+
+    #[test]
+    fn reads_standard_directories() {
+        let (_, layout) = read(&v4(r#"
+    <StandardDirectory Id="ProgramFiles64Folder">
+      <Directory Id="INSTALLDIR" Name="App" />
+    </StandardDirectory>"#))
+        .unwrap();
+
+        let parents = layout
+            .directories
+            .iter()
+            .map(|directory| {
+                (directory.id().as_str(), directory.parent_id().as_deref())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            parents,
+            [
+                ("TARGETDIR", None),
+                ("ProgramFiles64Folder", Some("TARGETDIR")),
+                ("INSTALLDIR", Some("ProgramFiles64Folder")),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_components_and_files() {
+        let (_, layout) = read(&v3(&format!(
+            r#"{LAYOUT}
+    <DirectoryRef Id="INSTALLDIR">
+      <Component Id="Docs" Guid="*" Win64="yes">
+        <File Name="README.txt" Source="SourceDir\bin\readme.txt"
+              Vital="no" />
+      </Component>
+      <Component Id="Folder" Guid="">
+        <CreateFolder />
+      </Component>
+    </DirectoryRef>
+    <Feature Id="Documentation">
+      <ComponentRef Id="Docs" />
+      <ComponentRef Id="Folder" />
+    </Feature>"#
+        )))
+        .unwrap();
+
+        let files = layout
+            .files
+            .iter()
+            .map(|file| {
+                (
+                    file.component_id().as_str(),
+                    file.directory_id().as_str(),
+                    file.name().as_str(),
+                    *file.vital(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            files,
+            [
+                ("Main", "INSTALLDIR", "app.exe", true),
+                ("Docs", "INSTALLDIR", "README.txt", false),
+            ]
+        );
+        assert_eq!(layout.files[0].file_id(), "AppExe");
+        assert!(layout.files[1].source().ends_with("bin/readme.txt"));
+
+        assert_eq!(
+            row(&layout, "Component", "Main"),
+            [
+                Value::from("Main"),
+                Value::from("{5A2B3C4D-1111-2222-3333-444455556666}"),
+                Value::from("INSTALLDIR"),
+                Value::from(0),
+                Value::Null,
+                Value::from("AppExe"),
+            ]
+        );
+        // A generated GUID, with the first file as the key path.
+        let docs = row(&layout, "Component", "Docs");
+        assert!(docs[1].as_str().unwrap().starts_with('{'));
+        assert_eq!(docs[3], Value::from(0x100));
+        assert_eq!(docs[5], Value::from(layout.files[1].file_id().as_str()));
+        // An empty GUID leaves the component unmanaged.
+        assert_eq!(row(&layout, "Component", "Folder")[1], Value::Null);
+    }
+
+    #[test]
+    fn reads_registry_keys_and_values() {
+        let (_, layout) = read(&v3(&format!(
+            r#"{LAYOUT}
+    <DirectoryRef Id="INSTALLDIR">
+      <Component Id="Settings">
+        <RegistryKey Root="HKLM" Key="Software\App">
+          <RegistryValue Id="Count" Name="Count" Type="integer" Value="3"
+                         KeyPath="yes" />
+          <RegistryValue Id="List" Name="List" Type="multiString"
+                         Action="append">
+            <MultiStringValue>a</MultiStringValue>
+            <MultiStringValue>b</MultiStringValue>
+          </RegistryValue>
+        </RegistryKey>
+      </Component>
+    </DirectoryRef>
+    <Feature Id="Configuration">
+      <ComponentRef Id="Settings" />
+    </Feature>"#
+        )))
+        .unwrap();
+
+        assert_eq!(
+            rows(&layout, "Registry"),
+            [
+                vec![
+                    Value::from("Count"),
+                    Value::from(2),
+                    Value::from(r"Software\App"),
+                    Value::from("Count"),
+                    Value::from("#3"),
+                    Value::from("Settings"),
+                ],
+                vec![
+                    Value::from("List"),
+                    Value::from(2),
+                    Value::from(r"Software\App"),
+                    Value::from("List"),
+                    Value::from("[~]a[~]b"),
+                    Value::from("Settings"),
+                ],
+            ]
+        );
+        let component = row(&layout, "Component", "Settings");
+        assert_eq!(component[3], Value::from(0x4));
+        assert_eq!(component[5], Value::from("Count"));
+    }
+
+    #[test]
+    fn reads_shortcuts() {
+        let text = v3(&LAYOUT.replace(
+            r#"KeyPath="yes" />"#,
+            r#"KeyPath="yes">
+              <Shortcut Id="MenuShortcut" Name="App" Directory="INSTALLDIR"
+                        Show="minimized" Arguments="/quiet" />
+            </File>"#,
+        ));
+        let (_, layout) = read(&text).unwrap();
+
+        let shortcut = row(&layout, "Shortcut", "MenuShortcut");
+        assert_eq!(shortcut[1], Value::from("INSTALLDIR"));
+        assert_eq!(shortcut[3], Value::from("Main"));
+        assert_eq!(shortcut[4], Value::from("[#AppExe]"));
+        assert_eq!(shortcut[5], Value::from("/quiet"));
+        assert_eq!(shortcut[10], Value::from(7));
+
+        let problems =
+            problems(&text.replace("Show=", r#"Advertise="yes" Show="#));
+        assert!(problems.contains("Advertised shortcuts are not supported"));
+    }
+
+    #[test]
+    fn reads_services() {
+        let text = v3(&LAYOUT.replace(
+            r#"KeyPath="yes" />"#,
+            r#"KeyPath="yes" />
+            <ServiceInstall Id="Service" Name="AppSvc" Type="ownProcess"
+                            Start="auto" ErrorControl="normal" Vital="yes">
+              <ServiceDependency Id="RpcSs" />
+            </ServiceInstall>
+            <ServiceControl Id="Control" Name="AppSvc" Start="install"
+                            Stop="both" Remove="uninstall" Wait="yes" />"#,
+        ));
+        let (_, layout) = read(&text).unwrap();
+
+        let service = row(&layout, "ServiceInstall", "Service");
+        assert_eq!(service[1], Value::from("AppSvc"));
+        assert_eq!(service[3], Value::from(0x10));
+        assert_eq!(service[4], Value::from(2));
+        assert_eq!(service[5], Value::from(0x8001));
+        assert_eq!(service[7], Value::from("RpcSs[~][~]"));
+        assert_eq!(service[11], Value::from("Main"));
+        assert_eq!(
+            row(&layout, "ServiceControl", "Control"),
+            [
+                Value::from("Control"),
+                Value::from("AppSvc"),
+                Value::from(0x1 | 0x22 | 0x80),
+                Value::Null,
+                Value::from(1),
+                Value::from("Main"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_created_and_removed_folders() {
+        let text = v3(&LAYOUT.replace(
+            r#"KeyPath="yes" />"#,
+            r#"KeyPath="yes" />
+            <CreateFolder />
+            <RemoveFolder Id="RemoveApp" On="uninstall" />"#,
+        ));
+        let (_, layout) = read(&text).unwrap();
+
+        assert_eq!(
+            rows(&layout, "CreateFolder"),
+            [vec![Value::from("INSTALLDIR"), Value::from("Main")]]
+        );
+        assert_eq!(
+            rows(&layout, "RemoveFile"),
+            [vec![
+                Value::from("RemoveApp"),
+                Value::from("Main"),
+                Value::Null,
+                Value::from("INSTALLDIR"),
+                Value::from(2),
+            ]]
+        );
+    }
+
+    #[test]
+    fn includes_the_components_of_nested_groups() {
+        let (_, layout) = read(&format!(
+            r#"<Wix xmlns="{WIX_V3_NAMESPACE}">
+  <Product Id="*" Name="App" Version="1.0.0" Manufacturer="Me">
+    {LAYOUT}
+    <Feature Id="Extras">
+      <ComponentGroupRef Id="Outer" />
+    </Feature>
+  </Product>
+  <Fragment>
+    <ComponentGroup Id="Inner" Directory="INSTALLDIR">
+      <Component Id="Docs">
+        <File Source="bin\readme.txt" />
+      </Component>
+    </ComponentGroup>
+    <ComponentGroup Id="Outer">
+      <ComponentGroupRef Id="Inner" />
+      <ComponentRef Id="Main" />
+    </ComponentGroup>
+  </Fragment>
+</Wix>"#
+        ))
+        .unwrap();
+
+        assert_eq!(
+            rows(&layout, "FeatureComponents"),
+            [
+                vec![Value::from("Complete"), Value::from("Main")],
+                vec![Value::from("Extras"), Value::from("Docs")],
+                vec![Value::from("Extras"), Value::from("Main")],
+            ]
+        );
+
+        let problems = problems(&v3(&format!(
+            r#"{LAYOUT}
+    <ComponentGroup Id="Loop">
+      <ComponentGroupRef Id="Loop" />
+    </ComponentGroup>
+    <Feature Id="Looped">
+      <ComponentGroupRef Id="Loop" />
+    </Feature>
+    <DirectoryRef Id="INSTALLDIR">
+      <Component Id="Stray" />
+    </DirectoryRef>"#
+        )));
+        assert!(problems.contains("ComponentGroup Loop includes itself"));
+        assert!(problems.contains("Component Stray is not in any feature"));
+    }
+
+    #[test]
+    fn reads_nested_features_and_their_levels() {
+        let text = v3(&LAYOUT.replace(
+            r#"<Feature Id="Complete">"#,
+            r#"<Feature Id="Complete" Title="App" Display="expand"
+             ConfigurableDirectory="INSTALLDIR" AllowAdvertise="no"
+             Absent="disallow">
+      <Feature Id="Docs" Display="hidden" Level="2">
+        <Condition Level="0">NOT DOCS</Condition>
+      </Feature>"#,
+        ));
+        let (_, layout) = read(&text).unwrap();
+
+        assert_eq!(
+            rows(&layout, "Feature"),
+            [
+                vec![
+                    Value::from("Complete"),
+                    Value::Null,
+                    Value::from("App"),
+                    Value::Null,
+                    Value::from(2),
+                    Value::from(1),
+                    Value::from("INSTALLDIR"),
+                    Value::from(0x8 | 0x10),
+                ],
+                vec![
+                    Value::from("Docs"),
+                    Value::from("Complete"),
+                    Value::Null,
+                    Value::Null,
+                    Value::from(0),
+                    Value::from(2),
+                    Value::Null,
+                    Value::from(0),
+                ],
+            ]
+        );
+        assert_eq!(
+            rows(&layout, "Condition"),
+            [vec![
+                Value::from("Docs"),
+                Value::from(0),
+                Value::from("NOT DOCS")
+            ]]
+        );
+
+        // v4 sources give the condition as a Level element.
+        let text = v4(r#"
+    <Feature Id="Complete">
+      <Level Value="0" Condition="NOT INSTALL" />
+    </Feature>"#);
+        let (_, layout) = read(&text).unwrap();
+        assert_eq!(
+            rows(&layout, "Condition"),
+            [vec![
+                Value::from("Complete"),
+                Value::from(0),
+                Value::from("NOT INSTALL"),
+            ]]
+        );
+    }
+
+    #[test]
+    fn reads_custom_actions_binaries_and_sequences() {
+        let (_, layout) = read(&v3(&format!(
+            r#"{LAYOUT}
+    <Binary Id="Helper" SourceFile="setup.dll" />
+    <CustomAction Id="Run" BinaryKey="Helper" DllEntry="Go"
+                  Execute="deferred" Return="ignore" Impersonate="no" />
+    <CustomAction Id="SetDir" Property="APPDIR" Value="[INSTALLDIR]" />
+    <InstallExecuteSequence>
+      <Custom Action="Run" After="InstallFiles">NOT Installed</Custom>
+      <Custom Action="SetDir" Before="CostInitialize" />
+    </InstallExecuteSequence>"#
+        )))
+        .unwrap();
+
+        assert_eq!(layout.tables.binaries[0].0, "Helper");
+        assert!(layout.tables.binaries[0].1.ends_with("setup.dll"));
+        assert_eq!(
+            rows(&layout, "CustomAction"),
+            [
+                vec![
+                    Value::from("Run"),
+                    Value::from(1 | 0x400 | 0x40 | 0x800),
+                    Value::from("Helper"),
+                    Value::from("Go"),
+                ],
+                vec![
+                    Value::from("SetDir"),
+                    Value::from(51),
+                    Value::from("APPDIR"),
+                    Value::from("[INSTALLDIR]"),
+                ],
+            ]
+        );
+        assert_eq!(
+            row(&layout, "InstallExecuteSequence", "Run"),
+            [
+                Value::from("Run"),
+                Value::from("NOT Installed"),
+                Value::from(4001),
+            ]
+        );
+        assert_eq!(
+            row(&layout, "InstallExecuteSequence", "SetDir")[2],
+            Value::from(799)
+        );
+
+        let problems = problems(&v3(r#"
+    <CustomAction Id="Run" Error="Stop" />
+    <InstallExecuteSequence>
+      <Custom Action="Run" Sequence="10" After="InstallFiles" />
+    </InstallExecuteSequence>"#));
+        assert!(problems
+            .contains("Custom needs exactly one of Sequence, After or Before"));
+    }
+
+    #[test]
+    fn reads_major_upgrades() {
+        let (_, layout) = read(&v3(
+            r#"<MajorUpgrade DowngradeErrorMessage="A newer version is installed" />"#,
+        ))
+        .unwrap();
+
+        let code = Value::from("{1D4B9F5E-7A43-4E37-8F0A-2C7B1E6F9D10}");
+        assert_eq!(
+            rows(&layout, "Upgrade"),
+            [
+                vec![
+                    code.clone(),
+                    Value::Null,
+                    Value::from("1.0.0"),
+                    Value::Null,
+                    Value::from(0x1),
+                    Value::Null,
+                    Value::from("WIX_UPGRADE_DETECTED"),
+                ],
+                vec![
+                    code,
+                    Value::from("1.0.0"),
+                    Value::Null,
+                    Value::Null,
+                    Value::from(0x2),
+                    Value::Null,
+                    Value::from("WIX_DOWNGRADE_DETECTED"),
+                ],
+            ]
+        );
+        assert_eq!(
+            rows(&layout, "LaunchCondition"),
+            [vec![
+                Value::from("NOT WIX_DOWNGRADE_DETECTED"),
+                Value::from("A newer version is installed"),
+            ]]
+        );
+        assert_eq!(
+            row(&layout, "InstallExecuteSequence", "RemoveExistingProducts")[2],
+            Value::from(1401)
+        );
+
+        let problems = problems(&v3("<MajorUpgrade />"));
+        assert!(problems.contains("MajorUpgrade needs a DowngradeErrorMessage"));
+    }
+
+    #[test]
+    fn reads_launch_conditions() {
+        let expected = [vec![
+            Value::from("Privileged"),
+            Value::from("Run as an administrator"),
+        ]];
+        let (_, layout) = read(&v3(
+            r#"<Condition Message="Run as an administrator">Privileged</Condition>"#,
+        ))
+        .unwrap();
+        assert_eq!(rows(&layout, "LaunchCondition"), expected);
+
+        let (_, layout) = read(&v4(
+            r#"<Launch Condition="Privileged" Message="Run as an administrator" />"#,
+        ))
+        .unwrap();
+        assert_eq!(rows(&layout, "LaunchCondition"), expected);
+    }
+
+    #[test]
+    fn reads_the_ui_and_its_license() {
+        let (config, _) = read(&v3(&format!(
+            r#"{LAYOUT}
+    <UIRef Id="WixUI_InstallDir" />
+    <WixVariable Id="WixUILicenseRtf" Value="license.rtf" />"#
+        )))
+        .unwrap();
+        assert!(config.ui == UiSet::InstallDir);
+        assert_eq!(config.license.as_deref(), Some("license.rtf".into()));
+
+        let problems = problems(&v3(r#"
+    <UIRef Id="WixUI_FeatureTree" />
+    <WixVariable Id="WixUIBannerBmp" Value="banner.bmp" />"#));
+        assert!(problems.contains("give the installation directory that Id"));
+        assert!(
+            problems.contains("WixVariable WixUIBannerBmp is not supported")
+        );
+    }
+
+    #[test]
+    fn reports_every_problem_with_its_location() {
+        let text = format!(
+            r#"<Wix xmlns="{WIX_V3_NAMESPACE}"
+     xmlns:util="http://schemas.microsoft.com/wix/UtilExtension">
+  <?define Version=1.0.0?>
+  <Product Id="*" Name="App" Version="$(var.Version)" Manufacturer="Me">
+    <Icon Id="App.ico" SourceFile="app.ico" />
+    <util:User Id="Account" Name="app" />
+    <DirectoryRef Id="TARGETDIR">
+      <Component Id="Main">
+        <File Source="missing.exe" />
+      </Component>
+    </DirectoryRef>
+    <Feature Id="Complete" Level="high">
+      <ComponentRef Id="Main" />
+    </Feature>
+  </Product>
+</Wix>"#
+        );
+        let problems = problems(&text);
+        let lines = problems.lines().collect::<Vec<_>>();
+
+        assert_eq!(
+            lines[..5],
+            [
+                "Found 6 problem(s) in WiX source app.wxs:",
+                "app.wxs:3:3: Preprocessor instruction <?define?> is not \
+                supported",
+                "app.wxs:4:3: Preprocessor and binder variables in Version \
+                are not supported",
+                "app.wxs:5:5: Unsupported element Icon in Product",
+                "app.wxs:6:5: Unsupported element util:User in Product",
+            ]
+        );
+        assert!(lines[5]
+            .starts_with("app.wxs:9:9: There is no file missing.exe in "));
+        assert_eq!(lines[6], "app.wxs:12:5: Level is high instead of a number");
+    }
+}
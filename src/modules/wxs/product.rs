@@ -0,0 +1,506 @@
+//! The `Product` and `Package` elements and the other elements that describe
+//! the product as a whole rather than what it installs.
+
+use std::collections::BTreeMap;
+
+use msi::Value;
+use roxmltree::Node;
+
+use crate::modules::{
+    config::{
+        msi_config::MsiConfig,
+        product_information::ProductInformationProperties, scan::ScanConfig,
+        summary_information::SummaryInformationProperties, ui::UiSet,
+    },
+    tables::value_check::SourcedRow,
+};
+
+use super::{elements, inner_text, optional, Reader};
+
+/// Properties the `Property` table gets from the product information, which
+/// can't also be set with a `Property` element.
+const PRODUCT_PROPERTIES: [&str; 5] = [
+    "ProductName",
+    "ProductVersion",
+    "Manufacturer",
+    "ProductLanguage",
+    "ProductCode",
+];
+
+/// [`msidbUpgradeAttributesMigrateFeatures`](https://learn.microsoft.com/en-us/windows/win32/msi/upgrade-table)
+const MIGRATE_FEATURES: i32 = 0x1;
+const ONLY_DETECT: i32 = 0x2;
+const IGNORE_REMOVE_FAILURE: i32 = 0x4;
+const VERSION_MAX_INCLUSIVE: i32 = 0x200;
+
+/// Properties set by the `Upgrade` rows of a `MajorUpgrade`, named as WiX
+/// names them so conditions written for WiX keep working.
+const UPGRADE_DETECTED: &str = "WIX_UPGRADE_DETECTED";
+const DOWNGRADE_DETECTED: &str = "WIX_DOWNGRADE_DETECTED";
+
+/// What the product elements say about the product, read before the
+/// config can be put together.
+#[derive(Default)]
+pub(super) struct Product {
+    location: Option<String>,
+    name: Option<String>,
+    version: Option<String>,
+    manufacturer: Option<String>,
+    language: Option<u16>,
+    code: Option<String>,
+    upgrade_code: Option<String>,
+    codepage: Option<String>,
+    package_code: Option<String>,
+    installer_version: Option<u16>,
+    compressed: bool,
+    platform: Option<&'static str>,
+    languages: Option<String>,
+    author: Option<String>,
+    comments: Option<String>,
+    upgrade: Option<MajorUpgrade>,
+}
+
+struct MajorUpgrade {
+    location: String,
+    downgrade_message: Option<String>,
+    allow_downgrades: bool,
+    allow_same_version: bool,
+    ignore_remove_failure: bool,
+    migrate_features: bool,
+    /// Sequence number of `RemoveExistingProducts`.
+    schedule: i32,
+}
+
+/// Whether the template of a package is for a 64-bit platform.
+pub(super) fn is_64bit(template: &str) -> bool {
+    template.starts_with("x64") || template.starts_with("Arm64")
+}
+
+impl Reader<'_, '_> {
+    /// A v3 `Product`, which has the summary information in a `Package`
+    /// child.
+    pub(super) fn product_v3(&mut self, node: Node) {
+        self.product_attributes(node, "Id");
+        for child in elements(node) {
+            match self.wix_name(child) {
+                Some("Package") => self.package_v3(child),
+                _ => self.package_child(child),
+            }
+        }
+    }
+
+    /// A v4 `Package`, which stands for both the v3 `Product` and `Package`.
+    pub(super) fn package_v4(&mut self, node: Node) {
+        self.product_attributes(node, "ProductCode");
+        self.product.code.get_or_insert_with(|| "*".to_owned());
+        self.product.installer_version = self.number(node, "InstallerVersion");
+        self.product.compressed = self.yes_no(node, "Compressed", true);
+        self.scope(node, "Scope");
+        for child in elements(node) {
+            match self.wix_name(child) {
+                Some("SummaryInformation") => self.summary_information(child),
+                _ => self.package_child(child),
+            }
+        }
+    }
+
+    fn product_attributes(&mut self, node: Node, code: &str) {
+        if self.product.location.is_some() {
+            self.problem(node, "The product is defined more than once");
+            return;
+        }
+        self.product.location = Some(self.location(node));
+        let attribute = |name: &str| node.attribute(name).map(str::to_owned);
+        self.product.name = attribute("Name");
+        self.product.version = attribute("Version");
+        self.product.manufacturer = attribute("Manufacturer");
+        self.product.code = self.guid(node, code);
+        self.product.upgrade_code = self.guid(node, "UpgradeCode");
+        self.product.codepage = attribute("Codepage");
+        self.product.language = self.number(node, "Language");
+    }
+
+    fn package_v3(&mut self, node: Node) {
+        let attribute = |name: &str| node.attribute(name).map(str::to_owned);
+        self.product.package_code = self.guid(node, "Id");
+        self.product.languages = attribute("Languages");
+        self.product.author = attribute("Manufacturer");
+        self.product.comments = attribute("Comments");
+        if self.product.codepage.is_none() {
+            self.product.codepage = attribute("SummaryCodepage");
+        }
+        self.product.installer_version = self.number(node, "InstallerVersion");
+        self.product.compressed = self.yes_no(node, "Compressed", false);
+        self.product.platform = self.choice(
+            node,
+            "Platform",
+            &[
+                ("x86", "Intel"),
+                ("intel", "Intel"),
+                ("x64", "x64"),
+                ("arm64", "Arm64"),
+            ],
+        );
+        self.scope(node, "InstallScope");
+        for child in elements(node) {
+            self.unsupported(child);
+        }
+    }
+
+    fn summary_information(&mut self, node: Node) {
+        let attribute = |name: &str| node.attribute(name).map(str::to_owned);
+        self.product.author = attribute("Manufacturer");
+        self.product.comments = attribute("Comments");
+        if self.product.codepage.is_none() {
+            self.product.codepage = attribute("Codepage");
+        }
+        for child in elements(node) {
+            self.unsupported(child);
+        }
+    }
+
+    fn scope(&mut self, node: Node, name: &str) {
+        let scope = self.choice(
+            node,
+            name,
+            &[("perMachine", true), ("perUser", false)],
+        );
+        let properties: &[(&str, &str)] = match scope {
+            Some(true) => &[("ALLUSERS", "1")],
+            Some(false) => &[("ALLUSERS", "2"), ("MSIINSTALLPERUSER", "1")],
+            None => &[],
+        };
+        for (property, value) in properties {
+            self.properties
+                .insert((*property).to_owned(), (*value).to_owned());
+        }
+    }
+
+    /// A `Property` with a value, which can also be marked as secure so it
+    /// is passed to the server side of the installation.
+    pub(super) fn property(&mut self, node: Node) {
+        let Some(id) = self.required(node, "Id") else {
+            return;
+        };
+        for child in elements(node) {
+            self.unsupported(child);
+        }
+        if PRODUCT_PROPERTIES.contains(&id) {
+            self.problem(
+                node,
+                format!("{id} is set from the attributes of the product"),
+            );
+            return;
+        }
+        if self.yes_no(node, "Secure", false) {
+            self.secure_properties.insert(id.to_owned());
+        }
+        let value = node.attribute("Value").or_else(|| inner_text(node));
+        let Some(value) = value.filter(|value| !value.is_empty()) else {
+            // A property without a value is only declared.
+            return;
+        };
+        if self.properties.contains_key(id) {
+            self.problem(
+                node,
+                format!("Property {id} is defined more than once"),
+            );
+            return;
+        }
+        self.properties.insert(id.to_owned(), value.to_owned());
+    }
+
+    pub(super) fn major_upgrade(&mut self, node: Node) {
+        if self.product.upgrade.is_some() {
+            self.problem(node, "There can only be one MajorUpgrade");
+            return;
+        }
+        for child in elements(node) {
+            self.unsupported(child);
+        }
+        let allow_downgrades = self.yes_no(node, "AllowDowngrades", false);
+        let downgrade_message = node.attribute("DowngradeErrorMessage");
+        if !allow_downgrades && downgrade_message.is_none() {
+            self.problem(
+                node,
+                "MajorUpgrade needs a DowngradeErrorMessage unless \
+                AllowDowngrades is yes",
+            );
+        }
+        let schedule = self
+            .choice(
+                node,
+                "Schedule",
+                &[
+                    ("afterInstallValidate", 1401),
+                    ("afterInstallInitialize", 1501),
+                    ("afterInstallFinalize", 6601),
+                ],
+            )
+            .unwrap_or(1401);
+        self.product.upgrade = Some(MajorUpgrade {
+            location: self.location(node),
+            downgrade_message: downgrade_message.map(str::to_owned),
+            allow_downgrades,
+            allow_same_version: self.yes_no(
+                node,
+                "AllowSameVersionUpgrades",
+                false,
+            ),
+            ignore_remove_failure: self.yes_no(
+                node,
+                "IgnoreRemoveFailure",
+                false,
+            ),
+            migrate_features: self.yes_no(node, "MigrateFeatures", true),
+            schedule,
+        });
+    }
+
+    /// A condition that has to be true for the installation to start, a
+    /// `Condition` under a v3 `Product` or a v4 `Launch`.
+    pub(super) fn launch_condition(&mut self, node: Node) {
+        let condition = match self.v4 {
+            true => self.required(node, "Condition"),
+            false => inner_text(node),
+        };
+        let message = self.required(node, "Message");
+        let (Some(condition), Some(message)) = (condition, message) else {
+            if !self.v4 {
+                self.problem(node, "Condition has no condition text");
+            }
+            return;
+        };
+        for child in elements(node) {
+            self.unsupported(child);
+        }
+        self.add_row(
+            "LaunchCondition",
+            node,
+            vec![Value::from(condition), Value::from(message)],
+        );
+    }
+
+    /// The WiX UI sets with a built-in counterpart.
+    pub(super) fn ui_ref(&mut self, node: Node) {
+        let set = self.choice(
+            node,
+            "Id",
+            &[
+                ("WixUI_Minimal", UiSet::Minimal),
+                ("WixUI_InstallDir", UiSet::InstallDir),
+                ("WixUI_FeatureTree", UiSet::FeatureTree),
+            ],
+        );
+        if let Some(set) = set {
+            if self.ui.is_some() {
+                self.problem(node, "There can only be one UIRef");
+                return;
+            }
+            self.ui = Some((set, self.location(node)));
+        }
+    }
+
+    /// `WixUILicenseRtf`, the only WiX variable with a counterpart.
+    pub(super) fn wix_variable(&mut self, node: Node) {
+        let (Some(id), Some(value)) =
+            (self.required(node, "Id"), self.required(node, "Value"))
+        else {
+            return;
+        };
+        match id {
+            "WixUILicenseRtf" => self.license = Some(value.into()),
+            _ => {
+                self.problem(node, format!("WixVariable {id} is not supported"))
+            }
+        }
+    }
+
+    /// Put the product information together into a config, adding the
+    /// rows of the major upgrade. Returns `None` if any required part is
+    /// missing.
+    pub(super) fn config(&mut self) -> Option<MsiConfig> {
+        let Some(location) = self.product.location.clone() else {
+            let element = match self.v4 {
+                true => "Package",
+                false => "Product",
+            };
+            self.problems.push(format!(
+                "{}: There is no {} element",
+                self.path, element
+            ));
+            return None;
+        };
+        let mut missing = Vec::new();
+        for (name, value) in [
+            ("Name", &self.product.name),
+            ("Version", &self.product.version),
+            ("Manufacturer", &self.product.manufacturer),
+            ("UpgradeCode", &self.product.upgrade_code),
+        ] {
+            let needed =
+                name != "UpgradeCode" || self.product.upgrade.is_some();
+            if needed && value.is_none() {
+                missing.push(name);
+            }
+        }
+        if !self.v4 && self.product.code.is_none() {
+            missing.push("Id");
+        }
+        if !missing.is_empty() {
+            self.problems.push(format!(
+                "{}: The product is missing the {} attribute(s)",
+                location,
+                missing.join(", ")
+            ));
+            return None;
+        }
+
+        if let Some((set, location)) = &self.ui {
+            if *set != UiSet::Minimal
+                && !self.directory_ids.contains("INSTALLDIR")
+            {
+                self.problems.push(format!(
+                    "{location}: This UI lets the user change the INSTALLDIR \
+                    directory, give the installation directory that Id"
+                ));
+            }
+        }
+        if let Some(upgrade_code) = &self.product.upgrade_code {
+            self.properties
+                .insert("UpgradeCode".to_owned(), upgrade_code.clone());
+        }
+        self.upgrade_rows();
+        if !self.secure_properties.is_empty() {
+            let mut secure = self
+                .secure_properties
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            if let Some(existing) =
+                self.properties.get("SecureCustomProperties")
+            {
+                secure.extend(existing.split(';'));
+                secure.sort_unstable();
+                secure.dedup();
+            }
+            let secure = secure.join(";");
+            self.properties
+                .insert("SecureCustomProperties".to_owned(), secure);
+        }
+
+        let product = &self.product;
+        let language = product.language.unwrap_or(0);
+        let languages = product
+            .languages
+            .clone()
+            .unwrap_or_else(|| language.to_string());
+        let platform = product.platform.unwrap_or("Intel");
+        Some(MsiConfig {
+            product_info: ProductInformationProperties {
+                product_name: product.name.as_deref()?.into(),
+                product_version: product.version.as_deref()?.into(),
+                manufacturer: product.manufacturer.as_deref()?.into(),
+                product_language: language,
+                product_code: product.code.as_deref()?.into(),
+            },
+            summary_info: SummaryInformationProperties {
+                page_count: product.installer_version.unwrap_or(500),
+                revision_number: product
+                    .package_code
+                    .as_deref()
+                    .unwrap_or("*")
+                    .into(),
+                template: format!("{platform};{languages}").into(),
+                word_count: Some(if product.compressed { 2 } else { 0 }),
+                author: product.author.clone().or(product.manufacturer.clone()),
+                code_page: product.codepage.clone(),
+                comments: product.comments.clone(),
+                generating_application: None,
+            },
+            merge_module: Vec::new(),
+            signing: None,
+            ui: self.ui.as_ref().map(|(set, _)| *set).unwrap_or_default(),
+            license: self.license.clone(),
+            dialog: Vec::new(),
+            localization: None,
+            property: std::mem::take(&mut self.properties),
+            // The features, registry values, shortcuts and custom actions
+            // of a source are written as rows of their own.
+            feature: Vec::new(),
+            registry: Vec::new(),
+            shortcut: Vec::new(),
+            custom_action: Vec::new(),
+            binary: BTreeMap::new(),
+            scan: ScanConfig::default(),
+            files: Vec::new(),
+            file_version: Vec::new(),
+            component: Vec::new(),
+        })
+    }
+
+    /// Find earlier versions of the product to remove and newer versions
+    /// to refuse to replace, as the `MajorUpgrade` element of WiX does.
+    fn upgrade_rows(&mut self) {
+        let (Some(upgrade), Some(upgrade_code), Some(version)) = (
+            self.product.upgrade.take(),
+            self.product.upgrade_code.clone(),
+            self.product.version.clone(),
+        ) else {
+            return;
+        };
+        let mut attributes = 0;
+        for (set, attribute) in [
+            (upgrade.migrate_features, MIGRATE_FEATURES),
+            (upgrade.ignore_remove_failure, IGNORE_REMOVE_FAILURE),
+            (upgrade.allow_same_version, VERSION_MAX_INCLUSIVE),
+        ] {
+            if set {
+                attributes |= attribute;
+            }
+        }
+        let source = Some(upgrade.location.clone().into());
+        let mut rows = vec![(
+            None,
+            (!upgrade.allow_downgrades).then_some(version.as_str()),
+            attributes,
+            UPGRADE_DETECTED,
+        )];
+        if !upgrade.allow_downgrades {
+            rows.push((
+                Some(version.as_str()),
+                None,
+                ONLY_DETECT,
+                DOWNGRADE_DETECTED,
+            ));
+        }
+        for (min, max, attributes, property) in rows {
+            let row = vec![
+                Value::from(upgrade_code.as_str()),
+                optional(min),
+                optional(max),
+                Value::Null,
+                Value::from(attributes),
+                Value::Null,
+                Value::from(property),
+            ];
+            self.rows
+                .entry("Upgrade")
+                .or_default()
+                .push(SourcedRow::new(row, source.clone()));
+            self.secure_properties.insert(property.to_owned());
+        }
+        if let Some(message) = &upgrade.downgrade_message {
+            self.rows.entry("LaunchCondition").or_default().push(
+                SourcedRow::new(
+                    vec![
+                        Value::from(format!("NOT {DOWNGRADE_DETECTED}")),
+                        Value::from(message.as_str()),
+                    ],
+                    source,
+                ),
+            );
+        }
+        self.actions.upgrade_schedule = Some(upgrade.schedule);
+    }
+}